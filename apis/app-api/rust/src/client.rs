@@ -0,0 +1,499 @@
+/*
+ * Copyright (C) 2018 Kubos Corporation
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use failure::{self, Error};
+use kubos_system::Config as ServiceConfig;
+use query::query;
+use serde::de::DeserializeOwned;
+use serde_json;
+use std::fmt;
+use std::time::Duration;
+
+/// Default time to wait for a service to respond to a request
+pub const DEFAULT_CLIENT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// The value of an argument to a GraphQL field
+#[derive(Clone, Debug, PartialEq)]
+pub enum Arg {
+    /// A string, which is quoted and escaped
+    String(String),
+    /// An integer
+    Int(i64),
+    /// A floating point number
+    Float(f64),
+    /// A boolean
+    Bool(bool),
+    /// An enum value, such as `ON`
+    Enum(String),
+    /// A list of values
+    List(Vec<Arg>),
+    /// An input object
+    Object(Vec<(String, Arg)>),
+    /// No value
+    Null,
+}
+
+impl Arg {
+    /// Create an enum value argument
+    pub fn enum_value(value: &str) -> Arg {
+        Arg::Enum(value.to_owned())
+    }
+
+    fn write(&self, out: &mut String) -> Result<(), Error> {
+        match *self {
+            Arg::String(ref value) => out.push_str(&serde_json::to_string(value)?),
+            Arg::Int(value) => out.push_str(&value.to_string()),
+            Arg::Float(value) => {
+                if !value.is_finite() {
+                    bail!("Invalid float argument: {}", value);
+                }
+                out.push_str(&format!("{:?}", value))
+            }
+            Arg::Bool(value) => out.push_str(&value.to_string()),
+            Arg::Enum(ref value) => {
+                // true, false and null would be read as other types of value
+                if !is_name(value) || value == "true" || value == "false" || value == "null" {
+                    bail!("Invalid enum value: {:?}", value);
+                }
+                out.push_str(value)
+            }
+            Arg::List(ref values) => {
+                out.push('[');
+                for (index, value) in values.iter().enumerate() {
+                    if index > 0 {
+                        out.push_str(", ");
+                    }
+                    value.write(out)?;
+                }
+                out.push(']');
+            }
+            Arg::Object(ref fields) => {
+                out.push('{');
+                for (index, &(ref name, ref value)) in fields.iter().enumerate() {
+                    if index > 0 {
+                        out.push_str(", ");
+                    }
+                    write_name(out, name)?;
+                    out.push_str(": ");
+                    value.write(out)?;
+                }
+                out.push('}');
+            }
+            Arg::Null => out.push_str("null"),
+        }
+        Ok(())
+    }
+}
+
+impl<'a> From<&'a str> for Arg {
+    fn from(value: &'a str) -> Arg {
+        Arg::String(value.to_owned())
+    }
+}
+
+impl From<String> for Arg {
+    fn from(value: String) -> Arg {
+        Arg::String(value)
+    }
+}
+
+impl From<bool> for Arg {
+    fn from(value: bool) -> Arg {
+        Arg::Bool(value)
+    }
+}
+
+impl From<f64> for Arg {
+    fn from(value: f64) -> Arg {
+        Arg::Float(value)
+    }
+}
+
+impl From<f32> for Arg {
+    fn from(value: f32) -> Arg {
+        Arg::Float(f64::from(value))
+    }
+}
+
+macro_rules! int_arg {
+    ($($int:ty),*) => {
+        $(
+            impl From<$int> for Arg {
+                fn from(value: $int) -> Arg {
+                    Arg::Int(i64::from(value))
+                }
+            }
+        )*
+    };
+}
+
+int_arg!(i8, i16, i32, i64, u8, u16, u32);
+
+impl<T: Into<Arg>> From<Vec<T>> for Arg {
+    fn from(values: Vec<T>) -> Arg {
+        Arg::List(values.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<T: Into<Arg>> From<Option<T>> for Arg {
+    fn from(value: Option<T>) -> Arg {
+        value.map(Into::into).unwrap_or(Arg::Null)
+    }
+}
+
+// Whether a string is a valid GraphQL name
+fn is_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) if first == '_' || first.is_ascii_alphabetic() => {
+            chars.all(|c| c == '_' || c.is_ascii_alphanumeric())
+        }
+        _ => false,
+    }
+}
+
+fn write_name(out: &mut String, name: &str) -> Result<(), Error> {
+    if !is_name(name) {
+        bail!("Invalid GraphQL name: {:?}", name);
+    }
+    out.push_str(name);
+    Ok(())
+}
+
+/// A field to request, along with its arguments and sub-fields
+///
+/// # Examples
+///
+/// ```
+/// use kubos_app::{Arg, Field};
+///
+/// let field = Field::new("power")
+///     .arg("state", Arg::enum_value("ON"))
+///     .fields(&["success", "errors"]);
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Field {
+    name: String,
+    alias: Option<String>,
+    args: Vec<(String, Arg)>,
+    fields: Vec<Field>,
+}
+
+impl Field {
+    /// Create a field with the given name
+    pub fn new(name: &str) -> Field {
+        Field {
+            name: name.to_owned(),
+            alias: None,
+            args: vec![],
+            fields: vec![],
+        }
+    }
+
+    /// Request the field under a different name in the response
+    pub fn alias(mut self, alias: &str) -> Field {
+        self.alias = Some(alias.to_owned());
+        self
+    }
+
+    /// Add an argument to the field
+    pub fn arg<T: Into<Arg>>(mut self, name: &str, value: T) -> Field {
+        self.args.push((name.to_owned(), value.into()));
+        self
+    }
+
+    /// Add a sub-field
+    pub fn field<F: Into<Field>>(mut self, field: F) -> Field {
+        self.fields.push(field.into());
+        self
+    }
+
+    /// Add several sub-fields which have no arguments or sub-fields of their own
+    pub fn fields(mut self, names: &[&str]) -> Field {
+        self.fields
+            .extend(names.iter().map(|name| Field::new(name)));
+        self
+    }
+
+    /// The key of the field in the response
+    pub fn key(&self) -> &str {
+        self.alias.as_ref().unwrap_or(&self.name)
+    }
+
+    fn has_field(&self, name: &str) -> bool {
+        self.fields.iter().any(|field| field.key() == name)
+    }
+
+    fn write(&self, out: &mut String) -> Result<(), Error> {
+        if let Some(ref alias) = self.alias {
+            write_name(out, alias)?;
+            out.push_str(": ");
+        }
+        write_name(out, &self.name)?;
+
+        if !self.args.is_empty() {
+            out.push('(');
+            for (index, &(ref name, ref value)) in self.args.iter().enumerate() {
+                if index > 0 {
+                    out.push_str(", ");
+                }
+                write_name(out, name)?;
+                out.push_str(": ");
+                value.write(out)?;
+            }
+            out.push(')');
+        }
+
+        write_selection(out, &self.fields)
+    }
+}
+
+impl<'a> From<&'a str> for Field {
+    fn from(name: &'a str) -> Field {
+        Field::new(name)
+    }
+}
+
+fn write_selection(out: &mut String, fields: &[Field]) -> Result<(), Error> {
+    if !fields.is_empty() {
+        out.push_str(" { ");
+        for (index, field) in fields.iter().enumerate() {
+            if index > 0 {
+                out.push_str(" ");
+            }
+            field.write(out)?;
+        }
+        out.push_str(" }");
+    }
+    Ok(())
+}
+
+/// Whether a request reads or changes the state of a service
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Operation {
+    /// A query
+    Query,
+    /// A mutation
+    Mutation,
+}
+
+/// A GraphQL request
+///
+/// # Examples
+///
+/// ```
+/// use kubos_app::{Field, Request};
+///
+/// let request = Request::query()
+///     .field(Field::new("apps").arg("active", true).field(Field::new("app").fields(&["uuid", "name"])));
+///
+/// assert_eq!(
+///     request.build().unwrap(),
+///     "{ apps(active: true) { app { uuid name } } }"
+/// );
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Request {
+    operation: Operation,
+    fields: Vec<Field>,
+}
+
+impl Request {
+    /// Create a query
+    pub fn query() -> Request {
+        Request {
+            operation: Operation::Query,
+            fields: vec![],
+        }
+    }
+
+    /// Create a mutation
+    pub fn mutation() -> Request {
+        Request {
+            operation: Operation::Mutation,
+            fields: vec![],
+        }
+    }
+
+    /// Add a top-level field to the request
+    pub fn field<F: Into<Field>>(mut self, field: F) -> Request {
+        self.fields.push(field.into());
+        self
+    }
+
+    /// Build the text of the request, checking that all names are valid
+    pub fn build(&self) -> Result<String, Error> {
+        if self.fields.is_empty() {
+            bail!("Request has no fields");
+        }
+
+        let mut out = String::new();
+        if self.operation == Operation::Mutation {
+            out.push_str("mutation");
+        }
+        write_selection(&mut out, &self.fields)?;
+
+        Ok(out.trim_start().to_owned())
+    }
+}
+
+/// The fields returned by mutations which don't return any specific data
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct MutationResponse {
+    /// Whether the mutation succeeded
+    pub success: bool,
+    /// Any errors encountered by the mutation
+    pub errors: String,
+}
+
+/// Error returned by the client when a mutation reports that it failed with `success: false`
+#[derive(Clone, Debug, PartialEq)]
+pub struct MutationError {
+    /// The mutation which failed
+    pub mutation: String,
+    /// The errors reported by the service
+    pub errors: String,
+}
+
+impl fmt::Display for MutationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} failed: {}", self.mutation, self.errors)
+    }
+}
+
+impl failure::Fail for MutationError {}
+
+/// A client for sending requests to a particular service
+///
+/// # Examples
+///
+/// ```
+/// # extern crate failure;
+/// # extern crate kubos_app;
+/// #[macro_use]
+/// extern crate serde_derive;
+///
+/// use kubos_app::*;
+///
+/// #[derive(Deserialize)]
+/// struct MemInfo {
+///     available: Option<u64>,
+/// }
+///
+/// # fn func() -> Result<(), failure::Error> {
+/// let monitor = Client::new(ServiceConfig::discover("monitor-service")?);
+/// let mem: MemInfo = monitor.get(Field::new("memInfo").field("available"))?;
+///
+/// if let Some(available) = mem.available {
+///     let telemetry = Client::new(ServiceConfig::discover("telemetry-service")?);
+///     telemetry.mutate::<MutationResponse>(
+///         Field::new("insert")
+///             .arg("subsystem", "OBC")
+///             .arg("parameter", "available_mem")
+///             .arg("value", available.to_string()),
+///     )?;
+/// }
+/// # Ok(())
+/// # }
+/// # fn main() {}
+/// ```
+#[derive(Clone, Debug)]
+pub struct Client {
+    config: ServiceConfig,
+    timeout: Option<Duration>,
+}
+
+impl Client {
+    /// Create a client for the service with the given configuration. Requests time out after
+    /// [`DEFAULT_CLIENT_TIMEOUT`].
+    ///
+    /// [`DEFAULT_CLIENT_TIMEOUT`]: constant.DEFAULT_CLIENT_TIMEOUT.html
+    pub fn new(config: ServiceConfig) -> Client {
+        Client {
+            config,
+            timeout: Some(DEFAULT_CLIENT_TIMEOUT),
+        }
+    }
+
+    /// Set how long to wait for responses. `None` waits forever.
+    pub fn timeout(mut self, timeout: Option<Duration>) -> Client {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Send a request, deserializing the `data` object of the response
+    pub fn send<T: DeserializeOwned>(&self, request: &Request) -> Result<T, Error> {
+        self.send_raw(&request.build()?)
+    }
+
+    /// Send a request written by hand, deserializing the `data` object of the response
+    pub fn send_raw<T: DeserializeOwned>(&self, request: &str) -> Result<T, Error> {
+        let data = query(self.config.clone(), request, self.timeout)?;
+        serde_json::from_value(data).map_err(|err| format_err!("Failed to parse response: {}", err))
+    }
+
+    /// Query a single field, deserializing its value
+    pub fn get<T: DeserializeOwned>(&self, field: Field) -> Result<T, Error> {
+        let key = field.key().to_owned();
+        let mut data: serde_json::Value = self.send(&Request::query().field(field))?;
+        take_field(&mut data, &key)
+    }
+
+    /// Run a single mutation, deserializing its result.
+    ///
+    /// The `success` and `errors` fields are requested if they aren't already, and a
+    /// [`MutationError`] is returned if the service reports that the mutation failed.
+    ///
+    /// [`MutationError`]: struct.MutationError.html
+    pub fn mutate<T: DeserializeOwned>(&self, mut field: Field) -> Result<T, Error> {
+        for name in &["success", "errors"] {
+            if !field.has_field(name) {
+                field = field.field(*name);
+            }
+        }
+
+        let key = field.key().to_owned();
+        let mut data: serde_json::Value = self.send(&Request::mutation().field(field))?;
+        let result = data
+            .get_mut(&key)
+            .map(|result| result.take())
+            .unwrap_or(serde_json::Value::Null);
+
+        if result.get("success") == Some(&serde_json::Value::Bool(false)) {
+            let errors = match result.get("errors") {
+                Some(serde_json::Value::String(errors)) => errors.clone(),
+                Some(errors) => errors.to_string(),
+                None => String::new(),
+            };
+            return Err(MutationError {
+                mutation: key,
+                errors,
+            }
+            .into());
+        }
+
+        serde_json::from_value(result)
+            .map_err(|err| format_err!("Failed to parse {} response: {}", key, err))
+    }
+}
+
+fn take_field<T: DeserializeOwned>(data: &mut serde_json::Value, key: &str) -> Result<T, Error> {
+    let value = data
+        .get_mut(key)
+        .map(|value| value.take())
+        .unwrap_or(serde_json::Value::Null);
+    serde_json::from_value(value).map_err(|err| format_err!("Failed to parse {}: {}", key, err))
+}
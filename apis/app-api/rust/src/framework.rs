@@ -21,8 +21,25 @@
 
 use failure::Error;
 use getopts::Options;
+use libc;
+use serde::de::DeserializeOwned;
 use std::env;
 use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use toml;
+
+/// Default number of seconds an application has to shut down after receiving `SIGTERM`
+pub const DEFAULT_SHUTDOWN_TIMEOUT: u64 = 5;
+
+// Set by the SIGTERM handler installed by `app_start`
+static SHUTDOWN: AtomicBool = AtomicBool::new(false);
+
+// When `SIGTERM` was received, from `monotonic_millis`
+static SHUTDOWN_TIME: AtomicUsize = AtomicUsize::new(0);
 
 /// The different ways an application can be started
 #[derive(Clone, Debug, PartialEq)]
@@ -31,6 +48,10 @@ pub enum RunLevel {
     OnBoot,
     /// Logic intended to be run if the application is started manually
     OnCommand,
+    /// Logic intended to be run once after a new version of the application is registered
+    OnUpgrade,
+    /// Logic intended to be run periodically
+    OnTick,
 }
 
 impl fmt::Display for RunLevel {
@@ -38,6 +59,25 @@ impl fmt::Display for RunLevel {
         match self {
             RunLevel::OnBoot => write!(f, "OnBoot"),
             RunLevel::OnCommand => write!(f, "OnCommand"),
+            RunLevel::OnUpgrade => write!(f, "OnUpgrade"),
+            RunLevel::OnTick => write!(f, "OnTick"),
+        }
+    }
+}
+
+impl FromStr for RunLevel {
+    type Err = Error;
+
+    fn from_str(level: &str) -> Result<RunLevel, Error> {
+        match level {
+            "OnBoot" => Ok(RunLevel::OnBoot),
+            "OnCommand" => Ok(RunLevel::OnCommand),
+            "OnUpgrade" => Ok(RunLevel::OnUpgrade),
+            "OnTick" => Ok(RunLevel::OnTick),
+            level => bail!(
+                "Unknown run level was requested - {}. Available run levels: OnBoot, OnCommand, OnUpgrade, OnTick",
+                level
+            ),
         }
     }
 }
@@ -49,6 +89,26 @@ pub trait AppHandler {
 
     /// Called when the application is started on-demand through the `start_app` GraphQL mutation
     fn on_command(&self, args: Vec<String>) -> Result<(), Error>;
+
+    /// Called once when the application is started after a new version of it is registered.
+    /// Does nothing by default.
+    fn on_upgrade(&self, _args: Vec<String>) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Called each time the application is started periodically, as configured by the `tick`
+    /// value of its manifest. Does nothing by default.
+    fn on_tick(&self, _args: Vec<String>) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Called when the application receives `SIGTERM`, for example from the `stopApp` GraphQL
+    /// mutation, once the run level handler has returned. The application is killed if it
+    /// hasn't exited by `deadline`, so any cleanup should be finished by then.
+    /// Does nothing by default.
+    fn on_shutdown(&self, _deadline: Instant) -> Result<(), Error> {
+        Ok(())
+    }
 }
 
 /// A helper macro which detects the requested run level and calls the appropriate handler function
@@ -95,6 +155,13 @@ macro_rules! app_main {
 
 /// The entry point for all KubOS applications. The preferred way to use this application
 /// is through the `app_main!` macro
+///
+/// If the application receives `SIGTERM` while the run level handler is running, the
+/// handler's `on_shutdown` function is called once the run level handler returns, with the
+/// deadline by which the application must exit. Long-running run level handlers should
+/// check `shutdown_requested` and return early once it's set.
+/// The time allowed for shutting down is read from the `KUBOS_APP_SHUTDOWN_TIMEOUT`
+/// environment variable, set by the applications service.
 pub fn app_start(_pid: u32, handler: &AppHandler) -> Result<(), Error> {
     let args: Vec<String> = env::args().collect();
     let program = args[0].clone();
@@ -121,14 +188,149 @@ pub fn app_start(_pid: u32, handler: &AppHandler) -> Result<(), Error> {
 
     let _uuid = env::var_os("KUBOS_APP_UUID");
     let run_level = matches.opt_str("r").unwrap_or("OnCommand".to_owned());
+    let run_level = run_level
+        .parse::<RunLevel>()
+        .map_err(|err| format_err!("Error: {}", err))?;
 
-    match run_level.as_ref() {
-        "OnBoot" => handler.on_boot(args),
-        "OnCommand" => handler.on_command(args),
-        level => {
-            bail!(
-                "Error: Unknown run level was requested - {}. Available run levels: OnBoot, OnCommand", level
-            );
-        }
+    let timeout = env::var("KUBOS_APP_SHUTDOWN_TIMEOUT")
+        .ok()
+        .and_then(|timeout| timeout.parse().ok())
+        .unwrap_or(DEFAULT_SHUTDOWN_TIMEOUT);
+
+    unsafe {
+        libc::signal(libc::SIGTERM, handle_sigterm as libc::sighandler_t);
+    }
+
+    let result = run_handler(handler, &run_level, args);
+
+    // The shutdown hook is called here rather than from the signal handler, since very little
+    // is safe to do in a signal handler
+    if shutdown_requested() {
+        let elapsed = Duration::from_millis(
+            monotonic_millis().wrapping_sub(SHUTDOWN_TIME.load(Ordering::SeqCst)) as u64,
+        );
+        let deadline = Instant::now()
+            + Duration::from_secs(timeout)
+                .checked_sub(elapsed)
+                .unwrap_or_else(|| Duration::from_secs(0));
+        handler
+            .on_shutdown(deadline)
+            .map_err(|err| format_err!("Failed to shut down: {}", err))?;
+    }
+
+    result
+}
+
+/// Whether the application has been asked to shut down with `SIGTERM`.
+///
+/// Long-running run level handlers can check this to stop work which shouldn't be
+/// interrupted part-way through by the application exiting.
+pub fn shutdown_requested() -> bool {
+    SHUTDOWN.load(Ordering::SeqCst)
+}
+
+extern "C" fn handle_sigterm(_signal: libc::c_int) {
+    if !SHUTDOWN.load(Ordering::SeqCst) {
+        SHUTDOWN_TIME.store(monotonic_millis(), Ordering::SeqCst);
+        SHUTDOWN.store(true, Ordering::SeqCst);
+    }
+}
+
+// Milliseconds on the monotonic clock, which `Instant` also uses. Unlike `Instant::now`,
+// `clock_gettime` is safe to call from a signal handler.
+fn monotonic_millis() -> usize {
+    let mut now = libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+    };
+    unsafe {
+        libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut now);
+    }
+    (now.tv_sec as usize)
+        .wrapping_mul(1000)
+        .wrapping_add(now.tv_nsec as usize / 1_000_000)
+}
+
+/// Call the handler function for the given run level
+pub fn run_handler(
+    handler: &AppHandler,
+    run_level: &RunLevel,
+    args: Vec<String>,
+) -> Result<(), Error> {
+    match run_level {
+        RunLevel::OnBoot => handler.on_boot(args),
+        RunLevel::OnCommand => handler.on_command(args),
+        RunLevel::OnUpgrade => handler.on_upgrade(args),
+        RunLevel::OnTick => handler.on_tick(args),
     }
 }
+
+/// Load the application-specific configuration from the `[config]` section of the
+/// application's `manifest.toml` file.
+///
+/// The manifest is found in the application's registry directory, which the applications
+/// service passes in the `KUBOS_APP_DIR` environment variable. If the variable isn't set,
+/// the directory containing the application executable is used instead.
+///
+/// If the manifest has no `[config]` section, the configuration is loaded from an empty table,
+/// so types whose fields all have defaults can still be loaded.
+///
+/// # Examples
+///
+/// ```
+/// extern crate failure;
+/// extern crate kubos_app;
+/// #[macro_use]
+/// extern crate serde_derive;
+///
+/// use failure::Error;
+/// use kubos_app::*;
+///
+/// #[derive(Deserialize)]
+/// struct MyConfig {
+///     threshold: u32,
+/// }
+///
+/// struct MyApp;
+///
+/// impl AppHandler for MyApp {
+///   fn on_boot(&self, _args: Vec<String>) -> Result<(), Error> {
+///     let config: MyConfig = app_config()?;
+///     println!("Threshold: {}", config.threshold);
+///     Ok(())
+///   }
+///   fn on_command(&self, _args: Vec<String>) -> Result<(), Error> {
+///     Ok(())
+///   }
+/// }
+/// # fn main() {}
+/// ```
+pub fn app_config<T: DeserializeOwned>() -> Result<T, Error> {
+    let dir = match env::var_os("KUBOS_APP_DIR") {
+        Some(dir) => PathBuf::from(dir),
+        None => env::current_exe()?
+            .parent()
+            .map(|dir| dir.to_path_buf())
+            .ok_or_else(|| format_err!("Unable to find the application directory"))?,
+    };
+
+    app_config_from(&dir.join("manifest.toml"))
+}
+
+/// Load the application-specific configuration from the `[config]` section of the given
+/// manifest file
+pub fn app_config_from<T: DeserializeOwned>(manifest: &Path) -> Result<T, Error> {
+    let contents = fs::read_to_string(manifest)
+        .map_err(|err| format_err!("Failed to read {}: {}", manifest.display(), err))?;
+    let mut manifest_toml: toml::Value = toml::from_str(&contents)
+        .map_err(|err| format_err!("Failed to parse {}: {}", manifest.display(), err))?;
+
+    let config = manifest_toml
+        .as_table_mut()
+        .and_then(|table| table.remove("config"))
+        .unwrap_or_else(|| toml::Value::Table(toml::value::Table::new()));
+
+    config
+        .try_into()
+        .map_err(|err| format_err!("Invalid app config in {}: {}", manifest.display(), err))
+}
@@ -41,7 +41,7 @@
 //!             }
 //!         }"#;
 //!
-//!     match query(ServiceConfig::new("radio-service"), request, Some(Duration::from_secs(1))) {
+//!     match query(ServiceConfig::new("radio-service")?, request, Some(Duration::from_secs(1))) {
 //!         Err(error) => bail!("Failed to communicate with radio service: {}", error),
 //!         Ok(data) => {
 //!             if let Some(success) = data.get("power")
@@ -73,6 +73,40 @@
 //! }
 //! ```
 //!
+//! Rather than writing requests and picking through their JSON responses by hand, apps can use
+//! a [`Client`] to build requests from [`Field`]s, with arguments escaped automatically,
+//! and deserialize the responses into their own types:
+//!
+//! ```
+//! # extern crate failure;
+//! # extern crate kubos_app;
+//! #[macro_use]
+//! extern crate serde_derive;
+//!
+//! use kubos_app::*;
+//!
+//! #[derive(Deserialize)]
+//! struct Power {
+//!     state: String,
+//! }
+//!
+//! # fn func() -> Result<(), failure::Error> {
+//! let radio = Client::new(ServiceConfig::new("radio-service")?);
+//! let power: Power = radio.mutate(Field::new("power").arg("state", Arg::enum_value("ON")).field("state"))?;
+//! println!("Radio power is now {}", power.state);
+//! # Ok(())
+//! # }
+//! # fn main() {}
+//! ```
+//!
+//! Services announce the address they're listening on when they start. Use
+//! `ServiceConfig::discover` instead of `ServiceConfig::new` to connect to that address
+//! (falling back to the one in the config file if the service isn't running), and
+//! `kubos_system::Discovery` to check whether a service is currently up.
+//!
+//! [`Client`]: struct.Client.html
+//! [`Field`]: struct.Field.html
+//!
 
 #![deny(missing_docs)]
 #![deny(warnings)]
@@ -85,6 +119,10 @@ extern crate juniper;
 #[cfg(test)]
 extern crate kubos_service;
 extern crate kubos_system;
+extern crate libc;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
 #[cfg(not(test))]
 extern crate serde_json;
 #[cfg(test)]
@@ -92,12 +130,15 @@ extern crate serde_json;
 extern crate serde_json;
 #[cfg(test)]
 extern crate tempfile;
+extern crate toml;
 
+mod client;
 mod framework;
 mod query;
 #[cfg(test)]
 mod tests;
 
+pub use client::*;
 pub use framework::*;
-pub use query::query;
+pub use query::{query, ErrorExtensions, ErrorLocation, PathSegment, QueryError, ResponseError};
 pub use kubos_system::Config as ServiceConfig;
@@ -17,15 +17,151 @@
 use failure;
 use kubos_system::Config as ServiceConfig;
 use serde_json;
+use std::fmt;
 use std::net::UdpSocket;
 use std::time::Duration;
 
 /// The result type used by `query`
 type AppResult<T> = Result<T, failure::Error>;
 
+/// Location within a query of the field which generated an error
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct ErrorLocation {
+    /// Line number, starting from 1
+    pub line: u32,
+    /// Column number, starting from 1
+    pub column: u32,
+}
+
+/// Additional, machine-readable information about an error
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+pub struct ErrorExtensions {
+    /// Error classification, for example `"TIMEOUT"` or `"BAD_ARGUMENT"`
+    pub code: Option<String>,
+    /// The subsystem which generated the error, if known
+    pub subsystem: Option<String>,
+    /// The chain of underlying causes of the error
+    #[serde(default)]
+    pub causes: Vec<String>,
+}
+
+/// A single step in the path to the field which generated an error
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(untagged)]
+pub enum PathSegment {
+    /// Name (or alias) of a field
+    Field(String),
+    /// Index within a list
+    Index(u64),
+}
+
+/// A single error reported by a service in response to a query
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct ResponseError {
+    /// Human-readable description of the error
+    pub message: String,
+    /// Locations within the query of the fields which generated the error
+    #[serde(default)]
+    pub locations: Vec<ErrorLocation>,
+    /// Path of fields leading to the field which generated the error
+    #[serde(default)]
+    pub path: Vec<PathSegment>,
+    /// Machine-readable information about the error
+    #[serde(default)]
+    pub extensions: ErrorExtensions,
+}
+
+impl ResponseError {
+    /// Returns the error's code, if the service provided one
+    pub fn code(&self) -> Option<&str> {
+        self.extensions.code.as_ref().map(|code| code.as_str())
+    }
+}
+
+/// Error returned by `query` when the service reported one or more errors.
+///
+/// Retrieve it from the returned `failure::Error` with `downcast_ref` in order to
+/// inspect the individual errors and their codes.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate failure;
+/// # extern crate kubos_app;
+/// use kubos_app::*;
+/// use std::time::Duration;
+///
+/// # fn func() -> Result<(), failure::Error> {
+/// let result = query(ServiceConfig::new("gps-service")?, "{ lockStatus }", Some(Duration::from_secs(1)));
+///
+/// if let Err(error) = result {
+///     match error.downcast_ref::<QueryError>() {
+///         Some(err) if err.has_code("TIMEOUT") => println!("GPS timed out, retrying"),
+///         _ => eprintln!("Query failed: {}", error),
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct QueryError {
+    errors: Vec<ResponseError>,
+    data: Option<serde_json::Value>,
+}
+
+impl QueryError {
+    /// Returns the errors reported by the service
+    pub fn errors(&self) -> &[ResponseError] {
+        &self.errors
+    }
+
+    /// Returns any partial data which was returned along with the errors
+    pub fn data(&self) -> Option<&serde_json::Value> {
+        self.data.as_ref()
+    }
+
+    /// Returns true if any of the reported errors has the given code
+    pub fn has_code(&self, code: &str) -> bool {
+        self.errors.iter().any(|err| err.code() == Some(code))
+    }
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let messages: Vec<&str> = self.errors.iter().map(|err| err.message.as_str()).collect();
+        write!(f, "{}", messages.join(", "))
+    }
+}
+
+impl failure::Fail for QueryError {}
+
+fn parse_errors(errs: &[serde_json::Value]) -> Vec<ResponseError> {
+    errs.iter()
+        .map(|err| match err.as_str() {
+            Some(message) => ResponseError {
+                message: message.to_owned(),
+                locations: vec![],
+                path: vec![],
+                extensions: ErrorExtensions::default(),
+            },
+            None => {
+                serde_json::from_value(err.clone()).unwrap_or_else(|_| ResponseError {
+                    message: err.to_string(),
+                    locations: vec![],
+                    path: vec![],
+                    extensions: ErrorExtensions::default(),
+                })
+            }
+        })
+        .collect()
+}
+
 /// Execute a GraphQL query against a running KubOS Service using UDP.
 ///
-/// Returns the parsed JSON result as a serde_json::Value on success
+/// Returns the parsed JSON result as a serde_json::Value on success.
+/// If the service reports any errors, a [`QueryError`] describing them is returned.
+///
+/// [`QueryError`]: struct.QueryError.html
 ///
 /// # Arguments
 ///
@@ -48,7 +184,7 @@ type AppResult<T> = Result<T, failure::Error>;
 /// 		ping
 /// 	}"#;
 ///
-/// let result = query(ServiceConfig::new_from_path("radio-service", "/home/kubos/config.toml".to_owned()), request, Some(Duration::from_secs(1)))?;
+/// let result = query(ServiceConfig::new_from_path("radio-service", "/home/kubos/config.toml".to_owned())?, request, Some(Duration::from_secs(1)))?;
 ///
 /// let data = result.get("ping").unwrap().as_str();
 ///
@@ -69,7 +205,7 @@ type AppResult<T> = Result<T, failure::Error>;
 /// 		power
 /// 	}"#;
 ///
-/// let result = query(ServiceConfig::new("antenna-service"), request, Some(Duration::from_secs(1)))?;
+/// let result = query(ServiceConfig::new("antenna-service")?, request, Some(Duration::from_secs(1)))?;
 ///
 /// let data = result.get("power").unwrap().as_str();
 ///
@@ -96,7 +232,15 @@ pub fn query(
     let v: serde_json::Value = serde_json::from_slice(&buf[0..(amt)])?;
 
     if let Some(errs) = v.get("errors") {
-        if errs.is_string() {
+        if let Some(errs) = errs.as_array() {
+            if !errs.is_empty() {
+                return Err(QueryError {
+                    errors: parse_errors(errs),
+                    data: v.get("data").cloned().filter(|data| !data.is_null()),
+                }.into());
+            }
+        } else if errs.is_string() {
+            // Legacy services report their errors as a single string
             let errs_str = errs.as_str().unwrap();
             if errs_str.len() > 0 {
                 return Err(format_err!("{}", errs_str.to_string()));
@@ -113,16 +257,6 @@ pub fn query(
         }
     }
 
-    match v.get(0) {
-        Some(err) if err.get("message").is_some() => {
-            return Err(format_err!(
-                "{}",
-                err["message"].as_str().unwrap().to_string(),
-            ));
-        }
-        _ => {}
-    }
-
     match v.get("data") {
         Some(result) => Ok(result.clone()),
         None => Err(format_err!(
@@ -0,0 +1,180 @@
+/*
+ * Copyright (C) 2018 Kubos Corporation
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use super::mock_service::*;
+use client::*;
+use kubos_service::Service;
+use kubos_system::Config as ServiceConfig;
+use query::QueryError;
+
+use tempfile::TempDir;
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct Echo {
+    text: String,
+    count: i32,
+    tags: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct Power {
+    state: String,
+}
+
+#[test]
+fn build_query() {
+    let request = Request::query()
+        .field(
+            Field::new("echo")
+                .alias("first")
+                .arg("text", "hi")
+                .arg("count", 3)
+                .fields(&["text", "count"]),
+        )
+        .field("ping");
+
+    assert_eq!(
+        request.build().unwrap(),
+        r#"{ first: echo(text: "hi", count: 3) { text count } ping }"#
+    );
+}
+
+#[test]
+fn build_mutation_args() {
+    let request = Request::mutation().field(
+        Field::new("configure")
+            .arg("state", Arg::enum_value("ON"))
+            .arg("gain", 1.0)
+            .arg("enabled", false)
+            .arg("channels", vec![1, 2])
+            .arg("name", None::<String>)
+            .arg(
+                "limits",
+                Arg::Object(vec![("max".to_owned(), Arg::from(-5i64))]),
+            )
+            .field("success"),
+    );
+
+    assert_eq!(
+        request.build().unwrap(),
+        "mutation { configure(state: ON, gain: 1.0, enabled: false, channels: [1, 2], name: null, limits: {max: -5}) { success } }"
+    );
+}
+
+#[test]
+fn build_escapes_strings() {
+    let request = Request::query().field(
+        Field::new("echo")
+            .arg("text", "say \"hi\"\n} { ping")
+            .field("text"),
+    );
+
+    assert_eq!(
+        request.build().unwrap(),
+        r#"{ echo(text: "say \"hi\"\n} { ping") { text } }"#
+    );
+}
+
+#[test]
+fn build_invalid_names() {
+    for request in vec![
+        Request::query().field("ping }"),
+        Request::query().field(Field::new("echo").arg("text)", "hi")),
+        Request::query().field(Field::new("echo").alias("1st")),
+        Request::mutation().field(Field::new("power").arg("state", Arg::enum_value("ON) {"))),
+        Request::mutation().field(Field::new("power").arg("state", Arg::enum_value("true"))),
+        Request::query().field(Field::new("echo").arg("count", ::std::f64::NAN)),
+        Request::query(),
+    ] {
+        assert!(request.build().is_err(), "{:?}", request);
+    }
+}
+
+#[test]
+fn client_get() {
+    let config_dir = TempDir::new().unwrap();
+    let config_file = config_dir.path().join("config.toml");
+    mock_service!(config_file, "0.0.0.0", 8759);
+
+    let client = Client::new(
+        ServiceConfig::new_from_path("mock-service", config_file.to_string_lossy().to_string())
+            .unwrap(),
+    );
+
+    let echo: Echo = client
+        .get(
+            Field::new("echo")
+                .arg("text", "a \"quoted\" string")
+                .arg("count", 2)
+                .arg("tags", vec!["x", "y"])
+                .fields(&["text", "count", "tags"]),
+        )
+        .unwrap();
+    assert_eq!(
+        echo,
+        Echo {
+            text: "a \"quoted\" string".to_owned(),
+            count: 2,
+            tags: vec!["x".to_owned(), "y".to_owned()],
+        }
+    );
+
+    // Errors reported by the service are passed through
+    let err = client
+        .get::<String>(Field::new("ping").arg("fail", true))
+        .unwrap_err();
+    assert!(err.downcast_ref::<QueryError>().is_some());
+
+    // As are responses which don't match the requested type
+    let err = client.get::<i32>(Field::new("ping")).unwrap_err();
+    assert!(err.to_string().starts_with("Failed to parse ping"));
+}
+
+#[test]
+fn client_mutate() {
+    let config_dir = TempDir::new().unwrap();
+    let config_file = config_dir.path().join("config.toml");
+    mock_service!(config_file, "0.0.0.0", 8758);
+
+    let client = Client::new(
+        ServiceConfig::new_from_path("mock-service", config_file.to_string_lossy().to_string())
+            .unwrap(),
+    );
+
+    let power: Power = client
+        .mutate(
+            Field::new("power")
+                .arg("state", Arg::enum_value("ON"))
+                .field("state"),
+        )
+        .unwrap();
+    assert_eq!(power.state, "ON");
+
+    let err = client
+        .mutate::<Power>(
+            Field::new("power")
+                .arg("state", Arg::enum_value("OFF"))
+                .field("state"),
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<MutationError>(),
+        Some(&MutationError {
+            mutation: "power".to_owned(),
+            errors: "Can't turn off".to_owned(),
+        })
+    );
+}
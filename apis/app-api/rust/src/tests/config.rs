@@ -0,0 +1,109 @@
+/*
+ * Copyright (C) 2018 Kubos Corporation
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use framework::app_config_from;
+use std::collections::HashMap;
+use std::fs;
+use tempfile::TempDir;
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct PayloadConfig {
+    port: u16,
+    #[serde(default)]
+    channels: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct MyConfig {
+    threshold: u32,
+    payload: PayloadConfig,
+}
+
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+struct DefaultConfig {
+    threshold: u32,
+}
+
+fn write_manifest(dir: &TempDir, contents: &str) -> ::std::path::PathBuf {
+    let path = dir.path().join("manifest.toml");
+    fs::write(&path, contents).unwrap();
+    path
+}
+
+#[test]
+fn config_good() {
+    let dir = TempDir::new().unwrap();
+    let manifest = write_manifest(
+        &dir,
+        r#"
+        name = "mission-app"
+        version = "1.0"
+        author = "user"
+
+        [config]
+        threshold = 5
+
+        [config.payload]
+        port = 9000
+        channels = ["a", "b"]
+        "#,
+    );
+
+    let config: MyConfig = app_config_from(&manifest).unwrap();
+    assert_eq!(
+        config,
+        MyConfig {
+            threshold: 5,
+            payload: PayloadConfig {
+                port: 9000,
+                channels: vec!["a".to_owned(), "b".to_owned()],
+            },
+        }
+    );
+
+    // Or as a generic table
+    let config: HashMap<String, ::toml::Value> = app_config_from(&manifest).unwrap();
+    assert_eq!(config["threshold"].as_integer(), Some(5));
+}
+
+#[test]
+fn config_missing_section() {
+    let dir = TempDir::new().unwrap();
+    let manifest = write_manifest(&dir, "name = \"mission-app\"\n");
+
+    let config: DefaultConfig = app_config_from(&manifest).unwrap();
+    assert_eq!(config, DefaultConfig::default());
+
+    let err = app_config_from::<MyConfig>(&manifest).unwrap_err();
+    assert!(err.to_string().contains("missing field `threshold`"));
+}
+
+#[test]
+fn config_wrong_type() {
+    let dir = TempDir::new().unwrap();
+    let manifest = write_manifest(&dir, "[config]\nthreshold = \"five\"\n");
+
+    let err = app_config_from::<DefaultConfig>(&manifest).unwrap_err();
+    assert!(err.to_string().starts_with("Invalid app config in"));
+}
+
+#[test]
+fn config_no_manifest() {
+    let dir = TempDir::new().unwrap();
+    let err = app_config_from::<DefaultConfig>(&dir.path().join("manifest.toml")).unwrap_err();
+    assert!(err.to_string().starts_with("Failed to read"));
+}
@@ -15,22 +15,54 @@
  */
 
 use juniper::{FieldError, FieldResult, Value};
-use kubos_service;
+use kubos_service::{self, ErrorCode, ServiceError};
 
 pub struct Subsystem;
 type Context = kubos_service::Context<Subsystem>;
 
+#[derive(GraphQLEnum)]
+pub enum PowerState {
+    On,
+    Off,
+}
+
+#[derive(GraphQLObject)]
+pub struct Echo {
+    pub text: String,
+    pub count: i32,
+    pub tags: Vec<String>,
+}
+
+#[derive(GraphQLObject)]
+pub struct PowerResponse {
+    pub success: bool,
+    pub errors: String,
+    pub state: String,
+}
+
 pub struct QueryRoot;
 
 /// Base GraphQL query model
 graphql_object!(QueryRoot: Context as "Query" |&self| {
-    field ping(fail = false: bool) -> FieldResult<String>
+    field ping(fail = false: bool, timeout = false: bool) -> FieldResult<String>
     {
+        if timeout {
+            let chain = format_err!("UART timed out").context("Query timed out");
+            return Err(ServiceError::from_fail(ErrorCode::Timeout, chain)
+                .subsystem("mock")
+                .into());
+        }
+
         match fail {
             true => Err(FieldError::new("Query failed", Value::null())),
             false => Ok(String::from("query"))
         }
     }
+
+    field echo(text: String, count: i32, tags: Option<Vec<String>>) -> FieldResult<Echo>
+    {
+        Ok(Echo { text, count, tags: tags.unwrap_or_default() })
+    }
 });
 
 pub struct MutationRoot;
@@ -41,4 +73,12 @@ graphql_object!(MutationRoot: Context as "Mutation" |&self| {
         {
             Ok(String::from("mutation"))
         }
+
+    field power(state: PowerState) -> FieldResult<PowerResponse>
+        {
+            Ok(match state {
+                PowerState::On => PowerResponse { success: true, errors: "".to_owned(), state: "ON".to_owned() },
+                PowerState::Off => PowerResponse { success: false, errors: "Can't turn off".to_owned(), state: "ON".to_owned() },
+            })
+        }
 });
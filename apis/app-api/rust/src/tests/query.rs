@@ -17,7 +17,8 @@
 use super::mock_service::*;
 use kubos_service::Service;
 use kubos_system::Config as ServiceConfig;
-use query::query;
+use query::{query, ErrorLocation, PathSegment, QueryError, ResponseError};
+use serde_json;
 
 use std::time::Duration;
 use tempfile::TempDir;
@@ -37,7 +38,8 @@ fn query_good() {
         });
 
     let result = query(
-        ServiceConfig::new_from_path("mock-service", config_file.to_string_lossy().to_string()),
+        ServiceConfig::new_from_path("mock-service", config_file.to_string_lossy().to_string())
+            .unwrap(),
         request,
         Some(Duration::from_secs(1)),
     ).unwrap();
@@ -56,52 +58,90 @@ fn query_error() {
         }"#;
 
     let result = query(
-        ServiceConfig::new_from_path("mock-service", config_file.to_string_lossy().to_string()),
+        ServiceConfig::new_from_path("mock-service", config_file.to_string_lossy().to_string())
+            .unwrap(),
         request,
         Some(Duration::from_secs(1)),
     ).unwrap_err();
 
     let result_str = format!("{}", result);
 
-    assert_eq!(result_str, "{\"message\":\"Query failed\",\"locations\":[{\"line\":2,\"column\":13}],\"path\":[\"ping\"]}");
+    assert_eq!(result_str, "Query failed");
+
+    let errors = result.downcast_ref::<QueryError>().unwrap().errors();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].path, vec![PathSegment::Field("ping".to_owned())]);
+    assert_eq!(errors[0].locations, vec![ErrorLocation { line: 2, column: 13 }]);
+    assert_eq!(errors[0].code(), Some("INTERNAL_ERROR"));
 }
 
 #[test]
-fn query_bad_service() {
+fn query_error_code() {
     let config_dir = TempDir::new().unwrap();
     let config_file = config_dir.path().join("config.toml");
-    mock_service!(config_file, "0.0.0.0", 8763);
+    mock_service!(config_file, "0.0.0.0", 8761);
 
     let request = r#"{
-            ping
+            ping(timeout: true)
         }"#;
 
     let result = query(
-        ServiceConfig::new_from_path("fake-service", config_file.to_string_lossy().to_string()),
+        ServiceConfig::new_from_path("mock-service", config_file.to_string_lossy().to_string())
+            .unwrap(),
         request,
         Some(Duration::from_secs(1)),
     ).unwrap_err();
 
-    let result_str = format!("{}", result);
-
-    assert_eq!(result_str, "Connection refused (os error 111)");
+    let error = result.downcast_ref::<QueryError>().unwrap();
+    assert!(error.has_code("TIMEOUT"));
+    assert_eq!(error.errors()[0].extensions.subsystem, Some("mock".to_owned()));
+    assert_eq!(error.errors()[0].extensions.causes, vec!["UART timed out".to_owned()]);
 }
 
 #[test]
-fn query_bad_file() {
+fn query_bad_argument() {
+    let config_dir = TempDir::new().unwrap();
+    let config_file = config_dir.path().join("config.toml");
+    mock_service!(config_file, "0.0.0.0", 8760);
+
     let request = r#"{
-            ping
+            ping(fail: "yes")
         }"#;
 
     let result = query(
-        ServiceConfig::new_from_path("mock-service", "/fake/path".to_string()),
+        ServiceConfig::new_from_path("mock-service", config_file.to_string_lossy().to_string())
+            .unwrap(),
         request,
         Some(Duration::from_secs(1)),
     ).unwrap_err();
 
-    let result_str = format!("{}", result);
+    let error = result.downcast_ref::<QueryError>().unwrap();
+    assert!(error.has_code("GRAPHQL_VALIDATION_FAILED"));
+}
+
+#[test]
+fn query_bad_service() {
+    let config_dir = TempDir::new().unwrap();
+    let config_file = config_dir.path().join("config.toml");
+    mock_service!(config_file, "0.0.0.0", 8763);
+
+    let path = config_file.to_string_lossy().to_string();
+    let result = ServiceConfig::new_from_path("fake-service", path.clone()).unwrap_err();
+
+    assert_eq!(
+        format!("{}", result),
+        format!("No [fake-service] section in {}", path)
+    );
+}
+
+#[test]
+fn query_bad_file() {
+    let result = ServiceConfig::new_from_path("mock-service", "/fake/path".to_string()).unwrap_err();
 
-    assert_eq!(result_str, "Connection refused (os error 111)");
+    assert_eq!(
+        format!("{}", result),
+        "Failed to read /fake/path: No such file or directory (os error 2)"
+    );
 }
 
 #[test]
@@ -119,10 +159,29 @@ fn query_mutation() {
         });
 
     let result = query(
-        ServiceConfig::new_from_path("mock-service", config_file.to_string_lossy().to_string()),
+        ServiceConfig::new_from_path("mock-service", config_file.to_string_lossy().to_string())
+            .unwrap(),
         request,
         Some(Duration::from_secs(1)),
     ).unwrap();
 
     assert_eq!(result, expected);
 }
+
+#[test]
+fn response_error_list_path() {
+    let error: ResponseError = serde_json::from_value(json!({
+        "message": "Failed to read telemetry",
+        "locations": [{ "line": 3, "column": 17 }],
+        "path": ["antennas", 2, "deployed"],
+    })).unwrap();
+
+    assert_eq!(
+        error.path,
+        vec![
+            PathSegment::Field("antennas".to_owned()),
+            PathSegment::Index(2),
+            PathSegment::Field("deployed".to_owned()),
+        ]
+    );
+}
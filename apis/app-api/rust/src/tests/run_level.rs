@@ -0,0 +1,86 @@
+/*
+ * Copyright (C) 2018 Kubos Corporation
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use failure::Error;
+use framework::*;
+use std::cell::RefCell;
+
+#[derive(Default)]
+struct RecordingApp {
+    calls: RefCell<Vec<String>>,
+}
+
+impl AppHandler for RecordingApp {
+    fn on_boot(&self, args: Vec<String>) -> Result<(), Error> {
+        self.calls.borrow_mut().push(format!("boot {:?}", args));
+        Ok(())
+    }
+
+    fn on_command(&self, args: Vec<String>) -> Result<(), Error> {
+        self.calls.borrow_mut().push(format!("command {:?}", args));
+        Ok(())
+    }
+
+    fn on_tick(&self, _args: Vec<String>) -> Result<(), Error> {
+        bail!("tick failed")
+    }
+}
+
+#[test]
+fn parse_run_levels() {
+    for level in &[
+        RunLevel::OnBoot,
+        RunLevel::OnCommand,
+        RunLevel::OnUpgrade,
+        RunLevel::OnTick,
+    ] {
+        assert_eq!(&level.to_string().parse::<RunLevel>().unwrap(), level);
+    }
+}
+
+#[test]
+fn parse_unknown_run_level() {
+    let err = "OnLaunch".parse::<RunLevel>().unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Unknown run level was requested - OnLaunch. Available run levels: OnBoot, OnCommand, OnUpgrade, OnTick"
+    );
+}
+
+#[test]
+fn run_handler_dispatch() {
+    let app = RecordingApp::default();
+
+    run_handler(&app, &RunLevel::OnBoot, vec!["a".to_owned()]).unwrap();
+    run_handler(&app, &RunLevel::OnCommand, vec![]).unwrap();
+    // Handlers which aren't implemented do nothing
+    run_handler(&app, &RunLevel::OnUpgrade, vec![]).unwrap();
+    assert!(app.on_shutdown(::std::time::Instant::now()).is_ok());
+
+    assert_eq!(
+        app.calls.into_inner(),
+        vec!["boot [\"a\"]".to_owned(), "command []".to_owned()]
+    );
+}
+
+#[test]
+fn run_handler_error() {
+    let app = RecordingApp::default();
+
+    let err = run_handler(&app, &RunLevel::OnTick, vec![]).unwrap_err();
+    assert_eq!(err.to_string(), "tick failed");
+    assert!(!shutdown_requested());
+}
@@ -0,0 +1,110 @@
+//
+// Copyright (C) 2018 Kubos Corporation
+//
+// Licensed under the Apache License, Version 2.0 (the "License")
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Command-line utility for working with the KubOS config file
+//!
+//! Usage: `kubos-config check [-c CONFIG]`
+//!
+//! `check` validates the section of every known service present in the config file
+//! (`/home/system/etc/config.toml` by default), printing the result for each service.
+//! The exit code is non-zero if any problems were found.
+//!
+//! Each service's settings are checked by running its executable with `--check-config`,
+//! so the service executables must be on the `PATH`. New services are registered by
+//! adding them to `KNOWN_SERVICES`.
+
+extern crate getopts;
+extern crate kubos_system;
+
+use getopts::Options;
+use kubos_system::{check_file, Validator, DEFAULT_PATH};
+use std::env;
+use std::process;
+
+// Every service which may have a section in the config file, and how to validate it
+const KNOWN_SERVICES: &[(&str, Validator)] = &[
+    ("app-service", Validator::Executable("kubos-app-service")),
+    ("telemetry-service", Validator::Executable("telemetry-service")),
+    ("file-transfer-service", Validator::Executable("file-service")),
+    ("shell-service", Validator::Executable("shell-service")),
+    ("monitor-service", Validator::Executable("monitor-service")),
+    ("ethernet-service", Validator::Executable("ethernet-service")),
+    ("iobc-supervisor-service", Validator::Executable("iobc-supervisor-service")),
+    ("isis-ants-service", Validator::Executable("isis-ants-service")),
+    ("mai400-service", Validator::Executable("mai400-service")),
+    ("novatel-oem6-service", Validator::Executable("novatel-oem6-service")),
+    ("pumpkin-mcu-service", Validator::Common),
+];
+
+fn usage(opts: &Options) -> String {
+    opts.usage("Usage: kubos-config check [options]")
+}
+
+fn main() {
+    let args: Vec<String> = env::args().collect();
+
+    let mut opts = Options::new();
+    opts.optopt("c", "config", "Path to config file", "CONFIG");
+    opts.optflag("h", "help", "Print this help menu");
+
+    let matches = match opts.parse(&args[1..]) {
+        Ok(matches) => matches,
+        Err(err) => {
+            eprintln!("{}\n{}", err, usage(&opts));
+            process::exit(2);
+        }
+    };
+
+    if matches.opt_present("h") {
+        println!("{}", usage(&opts));
+        return;
+    }
+
+    match matches.free.get(0).map(|cmd| cmd.as_str()) {
+        Some("check") => {}
+        _ => {
+            eprintln!("{}", usage(&opts));
+            process::exit(2);
+        }
+    }
+
+    let path = matches
+        .opt_str("c")
+        .unwrap_or_else(|| DEFAULT_PATH.to_owned());
+
+    let results = match check_file(&path, KNOWN_SERVICES) {
+        Ok(results) => results,
+        Err(err) => {
+            eprintln!("{}", err);
+            process::exit(1);
+        }
+    };
+
+    let mut failed = false;
+    for (name, result) in results {
+        match result {
+            Ok(()) => println!("{}: OK", name),
+            Err(err) => {
+                failed = true;
+                println!("{}", err);
+            }
+        }
+    }
+
+    if failed {
+        process::exit(1);
+    }
+}
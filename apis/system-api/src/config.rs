@@ -13,11 +13,17 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 //
+use discovery::Discovery;
+use failure::Error;
 use getopts::Options;
+use settings::{
+    parse_settings, parse_storage, ServiceSettings, StorageSettings, CHECK_CONFIG_FLAG,
+};
 use std::env;
 use std::fs::File;
 use std::io;
 use std::io::prelude::*;
+use std::net::IpAddr;
 use toml;
 use toml::Value;
 
@@ -29,6 +35,7 @@ pub static DEFAULT_IP: &str = "127.0.0.1";
 pub const DEFAULT_PORT: u16 = 8080;
 
 #[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
 /// A simple address consisting of an IP address and port number
 pub struct Address {
     ip: Option<String>,
@@ -75,9 +82,17 @@ impl Address {
 /// ```
 ///
 /// When `addr`, `addr.ip`, or `addr.port` are not provided in the config file, the default IP
-/// `"127.0.0.1"` and default port `8080` are used instead.
+/// `"127.0.0.1"` and default port `8080` are used instead. The category itself must be present,
+/// so that a misspelled name is reported rather than silently given the default address.
+///
+/// Services should load their configuration with [`load_settings`], which also validates the
+/// rest of their section.
+///
+/// [`load_settings`]: #method.load_settings
 #[derive(Clone, Debug)]
 pub struct Config {
+    name: String,
+    path: Option<String>,
     addr: Address,
     raw: Value,
 }
@@ -85,6 +100,8 @@ pub struct Config {
 impl Default for Config {
     fn default() -> Self {
         Config {
+            name: "".to_string(),
+            path: None,
             addr: Address::default(),
             raw: Value::String("".to_string()),
         }
@@ -96,28 +113,204 @@ impl Config {
     /// file or the path passed as the '-c' or '--config' option to this
     /// executable.
     ///
+    /// Equivalent to [`load`].
+    ///
     /// # Arguments
     /// `name` - Category name used as a key in the config file
-    pub fn new(name: &str) -> Self {
-        Self::new_from_path(name, get_config_path())
+    ///
+    /// [`load`]: #method.load
+    pub fn new(name: &str) -> Result<Self, Error> {
+        Self::load(name)
     }
 
     /// Creates and parses configuration data from the passed in configuration
     /// path.
+    ///
+    /// Equivalent to [`load_from_path`].
+    ///
+    /// # Arguments
+    /// `name` - Category name used as a key in the config file
+    /// `path` - Path to configuration file
+    ///
+    /// [`load_from_path`]: #method.load_from_path
+    pub fn new_from_path(name: &str, path: String) -> Result<Self, Error> {
+        Self::load_from_path(name, path)
+    }
+
+    /// Loads configuration data from the system configuration file or the path
+    /// passed as the '-c' or '--config' option to this executable.
+    ///
+    /// Returns an error if the file cannot be read or parsed, if it has no
+    /// category called `name`, or if it contains an invalid address.
+    ///
+    /// # Arguments
+    /// `name` - Category name used as a key in the config file
+    pub fn load(name: &str) -> Result<Self, Error> {
+        Self::load_from_path(name, get_config_path())
+    }
+
+    /// Loads configuration data from the passed in configuration path.
+    ///
+    /// Returns an error if the file cannot be read or parsed, if it has no
+    /// category called `name`, or if it contains an invalid address.
+    ///
     /// # Arguments
     /// `name` - Category name used as a key in the config file
     /// `path` - Path to configuration file
-    pub fn new_from_path(name: &str, path: String) -> Self {
-        parse_config_file(name, path).unwrap_or(Config::default())
+    pub fn load_from_path(name: &str, path: String) -> Result<Self, Error> {
+        let contents = get_file_data(path.clone())
+            .map_err(|err| format_err!("Failed to read {}: {}", path, err))?;
+        let mut config = parse_config_str(name, &contents, &path)?;
+        config.path = Some(path);
+        Ok(config)
+    }
+
+    /// Loads a service's configuration and validates its settings, for use when the service
+    /// starts up.
+    ///
+    /// The configuration is read from the system configuration file or the path passed as the
+    /// '-c' or '--config' option to this executable. Returns an error if it can't be loaded or
+    /// the settings are invalid.
+    ///
+    /// ### Examples
+    ///
+    /// ```rust,no_run
+    /// # extern crate kubos_system;
+    /// # #[macro_use]
+    /// # extern crate serde_derive;
+    /// use kubos_system::{Config, ServiceSettings};
+    ///
+    /// #[derive(Deserialize)]
+    /// struct MySettings {
+    ///     bus: String,
+    /// }
+    ///
+    /// impl ServiceSettings for MySettings {}
+    ///
+    /// # fn main() {
+    /// let (config, settings) = Config::load_settings::<MySettings>("my-service").unwrap();
+    /// println!("Bus: {}", settings.bus);
+    /// # }
+    /// ```
+    pub fn load_settings<T: ServiceSettings>(name: &str) -> Result<(Self, T), Error> {
+        Self::load_settings_from_path(name, get_config_path())
+    }
+
+    /// Same as [`load_settings`], but reads the configuration from the given path
+    ///
+    /// [`load_settings`]: #method.load_settings
+    pub fn load_settings_from_path<T: ServiceSettings>(
+        name: &str,
+        path: String,
+    ) -> Result<(Self, T), Error> {
+        let config = Self::load_from_path(name, path)?;
+        let settings = config.settings::<T>()?;
+        Ok((config, settings))
     }
 
     /// Creates and parses configuration data from the passed in configuration
     /// string.
+    ///
+    /// Returns an error if the string cannot be parsed, if it has no category
+    /// called `name`, or if it contains an invalid address.
+    ///
     /// # Arguments
     /// `name` - Category name used as a key in the config
     /// `config` - Config data as a string
-    pub fn new_from_str(name: &str, config: &str) -> Self {
-        parse_config_str(name, config).unwrap_or(Config::default())
+    pub fn new_from_str(name: &str, config: &str) -> Result<Self, Error> {
+        parse_config_str(name, config, "configuration string")
+    }
+
+    /// Loads configuration data like [`load`], but with the address taken from the
+    /// service's announcement if the service is currently running. This allows clients
+    /// to find a service even if its port has changed since the config file was written.
+    ///
+    /// # Arguments
+    /// `name` - Category name used as a key in the config file
+    ///
+    /// [`load`]: #method.load
+    pub fn discover(name: &str) -> Result<Self, Error> {
+        Ok(Self::load(name)?.discovered(&Discovery::new()))
+    }
+
+    /// Replaces this configuration's address with the one announced in the given discovery
+    /// directory, if the service is currently running. Otherwise the configuration is
+    /// returned unchanged.
+    pub fn discovered(mut self, discovery: &Discovery) -> Self {
+        match discovery.lookup(&self.name) {
+            Ok(ref record) if record.is_alive() => {
+                self.addr = Address {
+                    ip: Some(record.ip.clone()),
+                    port: Some(record.port),
+                };
+            }
+            Ok(_) => debug!("{} is not running. Using the configured address", self.name),
+            Err(err) => debug!("{}. Using the configured address", err),
+        }
+        self
+    }
+
+    fn named(name: &str) -> Self {
+        Config {
+            name: name.to_string(),
+            ..Config::default()
+        }
+    }
+
+    /// Returns the category name this configuration was loaded for
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the path of the file this configuration was loaded from, if any
+    pub fn path(&self) -> Option<&str> {
+        self.path.as_ref().map(|path| path.as_str())
+    }
+
+    /// Re-reads this configuration from the file it was originally loaded from.
+    ///
+    /// Returns an error if the file cannot be read or parsed, if the category is no longer
+    /// present, or if it contains an invalid address.
+    pub fn reload(&self) -> Result<Config, Error> {
+        match self.path {
+            Some(ref path) => Self::load_from_path(&self.name, path.clone()),
+            None => bail!("Configuration for {} was not loaded from a file", self.name),
+        }
+    }
+
+    /// Deserializes and validates this category's settings into the given type.
+    ///
+    /// Returns an error describing any missing, unknown, mistyped or out-of-range keys.
+    ///
+    /// ### Examples
+    ///
+    /// ```rust,no_run
+    /// # extern crate kubos_system;
+    /// # #[macro_use]
+    /// # extern crate serde_derive;
+    /// use kubos_system::{Config, ServiceSettings};
+    ///
+    /// #[derive(Deserialize)]
+    /// struct MySettings {
+    ///     bus: String,
+    /// }
+    ///
+    /// impl ServiceSettings for MySettings {}
+    ///
+    /// # fn main() {
+    /// let config = Config::load("my-service").unwrap();
+    /// let settings = config.settings::<MySettings>().unwrap();
+    /// println!("Bus: {}", settings.bus);
+    /// # }
+    /// ```
+    pub fn settings<T: ServiceSettings>(&self) -> Result<T, Error> {
+        parse_settings(self)
+    }
+
+    /// Returns the settings for this category's persistent key/value storage,
+    /// or `None` if no `storage` section is present
+    pub fn storage(&self) -> Result<Option<StorageSettings>, Error> {
+        parse_storage(self)
     }
 
     /// Returns the configured hosturl string in the following
@@ -136,7 +329,7 @@ impl Config {
     /// ```rust,no_run
     /// use kubos_system::Config;
     ///
-    /// let config = Config::new("example-service");
+    /// let config = Config::load("example-service").unwrap();
     /// let raw = config.raw();
     /// let bus = raw["bus"].as_str();
     /// ```
@@ -156,24 +349,24 @@ impl Config {
     }
 }
 
-fn get_config_path() -> String {
+/// Returns the path passed as the '-c' or '--config' option to this executable, if any
+pub fn config_path_arg() -> Option<String> {
     let args: Vec<String> = env::args().collect();
 
     let mut opts = Options::new();
     opts.optopt("c", "config", "Path to config file", "CONFIG");
-    let matches = match opts.parse(&args[1..]) {
-        Ok(m) => m,
-        Err(_) => {
-            // suppress errors so applications using Config can have their own Options
-            return DEFAULT_PATH.to_string();
-        }
-    };
-    match matches.opt_str("c") {
-        Some(s) => s,
-        None => DEFAULT_PATH.to_string(),
+    opts.optflag("", CHECK_CONFIG_FLAG, "Validate the config file and exit");
+    match opts.parse(&args[1..]) {
+        Ok(matches) => matches.opt_str("c"),
+        // suppress errors so applications using Config can have their own Options
+        Err(_) => None,
     }
 }
 
+fn get_config_path() -> String {
+    config_path_arg().unwrap_or_else(|| DEFAULT_PATH.to_string())
+}
+
 fn get_file_data(path: String) -> Result<String, io::Error> {
     let mut contents = String::new();
     let mut file = File::open(path)?;
@@ -181,14 +374,25 @@ fn get_file_data(path: String) -> Result<String, io::Error> {
     Ok(contents)
 }
 
-fn parse_config_file(name: &str, path: String) -> Result<Config, toml::de::Error> {
-    let contents = get_file_data(path).unwrap_or("".to_string());
-    parse_config_str(name, &contents)
+// `source` names where the contents came from, for error messages
+fn parse_config_str(name: &str, contents: &str, source: &str) -> Result<Config, Error> {
+    let data: Value =
+        toml::from_str(contents).map_err(|err| format_err!("Failed to parse {}: {}", source, err))?;
+    if data.get(name).is_none() {
+        bail!("No [{}] section in {}", name, source);
+    }
+    let config = parse_config_value(name, &data)
+        .map_err(|err| format_err!("Failed to parse {}: {}", source, err))?;
+
+    if config.addr.ip().parse::<IpAddr>().is_err() {
+        bail!("Invalid IP address for {}: {}", name, config.addr.ip());
+    }
+
+    Ok(config)
 }
 
-fn parse_config_str(name: &str, contents: &str) -> Result<Config, toml::de::Error> {
-    let data: Value = toml::from_str(&contents)?;
-    let mut config = Config::default();
+fn parse_config_value(name: &str, data: &Value) -> Result<Config, toml::de::Error> {
+    let mut config = Config::named(name);
 
     if let Some(data) = data.get(name) {
         if let Some(address) = data.get("addr") {
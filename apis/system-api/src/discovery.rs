@@ -0,0 +1,245 @@
+//
+// Copyright (C) 2018 Kubos Corporation
+//
+// Licensed under the Apache License, Version 2.0 (the "License")
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use failure::Error;
+use files::atomic_write;
+use nix::errno::Errno;
+use nix::sys::signal::kill;
+use nix::unistd::Pid;
+use std::env;
+use std::fs;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use toml;
+
+/// The default directory which services announce themselves in
+pub static DEFAULT_DISCOVERY_DIR: &str = "/var/run/kubos/services";
+/// Environment variable which overrides the discovery directory
+pub static DISCOVERY_DIR_ENV: &str = "KUBOS_DISCOVERY_DIR";
+/// How often running services refresh their announcements
+pub const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(10);
+/// How long an announcement stays valid without being refreshed
+pub const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(30);
+
+const RECORD_EXTENSION: &str = "toml";
+
+/// A running service's announcement of where and what it is
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ServiceRecord {
+    /// Name of the service, as used in the config file
+    pub name: String,
+    /// IP address the service is listening on
+    pub ip: String,
+    /// Port the service is listening on
+    pub port: u16,
+    /// Version of KubOS the service is running under
+    pub version: String,
+    /// Hash of the service's GraphQL schema, which changes whenever its queries or mutations do
+    pub schema_hash: String,
+    /// Process ID of the service
+    pub pid: u32,
+    /// Time the service was started, in seconds since the UNIX epoch
+    pub started: f64,
+    /// Time the service last refreshed this record, in seconds since the UNIX epoch
+    pub heartbeat: f64,
+}
+
+impl ServiceRecord {
+    /// Creates a record for the current process, started and last refreshed now
+    ///
+    /// # Arguments
+    /// `name` - Name of the service
+    /// `addr` - Address the service is listening on
+    /// `version` - Version of KubOS the service is running under
+    /// `schema_hash` - Hash of the service's GraphQL schema
+    pub fn new(name: &str, addr: &SocketAddr, version: &str, schema_hash: &str) -> Self {
+        let now = epoch_secs();
+        ServiceRecord {
+            name: name.to_owned(),
+            ip: addr.ip().to_string(),
+            port: addr.port(),
+            version: version.to_owned(),
+            schema_hash: schema_hash.to_owned(),
+            pid: process::id(),
+            started: now,
+            heartbeat: now,
+        }
+    }
+
+    /// Marks the record as refreshed now
+    pub fn refresh(&mut self) {
+        self.heartbeat = epoch_secs();
+    }
+
+    /// Returns the service's address in the same format as `Config::hosturl`
+    pub fn hosturl(&self) -> String {
+        format!("{}:{}", self.ip, self.port)
+    }
+
+    /// Whether the service's process is still running and has refreshed its
+    /// record within the last `HEARTBEAT_TIMEOUT`
+    pub fn is_alive(&self) -> bool {
+        let age = epoch_secs() - self.heartbeat;
+        if age > as_secs(HEARTBEAT_TIMEOUT) {
+            return false;
+        }
+
+        match kill(Pid::from_raw(self.pid as i32), None) {
+            Ok(_) => true,
+            // The process exists, but belongs to another user
+            Err(::nix::Error::Sys(Errno::EPERM)) => true,
+            Err(_) => false,
+        }
+    }
+}
+
+/// Directory of service announcements.
+///
+/// Each running service keeps a [`ServiceRecord`] in `<dir>/<service-name>.toml`, which is
+/// written when the service starts and refreshed every `HEARTBEAT_INTERVAL`. Clients look
+/// services up by name in order to find their current address and to check that they are
+/// still running.
+///
+/// [`ServiceRecord`]: struct.ServiceRecord.html
+///
+/// ### Examples
+///
+/// ```rust,no_run
+/// use kubos_system::Discovery;
+///
+/// match Discovery::new().lookup("telemetry-service") {
+///     Ok(ref record) if record.is_alive() => println!("Found at {}", record.hosturl()),
+///     Ok(_) => println!("The service is down"),
+///     Err(err) => println!("The service hasn't been started: {}", err),
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Discovery {
+    dir: PathBuf,
+}
+
+impl Default for Discovery {
+    fn default() -> Self {
+        Discovery::new()
+    }
+}
+
+impl Discovery {
+    /// Uses the directory given by the `KUBOS_DISCOVERY_DIR` environment variable,
+    /// or `/var/run/kubos/services` if it isn't set
+    pub fn new() -> Self {
+        match env::var(DISCOVERY_DIR_ENV) {
+            Ok(ref dir) if !dir.is_empty() => Discovery::at(dir),
+            _ => Discovery::at(DEFAULT_DISCOVERY_DIR),
+        }
+    }
+
+    /// Uses the given directory
+    pub fn at<P: AsRef<Path>>(dir: P) -> Self {
+        Discovery {
+            dir: dir.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Returns the directory announcements are kept in
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Publishes (or refreshes) a service's record, replacing any previous record
+    /// with the same name
+    pub fn announce(&self, record: &ServiceRecord) -> Result<(), Error> {
+        fs::create_dir_all(&self.dir)
+            .map_err(|err| format_err!("Failed to create {}: {}", self.dir.display(), err))?;
+
+        let contents = toml::to_string(record)?;
+        let path = self.path(&record.name)?;
+        atomic_write(&path, contents.as_bytes())
+            .map_err(|err| format_err!("Failed to write {}: {}", path.display(), err))?;
+        Ok(())
+    }
+
+    /// Returns the most recent record announced by the named service.
+    ///
+    /// The record is returned even if the service has since stopped; use
+    /// `ServiceRecord::is_alive` to check whether it is still running.
+    pub fn lookup(&self, name: &str) -> Result<ServiceRecord, Error> {
+        let path = self.path(name)?;
+        let contents = fs::read_to_string(&path)
+            .map_err(|err| format_err!("Service {} has not been announced: {}", name, err))?;
+        toml::from_str(&contents)
+            .map_err(|err| format_err!("Failed to parse {}: {}", path.display(), err))
+    }
+
+    /// Returns the records of all announced services, sorted by name.
+    ///
+    /// Unreadable records are skipped.
+    pub fn services(&self) -> Result<Vec<ServiceRecord>, Error> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(ref err) if err.kind() == ::std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(err) => bail!("Failed to read {}: {}", self.dir.display(), err),
+        };
+
+        let mut records: Vec<ServiceRecord> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.extension()
+                    .map_or(false, |ext| ext == RECORD_EXTENSION)
+            })
+            .filter_map(|path| match fs::read_to_string(&path) {
+                Ok(contents) => toml::from_str(&contents)
+                    .map_err(|err| warn!("Ignoring {}: {}", path.display(), err))
+                    .ok(),
+                Err(err) => {
+                    warn!("Ignoring {}: {}", path.display(), err);
+                    None
+                }
+            })
+            .collect();
+        records.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(records)
+    }
+
+    /// Removes the named service's record
+    pub fn withdraw(&self, name: &str) -> Result<(), Error> {
+        let path = self.path(name)?;
+        fs::remove_file(&path)
+            .map_err(|err| format_err!("Failed to remove {}: {}", path.display(), err))
+    }
+
+    fn path(&self, name: &str) -> Result<PathBuf, Error> {
+        if name.is_empty() || name.contains('/') || name.starts_with('.') {
+            bail!("Invalid service name: {:?}", name);
+        }
+        Ok(self.dir.join(format!("{}.{}", name, RECORD_EXTENSION)))
+    }
+}
+
+fn epoch_secs() -> f64 {
+    as_secs(
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default(),
+    )
+}
+
+fn as_secs(duration: Duration) -> f64 {
+    duration.as_secs() as f64 + f64::from(duration.subsec_nanos()) / 1_000_000_000.0
+}
@@ -23,14 +23,26 @@ extern crate failure;
 
 extern crate getopts;
 #[macro_use]
+extern crate log;
+extern crate nix;
+extern crate serde;
+#[macro_use]
 extern crate serde_derive;
 extern crate toml;
 
 mod config;
+mod discovery;
+mod files;
+mod settings;
 mod uboot;
+mod watcher;
 
 pub use config::*;
+pub use discovery::*;
+pub use files::atomic_write;
+pub use settings::*;
 pub use uboot::UBootVars;
+pub use watcher::{ConfigWatcher, WATCH_INTERVAL};
 
 /// The name of the KubOS app service that can be used to derive service configuration
 pub const SERVICE_APP: &'static str = "app-service";
@@ -0,0 +1,229 @@
+//
+// Copyright (C) 2018 Kubos Corporation
+//
+// Licensed under the Apache License, Version 2.0 (the "License")
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Typed, validated settings for KubOS services
+
+use config::Config;
+use failure::Error;
+use serde::de::DeserializeOwned;
+use std::env;
+use std::fmt::Display;
+use std::process::Command;
+use toml;
+
+/// Settings which a service expects to find in its section of the config file.
+///
+/// Implementors declare their expected keys and types by deriving `Deserialize`
+/// (typically with `#[serde(deny_unknown_fields)]` so that misspelled keys are caught),
+/// and any range or consistency checks in `validate`.
+pub trait ServiceSettings: DeserializeOwned {
+    /// Checks value ranges and relationships which can't be expressed by types alone
+    fn validate(&self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// Settings for services which have no configuration beyond their address and storage.
+/// Any other keys in the service's section are reported as unknown.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct NoSettings {}
+
+impl ServiceSettings for NoSettings {}
+
+/// Returns an error if `value` does not fall within `min..=max`
+///
+/// # Arguments
+/// `key` - Name of the config key being checked, used in the error message
+/// `value` - Configured value
+/// `min` - Minimum allowed value
+/// `max` - Maximum allowed value
+pub fn check_range<T: PartialOrd + Display>(key: &str, value: T, min: T, max: T) -> Result<(), Error> {
+    if value < min || value > max {
+        bail!("{} must be between {} and {}, found {}", key, min, max, value);
+    }
+    Ok(())
+}
+
+/// Default maximum size, in bytes, of a service's persistent key/value storage
+pub const DEFAULT_STORAGE_SIZE: usize = 64 * 1024;
+
+/// Settings for a service's persistent key/value storage, read from the optional
+/// `[<service>.storage]` section of the config file
+///
+/// ```toml
+/// [my-service.storage]
+/// dir = "/home/system/kubos/storage"
+/// max_size = 65536
+/// ```
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct StorageSettings {
+    /// Directory in which the storage file, `<service>.json`, is kept
+    pub dir: String,
+    /// Maximum total size, in bytes, of all stored keys and values
+    pub max_size: Option<usize>,
+}
+
+impl StorageSettings {
+    /// Returns the maximum storage size, falling back to `DEFAULT_STORAGE_SIZE`
+    pub fn max_size(&self) -> usize {
+        self.max_size.unwrap_or(DEFAULT_STORAGE_SIZE)
+    }
+}
+
+/// Reads a service's storage settings, if it has any
+pub fn parse_storage(config: &Config) -> Result<Option<StorageSettings>, Error> {
+    match config.get("storage") {
+        Some(section) => {
+            let settings: StorageSettings = section.try_into().map_err(|err| {
+                format_err!("Invalid storage configuration for {}: {}", config.name(), err)
+            })?;
+            if settings.dir.is_empty() {
+                bail!("Invalid storage configuration for {}: dir must not be empty", config.name());
+            }
+            Ok(Some(settings))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Deserializes and validates a service's section of the config file
+pub fn parse_settings<T: ServiceSettings>(config: &Config) -> Result<T, Error> {
+    let mut section = match config.raw() {
+        toml::Value::Table(table) => table,
+        _ => toml::value::Table::new(),
+    };
+    // The address and storage sections are common to all services and are checked separately
+    section.remove("addr");
+    section.remove("storage");
+
+    let settings: T = toml::Value::Table(section)
+        .try_into()
+        .map_err(|err| format_err!("Invalid configuration for {}: {}", config.name(), err))?;
+    settings
+        .validate()
+        .map_err(|err| format_err!("Invalid configuration for {}: {}", config.name(), err))?;
+    parse_storage(config)?;
+
+    Ok(settings)
+}
+
+/// Name of the flag which asks a service to only validate its configuration and then exit.
+/// Used by `kubos-config check`.
+pub const CHECK_CONFIG_FLAG: &str = "check-config";
+
+/// Returns true if this executable was started with `--check-config`
+pub fn check_config_requested() -> bool {
+    let flag = format!("--{}", CHECK_CONFIG_FLAG);
+    env::args().skip(1).any(|arg| arg == flag)
+}
+
+/// Reports the result of loading a service's settings if the service was started with
+/// `--check-config`.
+///
+/// Returns `None` if the flag wasn't given, in which case the service should carry on as
+/// normal. Otherwise, any error is printed to stderr and the exit code the service should
+/// stop with is returned.
+///
+/// ### Examples
+///
+/// ```rust,no_run
+/// # extern crate kubos_system;
+/// # #[macro_use]
+/// # extern crate serde_derive;
+/// use kubos_system::{check_config_status, Config, ServiceSettings};
+/// use std::process;
+///
+/// #[derive(Deserialize)]
+/// struct MySettings {
+///     bus: String,
+/// }
+///
+/// impl ServiceSettings for MySettings {}
+///
+/// # fn main() {
+/// let loaded = Config::load_settings::<MySettings>("my-service");
+/// if let Some(code) = check_config_status(&loaded) {
+///     process::exit(code);
+/// }
+/// # }
+/// ```
+pub fn check_config_status<T>(loaded: &Result<T, Error>) -> Option<i32> {
+    if !check_config_requested() {
+        return None;
+    }
+    match *loaded {
+        Ok(_) => Some(0),
+        Err(ref err) => {
+            eprintln!("{}", err);
+            Some(1)
+        }
+    }
+}
+
+/// How `kubos-config check` validates a service's section of the config file
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Validator {
+    /// Only the address and storage settings common to all services are checked
+    Common,
+    /// The common settings are checked, and then the named executable is run with
+    /// `-c <path> --check-config` to check the rest of the service's settings
+    Executable(&'static str),
+}
+
+impl Validator {
+    /// Validates the named service's section of the config file at `path`
+    pub fn check(&self, name: &str, path: &str) -> Result<(), Error> {
+        let config = Config::load_from_path(name, path.to_owned())?;
+        parse_storage(&config)?;
+
+        if let Validator::Executable(executable) = *self {
+            let output = Command::new(executable)
+                .args(&["-c", path, &format!("--{}", CHECK_CONFIG_FLAG)])
+                .output()
+                .map_err(|err| format_err!("Failed to run {}: {}", executable, err))?;
+            if !output.status.success() {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                match stderr.trim() {
+                    "" => bail!("{} exited with {}", executable, output.status),
+                    message => bail!("{}", message),
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Validates each of the given services which has a section in the config file at `path`.
+///
+/// Returns an error if the file can't be read or isn't valid TOML. Otherwise, returns the
+/// name of each service checked along with the result of its validation.
+pub fn check_file(
+    path: &str,
+    services: &[(&str, Validator)],
+) -> Result<Vec<(String, Result<(), Error>)>, Error> {
+    let contents = ::std::fs::read_to_string(path)
+        .map_err(|err| format_err!("Failed to read {}: {}", path, err))?;
+    let data: toml::Value =
+        toml::from_str(&contents).map_err(|err| format_err!("Failed to parse {}: {}", path, err))?;
+
+    Ok(services
+        .iter()
+        .filter(|&&(name, _)| data.get(name).is_some())
+        .map(|&(name, validator)| (name.to_owned(), validator.check(name, path)))
+        .collect())
+}
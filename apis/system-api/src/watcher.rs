@@ -0,0 +1,144 @@
+/*
+ * Copyright (C) 2018 Kubos Corporation
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use config::Config;
+use failure::Error;
+use nix::sys::signal::{sigaction, SaFlags, SigAction, SigHandler, SigSet, Signal};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, Once, ONCE_INIT};
+use std::thread;
+use std::time::Duration;
+
+/// How often the watcher thread checks whether a reload has been requested
+pub const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+// Number of SIGHUP signals received by this process
+static SIGHUP_COUNT: AtomicUsize = AtomicUsize::new(0);
+static SIGHUP_INIT: Once = ONCE_INIT;
+
+extern "C" fn handle_sighup(_: i32) {
+    SIGHUP_COUNT.fetch_add(1, Ordering::SeqCst);
+}
+
+type Callback = Box<Fn(&Config) + Send>;
+
+/// Watches a configuration file and notifies subscribers whenever it is reloaded.
+///
+/// Reloads can be triggered explicitly with [`reload`], or, once [`watch`] has been called,
+/// by sending the process a `SIGHUP`. The file is re-parsed and validated before any
+/// subscribers are notified. If it is invalid, the previous configuration remains in effect.
+///
+/// Cloned watchers share the same configuration and subscribers.
+///
+/// Note: changes to a service's `addr` section only take effect after the service is restarted.
+///
+/// [`reload`]: #method.reload
+/// [`watch`]: #method.watch
+///
+/// ### Examples
+///
+/// ```rust,no_run
+/// use kubos_system::{Config, ConfigWatcher};
+///
+/// let watcher = ConfigWatcher::new(Config::load("example-service").unwrap());
+///
+/// watcher.subscribe(|config| {
+///     println!("New timeout: {:?}", config.get("timeout"));
+/// });
+///
+/// // Reload the configuration whenever a SIGHUP is received
+/// watcher.watch();
+/// ```
+#[derive(Clone)]
+pub struct ConfigWatcher {
+    current: Arc<Mutex<Config>>,
+    subscribers: Arc<Mutex<Vec<Callback>>>,
+}
+
+impl ConfigWatcher {
+    /// Creates a new watcher, starting with the given configuration
+    ///
+    /// # Arguments
+    /// `config` - Initial configuration. Reloads re-read the file this config was loaded from.
+    pub fn new(config: Config) -> Self {
+        ConfigWatcher {
+            current: Arc::new(Mutex::new(config)),
+            subscribers: Arc::new(Mutex::new(vec![])),
+        }
+    }
+
+    /// Returns a copy of the current configuration
+    pub fn config(&self) -> Config {
+        self.current.lock().unwrap().clone()
+    }
+
+    /// Registers a callback which will be called with the new configuration
+    /// after each successful reload
+    pub fn subscribe<F>(&self, callback: F)
+    where
+        F: Fn(&Config) + Send + 'static,
+    {
+        self.subscribers.lock().unwrap().push(Box::new(callback));
+    }
+
+    /// Re-reads and validates the configuration file, then notifies all subscribers.
+    ///
+    /// Returns the new configuration, or an error describing why the file could not be
+    /// loaded, in which case the current configuration is left unchanged.
+    pub fn reload(&self) -> Result<Config, Error> {
+        let config = self.config().reload()?;
+
+        *self.current.lock().unwrap() = config.clone();
+
+        for callback in self.subscribers.lock().unwrap().iter() {
+            callback(&config);
+        }
+
+        Ok(config)
+    }
+
+    /// Starts a background thread which reloads the configuration whenever
+    /// the process receives a `SIGHUP` signal
+    pub fn watch(&self) -> Result<thread::JoinHandle<()>, Error> {
+        let mut result = Ok(());
+        SIGHUP_INIT.call_once(|| {
+            let action = SigAction::new(
+                SigHandler::Handler(handle_sighup),
+                SaFlags::SA_RESTART,
+                SigSet::empty(),
+            );
+            result = unsafe { sigaction(Signal::SIGHUP, &action) }.map(|_| ());
+        });
+        result.map_err(|err| format_err!("Failed to install SIGHUP handler: {}", err))?;
+
+        let watcher = self.clone();
+        let mut seen = SIGHUP_COUNT.load(Ordering::SeqCst);
+
+        Ok(thread::spawn(move || loop {
+            thread::sleep(WATCH_INTERVAL);
+
+            let count = SIGHUP_COUNT.load(Ordering::SeqCst);
+            if count != seen {
+                seen = count;
+                let name = watcher.config().name().to_owned();
+                match watcher.reload() {
+                    Ok(_) => info!("Reloaded configuration for {}", name),
+                    Err(err) => warn!("Failed to reload configuration for {}: {}", name, err),
+                }
+            }
+        }))
+    }
+}
@@ -41,6 +41,7 @@ fn default_config() {
         )
     );
     assert_eq!(config.get("addr"), None);
+    assert_eq!(config.name(), "");
 }
 
 #[test]
@@ -52,8 +53,9 @@ fn new_from_str() {
     a = 1
     b = 2
     "#,
-    );
+    ).unwrap();
 
+    assert_eq!(config.name(), "category-1");
     assert_eq!(config.get("a"), Some(Value::Integer(1)));
     assert_eq!(config.get("b"), Some(Value::Integer(2)));
     assert_eq!(
@@ -90,7 +92,7 @@ fn new_from_file() {
     let config = kubos_system::Config::new_from_path(
         "category-1",
         file.path().to_string_lossy().to_string(),
-    );
+    ).unwrap();
 
     assert_eq!(config.get("a"), Some(Value::Integer(1)));
     assert_eq!(config.get("b"), Some(Value::Integer(2)));
@@ -106,7 +108,7 @@ fn override_ip() {
     [category-1.addr]
     ip = "10.0.1.1"
     "#,
-    );
+    ).unwrap();
     assert_eq!(
         config.hosturl(),
         format!("10.0.1.1:{}", kubos_system::DEFAULT_PORT)
@@ -121,7 +123,7 @@ fn override_port() {
     [category-1.addr]
     port = 9876
     "#,
-    );
+    ).unwrap();
     assert_eq!(
         config.hosturl(),
         format!("{}:9876", kubos_system::DEFAULT_IP)
@@ -137,7 +139,7 @@ fn override_ip_port() {
     ip = "10.0.1.1"
     port = 9876
     "#,
-    );
+    ).unwrap();
     assert_eq!(config.hosturl(), "10.0.1.1:9876");
 }
 
@@ -157,7 +159,7 @@ fn only_category_config() {
     c = 5
     d = 6
     "#,
-    );
+    ).unwrap();
 
     assert_eq!(config.get("root-a"), None);
     assert_eq!(config.get("root-b"), None);
@@ -168,3 +170,27 @@ fn only_category_config() {
     assert_eq!(config.get("c"), None);
     assert_eq!(config.get("d"), None);
 }
+
+#[test]
+fn new_from_str_errors() {
+    let err = kubos_system::Config::new_from_str("category-1", "[category-1").unwrap_err();
+    assert!(err.to_string().starts_with("Failed to parse"));
+
+    let err = kubos_system::Config::new_from_str("category-1", "[category-2]").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "No [category-1] section in configuration string"
+    );
+
+    let err = kubos_system::Config::new_from_str(
+        "category-1",
+        r#"
+    [category-1.addr]
+    ip = "not-an-ip"
+    "#,
+    ).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Invalid IP address for category-1: not-an-ip"
+    );
+}
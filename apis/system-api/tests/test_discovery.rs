@@ -0,0 +1,172 @@
+/*
+ * Copyright (C) 2018 Kubos Corporation
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+#![deny(warnings)]
+#![deny(warnings)]
+extern crate kubos_system;
+extern crate tempfile;
+
+use kubos_system::{Config, Discovery, ServiceRecord, HEARTBEAT_TIMEOUT};
+use std::fs;
+use std::process::Command;
+use tempfile::TempDir;
+
+fn record(name: &str, port: u16) -> ServiceRecord {
+    ServiceRecord::new(
+        name,
+        &format!("127.0.0.1:{}", port).parse().unwrap(),
+        "1.0.0",
+        "0123456789abcdef",
+    )
+}
+
+fn config(port: u16) -> Config {
+    Config::new_from_str(
+        "test-service",
+        &format!(
+            r#"
+            [test-service.addr]
+            ip = "127.0.0.1"
+            port = {}
+            "#,
+            port
+        ),
+    ).unwrap()
+}
+
+#[test]
+fn announce_lookup() {
+    let dir = TempDir::new().unwrap();
+    let discovery = Discovery::at(dir.path().join("services"));
+
+    let announced = record("test-service", 8181);
+    discovery.announce(&announced).unwrap();
+
+    let found = discovery.lookup("test-service").unwrap();
+    assert_eq!(found, announced);
+    assert_eq!(found.hosturl(), "127.0.0.1:8181");
+    assert!(found.is_alive());
+}
+
+#[test]
+fn announce_replaces() {
+    let dir = TempDir::new().unwrap();
+    let discovery = Discovery::at(dir.path());
+
+    discovery.announce(&record("test-service", 8181)).unwrap();
+    discovery.announce(&record("test-service", 8282)).unwrap();
+
+    assert_eq!(discovery.lookup("test-service").unwrap().port, 8282);
+    assert_eq!(discovery.services().unwrap().len(), 1);
+}
+
+#[test]
+fn lookup_missing() {
+    let dir = TempDir::new().unwrap();
+    let discovery = Discovery::at(dir.path());
+
+    assert!(discovery.lookup("test-service").is_err());
+    assert!(discovery.lookup("../test-service").is_err());
+}
+
+#[test]
+fn services_sorted() {
+    let dir = TempDir::new().unwrap();
+    let discovery = Discovery::at(dir.path());
+
+    discovery
+        .announce(&record("telemetry-service", 8020))
+        .unwrap();
+    discovery.announce(&record("app-service", 8000)).unwrap();
+    fs::write(dir.path().join("broken.toml"), "name = ").unwrap();
+
+    let names: Vec<String> = discovery
+        .services()
+        .unwrap()
+        .into_iter()
+        .map(|record| record.name)
+        .collect();
+    assert_eq!(names, vec!["app-service", "telemetry-service"]);
+}
+
+#[test]
+fn services_missing_dir() {
+    let dir = TempDir::new().unwrap();
+    let discovery = Discovery::at(dir.path().join("services"));
+
+    assert!(discovery.services().unwrap().is_empty());
+}
+
+#[test]
+fn withdraw() {
+    let dir = TempDir::new().unwrap();
+    let discovery = Discovery::at(dir.path());
+
+    discovery.announce(&record("test-service", 8181)).unwrap();
+    discovery.withdraw("test-service").unwrap();
+
+    assert!(discovery.lookup("test-service").is_err());
+    assert!(discovery.withdraw("test-service").is_err());
+}
+
+#[test]
+fn stale_heartbeat() {
+    let mut record = record("test-service", 8181);
+    record.heartbeat -= HEARTBEAT_TIMEOUT.as_secs() as f64 + 1.0;
+    assert!(!record.is_alive());
+
+    record.refresh();
+    assert!(record.is_alive());
+}
+
+#[test]
+fn exited_process() {
+    let mut child = Command::new("true").spawn().unwrap();
+    child.wait().unwrap();
+
+    let mut record = record("test-service", 8181);
+    record.pid = child.id();
+    assert!(!record.is_alive());
+}
+
+#[test]
+fn config_discovered() {
+    let dir = TempDir::new().unwrap();
+    let discovery = Discovery::at(dir.path());
+    discovery.announce(&record("test-service", 8282)).unwrap();
+
+    let config = config(8181).discovered(&discovery);
+    assert_eq!(config.hosturl(), "127.0.0.1:8282");
+}
+
+#[test]
+fn config_discovered_not_running() {
+    let dir = TempDir::new().unwrap();
+    let discovery = Discovery::at(dir.path());
+
+    assert_eq!(
+        config(8181).discovered(&discovery).hosturl(),
+        "127.0.0.1:8181"
+    );
+
+    let mut record = record("test-service", 8282);
+    record.heartbeat -= HEARTBEAT_TIMEOUT.as_secs() as f64 + 1.0;
+    discovery.announce(&record).unwrap();
+
+    assert_eq!(
+        config(8181).discovered(&discovery).hosturl(),
+        "127.0.0.1:8181"
+    );
+}
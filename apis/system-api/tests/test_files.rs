@@ -0,0 +1,43 @@
+/*
+ * Copyright (C) 2018 Kubos Corporation
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+#![deny(warnings)]
+extern crate kubos_system;
+extern crate tempfile;
+
+use kubos_system::atomic_write;
+use std::fs;
+use tempfile::TempDir;
+
+#[test]
+fn atomic_write_replaces() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("state.toml");
+
+    atomic_write(&path, b"first").unwrap();
+    atomic_write(&path, b"second").unwrap();
+
+    assert_eq!(fs::read_to_string(&path).unwrap(), "second");
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+}
+
+#[test]
+fn atomic_write_missing_dir() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("missing/state.toml");
+
+    assert!(atomic_write(&path, b"contents").is_err());
+    assert!(!path.exists());
+}
@@ -0,0 +1,284 @@
+/*
+ * Copyright (C) 2018 Kubos Corporation
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+#![deny(warnings)]
+#[macro_use]
+extern crate failure;
+extern crate kubos_system;
+#[macro_use]
+extern crate serde_derive;
+extern crate tempfile;
+
+use failure::Error;
+use kubos_system::*;
+use std::io::Write;
+use tempfile::NamedTempFile;
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+struct TestSettings {
+    database: String,
+    port: Option<u16>,
+    count: Option<u32>,
+}
+
+impl ServiceSettings for TestSettings {
+    fn validate(&self) -> Result<(), Error> {
+        if let Some(count) = self.count {
+            check_range("count", count, 1, 100)?;
+        }
+        Ok(())
+    }
+}
+
+fn write_config(contents: &str) -> NamedTempFile {
+    let mut file = NamedTempFile::new().unwrap();
+    file.write_all(contents.as_bytes()).unwrap();
+    file
+}
+
+fn load(file: &NamedTempFile, name: &str) -> Result<Config, Error> {
+    Config::load_from_path(name, file.path().to_string_lossy().to_string())
+}
+
+#[test]
+fn load_missing_file() {
+    let err = Config::load_from_path("telemetry-service", "/no/such/config.toml".to_owned())
+        .unwrap_err();
+    assert!(err.to_string().starts_with("Failed to read /no/such/config.toml"));
+}
+
+#[test]
+fn load_bad_syntax() {
+    let file = write_config("[telemetry-service]\ndatabase = \"/tmp/db\n");
+    let err = load(&file, "telemetry-service").unwrap_err();
+    assert!(err.to_string().starts_with("Failed to parse"));
+}
+
+#[test]
+fn load_missing_section() {
+    let file = write_config("[telemetry-servce]\ndatabase = \"/tmp/db\"\n");
+    let err = load(&file, "telemetry-service").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        format!(
+            "No [telemetry-service] section in {}",
+            file.path().to_string_lossy()
+        )
+    );
+}
+
+#[test]
+fn load_bad_address() {
+    let file = write_config("[telemetry-service.addr]\nprot = 8089\n");
+    assert!(load(&file, "telemetry-service").is_err());
+}
+
+#[test]
+fn settings_good() {
+    let file = write_config(
+        r#"
+        [test-service]
+        database = "/var/lib/test.db"
+        port = 8105
+
+        [test-service.addr]
+        ip = "127.0.0.1"
+        port = 8089
+        "#,
+    );
+    let settings = load(&file, "test-service")
+        .unwrap()
+        .settings::<TestSettings>()
+        .unwrap();
+
+    assert_eq!(
+        settings,
+        TestSettings {
+            database: "/var/lib/test.db".to_owned(),
+            port: Some(8105),
+            count: None,
+        }
+    );
+}
+
+#[test]
+fn settings_missing_key() {
+    let file = write_config("[test-service.addr]\nport = 8089\n");
+    let err = load(&file, "test-service")
+        .unwrap()
+        .settings::<TestSettings>()
+        .unwrap_err();
+
+    assert_eq!(
+        err.to_string(),
+        "Invalid configuration for test-service: missing field `database`"
+    );
+}
+
+#[test]
+fn settings_unknown_key() {
+    let file = write_config("[test-service]\ndatabase = \"/tmp/db\"\nprot = 8105\n");
+    let err = load(&file, "test-service")
+        .unwrap()
+        .settings::<TestSettings>()
+        .unwrap_err();
+
+    assert!(err.to_string().contains("unknown field `prot`"));
+}
+
+#[test]
+fn settings_wrong_type() {
+    let file = write_config("[test-service]\ndatabase = \"/tmp/db\"\nport = \"8105\"\n");
+    assert!(
+        load(&file, "test-service")
+            .unwrap()
+            .settings::<TestSettings>()
+            .is_err()
+    );
+}
+
+#[test]
+fn settings_out_of_range() {
+    let file = write_config("[test-service]\ndatabase = \"/tmp/db\"\ncount = 0\n");
+    let err = load(&file, "test-service")
+        .unwrap()
+        .settings::<TestSettings>()
+        .unwrap_err();
+
+    assert_eq!(
+        err.to_string(),
+        "Invalid configuration for test-service: count must be between 1 and 100, found 0"
+    );
+}
+
+#[test]
+fn no_settings() {
+    let file = write_config("[test-service.addr]\nport = 8089\n");
+    assert!(load(&file, "test-service").unwrap().settings::<NoSettings>().is_ok());
+
+    let file = write_config("[test-service]\nbus = \"/dev/ttyS5\"\n");
+    let err = load(&file, "test-service")
+        .unwrap()
+        .settings::<NoSettings>()
+        .unwrap_err();
+    assert!(err.to_string().contains("unknown field `bus`"));
+}
+
+#[test]
+fn check_file_results() {
+    let file = write_config(
+        r#"
+        [common-service]
+        anything = "goes"
+
+        [passing-service]
+        [failing-service]
+        [missing-service]
+
+        [bad-storage-service.storage]
+        directory = "/tmp"
+        "#,
+    );
+    let services = [
+        ("common-service", Validator::Common),
+        ("passing-service", Validator::Executable("true")),
+        ("failing-service", Validator::Executable("false")),
+        ("missing-service", Validator::Executable("no-such-kubos-service")),
+        ("bad-storage-service", Validator::Executable("true")),
+        ("unconfigured-service", Validator::Common),
+    ];
+    let results = check_file(&file.path().to_string_lossy(), &services).unwrap();
+
+    assert_eq!(results.len(), 5);
+    assert_eq!(results[0].0, "common-service");
+    assert!(results[0].1.is_ok());
+    assert_eq!(results[1].0, "passing-service");
+    assert!(results[1].1.is_ok());
+    assert_eq!(results[2].0, "failing-service");
+    assert!(results[2].1.is_err());
+    assert_eq!(results[3].0, "missing-service");
+    assert!(
+        results[3]
+            .1
+            .as_ref()
+            .unwrap_err()
+            .to_string()
+            .starts_with("Failed to run no-such-kubos-service")
+    );
+    assert_eq!(results[4].0, "bad-storage-service");
+    assert!(results[4].1.is_err());
+}
+
+#[test]
+fn check_config_not_requested() {
+    assert!(!check_config_requested());
+    assert_eq!(check_config_status(&Err::<(), _>(format_err!("bad config"))), None);
+}
+
+#[test]
+fn load_settings_reports_errors() {
+    let file = write_config("[test-service]\ndatabase = \"/tmp/db\"\ncount = 0\n");
+    let err = Config::load_settings_from_path::<TestSettings>(
+        "test-service",
+        file.path().to_string_lossy().to_string(),
+    ).unwrap_err();
+
+    assert_eq!(
+        err.to_string(),
+        "Invalid configuration for test-service: count must be between 1 and 100, found 0"
+    );
+}
+
+#[test]
+fn new_reports_errors() {
+    let file = write_config("[telemetry-service\n");
+    let err = Config::new_from_path(
+        "telemetry-service",
+        file.path().to_string_lossy().to_string(),
+    ).unwrap_err();
+
+    assert!(err.to_string().starts_with("Failed to parse"));
+}
+
+#[test]
+fn storage_settings() {
+    let file = write_config(
+        r#"
+        [test-service.storage]
+        dir = "/home/system/kubos/storage"
+        "#,
+    );
+    let config = load(&file, "test-service").unwrap();
+
+    assert!(config.settings::<NoSettings>().is_ok());
+    assert_eq!(
+        config.storage().unwrap(),
+        Some(StorageSettings {
+            dir: "/home/system/kubos/storage".to_owned(),
+            max_size: None,
+        })
+    );
+    assert_eq!(config.storage().unwrap().unwrap().max_size(), DEFAULT_STORAGE_SIZE);
+}
+
+#[test]
+fn storage_settings_invalid() {
+    let file = write_config("[test-service.storage]\ndirectory = \"/tmp\"\n");
+    let config = load(&file, "test-service").unwrap();
+
+    assert!(config.storage().is_err());
+    assert!(config.settings::<NoSettings>().is_err());
+}
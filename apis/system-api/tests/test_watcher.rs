@@ -0,0 +1,119 @@
+/*
+ * Copyright (C) 2018 Kubos Corporation
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+#![deny(warnings)]
+extern crate kubos_system;
+extern crate nix;
+extern crate tempfile;
+extern crate toml;
+
+use kubos_system::{Config, ConfigWatcher};
+use nix::sys::signal::{raise, Signal};
+use std::fs;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tempfile::TempDir;
+use toml::Value;
+
+fn write_config(dir: &TempDir, timeout: i64) -> String {
+    let path = dir.path().join("config.toml");
+    fs::write(
+        &path,
+        format!(
+            r#"
+            [test-service]
+            timeout = {}
+            [test-service.addr]
+            ip = "127.0.0.1"
+            port = 8181
+            "#,
+            timeout
+        ),
+    ).unwrap();
+    path.to_string_lossy().to_string()
+}
+
+#[test]
+fn reload_good() {
+    let dir = TempDir::new().unwrap();
+    let path = write_config(&dir, 1);
+
+    let watcher = ConfigWatcher::new(Config::new_from_path("test-service", path).unwrap());
+    let received = Arc::new(Mutex::new(None));
+    let received_ref = received.clone();
+    watcher.subscribe(move |config| {
+        *received_ref.lock().unwrap() = config.get("timeout");
+    });
+
+    write_config(&dir, 5);
+    let config = watcher.reload().unwrap();
+
+    assert_eq!(config.get("timeout"), Some(Value::Integer(5)));
+    assert_eq!(watcher.config().get("timeout"), Some(Value::Integer(5)));
+    assert_eq!(*received.lock().unwrap(), Some(Value::Integer(5)));
+}
+
+#[test]
+fn reload_bad_syntax() {
+    let dir = TempDir::new().unwrap();
+    let path = write_config(&dir, 1);
+
+    let watcher = ConfigWatcher::new(Config::new_from_path("test-service", path.clone()).unwrap());
+    let notified = Arc::new(Mutex::new(false));
+    let notified_ref = notified.clone();
+    watcher.subscribe(move |_| *notified_ref.lock().unwrap() = true);
+
+    fs::write(&path, "[test-service\ntimeout = 5").unwrap();
+
+    assert!(watcher.reload().is_err());
+    assert_eq!(watcher.config().get("timeout"), Some(Value::Integer(1)));
+    assert_eq!(*notified.lock().unwrap(), false);
+}
+
+#[test]
+fn reload_bad_address() {
+    let dir = TempDir::new().unwrap();
+    let path = write_config(&dir, 1);
+
+    let watcher = ConfigWatcher::new(Config::new_from_path("test-service", path.clone()).unwrap());
+
+    fs::write(&path, "[test-service.addr]\nip = \"localhost:80\"").unwrap();
+
+    assert!(watcher.reload().is_err());
+    assert_eq!(watcher.config().hosturl(), "127.0.0.1:8181");
+}
+
+#[test]
+fn reload_no_file() {
+    let config = Config::new_from_str("test-service", "[test-service]").unwrap();
+    let watcher = ConfigWatcher::new(config);
+
+    assert!(watcher.reload().is_err());
+}
+
+#[test]
+fn reload_on_sighup() {
+    let dir = TempDir::new().unwrap();
+    let path = write_config(&dir, 1);
+
+    let watcher = ConfigWatcher::new(Config::new_from_path("test-service", path).unwrap());
+    watcher.watch().unwrap();
+
+    write_config(&dir, 10);
+    raise(Signal::SIGHUP).unwrap();
+    thread::sleep(kubos_system::WATCH_INTERVAL * 3);
+
+    assert_eq!(watcher.config().get("timeout"), Some(Value::Integer(10)));
+}
@@ -15,6 +15,7 @@
 //
 #[macro_use]
 extern crate diesel;
+extern crate libc;
 #[macro_use]
 extern crate log;
 #[macro_use]
@@ -22,18 +23,45 @@ extern crate serde_derive;
 extern crate time;
 
 pub mod models;
+pub mod pool;
 pub use models::*;
+pub use pool::*;
 
+use diesel::connection::SimpleConnection;
 use diesel::dsl::sql;
 use diesel::insert_into;
+use diesel::update;
 use diesel::prelude::*;
 use diesel::sql_query;
-use diesel::sql_types::Bool;
+use diesel::sql_types::{BigInt, Bool, Integer, Text};
 use diesel::sqlite::SqliteConnection;
 use diesel::*;
+use std::ffi::CString;
+use std::mem;
+use std::path::Path;
+
+// Columns of the telemetry table. Entries are numbered in the order they're inserted by the
+// `sequence` column, an alias of the rowid which AUTOINCREMENT stops from being reused once the
+// most recently inserted entries are deleted.
+const TELEMETRY_COLUMNS: &str = "
+    sequence INTEGER PRIMARY KEY AUTOINCREMENT,
+    timestamp INTEGER NOT NULL,
+    subsystem VARCHAR(255) NOT NULL,
+    parameter VARCHAR(255) NOT NULL,
+    value VARCHAR(255) NOT NULL,
+    value_type VARCHAR(16) NOT NULL DEFAULT 'string',
+    int_value BIGINT,
+    float_value DOUBLE,
+    blob_value BLOB,
+    UNIQUE (timestamp, subsystem, parameter)";
+
+/// Milliseconds a connection waits for another connection's lock on the database to be
+/// released before giving up with a "database is locked" error
+pub const BUSY_TIMEOUT: u32 = 5000;
 
 pub struct Database {
     pub connection: SqliteConnection,
+    path: String,
 }
 
 impl Database {
@@ -49,19 +77,45 @@ impl Database {
         if !::std::path::Path::new(path).exists() {
             info!("Creating database {}", path);
         }
-        Database {
-            connection: SqliteConnection::establish(&String::from(path)).expect(&format!(
-                "Could not create SQLite database connection to: {}",
-                path
-            )),
-        }
+        Self::open(path).expect(&format!(
+            "Could not create SQLite database connection to: {}",
+            path
+        ))
+    }
+
+    /// Opens another connection to a database, which waits up to `BUSY_TIMEOUT` for other
+    /// connections' locks to be released
+    ///
+    /// # Arguments
+    /// `path` - Path to database file
+    pub fn open(path: &str) -> ConnectionResult<Self> {
+        let connection = SqliteConnection::establish(path)?;
+        connection
+            .batch_execute(&format!("PRAGMA busy_timeout = {}", BUSY_TIMEOUT))
+            .map_err(ConnectionError::CouldntSetupConfiguration)?;
+
+        Ok(Database {
+            connection,
+            path: path.to_owned(),
+        })
+    }
+
+    /// Returns the path of the database file
+    pub fn path(&self) -> &str {
+        &self.path
     }
 
     /// Check if database has correct table and creates table if needed
     ///
+    /// Tables created by older versions, which stored every value as a string, are migrated
+    /// to include typed values. The types of existing values are inferred from their strings.
+    ///
+    /// The database is also switched to write-ahead logging, so that reading connections
+    /// don't block the writing connection, or each other.
+    ///
     /// # Panics
     ///
-    /// Will `panic!` if fails to locate and/or create telemetry table
+    /// Will `panic!` if fails to locate, create or migrate telemetry table
     pub fn setup(&self) {
         match select(sql::<Bool>(
             "EXISTS \
@@ -75,17 +129,27 @@ impl Database {
                 error!("Error querying table: {:?}", err);
                 panic!("Error querying table: {:?}", err)
             }
-            Ok(true) => info!("Table exists"),
+            Ok(true) => {
+                info!("Table exists");
+                if let Err(err) = self.migrate() {
+                    error!("Error migrating table: {:?}", err);
+                    panic!("Error migrating table: {:?}", err)
+                }
+                if let Err(err) = self.migrate_sequence() {
+                    warn!("Failed to number entries by insertion: {:?}", err);
+                }
+            }
             Ok(false) => {
                 info!("Telemetry table not found. Creating table.");
-                match sql_query(
-                    "CREATE TABLE telemetry (
-                    timestamp INTEGER NOT NULL,
-                    subsystem VARCHAR(255) NOT NULL,
-                    parameter VARCHAR(255) NOT NULL,
-                    value VARCHAR(255) NOT NULL,
-                    PRIMARY KEY (timestamp, subsystem, parameter))",
-                ).execute(&self.connection)
+                // Lets space freed by deleted entries be returned to the filesystem without
+                // rewriting the whole file. Only takes effect if the database is still empty.
+                if let Err(err) = sql_query("PRAGMA auto_vacuum = INCREMENTAL")
+                    .execute(&self.connection)
+                {
+                    warn!("Failed to enable incremental vacuum: {:?}", err);
+                }
+                match sql_query(format!("CREATE TABLE telemetry ({})", TELEMETRY_COLUMNS))
+                    .execute(&self.connection)
                 {
                     Ok(_) => info!("Telemetry table created"),
                     Err(err) => {
@@ -95,8 +159,183 @@ impl Database {
                 }
             }
         };
+
+        if let Err(err) = sql_query(
+            "CREATE TABLE IF NOT EXISTS export_cursors (
+            name VARCHAR(255) NOT NULL PRIMARY KEY,
+            acked BIGINT,
+            exported BIGINT)",
+        ).execute(&self.connection)
+        {
+            error!("Error creating export cursor table: {:?}", err);
+            panic!("Error creating export cursor table: {:?}", err)
+        }
+
+        if let Err(err) = sql_query(
+            "CREATE TABLE IF NOT EXISTS events (
+            id INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+            timestamp DOUBLE NOT NULL,
+            subsystem VARCHAR(255) NOT NULL,
+            parameter VARCHAR(255) NOT NULL,
+            value DOUBLE NOT NULL,
+            state VARCHAR(16) NOT NULL,
+            previous VARCHAR(16) NOT NULL)",
+        ).execute(&self.connection)
+        {
+            error!("Error creating events table: {:?}", err);
+            panic!("Error creating events table: {:?}", err)
+        }
+
+        // The journal mode is stored in the database file, so only needs to be set once, but
+        // can't be changed while another connection is using the database
+        match self
+            .connection
+            .batch_execute("PRAGMA journal_mode = WAL")
+            .and_then(|_| self.journal_mode())
+        {
+            Ok(ref mode) if mode == "wal" => {}
+            Ok(mode) => warn!("Failed to enable write-ahead logging, journal mode is {}", mode),
+            Err(err) => warn!("Failed to enable write-ahead logging: {:?}", err),
+        }
+    }
+
+    /// Converts a database created before incremental vacuuming was enabled, so that `vacuum`
+    /// can return the space freed by deleted entries to the filesystem.
+    ///
+    /// Changing the mode of an existing database rebuilds it in full, which temporarily needs
+    /// up to twice its size in free disk space. If there isn't enough, the database is left as
+    /// it is and `false` is returned. Its free pages are still reused by new entries.
+    pub fn enable_incremental_vacuum(&self) -> QueryResult<bool> {
+        let mode = select(sql::<Integer>(
+            "(SELECT auto_vacuum FROM pragma_auto_vacuum())",
+        )).get_result::<i32>(&self.connection)?;
+
+        // 2 is INCREMENTAL
+        if mode == 2 {
+            return Ok(true);
+        }
+
+        let needed = self.used_size()? * 2;
+        match disk_space(&self.path) {
+            Some(free) if free > needed => {}
+            _ => return Ok(false),
+        }
+
+        info!("Enabling incremental vacuum");
+        self.connection
+            .batch_execute("PRAGMA auto_vacuum = INCREMENTAL; VACUUM")?;
+        Ok(true)
+    }
+
+    /// Returns the database's journal mode, such as `wal` or `delete`
+    pub fn journal_mode(&self) -> QueryResult<String> {
+        select(sql::<Text>("(SELECT * FROM pragma_journal_mode())")).get_result(&self.connection)
     }
 
+    // Adds the typed value columns to a table created by an older version
+    fn migrate(&self) -> QueryResult<()> {
+        use self::telemetry::dsl;
+
+        let typed = select(sql::<Bool>(
+            "EXISTS \
+             (SELECT 1 \
+             FROM pragma_table_info('telemetry') \
+             WHERE name = 'value_type')",
+        )).get_result::<bool>(&self.connection)?;
+        if typed {
+            return Ok(());
+        }
+
+        info!("Migrating telemetry table to typed values");
+        self.connection.transaction(|| {
+            for column in &[
+                "value_type VARCHAR(16) NOT NULL DEFAULT 'string'",
+                "int_value BIGINT",
+                "float_value DOUBLE",
+                "blob_value BLOB",
+            ] {
+                sql_query(format!("ALTER TABLE telemetry ADD COLUMN {}", column))
+                    .execute(&self.connection)?;
+            }
+
+            let rows = dsl::telemetry
+                .select((dsl::timestamp, dsl::subsystem, dsl::parameter, dsl::value))
+                .load::<(f64, String, String, String)>(&self.connection)?;
+
+            let mut migrated = 0;
+            for (timestamp, subsystem, parameter, value) in rows {
+                let typed_value = TypedValue::infer(&value);
+                if typed_value.value_type() == ValueType::String {
+                    continue;
+                }
+
+                update(
+                    dsl::telemetry
+                        .filter(dsl::timestamp.eq(timestamp))
+                        .filter(dsl::subsystem.eq(&subsystem))
+                        .filter(dsl::parameter.eq(&parameter)),
+                ).set((
+                    dsl::value_type.eq(typed_value.value_type().as_str()),
+                    dsl::int_value.eq(typed_value.as_i64()),
+                    dsl::float_value.eq(typed_value.as_f64()),
+                ))
+                    .execute(&self.connection)?;
+                migrated += 1;
+            }
+
+            info!("Inferred the types of {} existing values", migrated);
+            Ok(())
+        })
+    }
+
+    // Rebuilds a table created by an older version with the `sequence` column. Existing
+    // entries keep their rowids, so export cursors stay in place.
+    //
+    // Until this succeeds the rowid still orders entries by insertion, but may reuse the
+    // numbers of the newest entries once they've been deleted.
+    fn migrate_sequence(&self) -> QueryResult<()> {
+        let numbered = select(sql::<Bool>(
+            "EXISTS \
+             (SELECT 1 \
+             FROM pragma_table_info('telemetry') \
+             WHERE name = 'sequence')",
+        )).get_result::<bool>(&self.connection)?;
+        if numbered {
+            return Ok(());
+        }
+
+        // The old table is only dropped once its copy is complete
+        let needed = self.used_size()?;
+        match disk_space(&self.path) {
+            Some(free) if free > needed => {}
+            free => {
+                warn!(
+                    "Not enough disk space to number entries by insertion ({:?} of {} bytes free). \
+                     Retrying on the next startup",
+                    free, needed
+                );
+                return Ok(());
+            }
+        }
+
+        info!("Numbering telemetry entries by insertion");
+        self.connection.transaction(|| {
+            self.connection.batch_execute(&format!(
+                "CREATE TABLE telemetry_new ({});
+                INSERT INTO telemetry_new \
+                (sequence, timestamp, subsystem, parameter, value, value_type, \
+                int_value, float_value, blob_value) \
+                SELECT rowid, timestamp, subsystem, parameter, value, value_type, \
+                int_value, float_value, blob_value \
+                FROM telemetry ORDER BY rowid;
+                DROP TABLE telemetry;
+                ALTER TABLE telemetry_new RENAME TO telemetry;",
+                TELEMETRY_COLUMNS
+            ))
+        })
+    }
+
+    /// Insert an entry, inferring the type of its value
     pub fn insert<'a>(
         &self,
         timestamp: f64,
@@ -104,18 +343,12 @@ impl Database {
         parameter: &'a str,
         value: &'a str,
     ) -> QueryResult<usize> {
-        use self::telemetry;
-
-        let new_entry = NewEntry {
-            timestamp: timestamp,
-            subsystem: subsystem,
-            parameter: parameter,
-            value: value,
-        };
-
-        insert_into(telemetry::table)
-            .values(&new_entry)
-            .execute(&self.connection)
+        self.insert_bulk(&[NewEntry {
+            timestamp,
+            subsystem,
+            parameter,
+            value,
+        }])
     }
 
     pub fn insert_systime<'a>(
@@ -124,17 +357,265 @@ impl Database {
         parameter: &'a str,
         value: &'a str,
     ) -> QueryResult<usize> {
-        let time = time::now_utc().to_timespec();
-        let timestamp = time.sec as f64 + (time.nsec as f64 / 1000000000.0);
-        self.insert(timestamp, subsystem, parameter, value)
+        self.insert(systime(), subsystem, parameter, value)
+    }
+
+    /// Insert an entry with a typed value
+    pub fn insert_typed<'a>(
+        &self,
+        timestamp: f64,
+        subsystem: &'a str,
+        parameter: &'a str,
+        value: TypedValue,
+    ) -> QueryResult<usize> {
+        self.insert_typed_bulk(&[TypedEntry {
+            timestamp,
+            subsystem,
+            parameter,
+            value,
+        }])
+    }
+
+    /// Insert multiple entries within a single transaction, inferring the types of their values
+    ///
+    /// Either all of the entries are inserted or, if any of them fail, none are.
+    /// Returns the number of entries inserted.
+    ///
+    /// # Arguments
+    /// `entries` - Entries to insert
+    pub fn insert_bulk(&self, entries: &[NewEntry]) -> QueryResult<usize> {
+        let typed_values: Vec<TypedValue> = entries
+            .iter()
+            .map(|entry| TypedValue::infer(entry.value))
+            .collect();
+
+        self.insert_rows(entries.iter().zip(typed_values.iter()).map(
+            |(entry, typed_value)| {
+                Row::new(
+                    entry.timestamp,
+                    entry.subsystem,
+                    entry.parameter,
+                    entry.value.to_owned(),
+                    typed_value,
+                )
+            },
+        ))
+    }
+
+    /// Insert multiple entries with typed values within a single transaction
+    ///
+    /// Either all of the entries are inserted or, if any of them fail, none are.
+    /// Returns the number of entries inserted.
+    ///
+    /// # Arguments
+    /// `entries` - Entries to insert
+    pub fn insert_typed_bulk(&self, entries: &[TypedEntry]) -> QueryResult<usize> {
+        self.insert_rows(entries.iter().map(|entry| {
+            Row::new(
+                entry.timestamp,
+                entry.subsystem,
+                entry.parameter,
+                entry.value.to_string(),
+                &entry.value,
+            )
+        }))
+    }
+
+    /// Returns the size of the database file, in bytes
+    pub fn size(&self) -> QueryResult<u64> {
+        self.pragma_pages("page_count")
+    }
+
+    /// Returns the number of bytes within the database file which are unused, and would be
+    /// returned to the filesystem by `vacuum`
+    pub fn free_size(&self) -> QueryResult<u64> {
+        self.pragma_pages("freelist_count")
+    }
+
+    /// Returns the space freed by deleted entries to the filesystem.
+    ///
+    /// Only the database's free pages are released, without rebuilding the file. Databases
+    /// created by older versions must first be converted by `enable_incremental_vacuum`.
+    pub fn vacuum(&self) -> QueryResult<()> {
+        // Run with batch_execute, since each step of the pragma only frees a single page
+        self.connection.batch_execute("PRAGMA incremental_vacuum")?;
+
+        // In WAL mode, the database file only shrinks once its log has been copied back into
+        // it. This leaves the log in place if any readers are still using it.
+        if self.journal_mode()? == "wal" {
+            self.connection
+                .batch_execute("PRAGMA wal_checkpoint(TRUNCATE)")?;
+        }
+        Ok(())
+    }
+
+    /// Deletes the oldest entries, returning the number of entries deleted
+    ///
+    /// # Arguments
+    /// `count` - Maximum number of entries to delete
+    pub fn delete_oldest(&self, count: i64) -> QueryResult<usize> {
+        sql_query(
+            "DELETE FROM telemetry WHERE rowid IN \
+             (SELECT rowid FROM telemetry ORDER BY timestamp LIMIT ?)",
+        ).bind::<BigInt, _>(count)
+            .execute(&self.connection)
+    }
+
+    /// Returns the named export cursor, if it exists
+    pub fn cursor(&self, name: &str) -> QueryResult<Option<Cursor>> {
+        use self::export_cursors::dsl;
+
+        dsl::export_cursors
+            .find(name)
+            .first(&self.connection)
+            .optional()
+    }
+
+    /// Returns all export cursors, sorted by name
+    pub fn cursors(&self) -> QueryResult<Vec<Cursor>> {
+        use self::export_cursors::dsl;
+
+        dsl::export_cursors
+            .order(dsl::name)
+            .load(&self.connection)
+    }
+
+    /// Records the sequence number of the last entry exported for a cursor, creating it if
+    /// needed
+    pub fn set_cursor_exported(&self, name: &str, sequence: i64) -> QueryResult<()> {
+        use self::export_cursors::dsl;
+
+        self.connection.transaction(|| {
+            self.create_cursor(name)?;
+            update(dsl::export_cursors.find(name))
+                .set(dsl::exported.eq(sequence))
+                .execute(&self.connection)?;
+            Ok(())
+        })
+    }
+
+    /// Acknowledges that a cursor's entries up to and including the given sequence number have
+    /// been received, creating the cursor if needed
+    pub fn ack_cursor(&self, name: &str, sequence: i64) -> QueryResult<()> {
+        use self::export_cursors::dsl;
+
+        self.connection.transaction(|| {
+            self.create_cursor(name)?;
+            update(dsl::export_cursors.find(name))
+                .set(dsl::acked.eq(sequence))
+                .execute(&self.connection)?;
+            Ok(())
+        })
+    }
+
+    fn create_cursor(&self, name: &str) -> QueryResult<usize> {
+        use self::export_cursors::dsl;
+
+        insert_or_ignore_into(dsl::export_cursors)
+            .values(dsl::name.eq(name))
+            .execute(&self.connection)
+    }
+
+    /// Records a change in a parameter's alarm state
+    pub fn insert_event(&self, event: &NewEvent) -> QueryResult<usize> {
+        insert_into(events::table)
+            .values(event)
+            .execute(&self.connection)
+    }
+
+    /// Returns the most recent event of each parameter which has had any
+    pub fn latest_events(&self) -> QueryResult<Vec<Event>> {
+        use self::events::dsl;
+
+        dsl::events
+            .filter(sql::<Bool>(
+                "id IN (SELECT MAX(id) FROM events GROUP BY subsystem, parameter)",
+            ))
+            .order((dsl::subsystem, dsl::parameter))
+            .load(&self.connection)
+    }
+
+    /// Returns the number of bytes within the database file which hold data
+    pub fn used_size(&self) -> QueryResult<u64> {
+        Ok(self.size()?.saturating_sub(self.free_size()?))
+    }
+
+    // Multiplies the given page count pragma by the page size
+    fn pragma_pages(&self, pragma: &str) -> QueryResult<u64> {
+        select(sql::<BigInt>(&format!(
+            "(SELECT * FROM pragma_{}()) * (SELECT * FROM pragma_page_size())",
+            pragma
+        ))).get_result::<i64>(&self.connection)
+            .map(|size| size as u64)
+    }
+
+    fn insert_rows<'a, I: Iterator<Item = Row<'a>>>(&self, rows: I) -> QueryResult<usize> {
+        use self::telemetry;
+
+        self.connection.transaction(|| {
+            let mut inserted = 0;
+            for row in rows {
+                inserted += insert_into(telemetry::table)
+                    .values(&row)
+                    .execute(&self.connection)?;
+            }
+            Ok(inserted)
+        })
     }
 }
 
+// Returns the bytes available on the filesystem holding the database, if they can be found
+fn disk_space(path: &str) -> Option<u64> {
+    let dir = match Path::new(path).parent() {
+        Some(dir) if dir != Path::new("") => dir,
+        _ => Path::new("."),
+    };
+    let dir = CString::new(dir.to_str()?).ok()?;
+
+    unsafe {
+        let mut stats: libc::statvfs = mem::zeroed();
+        if libc::statvfs(dir.as_ptr(), &mut stats) != 0 {
+            return None;
+        }
+        Some(stats.f_bavail as u64 * stats.f_frsize as u64)
+    }
+}
+
+/// Returns the current system time as a telemetry timestamp (seconds since the UNIX epoch)
+pub fn systime() -> f64 {
+    let time = time::now_utc().to_timespec();
+    time.sec as f64 + (time.nsec as f64 / 1000000000.0)
+}
+
 table! {
     telemetry (timestamp) {
         timestamp -> Double,
         subsystem -> Text,
         parameter -> Text,
         value -> Text,
+        value_type -> Text,
+        int_value -> Nullable<BigInt>,
+        float_value -> Nullable<Double>,
+        blob_value -> Nullable<Binary>,
+    }
+}
+
+table! {
+    export_cursors (name) {
+        name -> Text,
+        acked -> Nullable<BigInt>,
+        exported -> Nullable<BigInt>,
+    }
+}
+
+table! {
+    events (id) {
+        id -> Integer,
+        timestamp -> Double,
+        subsystem -> Text,
+        parameter -> Text,
+        value -> Double,
+        state -> Text,
+        previous -> Text,
     }
 }
@@ -14,21 +14,303 @@
 // limitations under the License.
 //
 
-use super::telemetry;
+use super::{events, telemetry};
+use diesel::sqlite::Sqlite;
+use diesel::Queryable;
+use std::fmt;
+use std::str::FromStr;
 
-#[derive(Debug, Queryable, Serialize, Deserialize)]
+/// The type of a telemetry value
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ValueType {
+    Integer,
+    Float,
+    Boolean,
+    String,
+    Blob,
+}
+
+impl ValueType {
+    /// Returns the name the type is stored as in the database
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            ValueType::Integer => "integer",
+            ValueType::Float => "float",
+            ValueType::Boolean => "boolean",
+            ValueType::String => "string",
+            ValueType::Blob => "blob",
+        }
+    }
+}
+
+impl FromStr for ValueType {
+    type Err = String;
+
+    fn from_str(value_type: &str) -> Result<Self, Self::Err> {
+        match value_type {
+            "integer" => Ok(ValueType::Integer),
+            "float" => Ok(ValueType::Float),
+            "boolean" => Ok(ValueType::Boolean),
+            "string" => Ok(ValueType::String),
+            "blob" => Ok(ValueType::Blob),
+            _ => Err(format!("Unknown value type: {}", value_type)),
+        }
+    }
+}
+
+/// A typed telemetry value
+#[derive(Clone, Debug, PartialEq)]
+pub enum TypedValue {
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+    String(String),
+    Blob(Vec<u8>),
+}
+
+impl Default for TypedValue {
+    fn default() -> Self {
+        TypedValue::String(String::new())
+    }
+}
+
+impl TypedValue {
+    /// Infers the type of a value given as a string: `true` and `false` are booleans,
+    /// whole numbers are integers, other finite numbers are floats and anything else is a string
+    pub fn infer(value: &str) -> Self {
+        match value {
+            "true" => TypedValue::Boolean(true),
+            "false" => TypedValue::Boolean(false),
+            _ => {
+                if let Ok(int) = value.parse::<i64>() {
+                    TypedValue::Integer(int)
+                } else {
+                    match value.parse::<f64>() {
+                        Ok(float) if float.is_finite() => TypedValue::Float(float),
+                        _ => TypedValue::String(value.to_owned()),
+                    }
+                }
+            }
+        }
+    }
+
+    /// Parses a value given as a string as the given type. Blobs are given as hex strings.
+    pub fn parse(value_type: ValueType, value: &str) -> Result<Self, String> {
+        let invalid = || format!("Invalid {} value: {}", value_type.as_str(), value);
+        Ok(match value_type {
+            ValueType::Integer => TypedValue::Integer(value.parse().map_err(|_| invalid())?),
+            ValueType::Float => TypedValue::Float(value.parse().map_err(|_| invalid())?),
+            ValueType::Boolean => TypedValue::Boolean(value.parse().map_err(|_| invalid())?),
+            ValueType::String => TypedValue::String(value.to_owned()),
+            ValueType::Blob => {
+                if value.len() % 2 != 0 || !value.is_ascii() {
+                    return Err(invalid());
+                }
+                TypedValue::Blob(
+                    (0..value.len())
+                        .step_by(2)
+                        .map(|i| u8::from_str_radix(&value[i..i + 2], 16))
+                        .collect::<Result<Vec<u8>, _>>()
+                        .map_err(|_| invalid())?,
+                )
+            }
+        })
+    }
+
+    /// Returns the value's type
+    pub fn value_type(&self) -> ValueType {
+        match *self {
+            TypedValue::Integer(_) => ValueType::Integer,
+            TypedValue::Float(_) => ValueType::Float,
+            TypedValue::Boolean(_) => ValueType::Boolean,
+            TypedValue::String(_) => ValueType::String,
+            TypedValue::Blob(_) => ValueType::Blob,
+        }
+    }
+
+    /// Returns the value as a number, if it's an integer, float or boolean (as 0 or 1)
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            TypedValue::Integer(int) => Some(int as f64),
+            TypedValue::Float(float) => Some(float),
+            TypedValue::Boolean(boolean) => Some(if boolean { 1.0 } else { 0.0 }),
+            _ => None,
+        }
+    }
+
+    /// Returns the value as an integer, if it's an integer or boolean (as 0 or 1)
+    pub fn as_i64(&self) -> Option<i64> {
+        match *self {
+            TypedValue::Integer(int) => Some(int),
+            TypedValue::Boolean(boolean) => Some(boolean as i64),
+            _ => None,
+        }
+    }
+
+    fn from_columns(
+        value: &str,
+        value_type: &str,
+        int_value: Option<i64>,
+        float_value: Option<f64>,
+        blob_value: Option<Vec<u8>>,
+    ) -> Self {
+        match (value_type.parse(), int_value, float_value, blob_value) {
+            (Ok(ValueType::Integer), Some(int), _, _) => TypedValue::Integer(int),
+            (Ok(ValueType::Float), _, Some(float), _) => TypedValue::Float(float),
+            (Ok(ValueType::Boolean), Some(int), _, _) => TypedValue::Boolean(int != 0),
+            (Ok(ValueType::Blob), _, _, Some(blob)) => TypedValue::Blob(blob),
+            _ => TypedValue::String(value.to_owned()),
+        }
+    }
+}
+
+impl fmt::Display for TypedValue {
+    /// Formats the value as its legacy string representation. Blobs are formatted as hex.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TypedValue::Integer(int) => write!(f, "{}", int),
+            TypedValue::Float(float) => write!(f, "{:?}", float),
+            TypedValue::Boolean(boolean) => write!(f, "{}", boolean),
+            TypedValue::String(ref string) => write!(f, "{}", string),
+            TypedValue::Blob(ref blob) => {
+                for byte in blob {
+                    write!(f, "{:02x}", byte)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Entry {
     pub timestamp: f64,
     pub subsystem: String,
     pub parameter: String,
+    /// The value's legacy string representation
     pub value: String,
+    /// The typed value
+    #[serde(skip)]
+    pub typed_value: TypedValue,
 }
 
-#[derive(Insertable)]
-#[table_name = "telemetry"]
+impl Queryable<telemetry::SqlType, Sqlite> for Entry {
+    type Row = (
+        f64,
+        String,
+        String,
+        String,
+        String,
+        Option<i64>,
+        Option<f64>,
+        Option<Vec<u8>>,
+    );
+
+    fn build(row: Self::Row) -> Self {
+        let (timestamp, subsystem, parameter, value, value_type, int_value, float_value, blob) =
+            row;
+        let typed_value =
+            TypedValue::from_columns(&value, &value_type, int_value, float_value, blob);
+        Entry {
+            timestamp,
+            subsystem,
+            parameter,
+            value,
+            typed_value,
+        }
+    }
+}
+
+/// An entry to insert, with a value given as a string whose type will be inferred
 pub struct NewEntry<'a> {
     pub timestamp: f64,
     pub subsystem: &'a str,
     pub parameter: &'a str,
     pub value: &'a str,
 }
+
+/// An entry to insert, with a typed value
+pub struct TypedEntry<'a> {
+    pub timestamp: f64,
+    pub subsystem: &'a str,
+    pub parameter: &'a str,
+    pub value: TypedValue,
+}
+
+// A full telemetry row, as inserted into the database
+#[derive(Insertable)]
+#[table_name = "telemetry"]
+pub(crate) struct Row<'a> {
+    pub timestamp: f64,
+    pub subsystem: &'a str,
+    pub parameter: &'a str,
+    pub value: String,
+    pub value_type: &'static str,
+    pub int_value: Option<i64>,
+    pub float_value: Option<f64>,
+    pub blob_value: Option<&'a [u8]>,
+}
+
+impl<'a> Row<'a> {
+    pub(crate) fn new(
+        timestamp: f64,
+        subsystem: &'a str,
+        parameter: &'a str,
+        value: String,
+        typed_value: &'a TypedValue,
+    ) -> Self {
+        Row {
+            timestamp,
+            subsystem,
+            parameter,
+            value,
+            value_type: typed_value.value_type().as_str(),
+            int_value: typed_value.as_i64(),
+            float_value: typed_value.as_f64(),
+            blob_value: match *typed_value {
+                TypedValue::Blob(ref blob) => Some(blob),
+                _ => None,
+            },
+        }
+    }
+}
+
+/// A named position in the telemetry, used to export only entries which haven't already
+/// been received
+#[derive(Debug, PartialEq, Queryable)]
+pub struct Cursor {
+    pub name: String,
+    /// Sequence number of the last entry acknowledged as received. Only entries inserted after
+    /// it are exported.
+    pub acked: Option<i64>,
+    /// Sequence number of the last entry in the most recent export
+    pub exported: Option<i64>,
+}
+
+/// A change in a telemetry parameter's alarm state
+#[derive(Debug, PartialEq, Queryable)]
+pub struct Event {
+    pub id: i32,
+    /// Timestamp of the entry which caused the change
+    pub timestamp: f64,
+    pub subsystem: String,
+    pub parameter: String,
+    /// Numeric value of the entry which caused the change
+    pub value: f64,
+    /// The new alarm state
+    pub state: String,
+    /// The alarm state before the change
+    pub previous: String,
+}
+
+/// An alarm state change to record
+#[derive(Insertable)]
+#[table_name = "events"]
+pub struct NewEvent<'a> {
+    pub timestamp: f64,
+    pub subsystem: &'a str,
+    pub parameter: &'a str,
+    pub value: f64,
+    pub state: &'a str,
+    pub previous: &'a str,
+}
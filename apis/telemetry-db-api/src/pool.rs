@@ -0,0 +1,150 @@
+//
+// Copyright (C) 2018 Kubos Corporation
+//
+// Licensed under the Apache License, Version 2.0 (the "License")
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use super::Database;
+use diesel::connection::SimpleConnection;
+use diesel::{ConnectionError, ConnectionResult};
+use std::ops::Deref;
+use std::sync::{Condvar, LockResult, Mutex, MutexGuard};
+
+/// Default maximum number of reading connections a pool opens
+pub const DEFAULT_READERS: usize = 4;
+
+/// Shares a database between threads, with a single connection for making changes and a pool
+/// of read-only connections for queries.
+///
+/// With write-ahead logging enabled by `Database::setup`, readers see the last committed state
+/// of the database while the writer is busy, so long-running queries don't hold up inserts.
+pub struct DatabasePool {
+    path: String,
+    writer: Mutex<Database>,
+    readers: Mutex<Readers>,
+    released: Condvar,
+    max_readers: usize,
+}
+
+// Reading connections which aren't in use, and the number which have been opened
+struct Readers {
+    idle: Vec<Database>,
+    open: usize,
+}
+
+impl DatabasePool {
+    /// Creates a pool around an existing connection, which becomes the writer.
+    /// Reading connections to the same database file are opened as they're needed.
+    ///
+    /// # Arguments
+    /// `writer` - Connection used for all changes to the database
+    /// `max_readers` - Maximum number of reading connections to open. At least one is allowed.
+    pub fn new(writer: Database, max_readers: usize) -> Self {
+        DatabasePool {
+            path: writer.path().to_owned(),
+            writer: Mutex::new(writer),
+            readers: Mutex::new(Readers {
+                idle: vec![],
+                open: 0,
+            }),
+            released: Condvar::new(),
+            max_readers: max_readers.max(1),
+        }
+    }
+
+    /// Locks the writing connection, waiting for any other thread using it to finish
+    pub fn writer(&self) -> LockResult<MutexGuard<Database>> {
+        self.writer.lock()
+    }
+
+    /// Takes a reading connection from the pool, opening a new one if none are idle.
+    /// If the maximum number are already in use, waits for one to be returned.
+    ///
+    /// The connection is returned to the pool when the `Reader` is dropped.
+    pub fn reader(&self) -> ConnectionResult<Reader> {
+        let mut readers = self.lock_readers();
+
+        loop {
+            if let Some(database) = readers.idle.pop() {
+                return Ok(Reader {
+                    pool: self,
+                    database: Some(database),
+                });
+            }
+
+            if readers.open < self.max_readers {
+                readers.open += 1;
+                drop(readers);
+                return match self.open_reader() {
+                    Ok(database) => Ok(Reader {
+                        pool: self,
+                        database: Some(database),
+                    }),
+                    Err(err) => {
+                        self.lock_readers().open -= 1;
+                        self.released.notify_one();
+                        Err(err)
+                    }
+                };
+            }
+
+            readers = self
+                .released
+                .wait(readers)
+                .unwrap_or_else(|err| err.into_inner());
+        }
+    }
+
+    fn open_reader(&self) -> ConnectionResult<Database> {
+        let database = Database::open(&self.path)?;
+        database
+            .connection
+            .batch_execute("PRAGMA query_only = ON")
+            .map_err(ConnectionError::CouldntSetupConfiguration)?;
+        Ok(database)
+    }
+
+    // The readers' state is only changed in single steps, so stays usable even if a thread
+    // panicked while holding the lock
+    fn lock_readers(&self) -> MutexGuard<Readers> {
+        self.readers.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    fn release(&self, database: Database) {
+        self.lock_readers().idle.push(database);
+        self.released.notify_one();
+    }
+}
+
+/// A reading connection borrowed from a `DatabasePool`
+pub struct Reader<'a> {
+    pool: &'a DatabasePool,
+    database: Option<Database>,
+}
+
+impl<'a> Deref for Reader<'a> {
+    type Target = Database;
+
+    fn deref(&self) -> &Database {
+        // Only taken while the reader is being dropped
+        self.database.as_ref().unwrap()
+    }
+}
+
+impl<'a> Drop for Reader<'a> {
+    fn drop(&mut self) {
+        if let Some(database) = self.database.take() {
+            self.pool.release(database);
+        }
+    }
+}
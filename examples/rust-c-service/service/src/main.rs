@@ -27,7 +27,7 @@ use schema::{MutationRoot, QueryRoot};
 
 fn main() {
     Service::new(
-        Config::new("example-service"),
+        Config::load("example-service").unwrap(),
         Subsystem::new(),
         QueryRoot,
         MutationRoot,
@@ -5,6 +5,8 @@ extern crate getopts;
 extern crate kubos_app;
 #[macro_use]
 extern crate log;
+#[macro_use]
+extern crate serde_derive;
 extern crate syslog;
 
 use failure::{Error, SyncFailure};
@@ -14,75 +16,56 @@ use std::thread;
 use std::time::Duration;
 use syslog::Facility;
 
+#[derive(Debug, Deserialize)]
+struct MemInfo {
+    available: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AppInfo {
+    uuid: String,
+    name: String,
+    version: String,
+    author: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct AppEntry {
+    active: bool,
+    app: AppInfo,
+}
+
 struct MyApp;
 
 impl AppHandler for MyApp {
     fn on_boot(&self, _args: Vec<String>) -> Result<(), Error> {
-        let monitor_service = ServiceConfig::new("monitor-service");
-        let telemetry_service = ServiceConfig::new("telemetry-service");
+        let monitor_service = Client::new(ServiceConfig::new("monitor-service")?);
+        let telemetry_service = Client::new(ServiceConfig::new("telemetry-service")?);
 
         loop {
             thread::sleep(Duration::from_secs(3));
             info!("OnBoot logic called");
 
             // Get the amount of memory currently available on the OBC
-            let request = "{memInfo{available}}";
-            let response = match query(
-                monitor_service.clone(),
-                request,
-                Some(Duration::from_secs(1)),
-            ) {
-                Ok(msg) => msg,
-                Err(err) => {
-                    info!("Monitor service query failed: {}", err);
-                    continue;
-                }
-            };
-
-            let memory = response.get("memInfo").and_then(|msg| msg.get("available"));
-
-            // Save the amount to the telemetry database
-            if let Some(mem) = memory {
-                let request = format!(
-                    r#"
-                    mutation {{
-                        insert(subsystem: "OBC", parameter: "available_mem", value: "{}") {{
-                            success,
-                            errors
-                        }}
-                    }}
-                "#,
-                    mem
-                );
-
-                match query(
-                    telemetry_service.clone(),
-                    &request,
-                    Some(Duration::from_secs(1)),
-                ) {
-                    Ok(msg) => {
-                        let success = msg.get("insert").and_then(|data| {
-                            data.get("success").and_then(|val| {
-                                val.as_bool()
-                            })
-                        });
-
-                        if success == Some(true) {
-                            info!("Current memory value saved to database");
-                        } else {
-                            match msg.get("errors") {
-                                Some(errors) => info!(
-                                    "Failed to save value to database: {}",
-                                    errors
-                                ),
-                                None => info!("Failed to save value to database"),
-                            };
-                        }
-                    }
+            let mem_info: MemInfo =
+                match monitor_service.get(Field::new("memInfo").field("available")) {
+                    Ok(mem_info) => mem_info,
                     Err(err) => {
-                        info!("Monitor service mutation failed: {}", err);
+                        info!("Monitor service query failed: {}", err);
                         continue;
                     }
+                };
+
+            // Save the amount to the telemetry database
+            if let Some(mem) = mem_info.available {
+                let insert = Field::new("insert")
+                    .arg("subsystem", "OBC")
+                    .arg("parameter", "available_mem")
+                    .arg("value", mem.to_string());
+
+                match telemetry_service.mutate::<MutationResponse>(insert) {
+                    Ok(_) => info!("Current memory value saved to database"),
+                    Err(err) => info!("Failed to save value to database: {}", err),
                 }
             }
         }
@@ -123,24 +106,12 @@ impl AppHandler for MyApp {
                 // Get a list of all the currently registered applications
                 info!("Querying for active applications");
                 
-                let request = r#"{
-                    apps {
-                        active,
-                        app {
-                            uuid,
-                            name,
-                            version,
-                            author
-                        }
-                    }
-                }"#;
-                
-                match query(
-                    ServiceConfig::new("app-service"),
-                    request,
-                    Some(Duration::from_secs(1)),
-                ) {
-                    Ok(msg) => info!("App query result: {:?}", msg),
+                let apps = Field::new("apps")
+                    .field("active")
+                    .field(Field::new("app").fields(&["uuid", "name", "version", "author"]));
+
+                match Client::new(ServiceConfig::new("app-service")?).get::<Vec<AppEntry>>(apps) {
+                    Ok(apps) => info!("App query result: {:?}", apps),
                     Err(err) => {
                         info!("App service query failed: {}", err);
                         bail!("App service query failed: {}", err)
@@ -148,7 +119,7 @@ impl AppHandler for MyApp {
                 }
             }
         }
-        
+
         Ok(())
     }
 }
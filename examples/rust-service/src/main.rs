@@ -37,7 +37,7 @@ fn main() {
     ).unwrap();
     
     Service::new(
-        Config::new("example-service"),
+        Config::load("example-service").unwrap(),
         Subsystem::new(),
         QueryRoot,
         MutationRoot,
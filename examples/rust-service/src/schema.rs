@@ -71,10 +71,9 @@ graphql_object!(QueryRoot : Context as "Query" |&self| {
     field subsystem(&executor) -> FieldResult<&Subsystem>
         as "Subsystem query"
     {
-        let num_queries = executor.context().get("num_queries");
+        let num_queries = executor.context().get_int("num_queries").unwrap_or(0);
         info!("Num queries {}", num_queries);
-        let num = num_queries.parse::<i32>().unwrap_or(0) + 1;
-        executor.context().set("num_queries", &format!("{}", num));
+        executor.context().set("num_queries", &format!("{}", num_queries + 1));
         Ok(executor.context().subsystem())
     }
 });
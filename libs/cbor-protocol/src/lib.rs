@@ -278,7 +278,8 @@ impl Protocol {
     /// ```
     ///
     pub fn peek_peer(&self) -> Result<SocketAddr, ProtocolError> {
-        let mut buf = vec![0; self.msg_size];
+        // Only the sender is needed, so the message is truncated rather than copied
+        let mut buf = [0; 1];
 
         let (_size, peer) = self
             .handle
@@ -315,6 +316,36 @@ impl Protocol {
         Ok((peer, message))
     }
 
+    /// Receive a UDP message and take note of the sender, even if the message can't be parsed
+    /// (no timeout)
+    ///
+    /// # Errors
+    ///
+    /// If the message can't be received, this function will return an error message string.
+    /// If it's received but can't be parsed, the sender is returned along with the error.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use cbor_protocol::*;
+    ///
+    /// let cbor_connection = Protocol::new("0.0.0.0:8000".to_owned(), 4096);
+    ///
+    /// let (source, message) = cbor_connection.recv_message_from().unwrap();
+    /// ```
+    ///
+    pub fn recv_message_from(
+        &self,
+    ) -> Result<(SocketAddr, Result<serde_cbor::Value, ProtocolError>), ProtocolError> {
+        let mut buf = vec![0; self.msg_size];
+        let (size, peer) = self
+            .handle
+            .recv_from(&mut buf)
+            .map_err(|err| ProtocolError::ReceiveFailed { err })?;
+
+        Ok((peer, self.recv_start(&buf[0..size])))
+    }
+
     /// Receive a UDP message and take note of the sender (with timeout)
     ///
     /// # Arguments
@@ -15,11 +15,56 @@
  */
 
 use error::*;
+use kubos_system::atomic_write;
+use std::collections::BTreeMap;
 use std::fs;
-use std::io::Write;
 use std::path::PathBuf;
 use toml;
 
+/// When an application should be automatically restarted after it exits
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RestartMode {
+    /// Never restart the application
+    Never,
+    /// Restart the application if it exits with a non-zero code or is killed by a signal
+    /// which wasn't requested through the app service
+    OnFailure,
+}
+
+/// The restart policy of an application, declared in the `[restart]` section of its manifest
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct RestartPolicy {
+    /// When the application should be restarted
+    pub policy: RestartMode,
+    /// The maximum number of consecutive restarts to attempt
+    pub max_retries: u32,
+    /// Seconds to wait before the first restart. The delay doubles with each further attempt.
+    pub backoff: u64,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        RestartPolicy {
+            policy: RestartMode::Never,
+            max_retries: 3,
+            backoff: 1,
+        }
+    }
+}
+
+/// Resource limits applied to an application's processes, declared in the `[limits]` section
+/// of its manifest
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct ResourceLimits {
+    /// Maximum CPU time, in seconds, each process may use before it's killed
+    pub cpu_time: Option<u64>,
+    /// Maximum size, in bytes, of each process's virtual memory
+    pub memory: Option<u64>,
+}
+
 /// The high level metadata of an application
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct AppMetadata {
@@ -29,6 +74,34 @@ pub struct AppMetadata {
     pub version: String,
     /// The author of the application
     pub author: String,
+    /// Services which must respond to a `ping` query before the application is started at boot
+    #[serde(default)]
+    pub requires: Vec<String>,
+    /// Seconds to wait for the required services before giving up on starting the application
+    pub requires_timeout: Option<u64>,
+    /// The directory the application is started in. Relative paths are relative to the
+    /// application's registry directory.
+    pub working_dir: Option<String>,
+    /// Seconds between runs of the application with the OnTick run level, if it should be
+    /// run periodically
+    pub tick: Option<u64>,
+    /// Seconds the application is given to exit after being sent `SIGTERM` before it's killed
+    pub shutdown_timeout: Option<u64>,
+    /// Environment variables to start the application with
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// Resource limits applied when the application is started
+    #[serde(default)]
+    pub limits: ResourceLimits,
+    /// Application-specific configuration, loaded by the application with `kubos_app::app_config`
+    pub config: Option<toml::Value>,
+    /// What to do when the application exits
+    #[serde(default)]
+    pub restart: RestartPolicy,
+    /// BLAKE2b-256 checksums (as generated by `b2sum -l 256`) of application files, keyed by
+    /// file name, which are verified when the application is registered
+    #[serde(default)]
+    pub checksums: BTreeMap<String, String>,
 }
 /// Kubos App struct
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -47,6 +120,16 @@ pub struct App {
 pub struct AppRegistryEntry {
     /// Whether or not this application is the active installation
     pub active_version: bool,
+    /// The number of consecutive times this version has failed when started with the
+    /// OnBoot run level
+    #[serde(default)]
+    pub boot_failures: u32,
+    /// The version which was active when this one was registered, used when rolling back
+    #[serde(default)]
+    pub previous_version: Option<String>,
+    /// Why this version was rolled back, if it was
+    #[serde(default)]
+    pub rollback_reason: Option<String>,
     /// The app itself
     pub app: App,
 }
@@ -80,7 +163,6 @@ impl AppRegistryEntry {
         let mut app_toml = PathBuf::from(self.app.path.clone());
         app_toml.set_file_name("app.toml");
 
-        let mut file = fs::File::create(app_toml)?;
         let toml_str = match toml::to_string(&self) {
             Ok(toml) => toml,
             Err(error) => {
@@ -91,6 +173,7 @@ impl AppRegistryEntry {
             }
         };
 
-        Ok(file.write_all(&toml_str.into_bytes())?)
+        // Replace the old file in one step, so it's never left partially written
+        Ok(atomic_write(app_toml, toml_str.as_bytes())?)
     }
 }
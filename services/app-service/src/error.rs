@@ -34,12 +34,36 @@ pub enum AppError {
         /// Underlying error encountered
         err: String,
     },
+    /// An application file didn't match the checksum listed in its manifest
+    #[fail(display = "Checksum mismatch: {}", err)]
+    ChecksumError {
+        /// Underlying error encountered
+        err: String,
+    },
     /// An error was encountered while starting an application
     #[fail(display = "Failed to start app: {}", err)]
     StartError {
         /// Underlying error encountered
         err: String,
     },
+    /// An error was encountered while stopping or signalling an application
+    #[fail(display = "Failed to kill app: {}", err)]
+    KillError {
+        /// Underlying error encountered
+        err: String,
+    },
+    /// An error was encountered while changing the active version of an application
+    #[fail(display = "Failed to change app version: {}", err)]
+    VersionError {
+        /// Underlying error encountered
+        err: String,
+    },
+    /// An error was encountered while scheduling an application
+    #[fail(display = "Failed to schedule app: {}", err)]
+    ScheduleError {
+        /// Underlying error encountered
+        err: String,
+    },
     /// An error was encountered while parsing data
     #[fail(display = "Failed to parse {}: {}", entity, err)]
     ParseError {
@@ -0,0 +1,105 @@
+/*
+ * Copyright (C) 2018 Kubos Corporation
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use app_entry::AppRegistryEntry;
+use error::*;
+use kubos_system::atomic_write;
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml;
+
+/// Name of the file in the registry directory which lists every registered application version
+pub const INDEX_FILE: &'static str = "registry.toml";
+
+/// Name of the directory in the registry where unusable files are moved instead of being deleted
+pub const QUARANTINE_DIR: &'static str = "quarantine";
+
+#[derive(Default, Deserialize, Serialize)]
+struct IndexFile {
+    #[serde(default)]
+    entry: Vec<AppRegistryEntry>,
+}
+
+/// Load the registry index of the registry at `apps_dir`.
+/// Returns `None` if the registry doesn't have an index yet.
+pub fn load_index(apps_dir: &str) -> Result<Option<Vec<AppRegistryEntry>>, AppError> {
+    let path = Path::new(apps_dir).join(INDEX_FILE);
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let contents = fs::read_to_string(&path)?;
+    let index: IndexFile = toml::from_str(&contents).map_err(|err| AppError::ParseError {
+        entity: INDEX_FILE.to_owned(),
+        err: err.to_string(),
+    })?;
+
+    Ok(Some(index.entry))
+}
+
+/// Save the registry index of the registry at `apps_dir`.
+///
+/// The index is written to a temporary file which then replaces the old one, so an interrupted
+/// save never leaves a partially written index behind.
+pub fn save_index(apps_dir: &str, entries: &[AppRegistryEntry]) -> Result<(), AppError> {
+    let path = Path::new(apps_dir).join(INDEX_FILE);
+    let contents = toml::to_string(&IndexFile {
+        entry: entries.to_vec(),
+    })
+    .map_err(|err| AppError::ParseError {
+        entity: INDEX_FILE.to_owned(),
+        err: err.to_string(),
+    })?;
+
+    atomic_write(path, contents.as_bytes())?;
+
+    Ok(())
+}
+
+/// Find the entry for a particular version of an application
+pub fn position(entries: &[AppRegistryEntry], uuid: &str, version: &str) -> Option<usize> {
+    entries
+        .iter()
+        .position(|entry| entry.app.uuid == uuid && entry.app.metadata.version == version)
+}
+
+/// Move a file or directory of the registry at `apps_dir` into its quarantine directory,
+/// keeping its path relative to the registry. Returns the new path.
+///
+/// If something with the same name was already quarantined, a numbered suffix is added.
+pub fn quarantine(apps_dir: &str, path: &Path) -> Result<PathBuf, AppError> {
+    let relative = path.strip_prefix(apps_dir).unwrap_or(path);
+    let name = relative
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let dir = Path::new(apps_dir)
+        .join(QUARANTINE_DIR)
+        .join(relative.parent().unwrap_or_else(|| Path::new("")));
+    fs::create_dir_all(&dir)?;
+
+    let mut dest = dir.join(&name);
+    let mut count = 1;
+    while dest.exists() {
+        dest = dir.join(format!("{}.{}", name, count));
+        count += 1;
+    }
+
+    fs::rename(path, &dest)?;
+    warn!("Quarantined {} in {}", path.display(), dest.display());
+
+    Ok(dest)
+}
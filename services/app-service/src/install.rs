@@ -0,0 +1,254 @@
+/*
+ * Copyright (C) 2018 Kubos Corporation
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use app_entry::AppMetadata;
+use blake2_rfc::blake2b::Blake2b;
+use error::*;
+use flate2::read::GzDecoder;
+use fs_extra;
+use logs::LOG_DIR;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use tar::Archive;
+use uuid::Uuid;
+
+/// Name of the directory in the registry where applications are prepared before being installed
+pub const STAGING_DIR: &'static str = ".staging";
+
+// Size, in bytes, of the BLAKE2b digests used as file checksums (`b2sum -l 256`)
+const CHECKSUM_SIZE: usize = 32;
+
+/// A copy of an application's files in the registry's staging area.
+///
+/// The files are removed when the staging area is dropped, unless they've been installed.
+pub struct Staging {
+    dir: PathBuf,
+    root: PathBuf,
+}
+
+impl Staging {
+    /// Copy or unpack an application into the staging area of the registry at `apps_dir`
+    ///
+    /// # Arguments
+    ///
+    /// * `apps_dir` - The root directory of the registry
+    /// * `source` - Either a directory containing the application files, or a `.tar.gz`
+    ///   archive of them
+    pub fn new(apps_dir: &str, source: &Path) -> Result<Staging, AppError> {
+        if !source.exists() {
+            return Err(AppError::RegisterError {
+                err: format!("{} does not exist", source.display()),
+            });
+        }
+
+        let is_archive = source
+            .file_name()
+            .map(|name| {
+                let name = name.to_string_lossy();
+                name.ends_with(".tar.gz") || name.ends_with(".tgz")
+            })
+            .unwrap_or(false);
+
+        if !source.is_dir() && !is_archive {
+            return Err(AppError::RegisterError {
+                err: format!("{} is not a directory or .tar.gz archive", source.display()),
+            });
+        }
+
+        let dir = Path::new(apps_dir)
+            .join(STAGING_DIR)
+            .join(Uuid::new_v4().hyphenated().to_string());
+        fs::create_dir_all(&dir)?;
+
+        // From here on, dropping the staging area cleans up after a failure
+        let mut staging = Staging {
+            root: dir.clone(),
+            dir,
+        };
+
+        if is_archive {
+            let file = File::open(source)?;
+            Archive::new(GzDecoder::new(file))
+                .unpack(&staging.dir)
+                .map_err(|err| AppError::RegisterError {
+                    err: format!("Failed to unpack {}: {}", source.display(), err),
+                })?;
+
+            // Archives may either contain the app files directly or a single directory of them
+            if !staging.dir.join("manifest.toml").exists() {
+                let contents: Vec<PathBuf> = fs::read_dir(&staging.dir)?
+                    .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                    .collect();
+                if contents.len() == 1 && contents[0].is_dir() {
+                    staging.root = contents[0].clone();
+                }
+            }
+        } else {
+            let files: Vec<PathBuf> = fs::read_dir(source)?
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .collect();
+
+            fs_extra::copy_items(&files, &staging.dir, &fs_extra::dir::CopyOptions::new())
+                .map_err(|error| AppError::RegisterError {
+                    err: format!("Error copying files into registry dir: {}", error),
+                })?;
+        }
+
+        Ok(staging)
+    }
+
+    /// The directory containing the staged application files
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Check the staged files against the checksums listed in the application's manifest
+    pub fn verify(&self, metadata: &AppMetadata) -> Result<(), AppError> {
+        verify_checksums(&self.root, metadata)
+    }
+
+    /// Move the staged files to their final location in the registry.
+    ///
+    /// Any existing files at `dest` are moved aside, and only removed once the returned
+    /// installation is finished. The switch is done with renames, so `dest` never contains a
+    /// partially installed application.
+    pub fn install(self, dest: &Path) -> Result<Installed, AppError> {
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let old = self.dir.with_extension("old");
+        let replacing = dest.exists();
+        if replacing {
+            fs::rename(dest, &old)?;
+        }
+
+        if let Err(err) = fs::rename(&self.root, dest) {
+            if replacing {
+                let _ = fs::rename(&old, dest);
+            }
+            return Err(err.into());
+        }
+
+        Ok(Installed {
+            dest: dest.to_path_buf(),
+            old: if replacing { Some(old) } else { None },
+            finished: false,
+        })
+    }
+}
+
+/// Application files which have been moved into the registry.
+///
+/// If the installation is dropped without being finished, the files are removed again and
+/// whatever they replaced is put back.
+pub struct Installed {
+    dest: PathBuf,
+    old: Option<PathBuf>,
+    finished: bool,
+}
+
+impl Installed {
+    /// Keep the installed files, removing whatever they replaced
+    pub fn finish(mut self) {
+        self.finished = true;
+        if let Some(ref old) = self.old {
+            // Keep the logs of the version being replaced
+            let _ = fs::rename(old.join(LOG_DIR), self.dest.join(LOG_DIR));
+            let _ = fs::remove_dir_all(old);
+        }
+    }
+}
+
+impl Drop for Installed {
+    fn drop(&mut self) {
+        if self.finished {
+            return;
+        }
+
+        if let Err(err) = fs::remove_dir_all(&self.dest) {
+            warn!("Failed to remove {}: {}", self.dest.display(), err);
+        }
+        if let Some(ref old) = self.old {
+            if let Err(err) = fs::rename(old, &self.dest) {
+                warn!("Failed to restore {}: {}", self.dest.display(), err);
+            }
+        }
+    }
+}
+
+impl Drop for Staging {
+    fn drop(&mut self) {
+        // Removes the staged files, or whatever was left around them once they were installed
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Remove anything left in the staging area of the registry at `apps_dir` by an interrupted
+/// registration
+pub fn clear_staging(apps_dir: &str) {
+    let _ = fs::remove_dir_all(Path::new(apps_dir).join(STAGING_DIR));
+}
+
+/// Calculate the BLAKE2b-256 checksum of a file, as a hex string
+pub fn checksum(path: &Path) -> Result<String, AppError> {
+    let mut file = File::open(path)?;
+    let mut hasher = Blake2b::new(CHECKSUM_SIZE);
+    let mut buf = [0; 4096];
+
+    loop {
+        let len = file.read(&mut buf)?;
+        if len == 0 {
+            break;
+        }
+        hasher.update(&buf[..len]);
+    }
+
+    Ok(hasher
+        .finalize()
+        .as_bytes()
+        .iter()
+        .map(|val| format!("{:02x}", val))
+        .collect())
+}
+
+/// Check the files in an application directory against the checksums listed in its manifest
+pub fn verify_checksums(dir: &Path, metadata: &AppMetadata) -> Result<(), AppError> {
+    for (file, expected) in metadata.checksums.iter() {
+        let path = dir.join(file);
+        if !path.starts_with(dir) || file.contains("..") {
+            return Err(AppError::ChecksumError {
+                err: format!("Invalid file name {}", file),
+            });
+        }
+
+        if !path.is_file() {
+            return Err(AppError::ChecksumError {
+                err: format!("{} not found", file),
+            });
+        }
+
+        let actual = checksum(&path)?;
+        if actual != expected.to_lowercase() {
+            return Err(AppError::ChecksumError {
+                err: format!("{} has checksum {}, expected {}", file, actual, expected),
+            });
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,194 @@
+/*
+ * Copyright (C) 2018 Kubos Corporation
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use app_entry::App;
+use chrono::Utc;
+use error::*;
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Name of the directory, within an application's version directory, holding its logs
+pub const LOG_DIR: &'static str = "logs";
+/// Name of the current log file. Rotated files have a numeric suffix, `.1` being the newest.
+pub const LOG_FILE: &'static str = "app.log";
+/// Default maximum size, in bytes, of a log file before it's rotated
+pub const DEFAULT_LOG_SIZE: u64 = 256 * 1024;
+/// Default number of rotated log files to keep
+pub const DEFAULT_LOG_FILES: u32 = 4;
+
+/// Returns the log directory of an application version
+pub fn log_dir(app: &App) -> PathBuf {
+    Path::new(&app.path)
+        .parent()
+        .unwrap_or_else(|| Path::new("/"))
+        .join(LOG_DIR)
+}
+
+/// Writes the output of application processes into rotated, size-limited log files.
+///
+/// Clones share the same lock, so output from concurrent processes of the same app is
+/// never interleaved mid-line or lost during a rotation.
+#[derive(Clone, Debug)]
+pub struct AppLogger {
+    max_size: u64,
+    max_files: u32,
+    lock: Arc<Mutex<()>>,
+}
+
+impl Default for AppLogger {
+    fn default() -> Self {
+        AppLogger::new(DEFAULT_LOG_SIZE, DEFAULT_LOG_FILES)
+    }
+}
+
+impl AppLogger {
+    /// Create a logger
+    ///
+    /// # Arguments
+    ///
+    /// * `max_size` - Size, in bytes, at which a log file is rotated
+    /// * `max_files` - Number of rotated files to keep
+    pub fn new(max_size: u64, max_files: u32) -> Self {
+        AppLogger {
+            max_size,
+            max_files,
+            lock: Arc::new(Mutex::new(())),
+        }
+    }
+
+    /// Append a timestamped line to the log in `dir`, noting which stream it came from
+    pub fn log(&self, dir: &Path, stream: &str, line: &str) {
+        let entry = format!(
+            "{} [{}] {}\n",
+            Utc::now().format("%Y-%m-%d %H:%M:%S%.3f"),
+            stream,
+            line.trim_end_matches(|c| c == '\n' || c == '\r')
+        );
+
+        let _guard = self.lock.lock().unwrap();
+        if let Err(err) = self.append(dir, &entry) {
+            warn!("Failed to write app log in {}: {}", dir.display(), err);
+        }
+    }
+
+    fn append(&self, dir: &Path, entry: &str) -> Result<(), AppError> {
+        fs::create_dir_all(dir)?;
+        let path = dir.join(LOG_FILE);
+
+        let size = fs::metadata(&path).map(|meta| meta.len()).unwrap_or(0);
+        if size > 0 && size + entry.len() as u64 > self.max_size {
+            self.rotate(dir)?;
+        }
+
+        let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+        file.write_all(entry.as_bytes())?;
+        Ok(())
+    }
+
+    // Shift each log file to the next suffix, dropping the oldest
+    fn rotate(&self, dir: &Path) -> Result<(), AppError> {
+        let path = dir.join(LOG_FILE);
+        if self.max_files == 0 {
+            fs::remove_file(&path)?;
+            return Ok(());
+        }
+
+        let _ = fs::remove_file(rotated(dir, self.max_files));
+        for num in (1..self.max_files).rev() {
+            let from = rotated(dir, num);
+            if from.exists() {
+                fs::rename(&from, rotated(dir, num + 1))?;
+            }
+        }
+        fs::rename(&path, rotated(dir, 1))?;
+        Ok(())
+    }
+
+    /// Copy everything written to `output` into the log in `dir`, one line at a time,
+    /// on a background thread
+    pub fn capture<R: Read + Send + 'static>(&self, dir: PathBuf, stream: &'static str, output: R) {
+        let logger = self.clone();
+        thread::spawn(move || {
+            let mut reader = BufReader::new(output);
+            let mut line = vec![];
+            loop {
+                line.clear();
+                match reader.read_until(b'\n', &mut line) {
+                    Ok(0) | Err(_) => break,
+                    Ok(_) => logger.log(&dir, stream, &String::from_utf8_lossy(&line)),
+                }
+            }
+        });
+    }
+}
+
+fn rotated(dir: &Path, num: u32) -> PathBuf {
+    dir.join(format!("{}.{}", LOG_FILE, num))
+}
+
+/// Returns the log files in `dir`, oldest first
+pub fn log_files(dir: &Path) -> Vec<PathBuf> {
+    let mut rotated: Vec<(u32, PathBuf)> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter_map(|entry| {
+                    let name = entry.file_name().to_string_lossy().into_owned();
+                    name.trim_start_matches(LOG_FILE)
+                        .trim_start_matches('.')
+                        .parse::<u32>()
+                        .ok()
+                        .filter(|_| name.starts_with(LOG_FILE))
+                        .map(|num| (num, entry.path()))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    rotated.sort_by(|a, b| b.0.cmp(&a.0));
+
+    let mut files: Vec<PathBuf> = rotated.into_iter().map(|(_, path)| path).collect();
+    let current = dir.join(LOG_FILE);
+    if current.exists() {
+        files.push(current);
+    }
+    files
+}
+
+/// Returns the last `lines` lines logged in `dir`, oldest first
+pub fn read_logs(dir: &Path, lines: usize) -> Result<Vec<String>, AppError> {
+    let mut result: Vec<String> = vec![];
+
+    // Work backwards from the current file until enough lines have been found
+    for path in log_files(dir).iter().rev() {
+        if result.len() >= lines {
+            break;
+        }
+        let contents = fs::read(path)?;
+        let mut file_lines: Vec<String> = String::from_utf8_lossy(&contents)
+            .lines()
+            .map(|line| line.to_owned())
+            .collect();
+        let skip = file_lines.len().saturating_sub(lines - result.len());
+        file_lines.drain(..skip);
+        file_lines.extend(result);
+        result = file_lines;
+    }
+
+    Ok(result)
+}
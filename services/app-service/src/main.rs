@@ -15,22 +15,29 @@
  */
 #![deny(warnings)]
 
+extern crate blake2_rfc;
+extern crate chrono;
 #[macro_use]
 extern crate failure;
+extern crate flate2;
 extern crate fs_extra;
 extern crate getopts;
 #[macro_use]
 extern crate juniper;
 extern crate kubos_app;
 extern crate kubos_service;
+extern crate kubos_system;
+extern crate libc;
 #[macro_use]
 extern crate log;
+extern crate nix;
 #[macro_use]
 extern crate serde_derive;
 #[cfg(test)]
 #[macro_use]
 extern crate serde_json;
 extern crate syslog;
+extern crate tar;
 #[cfg(test)]
 extern crate tempfile;
 extern crate toml;
@@ -38,19 +45,61 @@ extern crate uuid;
 
 mod app_entry;
 mod error;
+mod index;
+mod install;
+mod logs;
 mod objects;
 mod registry;
+mod scheduler;
 mod schema;
+mod supervisor;
 #[cfg(test)]
 mod tests;
 
 use failure::Error;
 use getopts::Options;
 use kubos_service::{Config, Service};
+use kubos_system::{
+    check_config_status, check_range, ServiceSettings, CHECK_CONFIG_FLAG, DEFAULT_PATH,
+};
+use logs::{AppLogger, DEFAULT_LOG_FILES, DEFAULT_LOG_SIZE};
 use registry::AppRegistry;
 use std::env;
+use std::process;
 use syslog::Facility;
 
+/// Settings for the applications service
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct AppServiceSettings {
+    /// Directory in which registered applications are stored
+    #[serde(rename = "registry-dir")]
+    pub registry_dir: Option<String>,
+    /// Number of consecutive failed OnBoot starts after which a newly registered app version
+    /// is rolled back to the previous version. Zero disables automatic rollback.
+    #[serde(rename = "rollback-threshold")]
+    pub rollback_threshold: Option<u32>,
+    /// Maximum size, in bytes, of each application log file before it's rotated
+    #[serde(rename = "log-max-size")]
+    pub log_max_size: Option<u64>,
+    /// Number of rotated log files to keep for each application version, in addition to the
+    /// current one
+    #[serde(rename = "log-max-files")]
+    pub log_max_files: Option<u32>,
+}
+
+impl ServiceSettings for AppServiceSettings {
+    fn validate(&self) -> Result<(), Error> {
+        if let Some(size) = self.log_max_size {
+            check_range("log-max-size", size, 1024, u64::from(u32::max_value()))?;
+        }
+        if let Some(files) = self.log_max_files {
+            check_range("log-max-files", files, 0, 100)?;
+        }
+        Ok(())
+    }
+}
+
 fn main() -> Result<(), Error> {
     syslog::init(
         Facility::LOG_DAEMON,
@@ -63,6 +112,7 @@ fn main() -> Result<(), Error> {
 
     opts.optflag("b", "onboot", "Execute OnBoot logic");
     opts.optopt("c", "config", "Path to config file", "CONFIG");
+    opts.optflag("", CHECK_CONFIG_FLAG, "Validate the config file and exit");
     let matches = match opts.parse(&args[1..]) {
         Ok(m) => m,
         Err(err) => {
@@ -70,17 +120,34 @@ fn main() -> Result<(), Error> {
         }
     };
 
-    let config = match matches.opt_str("c") {
-        Some(file) => Config::new_from_path("app-service", file),
-        None => Config::new("app-service"),
+    let config_path = matches
+        .opt_str("c")
+        .unwrap_or_else(|| DEFAULT_PATH.to_owned());
+    let loaded =
+        Config::load_settings_from_path::<AppServiceSettings>("app-service", config_path.clone());
+    if let Some(code) = check_config_status(&loaded) {
+        process::exit(code);
+    }
+    let (config, settings) = match loaded {
+        Ok(loaded) => loaded,
+        Err(err) => {
+            error!("Failed to load configuration: {}", err);
+            process::exit(1);
+        }
     };
 
-    let registry = {
-        match config.get("registry-dir") {
-            Some(dir) => AppRegistry::new_from_dir(dir.as_str().unwrap())?,
-            None => AppRegistry::new()?,
-        }
+    let mut registry = match settings.registry_dir {
+        Some(dir) => AppRegistry::new_from_dir(&dir)?,
+        None => AppRegistry::new()?,
     };
+    registry.config_path = config_path;
+    if let Some(threshold) = settings.rollback_threshold {
+        registry.rollback_threshold = threshold;
+    }
+    registry.supervisor.logger = AppLogger::new(
+        settings.log_max_size.unwrap_or(DEFAULT_LOG_SIZE),
+        settings.log_max_files.unwrap_or(DEFAULT_LOG_FILES),
+    );
 
     match matches.opt_present("b") {
         true => registry
@@ -89,6 +156,8 @@ fn main() -> Result<(), Error> {
         false => {}
     }
 
+    registry.start_scheduler();
+
     Service::new(config, registry, schema::QueryRoot, schema::MutationRoot).start();
 
     Ok(())
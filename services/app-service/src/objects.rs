@@ -16,6 +16,8 @@
 
 use app_entry;
 use juniper::FieldResult;
+use scheduler::Schedule;
+use supervisor::{epoch_secs, AppInstance, AppState};
 
 /// Common response fields structure for requests
 /// which don't return any specific data
@@ -49,6 +51,88 @@ pub struct StartResponse {
     pub pid: Option<i32>,
 }
 
+/// Response fields for the `verifyRegistry` mutation
+#[derive(GraphQLObject)]
+pub struct VerifyResponse {
+    /// Any errors encountered by the request
+    pub errors: String,
+    /// Request completion success or failure
+    pub success: bool,
+    /// The inconsistencies which were found in the registry
+    pub issues: Vec<String>,
+}
+
+/// Recent output of an application version
+#[derive(GraphQLObject)]
+#[graphql(name = "AppLogs")]
+pub struct KAppLogs {
+    /// Path of the current log file
+    pub path: String,
+    /// Paths of all of the app's log files, oldest first
+    pub files: Vec<String>,
+    /// The most recent log lines, oldest first
+    pub lines: Vec<String>,
+}
+
+/// Response fields for the `scheduleApp` mutation
+#[derive(GraphQLObject)]
+pub struct ScheduleResponse {
+    /// Any errors encountered by the request
+    pub errors: String,
+    /// Request completion success or failure
+    pub success: bool,
+    /// The new schedule
+    pub schedule: Option<KSchedule>,
+}
+
+pub struct KSchedule(pub Schedule);
+
+graphql_object!(KSchedule: () as "Schedule" |&self| {
+    description: "A scheduled run of a Kubos Application"
+
+    field id() -> FieldResult<i32>
+        as "Schedule ID"
+    {
+        Ok(self.0.id as i32)
+    }
+
+    field uuid() -> FieldResult<&String>
+        as "UUID of the app to start"
+    {
+        Ok(&self.0.uuid)
+    }
+
+    field args() -> FieldResult<&Vec<String>>
+        as "Additional arguments to start the app with"
+    {
+        Ok(&self.0.args)
+    }
+
+    field every() -> FieldResult<Option<i32>>
+        as "Seconds between runs, if periodic"
+    {
+        Ok(self.0.every.map(|every| every as i32))
+    }
+
+    field next_run() -> FieldResult<f64>
+        as "Time of the next run, in seconds since the UNIX epoch"
+    {
+        Ok(self.0.next_run)
+    }
+
+    field last_run() -> FieldResult<Option<f64>>
+        as "Time of the last run, in seconds since the UNIX epoch"
+    {
+        Ok(self.0.last_run)
+    }
+
+    field last_error() -> FieldResult<Option<&String>>
+        as "Why the app couldn't be started on the last run, if it couldn't"
+    {
+        Ok(self.0.last_error.as_ref())
+    }
+});
+
 pub struct KApp(pub app_entry::App);
 
 graphql_object!(KApp: () as "App" |&self| {
@@ -91,13 +175,79 @@ graphql_object!(KApp: () as "App" |&self| {
     }
 });
 
-pub struct KAppRegistryEntry(pub app_entry::AppRegistryEntry);
+pub struct KAppProcess(pub AppInstance);
+
+graphql_object!(KAppProcess: () as "AppProcess" |&self| {
+    description: "A started instance of a Kubos Application"
+
+    field pid() -> FieldResult<i32>
+        as "Process ID of the most recently started process"
+    {
+        Ok(self.0.pid as i32)
+    }
+
+    field state() -> FieldResult<AppState>
+        as "Current state"
+    {
+        Ok(self.0.state)
+    }
+
+    field run_level() -> FieldResult<String>
+        as "Run level the app was started with"
+    {
+        Ok(format!("{}", self.0.run_level))
+    }
+
+    field args() -> FieldResult<&Vec<String>>
+        as "Additional arguments the app was started with"
+    {
+        Ok(&self.0.args)
+    }
+
+    field exit_code() -> FieldResult<Option<i32>>
+        as "Exit code, if the process exited normally"
+    {
+        Ok(self.0.exit_code)
+    }
+
+    field signal() -> FieldResult<Option<i32>>
+        as "Signal which terminated the process, if any"
+    {
+        Ok(self.0.signal)
+    }
+
+    field start_time() -> FieldResult<f64>
+        as "Time the process was started, in seconds since the UNIX epoch"
+    {
+        Ok(epoch_secs(self.0.start_time))
+    }
+
+    field end_time() -> FieldResult<Option<f64>>
+        as "Time the process ended, in seconds since the UNIX epoch"
+    {
+        Ok(self.0.end_time.map(epoch_secs))
+    }
+
+    field restarts() -> FieldResult<i32>
+        as "Number of automatic restarts"
+    {
+        Ok(self.0.restarts as i32)
+    }
+});
+
+pub struct KAppRegistryEntry(pub app_entry::AppRegistryEntry, pub Vec<AppInstance>);
 
 graphql_object!(KAppRegistryEntry: () as "AppRegistryEntry" |&self| {
     field app() -> FieldResult<KApp>
         as "App"
     {
-        Ok(KApp(self.0.app.clone()))
+        let mut app = self.0.app.clone();
+        app.pid = self.1
+            .iter()
+            .find(|instance| instance.state == AppState::Running)
+            .map(|instance| instance.pid)
+            .unwrap_or(0);
+        Ok(KApp(app))
     }
 
     field active() -> FieldResult<bool>
@@ -105,4 +255,28 @@ graphql_object!(KAppRegistryEntry: () as "AppRegistryEntry" |&self| {
     {
         Ok(self.0.active_version)
     }
+
+    field boot_failures() -> FieldResult<i32>
+        as "Consecutive failed OnBoot starts of this version"
+    {
+        Ok(self.0.boot_failures as i32)
+    }
+
+    field previous_version() -> FieldResult<Option<&String>>
+        as "Version which was active when this one was registered"
+    {
+        Ok(self.0.previous_version.as_ref())
+    }
+
+    field rollback_reason() -> FieldResult<Option<&String>>
+        as "Why this version was rolled back, if it was"
+    {
+        Ok(self.0.rollback_reason.as_ref())
+    }
+
+    field processes() -> FieldResult<Vec<KAppProcess>>
+        as "Running instances of this app version, along with the most recently finished one"
+    {
+        Ok(self.1.iter().cloned().map(KAppProcess).collect())
+    }
 });
@@ -16,27 +16,53 @@
 
 use app_entry::*;
 use error::*;
-use fs_extra;
-use kubos_app::RunLevel;
-use std::cell::RefCell;
+use index::{load_index, position, quarantine, save_index, INDEX_FILE, QUARANTINE_DIR};
+use install::{clear_staging, verify_checksums, Staging};
+use kubos_app::{query, RunLevel, ServiceConfig};
+use kubos_system::{Discovery, DEFAULT_PATH};
+use logs::log_dir;
+use scheduler::{Scheduler, SCHEDULE_FILE};
+use std::collections::HashMap;
 use std::fs;
 use std::io::Read;
 use std::os::unix;
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+use supervisor::{epoch_secs, AppInstance, AppState, ExitHook, Supervisor};
 use toml;
 use uuid::Uuid;
 
 /// The default application registry directory in KubOS
 pub const K_APPS_DIR: &'static str = "/home/system/kubos/apps";
 
+/// The default number of seconds to wait for an app's required services to respond at boot
+pub const DEFAULT_REQUIRES_TIMEOUT: u64 = 60;
+
+/// The default number of consecutive OnBoot failures after which an app is rolled back
+pub const DEFAULT_ROLLBACK_THRESHOLD: u32 = 3;
+
 /// AppRegistry
-#[derive(Deserialize, Serialize, Debug)]
+///
+/// Clones share the same entries and supervisor.
+#[derive(Clone, Debug)]
 pub struct AppRegistry {
     #[doc(hidden)]
-    pub entries: RefCell<Vec<AppRegistryEntry>>,
+    pub entries: Arc<Mutex<Vec<AppRegistryEntry>>>,
     /// The managed root directory of the AppRegistry
     pub apps_dir: String,
+    /// Tracks the processes of started applications
+    pub supervisor: Supervisor,
+    /// The number of consecutive OnBoot failures after which the active version of an app is
+    /// automatically rolled back to its previous version. Zero disables automatic rollback.
+    pub rollback_threshold: u32,
+    /// Scheduled application runs
+    pub scheduler: Scheduler,
+    /// The config file used to find the services which applications require
+    pub config_path: String,
+    // Time of the last OnTick run of each app, in seconds since the UNIX epoch
+    last_ticks: Arc<Mutex<HashMap<String, f64>>>,
 }
 
 impl AppRegistry {
@@ -54,8 +80,13 @@ impl AppRegistry {
     /// ```
     pub fn new_from_dir(apps_dir: &str) -> Result<AppRegistry, AppError> {
         let registry = AppRegistry {
-            entries: RefCell::new(Vec::new()),
+            entries: Arc::new(Mutex::new(Vec::new())),
             apps_dir: String::from(apps_dir),
+            supervisor: Supervisor::default(),
+            rollback_threshold: DEFAULT_ROLLBACK_THRESHOLD,
+            scheduler: Scheduler::load(Path::new(apps_dir).join(SCHEDULE_FILE)),
+            config_path: DEFAULT_PATH.to_owned(),
+            last_ticks: Arc::new(Mutex::new(HashMap::new())),
         };
 
         let active_dir = PathBuf::from(format!("{}/active", apps_dir));
@@ -63,10 +94,30 @@ impl AppRegistry {
             fs::create_dir_all(&active_dir)?;
         }
 
-        registry
-            .entries
-            .borrow_mut()
-            .extend(registry.discover_apps()?);
+        // Remove anything left behind by an interrupted registration
+        clear_staging(apps_dir);
+
+        let entries = match load_index(apps_dir) {
+            Ok(Some(entries)) => entries,
+            result => {
+                // Rebuild the index from the app.toml files of each installed version
+                if let Err(err) = result {
+                    warn!("Failed to load registry index, rebuilding it: {}", err);
+                    quarantine(apps_dir, &Path::new(apps_dir).join(INDEX_FILE))?;
+                }
+                let entries = registry.discover_apps()?;
+                save_index(apps_dir, &entries)?;
+                entries
+            }
+        };
+
+        for entry in entries.iter().filter(|entry| entry.active_version) {
+            if let Some(dir) = Path::new(&entry.app.path).parent() {
+                registry.set_active(&entry.app.uuid, &dir.to_string_lossy())?;
+            }
+        }
+
+        registry.entries.lock().unwrap().extend(entries);
 
         Ok(registry)
     }
@@ -90,7 +141,13 @@ impl AppRegistry {
         for entry in fs::read_dir(&self.apps_dir)? {
             if let Ok(entry) = entry {
                 if let Ok(file_type) = entry.file_type() {
-                    if file_type.is_dir() && entry.file_name().to_str() != Some("active") {
+                    let name = entry.file_name();
+                    let name = name.to_string_lossy();
+                    if file_type.is_dir()
+                        && name != "active"
+                        && name != QUARANTINE_DIR
+                        && !name.starts_with('.')
+                    {
                         reg_entries.extend(self.discover_versions(entry.path())?);
                     }
                 }
@@ -114,22 +171,16 @@ impl AppRegistry {
                 .file_type()
                 .and_then(|file_type| Ok(file_type.is_dir()))
             {
-                Ok(true) => {
-                    if let Ok(entry) = AppRegistryEntry::from_dir(&version.path()) {
-                        if entry.active_version == true {
-                            self.set_active(&entry.app.uuid, &version.path().to_string_lossy())?;
-                        }
-                        reg_entries.push(entry);
-                    } else {
-                        // Don't really care if this fails, since this is just trying to help
-                        // prevent orphan files
-                        let _ = fs::remove_dir_all(version.path());
+                Ok(true) => match AppRegistryEntry::from_dir(&version.path()) {
+                    Ok(entry) => reg_entries.push(entry),
+                    Err(err) => {
+                        // Keep the files around in case they can be recovered by hand
+                        warn!("Failed to load {}: {}", version.path().display(), err);
+                        let _ = quarantine(&self.apps_dir, &version.path());
                     }
-                }
+                },
                 _ => {
-                    // Don't really care if this fails, since this is just trying to help
-                    // prevent orphan files
-                    let _ = fs::remove_dir_all(version.path());
+                    let _ = quarantine(&self.apps_dir, &version.path());
                 }
             }
         }
@@ -140,7 +191,8 @@ impl AppRegistry {
     // Create or update the active version symlink for an application
     fn set_active(&self, uuid: &str, app_dir: &str) -> Result<(), AppError> {
         let active_symlink = PathBuf::from(format!("{}/active/{}", self.apps_dir, uuid));
-        if active_symlink.exists() {
+        // Check the link itself, so that links to missing versions are replaced too
+        if active_symlink.symlink_metadata().is_ok() {
             if let Err(err) = fs::remove_file(active_symlink.clone()) {
                 return Err(AppError::RegisterError {
                     err: format!(
@@ -166,12 +218,16 @@ impl AppRegistry {
         Ok(())
     }
 
-    /// Register an application binary with the AppRegistry, extracting metadata and installing it
+    /// Register an application with the AppRegistry, extracting metadata and installing it
     /// into the proper folder structure under the AppRegistry directory.
     ///
+    /// Any checksums listed in the application's manifest are verified before it's installed.
+    ///
     /// # Arguments
     ///
-    /// * `path` - The path to an application binary
+    /// * `path` - The path to a directory containing the application files, or to a `.tar.gz`
+    ///   archive of them
+    /// * `uuid` - The UUID of an existing application to register a new version of
     ///
     /// # Examples
     ///
@@ -181,18 +237,10 @@ impl AppRegistry {
     /// registry.register("/home/kubos/my-app-bin");
     /// ```
     pub fn register(&self, path: &str, uuid: Option<String>) -> Result<AppRegistryEntry, AppError> {
-        let app_path = Path::new(path);
-        if !app_path.exists() {
-            return Err(AppError::RegisterError {
-                err: format!("{} does not exist", path),
-            });
-        }
-
-        if !app_path.is_dir() {
-            return Err(AppError::RegisterError {
-                err: format!("{} is not a directory", path),
-            });
-        }
+        // Copy or unpack the application into the staging area, which is cleaned up if
+        // anything goes wrong before it's installed
+        let staging = Staging::new(&self.apps_dir, Path::new(path))?;
+        let app_path = staging.root().to_path_buf();
 
         // Load the metadata
         let mut data = String::new();
@@ -217,23 +265,13 @@ impl AppRegistry {
             });
         }
 
-        let mut entries = self.entries.borrow_mut();
-        let app_uuid = match uuid {
-            Some(val) => {
-                for entry in entries.iter_mut() {
-                    // Find the existing active version of the app and make it inactive.
-                    // Use the existing UUID for our new app
-                    if entry.active_version && entry.app.uuid == val {
-                        entry.active_version = false;
-                        entry.save()?;
-                        break;
-                    }
-                }
+        staging.verify(&metadata)?;
 
-                val
-            }
-            None => Uuid::new_v4().hyphenated().to_string(),
-        };
+        self.required_services(&metadata.requires)
+            .map_err(|err| AppError::RegisterError { err })?;
+
+        let mut entries = self.entries.lock().unwrap();
+        let app_uuid = uuid.unwrap_or_else(|| Uuid::new_v4().hyphenated().to_string());
 
         let app_dir_str = format!(
             "{}/{}/{}",
@@ -241,45 +279,120 @@ impl AppRegistry {
             app_uuid,
             metadata.version.as_str()
         );
-        let app_dir = Path::new(&app_dir_str);
 
-        if !app_dir.exists() {
-            fs::create_dir_all(app_dir)?;
+        // Move everything into the official registry directory. Any files of an earlier
+        // registration of the same version are only removed once the entry has been added.
+        let installed = staging.install(Path::new(&app_dir_str))?;
+
+        let original = entries.clone();
+        let (reg_entry, upgraded) =
+            match self.add_entry(&mut entries, &app_uuid, metadata, &app_dir_str) {
+                Ok(added) => added,
+                Err(err) => {
+                    // Put the files back before the entries which refer to them
+                    drop(installed);
+                    self.undo_install(&mut entries, original, &app_uuid);
+                    return Err(err);
+                }
+            };
+        installed.finish();
+        drop(entries);
+
+        // Give the new version a chance to migrate anything left behind by the old one
+        if upgraded {
+            if let Err(err) = self.start_app(&reg_entry.app.uuid, RunLevel::OnUpgrade, None) {
+                warn!(
+                    "Failed to start {} version {} with the OnUpgrade run level: {}",
+                    reg_entry.app.uuid, reg_entry.app.metadata.version, err
+                );
+            }
         }
 
-        // Copy everything into the official registry directory
-        let files: Vec<PathBuf> = fs::read_dir(app_path)?
-            .filter_map(|file| 
-                if let Some(entry) = file.ok() {
-                    Some(entry.path())
+        Ok(reg_entry)
+    }
+
+    // Add the entry for a newly installed app version, making it the active version.
+    // Returns the entry and whether it replaced a different version.
+    fn add_entry(
+        &self,
+        entries: &mut Vec<AppRegistryEntry>,
+        app_uuid: &str,
+        metadata: AppMetadata,
+        app_dir: &str,
+    ) -> Result<(AppRegistryEntry, bool), AppError> {
+        // Find the existing active version of the app and make it inactive
+        let mut previous_version = None;
+        let mut upgraded = false;
+        for entry in entries.iter_mut() {
+            if entry.active_version && entry.app.uuid == app_uuid {
+                entry.active_version = false;
+                entry.save()?;
+                upgraded = entry.app.metadata.version != metadata.version;
+                previous_version = if upgraded {
+                    Some(entry.app.metadata.version.clone())
                 } else {
-                    None
-                }
-            ).collect();
+                    entry.previous_version.clone()
+                };
+                break;
+            }
+        }
 
-        fs_extra::copy_items(&files, app_dir, &fs_extra::dir::CopyOptions::new()).map_err(
-            |error| AppError::RegisterError {
-                err: format!("Error copying files into registry dir: {}", error),
-            },
-        )?;
+        // Re-registering a version replaces its files, so drop the stale entry
+        entries.retain(|entry| {
+            entry.app.uuid != app_uuid || entry.app.metadata.version != metadata.version
+        });
 
-        self.set_active(&app_uuid, &app_dir_str)?;
+        self.set_active(app_uuid, app_dir)?;
 
         let reg_entry = AppRegistryEntry {
             app: App {
-                uuid: app_uuid,
+                uuid: app_uuid.to_owned(),
+                path: format!("{}/{}", app_dir, metadata.name),
                 metadata: metadata,
                 pid: 0,
-                path: format!("{}/{}", app_dir_str, app_name),
             },
             active_version: true,
+            boot_failures: 0,
+            previous_version,
+            rollback_reason: None,
         };
 
-        // Add the new registry entry
-        entries.push(reg_entry);
         // Create the app.toml file and save the metadata information
-        entries[entries.len() - 1].save()?;
-        Ok(entries[entries.len() - 1].clone())
+        reg_entry.save()?;
+        // Add the new registry entry
+        entries.push(reg_entry.clone());
+        save_index(&self.apps_dir, entries)?;
+
+        Ok((reg_entry, upgraded))
+    }
+
+    // Put the registry entries back the way they were before a failed registration, once
+    // the installed files have been removed
+    fn undo_install(
+        &self,
+        entries: &mut Vec<AppRegistryEntry>,
+        original: Vec<AppRegistryEntry>,
+        app_uuid: &str,
+    ) {
+        *entries = original;
+
+        let previous = entries
+            .iter()
+            .find(|entry| entry.active_version && entry.app.uuid == app_uuid)
+            .map(|entry| entry.app.metadata.version.clone());
+        let restored = match previous {
+            Some(previous) => self.activate(entries, app_uuid, &previous),
+            None => {
+                let _ = fs::remove_file(format!("{}/active/{}", self.apps_dir, app_uuid));
+                save_index(&self.apps_dir, entries)
+            }
+        };
+        if let Err(err) = restored {
+            warn!(
+                "Failed to restore the registry after a failed registration: {}",
+                err
+            );
+        }
     }
 
     /// Uninstall an application from the AppRegistry
@@ -308,17 +421,22 @@ impl AppRegistry {
         }
 
         // Remove the app entry from the registry list
-        let mut entries = self.entries.borrow_mut();
-        match entries.binary_search_by(|ref e| {
-            e.app
-                .uuid
-                .cmp(&String::from(app_uuid))
-                .then(e.app.metadata.version.cmp(&String::from(version)))
-        }) {
-            Ok(index) => {
-                entries.remove(index);
-            }
-            Err(_) => {
+        let mut entries = self.entries.lock().unwrap();
+        match position(&entries, app_uuid, version) {
+            Some(index) => {
+                let entry = entries.remove(index);
+                if entry.active_version {
+                    let _ = fs::remove_file(format!("{}/active/{}", self.apps_dir, app_uuid));
+                }
+                if let Err(error) = save_index(&self.apps_dir, &entries) {
+                    let error = format!("Failed to save registry index: {}", error);
+                    errors = Some(match errors {
+                        Some(errors) => format!("{}. {}", errors, error),
+                        None => error,
+                    });
+                }
+            }
+            None => {
                 if let Some(error) = errors {
                     errors = Some(format!(
                         "{}. {} version {} not found in registry",
@@ -339,8 +457,198 @@ impl AppRegistry {
         }
     }
 
+    /// Check the registry for inconsistencies between the registry index and the installed
+    /// application files. Returns a description of each problem found.
+    ///
+    /// The following are checked:
+    ///
+    /// - Each registered version's files exist and match the checksums in its manifest
+    /// - Each installed version is in the registry index
+    /// - Each application has exactly one active version, which the `active` symlink points to
+    ///
+    /// If `repair` is true, the problems are also fixed. Unusable application files are moved
+    /// into the registry's quarantine directory rather than deleted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kubos_app::registry::AppRegistry;
+    /// let registry = AppRegistry::new();
+    /// for issue in registry.verify(true).unwrap() {
+    ///     println!("Repaired: {}", issue);
+    /// }
+    /// ```
+    pub fn verify(&self, repair: bool) -> Result<Vec<String>, AppError> {
+        let mut entries = self.entries.lock().unwrap();
+        let mut issues = vec![];
+
+        // Registered versions whose files are missing or have been modified
+        let mut index = 0;
+        while index < entries.len() {
+            let problem = {
+                let app = &entries[index].app;
+                let dir = Path::new(&app.path)
+                    .parent()
+                    .unwrap_or_else(|| Path::new("/"));
+                if !Path::new(&app.path).exists() {
+                    Some(format!(
+                        "{} version {}: {} is missing",
+                        app.uuid, app.metadata.version, app.path
+                    ))
+                } else {
+                    verify_checksums(dir, &app.metadata).err().map(|err| {
+                        format!("{} version {}: {}", app.uuid, app.metadata.version, err)
+                    })
+                }
+            };
+
+            match problem {
+                Some(problem) => {
+                    issues.push(problem);
+                    if repair {
+                        let entry = entries.remove(index);
+                        if let Some(dir) = Path::new(&entry.app.path).parent() {
+                            if dir.exists() {
+                                quarantine(&self.apps_dir, dir)?;
+                            }
+                        }
+                    } else {
+                        index += 1;
+                    }
+                }
+                None => index += 1,
+            }
+        }
+
+        // Installed versions which are missing from the index
+        for app_dir in fs::read_dir(&self.apps_dir)? {
+            let app_dir = app_dir?;
+            let uuid = app_dir.file_name().to_string_lossy().into_owned();
+            if !app_dir.file_type()?.is_dir()
+                || uuid == "active"
+                || uuid == QUARANTINE_DIR
+                || uuid.starts_with('.')
+            {
+                continue;
+            }
+
+            for version_dir in fs::read_dir(app_dir.path())? {
+                let version_dir = version_dir?;
+                let version = version_dir.file_name().to_string_lossy().into_owned();
+                if position(&entries, &uuid, &version).is_some() {
+                    continue;
+                }
+
+                match AppRegistryEntry::from_dir(&version_dir.path()) {
+                    Ok(mut entry) => {
+                        issues.push(format!(
+                            "{} version {} is installed but not registered",
+                            uuid, version
+                        ));
+                        if repair {
+                            entry.active_version = false;
+                            entries.push(entry);
+                        }
+                    }
+                    Err(err) => {
+                        issues.push(format!(
+                            "{} is not a valid app: {}",
+                            version_dir.path().display(),
+                            err
+                        ));
+                        if repair {
+                            quarantine(&self.apps_dir, &version_dir.path())?;
+                        }
+                    }
+                }
+            }
+        }
+
+        // Each app should have exactly one active version, pointed to by its active symlink
+        let mut uuids: Vec<String> = entries.iter().map(|entry| entry.app.uuid.clone()).collect();
+        uuids.sort();
+        uuids.dedup();
+        for uuid in uuids.iter() {
+            let linked = fs::read_link(format!("{}/active/{}", self.apps_dir, uuid)).ok();
+            let is_linked = |entry: &AppRegistryEntry| {
+                linked.as_ref().map(|link| link.as_path()) == Path::new(&entry.app.path).parent()
+            };
+
+            let active: Vec<usize> = (0..entries.len())
+                .filter(|&index| entries[index].app.uuid == *uuid && entries[index].active_version)
+                .collect();
+            if active.len() > 1 {
+                issues.push(format!("{} has {} active versions", uuid, active.len()));
+            } else if active.is_empty() {
+                issues.push(format!("{} has no active version", uuid));
+            }
+
+            let keep = match active.len() {
+                1 => active[0],
+                _ => {
+                    // Prefer the version the symlink points to, then the most recently registered
+                    let candidates: Vec<usize> = (0..entries.len())
+                        .filter(|&index| entries[index].app.uuid == *uuid)
+                        .filter(|&index| active.is_empty() || active.contains(&index))
+                        .collect();
+                    match candidates.iter().find(|&&index| is_linked(&entries[index])) {
+                        Some(&index) => index,
+                        None => candidates[candidates.len() - 1],
+                    }
+                }
+            };
+
+            let relink = !is_linked(&entries[keep]);
+            if active.len() == 1 && relink {
+                issues.push(format!(
+                    "The active symlink of {} doesn't point to its active version {}",
+                    uuid, entries[keep].app.metadata.version
+                ));
+            }
+
+            if repair {
+                for entry in entries.iter_mut().filter(|entry| entry.app.uuid == *uuid) {
+                    entry.active_version = false;
+                }
+                entries[keep].active_version = true;
+                if relink {
+                    if let Some(dir) = Path::new(&entries[keep].app.path).parent() {
+                        self.set_active(uuid, &dir.to_string_lossy())?;
+                    }
+                }
+            }
+        }
+
+        // Active symlinks of apps which aren't registered
+        for link in fs::read_dir(format!("{}/active", self.apps_dir))? {
+            let link = link?;
+            let uuid = link.file_name().to_string_lossy().into_owned();
+            if !uuids.contains(&uuid) {
+                issues.push(format!(
+                    "{} has an active symlink but isn't registered",
+                    uuid
+                ));
+                if repair {
+                    fs::remove_file(link.path())?;
+                }
+            }
+        }
+
+        if repair {
+            for entry in entries.iter() {
+                entry.save()?;
+            }
+            save_index(&self.apps_dir, &entries)?;
+        }
+
+        Ok(issues)
+    }
+
     /// Start an application. If successful, returns the pid of the application process.
     ///
+    /// The process is supervised until it exits, and is restarted according to the
+    /// restart policy in the application's manifest.
+    ///
     /// # Arguments
     ///
     /// * `app_uuid` - The UUID generated for the app when it was registered
@@ -361,7 +669,7 @@ impl AppRegistry {
     ) -> Result<u32, AppError> {
         // Look up the active version of the requested application
         let app = {
-            let entries = self.entries.borrow();
+            let entries = self.entries.lock().unwrap();
             match entries
                 .iter()
                 .find(|ref e| e.active_version && e.app.uuid == app_uuid)
@@ -388,24 +696,223 @@ impl AppRegistry {
             return Err(AppError::StartError { err: msg });
         }
 
-        let mut cmd = Command::new(app_path);
+        // Keep track of consecutive OnBoot failures so a bad upgrade can be rolled back
+        let on_exit = match run_level {
+            RunLevel::OnBoot => {
+                let registry = self.clone();
+                let hook: ExitHook = Box::new(move |instance| registry.boot_finished(instance));
+                Some(hook)
+            }
+            RunLevel::OnCommand | RunLevel::OnUpgrade | RunLevel::OnTick => None,
+        };
+
+        self.supervisor
+            .start(&app, run_level.clone(), args.unwrap_or_default(), on_exit)
+            .map_err(|err| {
+                if run_level == RunLevel::OnBoot {
+                    self.record_boot_result(
+                        &app.uuid,
+                        &app.metadata.version,
+                        Some(err.to_string()),
+                    );
+                }
+                err
+            })
+    }
+
+    /// Make a registered version of an application the active version
+    ///
+    /// # Arguments
+    ///
+    /// * `app_uuid` - The UUID of the application
+    /// * `version` - The version to activate
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kubos_app::registry::AppRegistry;
+    /// let registry = AppRegistry::new();
+    /// registry.set_version("01234567-89ab-cdef0-1234-56789abcdef0", "1.0");
+    /// ```
+    pub fn set_version(&self, app_uuid: &str, version: &str) -> Result<(), AppError> {
+        let mut entries = self.entries.lock().unwrap();
+        self.activate(&mut entries, app_uuid, version)
+    }
+
+    /// Deactivate the active version of an application and reactivate the version which was
+    /// active before it was registered
+    ///
+    /// # Arguments
+    ///
+    /// * `app_uuid` - The UUID of the application
+    /// * `reason` - Why the version is being rolled back. Recorded in its `app.toml` file.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use kubos_app::registry::AppRegistry;
+    /// let registry = AppRegistry::new();
+    /// registry.rollback("01234567-89ab-cdef0-1234-56789abcdef0", "Telemetry is garbled");
+    /// ```
+    pub fn rollback(&self, app_uuid: &str, reason: &str) -> Result<String, AppError> {
+        let mut entries = self.entries.lock().unwrap();
+        self.rollback_entries(&mut entries, app_uuid, reason)
+    }
+
+    // Returns the version which is now active
+    fn rollback_entries(
+        &self,
+        entries: &mut Vec<AppRegistryEntry>,
+        app_uuid: &str,
+        reason: &str,
+    ) -> Result<String, AppError> {
+        let index = entries
+            .iter()
+            .position(|entry| entry.active_version && entry.app.uuid == app_uuid)
+            .ok_or_else(|| AppError::VersionError {
+                err: format!("No active version found for UUID {}", app_uuid),
+            })?;
+
+        let previous = match entries[index].previous_version.clone() {
+            Some(version) => version,
+            None => {
+                return Err(AppError::VersionError {
+                    err: format!("No previous version of {} to roll back to", app_uuid),
+                })
+            }
+        };
+
+        self.activate(entries, app_uuid, &previous)?;
+
+        entries[index].rollback_reason = Some(reason.to_owned());
+        self.save_entry(entries, index)?;
+
+        Ok(previous)
+    }
+
+    // Switch the active version of an app, updating the app.toml files and the active symlink
+    fn activate(
+        &self,
+        entries: &mut Vec<AppRegistryEntry>,
+        app_uuid: &str,
+        version: &str,
+    ) -> Result<(), AppError> {
+        let index = position(entries, app_uuid, version).ok_or_else(|| AppError::VersionError {
+            err: format!("{} version {} not found in registry", app_uuid, version),
+        })?;
+
+        let app_dir = match Path::new(&entries[index].app.path).parent() {
+            Some(dir) => dir.to_string_lossy().into_owned(),
+            None => {
+                return Err(AppError::VersionError {
+                    err: format!("Invalid app path {}", entries[index].app.path),
+                })
+            }
+        };
+        self.set_active(app_uuid, &app_dir)?;
 
-        cmd.env("KUBOS_APP_UUID", app.uuid.clone())
-            .arg("-r")
-            .arg(format!("{}", run_level));
+        for entry in entries
+            .iter_mut()
+            .filter(|entry| entry.app.uuid == app_uuid && entry.active_version)
+        {
+            entry.active_version = false;
+            entry.save()?;
+        }
 
-        if let Some(add_args) = args {
-            cmd.args(&add_args);
+        {
+            let entry = &mut entries[index];
+            entry.active_version = true;
+            entry.boot_failures = 0;
+            entry.rollback_reason = None;
         }
+        self.save_entry(entries, index)
+    }
 
-        match cmd.spawn() {
-            Ok(child) => Ok(child.id()),
-            Err(err) => {
-                return Err(AppError::StartError {
-                    err: format!("Failed to spawn app: {:?}", err),
+    // Save an entry's app.toml file along with the registry index
+    fn save_entry(&self, entries: &[AppRegistryEntry], index: usize) -> Result<(), AppError> {
+        entries[index].save()?;
+        save_index(&self.apps_dir, entries)
+    }
+
+    // Called whenever an OnBoot process finishes. Returns whether it may be restarted.
+    fn boot_finished(&self, instance: &AppInstance) -> bool {
+        let failure = match instance.state {
+            AppState::Exited => None,
+            AppState::Restarting | AppState::Failed => {
+                Some(match (instance.exit_code, instance.signal) {
+                    (Some(code), _) => format!("Exited with code {}", code),
+                    (None, Some(signal)) => format!("Killed by signal {}", signal),
+                    (None, None) => "Exited abnormally".to_owned(),
                 })
             }
+            _ => return true,
+        };
+
+        self.record_boot_result(&instance.app.uuid, &instance.app.metadata.version, failure)
+    }
+
+    // Update the count of consecutive OnBoot failures of an app version, rolling back to the
+    // previous version once it reaches the threshold. Returns false if the app was rolled back.
+    fn record_boot_result(&self, app_uuid: &str, version: &str, failure: Option<String>) -> bool {
+        let rolled_back = {
+            let mut entries = self.entries.lock().unwrap();
+            let index = match position(&entries, app_uuid, version) {
+                Some(index) => index,
+                None => return true,
+            };
+
+            let failure = match failure {
+                Some(failure) => failure,
+                None => {
+                    if entries[index].boot_failures != 0 {
+                        entries[index].boot_failures = 0;
+                        if let Err(err) = self.save_entry(&entries, index) {
+                            warn!("Failed to save {} version {}: {}", app_uuid, version, err);
+                        }
+                    }
+                    return true;
+                }
+            };
+
+            entries[index].boot_failures += 1;
+            if let Err(err) = self.save_entry(&entries, index) {
+                warn!("Failed to save {} version {}: {}", app_uuid, version, err);
+            }
+
+            let boot_failures = entries[index].boot_failures;
+            if !entries[index].active_version
+                || entries[index].previous_version.is_none()
+                || self.rollback_threshold == 0
+                || boot_failures < self.rollback_threshold
+            {
+                return true;
+            }
+
+            let reason = format!(
+                "Failed to start with the OnBoot run level {} times in a row. Last failure: {}",
+                boot_failures, failure
+            );
+            match self.rollback_entries(&mut entries, app_uuid, &reason) {
+                Ok(previous) => previous,
+                Err(err) => {
+                    error!(
+                        "Failed to roll back {} version {}: {}",
+                        app_uuid, version, err
+                    );
+                    return true;
+                }
+            }
+        };
+
+        warn!(
+            "Rolled back {} from version {} to version {}",
+            app_uuid, version, rolled_back
+        );
+        if let Err(err) = self.start_app(app_uuid, RunLevel::OnBoot, None) {
+            error!("Failed to start {} after rolling back: {}", app_uuid, err);
         }
+
+        false
     }
 
     /// Call the active version of all registered applications with the "OnBoot" run level
@@ -419,6 +926,7 @@ impl AppRegistry {
     /// ```
     pub fn run_onboot(&self) -> Result<(), AppError> {
         let mut apps_started = 0;
+        let mut apps_waiting = 0;
         let mut apps_not_started = 0;
 
         let active_symlink = PathBuf::from(format!("{}/active", self.apps_dir));
@@ -432,6 +940,16 @@ impl AppRegistry {
             match entry {
                 Ok(file) => {
                     let uuid = file.file_name();
+
+                    // Apps which require other services are started once those services respond
+                    if let Some(app) = self.active_app(&uuid.to_string_lossy()) {
+                        if !app.metadata.requires.is_empty() {
+                            self.start_when_ready(app);
+                            apps_waiting += 1;
+                            continue;
+                        }
+                    }
+
                     match self.start_app(&uuid.to_string_lossy(), RunLevel::OnBoot, None) {
                         Ok(_) => apps_started += 1,
                         Err(error) => {
@@ -445,8 +963,8 @@ impl AppRegistry {
         }
 
         info!(
-            "Apps started: {}, Apps failed: {}",
-            apps_started, apps_not_started
+            "Apps started: {}, Apps waiting for services: {}, Apps failed: {}",
+            apps_started, apps_waiting, apps_not_started
         );
 
         if apps_not_started != 0 {
@@ -457,4 +975,173 @@ impl AppRegistry {
 
         Ok(())
     }
+
+    fn active_app(&self, app_uuid: &str) -> Option<App> {
+        self.entries
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|entry| entry.active_version && entry.app.uuid == app_uuid)
+            .map(|entry| entry.app.clone())
+    }
+
+    // Start an app with the OnBoot run level once its required services respond,
+    // without holding up the rest of the boot process
+    fn start_when_ready(&self, app: App) {
+        let registry = self.clone();
+        thread::spawn(move || {
+            let timeout = Duration::from_secs(
+                app.metadata
+                    .requires_timeout
+                    .unwrap_or(DEFAULT_REQUIRES_TIMEOUT),
+            );
+
+            let result = registry
+                .wait_for_services(&app.metadata.requires, timeout)
+                .and_then(|_| registry.start_app(&app.uuid, RunLevel::OnBoot, None));
+
+            if let Err(err) = result {
+                error!("Failed to start {}: {}", app.uuid, err);
+                registry
+                    .supervisor
+                    .logger
+                    .log(&log_dir(&app), "service", &err.to_string());
+            }
+        });
+    }
+
+    // Look up the configuration of each of the services an app requires, so that an app which
+    // requires a service that isn't in the config file is rejected rather than left waiting
+    fn required_services(&self, services: &[String]) -> Result<Vec<ServiceConfig>, String> {
+        services
+            .iter()
+            .map(|service| {
+                ServiceConfig::load_from_path(service, self.config_path.clone()).map_err(|err| {
+                    format!("Required service {} is not configured: {}", service, err)
+                })
+            })
+            .collect()
+    }
+
+    // Wait for each of the given services to answer a `ping` query
+    fn wait_for_services(&self, services: &[String], timeout: Duration) -> Result<(), AppError> {
+        let configs = self
+            .required_services(services)
+            .map_err(|err| AppError::StartError { err })?;
+        let start = Instant::now();
+
+        for (service, config) in services.iter().zip(configs) {
+            loop {
+                // The service may announce a different address once it's running
+                let config = config.clone().discovered(&Discovery::new());
+                if let Ok(data) = query(config, "{ ping }", Some(Duration::from_secs(1))) {
+                    if data["ping"] == "pong" {
+                        break;
+                    }
+                }
+
+                if start.elapsed() >= timeout {
+                    return Err(AppError::StartError {
+                        err: format!(
+                            "Required service {} did not respond within {} seconds",
+                            service,
+                            timeout.as_secs()
+                        ),
+                    });
+                }
+                thread::sleep(Duration::from_millis(500));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Start the applications of any schedules which are due to run at `now`
+    /// (in seconds since the UNIX epoch). Applications are started with the OnCommand run level.
+    pub fn run_schedules(&self, now: f64) {
+        for schedule in self.scheduler.take_due(now) {
+            let error = match self.start_app(
+                &schedule.uuid,
+                RunLevel::OnCommand,
+                Some(schedule.args.clone()),
+            ) {
+                Ok(pid) => {
+                    info!(
+                        "Started {} (pid {}) for schedule {}",
+                        schedule.uuid, pid, schedule.id
+                    );
+                    None
+                }
+                Err(err) => {
+                    error!(
+                        "Failed to start {} for schedule {}: {}",
+                        schedule.uuid, schedule.id, err
+                    );
+                    Some(err.to_string())
+                }
+            };
+            self.scheduler.record(schedule.id, error);
+        }
+    }
+
+    /// Start the active version of any applications whose manifest sets a `tick` interval
+    /// with the OnTick run level, if that interval has passed since their last tick.
+    ///
+    /// An application's first tick happens one interval after it's first checked, and ticks are
+    /// skipped while the previous one is still running.
+    pub fn run_ticks(&self, now: f64) {
+        let ticking: Vec<(String, u64)> = self
+            .entries
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|entry| entry.active_version)
+            .filter_map(|entry| match entry.app.metadata.tick {
+                Some(tick) if tick > 0 => Some((entry.app.uuid.clone(), tick)),
+                _ => None,
+            })
+            .collect();
+
+        let mut last_ticks = self.last_ticks.lock().unwrap();
+        last_ticks.retain(|uuid, _| ticking.iter().any(|&(ref ticking, _)| ticking == uuid));
+
+        for (uuid, tick) in ticking {
+            let last = *last_ticks.entry(uuid.clone()).or_insert(now);
+            if now - last < tick as f64 {
+                continue;
+            }
+            last_ticks.insert(uuid.clone(), now);
+
+            let busy = self
+                .supervisor
+                .instances(&uuid)
+                .iter()
+                .any(|instance| instance.run_level == RunLevel::OnTick && instance.is_active());
+            if busy {
+                warn!(
+                    "Skipping tick of {}, the previous one is still running",
+                    uuid
+                );
+                continue;
+            }
+
+            if let Err(err) = self.start_app(&uuid, RunLevel::OnTick, None) {
+                error!(
+                    "Failed to start {} with the OnTick run level: {}",
+                    uuid, err
+                );
+            }
+        }
+    }
+
+    /// Check for due schedules and ticks once a second on a background thread
+    pub fn start_scheduler(&self) {
+        let registry = self.clone();
+        thread::spawn(move || loop {
+            let now = epoch_secs(SystemTime::now());
+            registry.run_schedules(now);
+            registry.run_ticks(now);
+            thread::sleep(Duration::from_secs(1));
+        });
+    }
 }
@@ -0,0 +1,252 @@
+/*
+ * Copyright (C) 2018 Kubos Corporation
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use error::*;
+use kubos_system::atomic_write;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use toml;
+
+/// Name of the file in the registry directory where schedules are saved
+pub const SCHEDULE_FILE: &'static str = "schedules.toml";
+
+/// A request to start an application at a particular time, and optionally periodically after that
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Schedule {
+    /// Identifies the schedule so it can be cancelled
+    pub id: u32,
+    /// The UUID of the application to start
+    pub uuid: String,
+    /// Additional arguments to start the application with
+    pub args: Vec<String>,
+    /// Seconds between each run, if the schedule is periodic
+    pub every: Option<u64>,
+    /// When the application will next be started, in seconds since the UNIX epoch
+    pub next_run: f64,
+    /// When the application was last started by this schedule
+    pub last_run: Option<f64>,
+    /// Why the application couldn't be started the last time this schedule ran, if it couldn't
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct ScheduleFile {
+    // The id of the next schedule added. Kept so that ids aren't reused once the newest
+    // schedules have been removed.
+    #[serde(default)]
+    next_id: u32,
+    #[serde(default)]
+    schedule: Vec<Schedule>,
+}
+
+/// The persistent list of scheduled application runs.
+///
+/// Every change is saved to disk, so schedules survive restarts of the service.
+/// Clones share the same list.
+#[derive(Clone, Debug, Default)]
+pub struct Scheduler {
+    path: Option<PathBuf>,
+    schedules: Arc<Mutex<ScheduleFile>>,
+}
+
+impl Scheduler {
+    /// Load the schedules saved at `path`.
+    ///
+    /// If the file can't be parsed, it's moved aside so that new schedules can still be saved.
+    pub fn load(path: PathBuf) -> Self {
+        let mut schedules = match fs::read_to_string(&path) {
+            Ok(contents) => match toml::from_str::<ScheduleFile>(&contents) {
+                Ok(file) => file,
+                Err(err) => {
+                    error!(
+                        "Failed to parse {}, discarding schedules: {}",
+                        path.display(),
+                        err
+                    );
+                    let _ = fs::rename(&path, path.with_extension("toml.bad"));
+                    ScheduleFile::default()
+                }
+            },
+            Err(_) => ScheduleFile::default(),
+        };
+
+        // Files saved by older versions don't record the next id
+        let max_id = schedules.schedule.iter().map(|s| s.id).max().unwrap_or(0);
+        schedules.next_id = schedules.next_id.max(max_id + 1);
+
+        Scheduler {
+            path: Some(path),
+            schedules: Arc::new(Mutex::new(schedules)),
+        }
+    }
+
+    /// Add a schedule
+    ///
+    /// # Arguments
+    ///
+    /// * `uuid` - The UUID of the application to start
+    /// * `at` - When to first start the application, in seconds since the UNIX epoch
+    /// * `every` - Seconds between each run. Without `at`, the first run is `every` seconds from now.
+    /// * `args` - Additional arguments to start the application with
+    /// * `now` - The current time, in seconds since the UNIX epoch
+    pub fn add(
+        &self,
+        uuid: &str,
+        at: Option<f64>,
+        every: Option<u64>,
+        args: Vec<String>,
+        now: f64,
+    ) -> Result<Schedule, AppError> {
+        let next_run = match (at, every) {
+            (_, Some(0)) => {
+                return Err(AppError::ScheduleError {
+                    err: "Period must be at least one second".to_owned(),
+                })
+            }
+            (Some(at), _) if at < now => {
+                return Err(AppError::ScheduleError {
+                    err: format!("Start time {} is in the past", at),
+                })
+            }
+            (Some(at), _) => at,
+            (None, Some(every)) => now + every as f64,
+            (None, None) => {
+                return Err(AppError::ScheduleError {
+                    err: "Either a start time or a period is required".to_owned(),
+                })
+            }
+        };
+
+        let mut schedules = self.schedules.lock().unwrap();
+        let schedule = Schedule {
+            id: schedules.next_id,
+            uuid: uuid.to_owned(),
+            args,
+            every,
+            next_run,
+            last_run: None,
+            last_error: None,
+        };
+
+        schedules.schedule.push(schedule.clone());
+        schedules.next_id += 1;
+        if let Err(err) = self.save(&schedules) {
+            schedules.schedule.pop();
+            schedules.next_id -= 1;
+            return Err(err);
+        }
+
+        Ok(schedule)
+    }
+
+    /// Remove a schedule
+    pub fn cancel(&self, id: u32) -> Result<(), AppError> {
+        let mut schedules = self.schedules.lock().unwrap();
+        let index = schedules
+            .schedule
+            .iter()
+            .position(|schedule| schedule.id == id)
+            .ok_or_else(|| AppError::ScheduleError {
+                err: format!("Schedule {} not found", id),
+            })?;
+
+        let removed = schedules.schedule.remove(index);
+        if let Err(err) = self.save(&schedules) {
+            schedules.schedule.insert(index, removed);
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    /// Returns all schedules, in the order they were added
+    pub fn list(&self) -> Vec<Schedule> {
+        self.schedules.lock().unwrap().schedule.clone()
+    }
+
+    /// Returns the schedules which are due to run at `now`, moving periodic schedules on to
+    /// their next run and removing one-off schedules.
+    ///
+    /// If the service was down for several periods, the missed runs are only made up once.
+    pub fn take_due(&self, now: f64) -> Vec<Schedule> {
+        let mut schedules = self.schedules.lock().unwrap();
+        let due: Vec<Schedule> = schedules
+            .schedule
+            .iter()
+            .filter(|schedule| schedule.next_run <= now)
+            .cloned()
+            .collect();
+
+        if due.is_empty() {
+            return due;
+        }
+
+        schedules
+            .schedule
+            .retain(|schedule| schedule.next_run > now || schedule.every.is_some());
+        for schedule in schedules.schedule.iter_mut().filter(|s| s.next_run <= now) {
+            let every = schedule.every.unwrap_or(1) as f64;
+            let missed = ((now - schedule.next_run) / every).floor() + 1.0;
+            schedule.next_run += missed * every;
+            schedule.last_run = Some(now);
+        }
+
+        if let Err(err) = self.save(&schedules) {
+            error!("Failed to save schedules: {}", err);
+        }
+
+        due
+    }
+
+    /// Record the result of starting the application of a periodic schedule
+    pub fn record(&self, id: u32, error: Option<String>) {
+        let mut schedules = self.schedules.lock().unwrap();
+        if let Some(schedule) = schedules
+            .schedule
+            .iter_mut()
+            .find(|schedule| schedule.id == id)
+        {
+            if schedule.last_error == error {
+                return;
+            }
+            schedule.last_error = error;
+        } else {
+            return;
+        }
+
+        if let Err(err) = self.save(&schedules) {
+            error!("Failed to save schedules: {}", err);
+        }
+    }
+
+    // Atomically replace the schedule file
+    fn save(&self, schedules: &ScheduleFile) -> Result<(), AppError> {
+        let path = match self.path {
+            Some(ref path) => path,
+            None => return Ok(()),
+        };
+
+        let contents = toml::to_string(schedules).map_err(|err| AppError::ParseError {
+            entity: "schedules".to_owned(),
+            err: err.to_string(),
+        })?;
+
+        atomic_write(path, contents.as_bytes())?;
+
+        Ok(())
+    }
+}
@@ -17,8 +17,15 @@
 use juniper::FieldResult;
 use kubos_app::RunLevel;
 use kubos_service;
+use logs::{log_dir, log_files, read_logs, LOG_FILE};
+use nix::sys::signal::Signal;
 use objects::*;
 use registry::AppRegistry;
+use std::time::{Duration, SystemTime};
+use supervisor::epoch_secs;
+
+/// Default number of lines returned by the `logs` query
+const DEFAULT_LOG_LINES: i32 = 100;
 
 type Context = kubos_service::Context<AppRegistry>;
 
@@ -35,7 +42,8 @@ graphql_object!(QueryRoot : Context as "Query" |&self| {
         -> FieldResult<Vec<KAppRegistryEntry>> as "Kubos Apps Query"
     {
         let mut result: Vec<KAppRegistryEntry> = Vec::new();
-        let entries = executor.context().subsystem().entries.borrow();
+        let registry = executor.context().subsystem();
+        let entries = registry.entries.lock().unwrap();
         let mut final_iter = entries.iter().filter(|ref e| {
             if uuid.is_some() && &e.app.uuid != uuid.as_ref().unwrap() {
                 return false;
@@ -53,11 +61,64 @@ graphql_object!(QueryRoot : Context as "Query" |&self| {
         });
 
         for entry in final_iter {
-            result.push(KAppRegistryEntry(entry.clone()));
+            let instances = registry
+                .supervisor
+                .instances(&entry.app.uuid)
+                .into_iter()
+                .filter(|instance| instance.app.metadata.version == entry.app.metadata.version)
+                .collect();
+            result.push(KAppRegistryEntry(entry.clone(), instances));
         }
 
         Ok(result)
     }
+
+    field schedules(&executor, uuid: Option<String>) -> FieldResult<Vec<KSchedule>>
+        as "Scheduled app runs"
+    {
+        Ok(executor
+            .context()
+            .subsystem()
+            .scheduler
+            .list()
+            .into_iter()
+            .filter(|schedule| uuid.as_ref().map(|uuid| &schedule.uuid == uuid).unwrap_or(true))
+            .map(KSchedule)
+            .collect())
+    }
+
+    field logs(&executor, uuid: String, version: Option<String>, lines: Option<i32>)
+        -> FieldResult<KAppLogs> as "Recent output of an app (by default, its active version)"
+    {
+        let registry = executor.context().subsystem();
+        let app = {
+            let entries = registry.entries.lock().unwrap();
+            entries
+                .iter()
+                .find(|entry| {
+                    entry.app.uuid == uuid && match version {
+                        Some(ref version) => &entry.app.metadata.version == version,
+                        None => entry.active_version,
+                    }
+                })
+                .map(|entry| entry.app.clone())
+                .ok_or_else(|| match version {
+                    Some(ref version) => format!("{} version {} not found in registry", uuid, version),
+                    None => format!("No active version found for UUID {}", uuid),
+                })?
+        };
+
+        let dir = log_dir(&app);
+        let lines = read_logs(&dir, lines.unwrap_or(DEFAULT_LOG_LINES).max(0) as usize)?;
+        Ok(KAppLogs {
+            path: dir.join(LOG_FILE).to_string_lossy().into_owned(),
+            files: log_files(&dir)
+                .iter()
+                .map(|path| path.to_string_lossy().into_owned())
+                .collect(),
+            lines,
+        })
+    }
 });
 
 ///
@@ -71,7 +132,7 @@ graphql_object!(MutationRoot : Context as "Mutation" |&self| {
     {
         let registry = executor.context().subsystem();
         Ok(match registry.register(&path, uuid) {
-            Ok(app) =>  RegisterResponse { success: true, errors: "".to_owned(), entry: Some(KAppRegistryEntry(app))},
+            Ok(app) =>  RegisterResponse { success: true, errors: "".to_owned(), entry: Some(KAppRegistryEntry(app, vec![]))},
             Err(error) => RegisterResponse {
                 success: false,
                 errors: error.to_string(),
@@ -89,14 +150,40 @@ graphql_object!(MutationRoot : Context as "Mutation" |&self| {
         })
     }
 
+    field set_version(&executor, uuid: String, version: String) -> FieldResult<GenericResponse>
+        as "Make a registered version of an app the active version"
+    {
+        Ok(match executor.context().subsystem().set_version(&uuid, &version) {
+            Ok(_) => GenericResponse { success: true, errors: "".to_owned() },
+            Err(error) => GenericResponse { success: false, errors: error.to_string() },
+        })
+    }
+
+    field rollback(&executor, uuid: String, reason: Option<String>) -> FieldResult<GenericResponse>
+        as "Reactivate the version of an app which was active before the current one was registered"
+    {
+        let reason = reason.unwrap_or_else(|| "Rolled back by request".to_owned());
+        Ok(match executor.context().subsystem().rollback(&uuid, &reason) {
+            Ok(_) => GenericResponse { success: true, errors: "".to_owned() },
+            Err(error) => GenericResponse { success: false, errors: error.to_string() },
+        })
+    }
+
+    field verify_registry(&executor, repair: Option<bool>) -> FieldResult<VerifyResponse>
+        as "Check the registry for inconsistencies, optionally repairing them"
+    {
+        Ok(match executor.context().subsystem().verify(repair.unwrap_or(false)) {
+            Ok(issues) => VerifyResponse { success: true, errors: "".to_owned(), issues },
+            Err(error) => VerifyResponse { success: false, errors: error.to_string(), issues: vec![] },
+        })
+    }
+
     field start_app(&executor, uuid: String, run_level: String, args: Option<Vec<String>>) -> FieldResult<StartResponse>
         as "Start App"
     {
-        let run_level_o = {
-            match run_level.as_ref() {
-                "OnBoot" => RunLevel::OnBoot,
-                _ => RunLevel::OnCommand
-            }
+        let run_level_o = match run_level.parse::<RunLevel>() {
+            Ok(run_level) => run_level,
+            Err(error) => return Ok(StartResponse { success: false, errors: error.to_string(), pid: None }),
         };
 
         Ok(match executor.context().subsystem().start_app(&uuid, run_level_o, args) {
@@ -104,4 +191,70 @@ graphql_object!(MutationRoot : Context as "Mutation" |&self| {
             Err(error) => StartResponse { success: false, errors: error.to_string(), pid: None },
         })
     }
+
+    field schedule_app(&executor, uuid: String, at: Option<f64>, every: Option<i32>, args: Option<Vec<String>>)
+        -> FieldResult<ScheduleResponse>
+        as "Start an app with the OnCommand run level at a given time (in seconds since the UNIX epoch) and/or periodically (every given number of seconds)"
+    {
+        let registry = executor.context().subsystem();
+        let registered = registry
+            .entries
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|entry| entry.active_version && entry.app.uuid == uuid);
+        if !registered {
+            return Ok(ScheduleResponse {
+                success: false,
+                errors: format!("No active version found for UUID {}", uuid),
+                schedule: None,
+            });
+        }
+
+        let every = every.map(|every| every.max(0) as u64);
+        let now = epoch_secs(SystemTime::now());
+        Ok(match registry.scheduler.add(&uuid, at, every, args.unwrap_or_default(), now) {
+            Ok(schedule) => ScheduleResponse { success: true, errors: "".to_owned(), schedule: Some(KSchedule(schedule)) },
+            Err(error) => ScheduleResponse { success: false, errors: error.to_string(), schedule: None },
+        })
+    }
+
+    field cancel_schedule(&executor, id: i32) -> FieldResult<GenericResponse>
+        as "Remove a scheduled app run"
+    {
+        Ok(match executor.context().subsystem().scheduler.cancel(id as u32) {
+            Ok(_) => GenericResponse { success: true, errors: "".to_owned() },
+            Err(error) => GenericResponse { success: false, errors: error.to_string() },
+        })
+    }
+
+    field kill_app(&executor, uuid: String, pid: Option<i32>, signal: Option<i32>) -> FieldResult<GenericResponse>
+        as "Send a signal (SIGKILL by default) to the running instances of an app"
+    {
+        let signal = match Signal::from_c_int(signal.unwrap_or(Signal::SIGKILL as i32)) {
+            Ok(signal) => signal,
+            Err(_) => return Ok(GenericResponse {
+                success: false,
+                errors: format!("Invalid signal: {}", signal.unwrap_or_default()),
+            }),
+        };
+
+        let supervisor = &executor.context().subsystem().supervisor;
+        Ok(match supervisor.signal(&uuid, pid.map(|pid| pid as u32), signal) {
+            Ok(_) => GenericResponse { success: true, errors: "".to_owned() },
+            Err(error) => GenericResponse { success: false, errors: error.to_string() },
+        })
+    }
+
+    field stop_app(&executor, uuid: String, pid: Option<i32>, timeout: Option<i32>) -> FieldResult<GenericResponse>
+        as "Stop the running instances of an app with SIGTERM, killing any which don't exit within the timeout (seconds, defaulting to the app's shutdown timeout)"
+    {
+        let timeout = timeout.map(|timeout| Duration::from_secs(timeout.max(0) as u64));
+
+        let supervisor = &executor.context().subsystem().supervisor;
+        Ok(match supervisor.stop(&uuid, pid.map(|pid| pid as u32), timeout) {
+            Ok(_) => GenericResponse { success: true, errors: "".to_owned() },
+            Err(error) => GenericResponse { success: false, errors: error.to_string() },
+        })
+    }
 });
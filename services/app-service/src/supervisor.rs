@@ -0,0 +1,510 @@
+/*
+ * Copyright (C) 2018 Kubos Corporation
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use app_entry::{App, ResourceLimits, RestartMode};
+use error::*;
+use kubos_app::{RunLevel, DEFAULT_SHUTDOWN_TIMEOUT};
+use logs::{log_dir, AppLogger};
+use nix::sys::signal::{kill, Signal};
+use nix::unistd::Pid;
+use libc;
+use std::cmp;
+use std::io;
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::path::Path;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The state of a started application instance
+#[derive(Clone, Copy, Debug, GraphQLEnum, PartialEq)]
+pub enum AppState {
+    /// The application is currently running
+    Running,
+    /// The application failed and is waiting to be restarted
+    Restarting,
+    /// The application exited successfully
+    Exited,
+    /// The application exited with a non-zero code or was killed by an unrequested signal,
+    /// and will not be restarted
+    Failed,
+    /// The application was stopped or killed through the app service
+    Stopped,
+}
+
+/// A started application and the state of its process
+#[derive(Clone, Debug)]
+pub struct AppInstance {
+    id: u64,
+    /// The application which was started
+    pub app: App,
+    /// The run level the application was started with
+    pub run_level: RunLevel,
+    /// Additional arguments the application was started with
+    pub args: Vec<String>,
+    /// The process ID of the most recent process started for this instance
+    pub pid: u32,
+    /// The current state of the instance
+    pub state: AppState,
+    /// The exit code of the process, if it has exited normally
+    pub exit_code: Option<i32>,
+    /// The signal which terminated the process, if any
+    pub signal: Option<i32>,
+    /// When the most recent process was started
+    pub start_time: SystemTime,
+    /// When the most recent process ended, if it has
+    pub end_time: Option<SystemTime>,
+    /// The number of times the application has been automatically restarted
+    pub restarts: u32,
+    stop_requested: bool,
+}
+
+impl AppInstance {
+    /// Whether the instance has a live process or is waiting to be restarted
+    pub fn is_active(&self) -> bool {
+        self.state == AppState::Running || self.state == AppState::Restarting
+    }
+}
+
+#[derive(Debug, Default)]
+struct Instances {
+    next_id: u64,
+    list: Vec<AppInstance>,
+}
+
+impl Instances {
+    fn get_mut(&mut self, id: u64) -> Option<&mut AppInstance> {
+        self.list.iter_mut().find(|instance| instance.id == id)
+    }
+
+    // Only keep the active instances of an app, along with the most recent finished one
+    fn prune(&mut self, uuid: &str) {
+        let latest = self
+            .list
+            .iter()
+            .filter(|instance| instance.app.uuid == uuid && !instance.is_active())
+            .map(|instance| instance.id)
+            .max();
+
+        self.list.retain(|instance| {
+            instance.app.uuid != uuid || instance.is_active() || Some(instance.id) == latest
+        });
+    }
+}
+
+/// The time an application is given to exit after being sent `SIGTERM`
+pub fn shutdown_timeout(app: &App) -> Duration {
+    Duration::from_secs(
+        app.metadata
+            .shutdown_timeout
+            .unwrap_or(DEFAULT_SHUTDOWN_TIMEOUT),
+    )
+}
+
+/// Returns the number of seconds between the UNIX epoch and the given time
+pub fn epoch_secs(time: SystemTime) -> f64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() as f64 + f64::from(duration.subsec_millis()) / 1000.0)
+        .unwrap_or(0.0)
+}
+
+// Start an app process, capturing its stdout and stderr in the app's log
+fn spawn(
+    app: &App,
+    run_level: &RunLevel,
+    args: &[String],
+    logger: &AppLogger,
+) -> Result<Child, AppError> {
+    let mut cmd = Command::new(&app.path);
+    let app_dir = Path::new(&app.path)
+        .parent()
+        .unwrap_or_else(|| Path::new("/"))
+        .to_path_buf();
+
+    if let Some(ref dir) = app.metadata.working_dir {
+        cmd.current_dir(app_dir.join(dir));
+    }
+
+    let limits = app.metadata.limits.clone();
+    unsafe {
+        cmd.pre_exec(move || apply_limits(&limits));
+    }
+
+    cmd.envs(&app.metadata.env)
+        .env("KUBOS_APP_UUID", app.uuid.clone())
+        .env("KUBOS_APP_DIR", &app_dir)
+        .env(
+            "KUBOS_APP_SHUTDOWN_TIMEOUT",
+            shutdown_timeout(app).as_secs().to_string(),
+        )
+        .arg("-r")
+        .arg(format!("{}", run_level))
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+
+    let dir = log_dir(app);
+    let mut child = cmd.spawn().map_err(|err| {
+        logger.log(&dir, "service", &format!("Failed to start: {}", err));
+        AppError::StartError {
+            err: format!("Failed to spawn app: {:?}", err),
+        }
+    })?;
+
+    logger.log(
+        &dir,
+        "service",
+        &format!(
+            "Started version {} (pid {}) with run level {}",
+            app.metadata.version,
+            child.id(),
+            run_level
+        ),
+    );
+    if let Some(stdout) = child.stdout.take() {
+        logger.capture(dir.clone(), "stdout", stdout);
+    }
+    if let Some(stderr) = child.stderr.take() {
+        logger.capture(dir, "stderr", stderr);
+    }
+
+    Ok(child)
+}
+
+// Apply an app's resource limits to the current process. Called in the child process
+// between fork and exec, so it must not allocate.
+fn apply_limits(limits: &ResourceLimits) -> io::Result<()> {
+    let set = |resource, value: u64| {
+        let limit = libc::rlimit {
+            rlim_cur: value as libc::rlim_t,
+            rlim_max: value as libc::rlim_t,
+        };
+        if unsafe { libc::setrlimit(resource, &limit) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    };
+
+    if let Some(cpu_time) = limits.cpu_time {
+        set(libc::RLIMIT_CPU, cpu_time)?;
+    }
+    if let Some(memory) = limits.memory {
+        set(libc::RLIMIT_AS, memory)?;
+    }
+    Ok(())
+}
+
+// Describe how a process ended, for the app's log
+fn describe_exit(pid: u32, status: &::std::io::Result<ExitStatus>) -> String {
+    match *status {
+        Ok(ref status) => match (status.code(), status.signal()) {
+            (Some(code), _) => format!("Process {} exited with code {}", pid, code),
+            (None, Some(signal)) => format!("Process {} killed by signal {}", pid, signal),
+            _ => format!("Process {} exited", pid),
+        },
+        Err(ref err) => format!("Failed to wait for process {}: {}", pid, err),
+    }
+}
+
+/// Called each time a supervised process finishes, once the instance's state has been updated.
+///
+/// Returning `false` prevents the instance from being restarted.
+pub type ExitHook = Box<Fn(&AppInstance) -> bool + Send>;
+
+/// Starts application processes, reaps them when they exit and restarts them according
+/// to their restart policy.
+///
+/// Each started process is watched by its own thread, so clones of the supervisor
+/// share the same set of instances.
+#[derive(Clone, Debug, Default)]
+pub struct Supervisor {
+    instances: Arc<Mutex<Instances>>,
+    /// Captures the output of started processes
+    pub logger: AppLogger,
+}
+
+impl Supervisor {
+    /// Start an application and begin supervising it. Returns the pid of the new process.
+    ///
+    /// If given, `on_exit` is called every time the instance's process finishes.
+    pub fn start(
+        &self,
+        app: &App,
+        run_level: RunLevel,
+        args: Vec<String>,
+        on_exit: Option<ExitHook>,
+    ) -> Result<u32, AppError> {
+        let child = spawn(app, &run_level, &args, &self.logger)?;
+        let pid = child.id();
+
+        let id = {
+            let mut instances = self.instances.lock().unwrap();
+            let id = instances.next_id;
+            instances.next_id += 1;
+            instances.list.push(AppInstance {
+                id,
+                app: app.clone(),
+                run_level,
+                args,
+                pid,
+                state: AppState::Running,
+                exit_code: None,
+                signal: None,
+                start_time: SystemTime::now(),
+                end_time: None,
+                restarts: 0,
+                stop_requested: false,
+            });
+            instances.prune(&app.uuid);
+            id
+        };
+
+        let supervisor = self.clone();
+        thread::spawn(move || supervisor.reap(id, child, on_exit));
+
+        Ok(pid)
+    }
+
+    /// Returns the tracked instances of an application: any which are still active,
+    /// along with the most recently finished one
+    pub fn instances(&self, uuid: &str) -> Vec<AppInstance> {
+        self.instances
+            .lock()
+            .unwrap()
+            .list
+            .iter()
+            .filter(|instance| instance.app.uuid == uuid)
+            .cloned()
+            .collect()
+    }
+
+    /// Send a signal to the running instances of an application.
+    ///
+    /// Instances sent `SIGTERM` or `SIGKILL` will not be automatically restarted. Other
+    /// signals are passed on to the app, which is restarted as its policy dictates if the
+    /// signal causes it to exit.
+    /// Returns the pids of the processes which were signalled.
+    ///
+    /// # Arguments
+    ///
+    /// * `uuid` - The UUID of the application
+    /// * `pid` - Only signal the instance with this process ID
+    /// * `signal` - The signal to send
+    pub fn signal(
+        &self,
+        uuid: &str,
+        pid: Option<u32>,
+        signal: Signal,
+    ) -> Result<Vec<u32>, AppError> {
+        let mut instances = self.instances.lock().unwrap();
+        let mut pids = vec![];
+
+        for instance in instances.list.iter_mut().filter(|instance| {
+            instance.app.uuid == uuid
+                && instance.is_active()
+                && pid.map(|pid| pid == instance.pid).unwrap_or(true)
+        }) {
+            if signal == Signal::SIGTERM || signal == Signal::SIGKILL {
+                instance.stop_requested = true;
+            }
+            if instance.state == AppState::Running {
+                kill(Pid::from_raw(instance.pid as i32), signal).map_err(|err| {
+                    AppError::KillError {
+                        err: format!("Failed to signal {}: {}", instance.pid, err),
+                    }
+                })?;
+            }
+            pids.push(instance.pid);
+        }
+
+        if pids.is_empty() {
+            return Err(AppError::KillError {
+                err: format!("No running instance of {} found", uuid),
+            });
+        }
+
+        Ok(pids)
+    }
+
+    /// Ask the running instances of an application to exit by sending `SIGTERM`. Any which are
+    /// still running once `timeout` has passed are killed with `SIGKILL`.
+    ///
+    /// If no timeout is given, each instance is given the shutdown timeout from its manifest.
+    pub fn stop(
+        &self,
+        uuid: &str,
+        pid: Option<u32>,
+        timeout: Option<Duration>,
+    ) -> Result<Vec<u32>, AppError> {
+        let pids = self.signal(uuid, pid, Signal::SIGTERM)?;
+
+        for instance in self
+            .instances(uuid)
+            .into_iter()
+            .filter(|instance| pids.contains(&instance.pid))
+        {
+            let supervisor = self.clone();
+            let uuid = uuid.to_owned();
+            let timeout = timeout.unwrap_or_else(|| shutdown_timeout(&instance.app));
+            thread::spawn(move || {
+                thread::sleep(timeout);
+                let running = supervisor.instances(&uuid).iter().any(|other| {
+                    other.id == instance.id && other.state == AppState::Running
+                });
+                if running {
+                    warn!(
+                        "App {} (pid {}) did not stop in time, killing it",
+                        uuid, instance.pid
+                    );
+                    let _ = kill(Pid::from_raw(instance.pid as i32), Signal::SIGKILL);
+                }
+            });
+        }
+
+        Ok(pids)
+    }
+
+    // Wait for an instance's process to exit, restarting it as its policy dictates
+    fn reap(&self, id: u64, mut child: Child, on_exit: Option<ExitHook>) {
+        let dir = match self.instances.lock().unwrap().get_mut(id) {
+            Some(instance) => log_dir(&instance.app),
+            None => return,
+        };
+
+        loop {
+            let status = child.wait();
+            self.logger
+                .log(&dir, "service", &describe_exit(child.id(), &status));
+
+            let (restart, finished) = {
+                let mut instances = self.instances.lock().unwrap();
+                let uuid;
+                let finished;
+                let restart = {
+                    let instance = match instances.get_mut(id) {
+                        Some(instance) => instance,
+                        None => return,
+                    };
+                    uuid = instance.app.uuid.clone();
+                    instance.end_time = Some(SystemTime::now());
+
+                    let success = match status {
+                        Ok(status) => {
+                            instance.exit_code = status.code();
+                            instance.signal = status.signal();
+                            status.success()
+                        }
+                        Err(ref err) => {
+                            warn!("Failed to wait for app {}: {}", instance.app.uuid, err);
+                            false
+                        }
+                    };
+
+                    let policy = &instance.app.metadata.restart;
+                    let restart = if instance.stop_requested {
+                        instance.state = AppState::Stopped;
+                        None
+                    } else if success {
+                        instance.state = AppState::Exited;
+                        None
+                    } else if policy.policy == RestartMode::OnFailure
+                        && instance.restarts < policy.max_retries
+                    {
+                        instance.state = AppState::Restarting;
+                        let delay = policy
+                            .backoff
+                            .saturating_mul(1 << cmp::min(instance.restarts, 16));
+                        instance.restarts += 1;
+                        Some(Duration::from_secs(delay))
+                    } else {
+                        instance.state = AppState::Failed;
+                        None
+                    };
+                    finished = instance.clone();
+                    restart
+                };
+
+                if restart.is_none() {
+                    instances.prune(&uuid);
+                }
+                (restart, finished)
+            };
+
+            // The hook may start or stop other apps, so it's called without holding the lock
+            let keep = on_exit.as_ref().map(|hook| hook(&finished)).unwrap_or(true);
+
+            let delay = match restart {
+                Some(delay) => delay,
+                None => return,
+            };
+
+            if !keep {
+                let mut instances = self.instances.lock().unwrap();
+                if let Some(instance) = instances.get_mut(id) {
+                    instance.state = AppState::Failed;
+                }
+                instances.prune(&finished.app.uuid);
+                return;
+            }
+
+            thread::sleep(delay);
+
+            let mut instances = self.instances.lock().unwrap();
+            let uuid = {
+                let instance = match instances.get_mut(id) {
+                    Some(instance) => instance,
+                    None => return,
+                };
+
+                // The app may have been stopped while we were waiting
+                if !instance.stop_requested {
+                    info!(
+                        "Restarting app {} (attempt {})",
+                        instance.app.uuid, instance.restarts
+                    );
+                    match spawn(
+                        &instance.app,
+                        &instance.run_level,
+                        &instance.args,
+                        &self.logger,
+                    ) {
+                        Ok(new_child) => {
+                            instance.pid = new_child.id();
+                            instance.state = AppState::Running;
+                            instance.exit_code = None;
+                            instance.signal = None;
+                            instance.start_time = SystemTime::now();
+                            instance.end_time = None;
+                            child = new_child;
+                            continue;
+                        }
+                        Err(err) => {
+                            error!("Failed to restart app {}: {}", instance.app.uuid, err);
+                            instance.state = AppState::Failed;
+                        }
+                    }
+                } else {
+                    instance.state = AppState::Stopped;
+                }
+                instance.app.uuid.clone()
+            };
+            instances.prune(&uuid);
+            return;
+        }
+    }
+}
@@ -0,0 +1,144 @@
+/*
+ * Copyright (C) 2018 Kubos Corporation
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use kubos_app::RunLevel;
+use kubos_service::{Config, Service};
+use serde_json;
+use std::fs;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tempfile::TempDir;
+
+use super::*;
+
+use logs::*;
+use registry::*;
+use schema;
+
+#[test]
+fn logs_captured() {
+    let registry_dir = TempDir::new().unwrap();
+    create_app(&registry_dir, "echo hello\necho oops >&2\nexit 2", "");
+
+    let registry = AppRegistry::new_from_dir(&registry_dir.path().to_string_lossy()).unwrap();
+    let pid = registry
+        .start_app("a-b-c-d-e", RunLevel::OnCommand, None)
+        .unwrap();
+
+    let service = mock_service!(registry_dir);
+    let query = r#"{ logs(uuid: "a-b-c-d-e") { path files lines } }"#;
+    let expected = vec![
+        format!(
+            "[service] Started version 1.0 (pid {}) with run level OnCommand",
+            pid
+        ),
+        "[stdout] hello".to_owned(),
+        "[stderr] oops".to_owned(),
+        format!("[service] Process {} exited with code 2", pid),
+    ];
+
+    let start = Instant::now();
+    loop {
+        let res: serde_json::Value =
+            serde_json::from_str(&service.process(query.to_owned())).unwrap();
+        let lines: Vec<String> = res["data"]["logs"]["lines"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|line| line.as_str().unwrap().to_owned())
+            .collect();
+
+        // The output streams are captured independently, so only check each line is present
+        if expected
+            .iter()
+            .all(|want| lines.iter().any(|line| line.ends_with(want.as_str())))
+        {
+            let path = registry_dir.path().join("a-b-c-d-e/1.0/logs/app.log");
+            assert_eq!(res["data"]["logs"]["path"], json!(path.to_string_lossy()));
+            assert_eq!(
+                res["data"]["logs"]["files"],
+                json!([path.to_string_lossy()])
+            );
+            assert!(lines[0].ends_with(&expected[0]));
+            break;
+        }
+
+        assert!(
+            start.elapsed() < Duration::from_secs(5),
+            "Missing output: {:?}",
+            lines
+        );
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    // Only the requested number of lines are returned
+    let res: serde_json::Value =
+        serde_json::from_str(&service.process(
+            r#"{ logs(uuid: "a-b-c-d-e", version: "1.0", lines: 1) { lines } }"#.to_owned(),
+        ))
+        .unwrap();
+    assert_eq!(res["data"]["logs"]["lines"].as_array().unwrap().len(), 1);
+}
+
+#[test]
+fn logs_unknown_app() {
+    let registry_dir = TempDir::new().unwrap();
+    let service = mock_service!(registry_dir);
+
+    let res: serde_json::Value = serde_json::from_str(
+        &service.process(r#"{ logs(uuid: "a-b-c-d-e", version: "2.0") { lines } }"#.to_owned()),
+    )
+    .unwrap();
+    assert_eq!(
+        res["errors"][0]["message"],
+        "a-b-c-d-e version 2.0 not found in registry"
+    );
+}
+
+#[test]
+fn logs_rotated() {
+    let dir = TempDir::new().unwrap();
+    let logger = AppLogger::new(1024, 2);
+
+    for num in 0..100 {
+        logger.log(dir.path(), "stdout", &format!("line {}", num));
+    }
+
+    let files = log_files(dir.path());
+    assert_eq!(
+        files,
+        vec![
+            dir.path().join("app.log.2"),
+            dir.path().join("app.log.1"),
+            dir.path().join("app.log"),
+        ]
+    );
+    for file in files {
+        assert!(fs::metadata(file).unwrap().len() <= 1024);
+    }
+
+    // Lines are read back across rotated files, oldest first
+    let lines = read_logs(dir.path(), 40).unwrap();
+    assert_eq!(lines.len(), 40);
+    assert!(lines[0].ends_with("[stdout] line 60"));
+    assert!(lines[39].ends_with("[stdout] line 99"));
+
+    // The oldest lines have been dropped
+    let lines = read_logs(dir.path(), 1000).unwrap();
+    assert!(lines.len() < 100);
+    assert!(lines[lines.len() - 1].ends_with("[stdout] line 99"));
+}
@@ -0,0 +1,205 @@
+/*
+ * Copyright (C) 2018 Kubos Corporation
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use kubos_app::RunLevel;
+use std::fs;
+use std::net::UdpSocket;
+use std::thread;
+use std::time::Duration;
+
+use tempfile::TempDir;
+
+use super::*;
+
+use registry::*;
+
+#[test]
+fn start_with_env_and_working_dir() {
+    let registry_dir = TempDir::new().unwrap();
+    create_app(
+        &registry_dir,
+        "echo \"mode=$MODE\"\necho \"dir=$KUBOS_APP_DIR\"\necho \"pwd=$(pwd)\"",
+        r#"
+            working_dir = "data"
+
+            [app.metadata.env]
+            MODE = "safe"
+            "#,
+    );
+    let app_dir = registry_dir.path().join("a-b-c-d-e/1.0");
+    fs::create_dir(app_dir.join("data")).unwrap();
+
+    let registry = AppRegistry::new_from_dir(&registry_dir.path().to_string_lossy()).unwrap();
+    registry
+        .start_app("a-b-c-d-e", RunLevel::OnCommand, None)
+        .unwrap();
+
+    let lines = wait_for_log(&registry_dir, "1.0", "exited with code 0");
+    let output: Vec<&str> = lines
+        .iter()
+        .filter_map(|line| line.split("[stdout] ").nth(1))
+        .collect();
+    assert!(output.contains(&"mode=safe"));
+    assert!(output.contains(&format!("dir={}", app_dir.display()).as_str()));
+    assert!(output.contains(&format!("pwd={}", app_dir.join("data").display()).as_str()));
+}
+
+#[test]
+fn start_with_limits() {
+    let registry_dir = TempDir::new().unwrap();
+    create_app(
+        &registry_dir,
+        "echo \"cpu=$(ulimit -t)\"\necho \"memory=$(ulimit -v)\"",
+        r#"
+            [app.metadata.limits]
+            cpu_time = 30
+            memory = 536870912
+            "#,
+    );
+
+    let registry = AppRegistry::new_from_dir(&registry_dir.path().to_string_lossy()).unwrap();
+    registry
+        .start_app("a-b-c-d-e", RunLevel::OnCommand, None)
+        .unwrap();
+
+    let lines = wait_for_log(&registry_dir, "1.0", "exited with code 0");
+    assert!(lines.iter().any(|line| line.ends_with("[stdout] cpu=30")));
+    // `ulimit -v` reports kilobytes
+    assert!(lines
+        .iter()
+        .any(|line| line.ends_with("[stdout] memory=524288")));
+}
+
+#[test]
+fn onboot_waits_for_required_services() {
+    let registry_dir = TempDir::new().unwrap();
+    create_app(
+        &registry_dir,
+        "exit 0",
+        r#"requires = ["fake-service"]
+            requires_timeout = 10"#,
+    );
+
+    // Reserve a port for the fake service
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let port = socket.local_addr().unwrap().port();
+    let config = registry_dir.path().join("config.toml");
+    fs::write(
+        &config,
+        format!("[fake-service.addr]\nip = \"127.0.0.1\"\nport = {}\n", port),
+    )
+    .unwrap();
+
+    let mut registry = AppRegistry::new_from_dir(&registry_dir.path().to_string_lossy()).unwrap();
+    registry.config_path = config.to_string_lossy().into_owned();
+    registry.run_onboot().unwrap();
+
+    // The app isn't started until the service responds
+    thread::sleep(Duration::from_millis(1500));
+    assert!(registry.supervisor.instances("a-b-c-d-e").is_empty());
+
+    thread::spawn(move || {
+        let mut buf = [0; 1024];
+        loop {
+            if let Ok((_, peer)) = socket.recv_from(&mut buf) {
+                let _ = socket.send_to(br#"{"data":{"ping":"pong"}}"#, peer);
+            }
+        }
+    });
+
+    wait_for_log(&registry_dir, "1.0", "with run level OnBoot");
+}
+
+#[test]
+fn onboot_required_service_timeout() {
+    let registry_dir = TempDir::new().unwrap();
+    create_app(
+        &registry_dir,
+        "exit 0",
+        r#"requires = ["missing-service"]
+            requires_timeout = 1"#,
+    );
+
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let port = socket.local_addr().unwrap().port();
+    let config = registry_dir.path().join("config.toml");
+    fs::write(
+        &config,
+        format!(
+            "[missing-service.addr]\nip = \"127.0.0.1\"\nport = {}\n",
+            port
+        ),
+    )
+    .unwrap();
+
+    let mut registry = AppRegistry::new_from_dir(&registry_dir.path().to_string_lossy()).unwrap();
+    registry.config_path = config.to_string_lossy().into_owned();
+    registry.run_onboot().unwrap();
+
+    wait_for_log(
+        &registry_dir,
+        "1.0",
+        "Failed to start app: Required service missing-service did not respond within 1 seconds",
+    );
+    assert!(registry.supervisor.instances("a-b-c-d-e").is_empty());
+}
+
+#[test]
+fn onboot_required_service_not_configured() {
+    let registry_dir = TempDir::new().unwrap();
+    create_app(&registry_dir, "exit 0", r#"requires = ["missing-service"]"#);
+
+    let config = registry_dir.path().join("config.toml");
+    fs::write(&config, "[other-service.addr]\nport = 9999\n").unwrap();
+
+    let mut registry = AppRegistry::new_from_dir(&registry_dir.path().to_string_lossy()).unwrap();
+    registry.config_path = config.to_string_lossy().into_owned();
+    registry.run_onboot().unwrap();
+
+    wait_for_log(
+        &registry_dir,
+        "1.0",
+        &format!(
+            "Failed to start app: Required service missing-service is not configured: \
+             No [missing-service] section in {}",
+            config.display()
+        ),
+    );
+    assert!(registry.supervisor.instances("a-b-c-d-e").is_empty());
+}
+
+#[test]
+fn register_required_service_not_configured() {
+    let registry_dir = TempDir::new().unwrap();
+    let config = registry_dir.path().join("config.toml");
+    fs::write(&config, "[other-service.addr]\nport = 9999\n").unwrap();
+
+    let mut registry = AppRegistry::new_from_dir(&registry_dir.path().to_string_lossy()).unwrap();
+    registry.config_path = config.to_string_lossy().into_owned();
+
+    let app_dir = app_source("1.0", "exit 0", r#"requires = ["missing-service"]"#);
+    let err = registry
+        .register(
+            &app_dir.path().to_string_lossy(),
+            Some("a-b-c-d-e".to_owned()),
+        )
+        .unwrap_err();
+
+    assert!(err
+        .to_string()
+        .contains("Required service missing-service is not configured"));
+    assert!(!registry_dir.path().join("a-b-c-d-e").exists());
+}
@@ -14,6 +14,17 @@
  * limitations under the License.
  */
 
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tempfile::TempDir;
+
+use logs::read_logs;
+use registry::AppRegistry;
+use supervisor::{AppInstance, AppState};
+
 macro_rules! mock_service {
     ($registry_dir:ident) => {{
         let registry = AppRegistry::new_from_dir(&$registry_dir.path().to_string_lossy()).unwrap();
@@ -29,7 +40,7 @@ macro_rules! mock_service {
         );
 
         Service::new(
-            Config::new_from_str("app-service", &config),
+            Config::new_from_str("app-service", &config).unwrap(),
             registry,
             schema::QueryRoot,
             schema::MutationRoot,
@@ -37,8 +48,132 @@ macro_rules! mock_service {
     }};
 }
 
+// Create version 1.0 of the app `a-b-c-d-e` directly in the registry directory. Its binary runs
+// the given shell commands, and `metadata` is appended to its `[app.metadata]` section.
+fn create_app(registry_dir: &TempDir, script: &str, metadata: &str) {
+    let app_dir = registry_dir.path().join("a-b-c-d-e/1.0");
+    fs::create_dir_all(app_dir.clone()).unwrap();
+
+    let bin = app_dir.join("tiny-app");
+    fs::write(&bin, format!("#!/bin/sh\n{}\n", script)).unwrap();
+    fs::set_permissions(&bin, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let toml = format!(
+        r#"
+            active_version = true
+
+            [app]
+            uuid = "a-b-c-d-e"
+            pid = 0
+            path = "{}"
+
+            [app.metadata]
+            name = "tiny-app"
+            version = "1.0"
+            author = "user"
+
+            {}
+            "#,
+        bin.to_string_lossy(),
+        metadata,
+    );
+
+    fs::write(app_dir.join("app.toml"), toml).unwrap();
+}
+
+// Create an app directory, ready to be registered, whose binary runs the given shell commands.
+// `manifest` is appended to the generated manifest.
+fn app_source(version: &str, script: &str, manifest: &str) -> TempDir {
+    let app_dir = TempDir::new().unwrap();
+
+    let bin = app_dir.path().join("tiny-app");
+    fs::write(&bin, format!("#!/bin/sh\n{}\n", script)).unwrap();
+    fs::set_permissions(&bin, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let manifest = format!(
+        r#"
+            name = "tiny-app"
+            version = "{}"
+            author = "user"
+
+            {}
+            "#,
+        version, manifest
+    );
+    fs::write(app_dir.path().join("manifest.toml"), manifest).unwrap();
+
+    app_dir
+}
+
+// Register a version of an app created with `app_source`
+fn register_app(registry: &AppRegistry, uuid: &str, version: &str, script: &str, manifest: &str) {
+    let app_dir = app_source(version, script, manifest);
+    registry
+        .register(&app_dir.path().to_string_lossy(), Some(uuid.to_owned()))
+        .unwrap();
+}
+
+// Wait for the latest instance of `a-b-c-d-e` to reach the given state
+fn wait_for(registry: &AppRegistry, state: AppState) -> AppInstance {
+    let start = Instant::now();
+    loop {
+        if let Some(instance) = registry.supervisor.instances("a-b-c-d-e").pop() {
+            if instance.state == state {
+                return instance;
+            }
+        }
+        if start.elapsed() > Duration::from_secs(5) {
+            panic!(
+                "Timed out waiting for {:?}: {:?}",
+                state,
+                registry.supervisor.instances("a-b-c-d-e")
+            );
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+// The last lines logged by a version of `a-b-c-d-e`
+fn version_logs(registry_dir: &TempDir, version: &str) -> Vec<String> {
+    read_logs(
+        &registry_dir
+            .path()
+            .join("a-b-c-d-e")
+            .join(version)
+            .join("logs"),
+        100,
+    )
+    .unwrap_or_default()
+}
+
+// Wait for a line ending with `want` to appear in the log of a version of `a-b-c-d-e`
+fn wait_for_log(registry_dir: &TempDir, version: &str, want: &str) -> Vec<String> {
+    let start = Instant::now();
+    loop {
+        let lines = version_logs(registry_dir, version);
+        if lines.iter().any(|line| line.ends_with(want)) {
+            return lines;
+        }
+        assert!(
+            start.elapsed() < Duration::from_secs(10),
+            "{:?} not logged: {:?}",
+            want,
+            lines
+        );
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+mod app_logs;
+mod app_manifest;
 mod register_app;
+mod register_archive;
+mod registry_index;
 mod registry_onboot;
+mod registry_rollback;
+mod registry_schedule;
 mod registry_start_app;
+mod registry_supervisor;
 mod registry_test;
+mod run_levels;
 mod upgrade_app;
@@ -62,7 +62,6 @@ fn register_good() {
     );
 
     let expected = json!({
-            "errors": "",
             "data": {
                "register": {
                    "entry": {
@@ -114,7 +113,6 @@ fn register_no_manifest() {
     );
 
     let expected = json!({
-            "errors": "",
             "data": {
                "register": {
                    "entry": null,
@@ -166,7 +164,6 @@ fn register_no_name() {
     );
 
     let expected = json!({
-            "errors": "",
             "data": {
                "register": {
                    "entry": null,
@@ -219,7 +216,6 @@ fn register_bad_name() {
     );
 
     let expected = json!({
-            "errors": "",
             "data": {
                "register": {
                    "entry": null,
@@ -271,7 +267,6 @@ fn register_no_version() {
     );
 
     let expected = json!({
-            "errors": "",
             "data": {
                "register": {
                    "entry": null,
@@ -323,7 +318,6 @@ fn register_no_author() {
     );
 
     let expected = json!({
-            "errors": "",
             "data": {
                "register": {
                    "entry": null,
@@ -357,7 +351,6 @@ fn register_bad_path() {
     }"#;
 
     let expected = json!({
-            "errors": "",
             "data": {
                "register": {
                    "entry": null,
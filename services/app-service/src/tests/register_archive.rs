@@ -0,0 +1,217 @@
+/*
+ * Copyright (C) 2018 Kubos Corporation
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use tar;
+
+use tempfile::TempDir;
+
+use error::*;
+use install::*;
+use registry::*;
+
+const MANIFEST: &'static str = r#"
+name = "tiny-app"
+version = "1.0"
+author = "user"
+"#;
+
+// Build a .tar.gz of the given (name, contents) files, optionally nested under a directory
+fn create_archive(dir: &TempDir, files: &[(&str, &str)], prefix: Option<&str>) -> PathBuf {
+    let path = dir.path().join("tiny-app.tar.gz");
+    let encoder = GzEncoder::new(File::create(&path).unwrap(), Compression::default());
+    let mut builder = tar::Builder::new(encoder);
+
+    for (name, contents) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o755);
+        header.set_cksum();
+        let name = match prefix {
+            Some(prefix) => format!("{}/{}", prefix, name),
+            None => name.to_string(),
+        };
+        builder
+            .append_data(&mut header, name, contents.as_bytes())
+            .unwrap();
+    }
+
+    builder.into_inner().unwrap().finish().unwrap();
+    path
+}
+
+fn staging_is_empty(registry_dir: &TempDir) -> bool {
+    let staging = registry_dir.path().join(STAGING_DIR);
+    !staging.exists() || fs::read_dir(staging).unwrap().next().is_none()
+}
+
+#[test]
+fn register_archive_good() {
+    let registry_dir = TempDir::new().unwrap();
+    let upload_dir = TempDir::new().unwrap();
+
+    let bin = "#!/bin/sh\nexit 0\n";
+    fs::write(upload_dir.path().join("bin"), bin).unwrap();
+    let manifest = format!(
+        "{}\n[checksums]\ntiny-app = \"{}\"\n",
+        MANIFEST,
+        checksum(&upload_dir.path().join("bin")).unwrap()
+    );
+    let archive = create_archive(
+        &upload_dir,
+        &[("manifest.toml", &manifest), ("tiny-app", bin)],
+        None,
+    );
+
+    let registry = AppRegistry::new_from_dir(&registry_dir.path().to_string_lossy()).unwrap();
+    let entry = registry
+        .register(&archive.to_string_lossy(), Some("a-b-c-d-e".to_owned()))
+        .unwrap();
+
+    let app_dir = registry_dir.path().join("a-b-c-d-e/1.0");
+    assert_eq!(entry.app.path, app_dir.join("tiny-app").to_string_lossy());
+    assert_eq!(fs::read_to_string(app_dir.join("tiny-app")).unwrap(), bin);
+    assert!(app_dir.join("app.toml").exists());
+    assert!(staging_is_empty(&registry_dir));
+}
+
+#[test]
+fn register_archive_nested() {
+    let registry_dir = TempDir::new().unwrap();
+    let upload_dir = TempDir::new().unwrap();
+
+    let archive = create_archive(
+        &upload_dir,
+        &[("manifest.toml", MANIFEST), ("tiny-app", "#!/bin/sh\n")],
+        Some("tiny-app-1.0"),
+    );
+
+    let registry = AppRegistry::new_from_dir(&registry_dir.path().to_string_lossy()).unwrap();
+    registry
+        .register(&archive.to_string_lossy(), Some("a-b-c-d-e".to_owned()))
+        .unwrap();
+
+    assert!(registry_dir
+        .path()
+        .join("a-b-c-d-e/1.0/manifest.toml")
+        .exists());
+    assert!(staging_is_empty(&registry_dir));
+}
+
+#[test]
+fn register_archive_bad_checksum() {
+    let registry_dir = TempDir::new().unwrap();
+    let upload_dir = TempDir::new().unwrap();
+
+    let manifest = format!(
+        "{}\n[checksums]\ntiny-app = \"{}\"\n",
+        MANIFEST,
+        "00".repeat(32)
+    );
+    let archive = create_archive(
+        &upload_dir,
+        &[("manifest.toml", &manifest), ("tiny-app", "#!/bin/sh\n")],
+        None,
+    );
+
+    let registry = AppRegistry::new_from_dir(&registry_dir.path().to_string_lossy()).unwrap();
+    match registry.register(&archive.to_string_lossy(), Some("a-b-c-d-e".to_owned())) {
+        Err(AppError::ChecksumError { err }) => assert!(err.starts_with("tiny-app has checksum")),
+        other => panic!("Unexpected result: {:?}", other),
+    }
+
+    assert!(!registry_dir.path().join("a-b-c-d-e").exists());
+    assert!(staging_is_empty(&registry_dir));
+    assert_eq!(registry.entries.lock().unwrap().len(), 0);
+}
+
+#[test]
+fn register_archive_corrupt() {
+    let registry_dir = TempDir::new().unwrap();
+    let upload_dir = TempDir::new().unwrap();
+
+    let archive = upload_dir.path().join("tiny-app.tar.gz");
+    fs::write(&archive, "not an archive").unwrap();
+
+    let registry = AppRegistry::new_from_dir(&registry_dir.path().to_string_lossy()).unwrap();
+    let err = registry
+        .register(&archive.to_string_lossy(), None)
+        .unwrap_err();
+
+    assert!(err.to_string().starts_with(&format!(
+        "Failed to register app: Failed to unpack {}",
+        archive.display()
+    )));
+    assert!(staging_is_empty(&registry_dir));
+}
+
+#[test]
+fn register_not_archive() {
+    let registry_dir = TempDir::new().unwrap();
+    let upload_dir = TempDir::new().unwrap();
+
+    let path = upload_dir.path().join("tiny-app.zip");
+    fs::write(&path, "").unwrap();
+
+    let registry = AppRegistry::new_from_dir(&registry_dir.path().to_string_lossy()).unwrap();
+    assert_eq!(
+        registry
+            .register(&path.to_string_lossy(), None)
+            .unwrap_err(),
+        AppError::RegisterError {
+            err: format!("{} is not a directory or .tar.gz archive", path.display()),
+        }
+    );
+}
+
+#[test]
+fn reregister_same_version() {
+    let registry_dir = TempDir::new().unwrap();
+    let upload_dir = TempDir::new().unwrap();
+    let registry = AppRegistry::new_from_dir(&registry_dir.path().to_string_lossy()).unwrap();
+
+    for bin in &["#!/bin/sh\nexit 1\n", "#!/bin/sh\nexit 0\n"] {
+        let archive = create_archive(
+            &upload_dir,
+            &[("manifest.toml", MANIFEST), ("tiny-app", bin)],
+            None,
+        );
+        registry
+            .register(&archive.to_string_lossy(), Some("a-b-c-d-e".to_owned()))
+            .unwrap();
+    }
+
+    assert_eq!(registry.entries.lock().unwrap().len(), 1);
+    assert_eq!(
+        fs::read_to_string(registry_dir.path().join("a-b-c-d-e/1.0/tiny-app")).unwrap(),
+        "#!/bin/sh\nexit 0\n"
+    );
+}
+
+#[test]
+fn leftover_staging_removed() {
+    let registry_dir = TempDir::new().unwrap();
+    let leftover = registry_dir.path().join(STAGING_DIR).join("a-b-c-d-e");
+    fs::create_dir_all(&leftover).unwrap();
+    fs::write(leftover.join("manifest.toml"), MANIFEST).unwrap();
+
+    let registry = AppRegistry::new_from_dir(&registry_dir.path().to_string_lossy()).unwrap();
+    assert_eq!(registry.entries.lock().unwrap().len(), 0);
+    assert!(!Path::new(&leftover).exists());
+}
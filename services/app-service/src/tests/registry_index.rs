@@ -0,0 +1,343 @@
+/*
+ * Copyright (C) 2018 Kubos Corporation
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use kubos_service::{Config, Service};
+use serde_json;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+
+use tempfile::TempDir;
+
+use super::*;
+
+use index::*;
+use install::checksum;
+use registry::*;
+use schema;
+
+// Register a version of an app, listing the checksum of its binary in the manifest
+fn register(registry: &AppRegistry, uuid: &str, version: &str) {
+    let app_dir = app_source(version, "exit 0", "");
+
+    let sum = checksum(&app_dir.path().join("tiny-app")).unwrap();
+    let mut manifest = OpenOptions::new()
+        .append(true)
+        .open(app_dir.path().join("manifest.toml"))
+        .unwrap();
+    write!(manifest, "[checksums]\ntiny-app = \"{}\"\n", sum).unwrap();
+
+    registry
+        .register(&app_dir.path().to_string_lossy(), Some(uuid.to_owned()))
+        .unwrap();
+}
+
+fn versions(registry: &AppRegistry) -> Vec<(String, String, bool)> {
+    let mut versions: Vec<(String, String, bool)> = registry
+        .entries
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|entry| {
+            (
+                entry.app.uuid.clone(),
+                entry.app.metadata.version.clone(),
+                entry.active_version,
+            )
+        })
+        .collect();
+    versions.sort();
+    versions
+}
+
+#[test]
+fn register_failure_removes_files() {
+    let registry_dir = TempDir::new().unwrap();
+    let registry = AppRegistry::new_from_dir(&registry_dir.path().to_string_lossy()).unwrap();
+    register(&registry, "a-a-a-a-a", "1.0");
+
+    // The active symlink can't replace a directory, so registration fails once installed
+    fs::create_dir_all(registry_dir.path().join("active/b-b-b-b-b/blocked")).unwrap();
+    let app_dir = app_source("1.0", "exit 0", "");
+    assert!(registry
+        .register(
+            &app_dir.path().to_string_lossy(),
+            Some("b-b-b-b-b".to_owned())
+        )
+        .is_err());
+
+    assert!(!registry_dir.path().join("b-b-b-b-b/1.0").exists());
+    assert_eq!(
+        versions(&registry),
+        vec![("a-a-a-a-a".to_owned(), "1.0".to_owned(), true)]
+    );
+    let registry = AppRegistry::new_from_dir(&registry_dir.path().to_string_lossy()).unwrap();
+    assert_eq!(
+        versions(&registry),
+        vec![("a-a-a-a-a".to_owned(), "1.0".to_owned(), true)]
+    );
+}
+
+#[test]
+fn reregister_failure_keeps_files() {
+    let registry_dir = TempDir::new().unwrap();
+    let registry = AppRegistry::new_from_dir(&registry_dir.path().to_string_lossy()).unwrap();
+    register(&registry, "a-a-a-a-a", "1.0");
+
+    // The index can't be replaced by a file, so registration fails once installed
+    let index = registry_dir.path().join(INDEX_FILE);
+    fs::remove_file(&index).unwrap();
+    fs::create_dir(&index).unwrap();
+    let app_dir = app_source("1.0", "exit 1", "");
+    assert!(registry
+        .register(
+            &app_dir.path().to_string_lossy(),
+            Some("a-a-a-a-a".to_owned())
+        )
+        .is_err());
+
+    let bin = fs::read_to_string(registry_dir.path().join("a-a-a-a-a/1.0/tiny-app")).unwrap();
+    assert!(bin.contains("exit 0"));
+    assert_eq!(
+        versions(&registry),
+        vec![("a-a-a-a-a".to_owned(), "1.0".to_owned(), true)]
+    );
+    assert_eq!(
+        fs::read_link(registry_dir.path().join("active/a-a-a-a-a")).unwrap(),
+        registry_dir.path().join("a-a-a-a-a/1.0")
+    );
+}
+
+#[test]
+fn uninstall_unsorted() {
+    let registry_dir = TempDir::new().unwrap();
+    let registry = AppRegistry::new_from_dir(&registry_dir.path().to_string_lossy()).unwrap();
+    register(&registry, "c-c-c-c-c", "2.0");
+    register(&registry, "a-a-a-a-a", "1.0");
+    register(&registry, "c-c-c-c-c", "1.0");
+    register(&registry, "b-b-b-b-b", "1.0");
+
+    for &(uuid, version) in &[
+        ("c-c-c-c-c", "2.0"),
+        ("b-b-b-b-b", "1.0"),
+        ("a-a-a-a-a", "1.0"),
+        ("c-c-c-c-c", "1.0"),
+    ] {
+        registry.uninstall(uuid, version).unwrap();
+    }
+    assert!(versions(&registry).is_empty());
+    assert!(fs::read_dir(registry_dir.path().join("active"))
+        .unwrap()
+        .next()
+        .is_none());
+
+    // The index is updated as well
+    let registry = AppRegistry::new_from_dir(&registry_dir.path().to_string_lossy()).unwrap();
+    assert!(versions(&registry).is_empty());
+}
+
+#[test]
+fn load_from_index() {
+    let registry_dir = TempDir::new().unwrap();
+    {
+        let registry = AppRegistry::new_from_dir(&registry_dir.path().to_string_lossy()).unwrap();
+        register(&registry, "a-b-c-d-e", "1.0");
+        register(&registry, "a-b-c-d-e", "2.0");
+        registry.set_version("a-b-c-d-e", "1.0").unwrap();
+    }
+    assert!(registry_dir.path().join(INDEX_FILE).exists());
+
+    // The index is used instead of each version's app.toml once it exists
+    fs::remove_file(registry_dir.path().join("a-b-c-d-e/1.0/app.toml")).unwrap();
+    fs::remove_file(registry_dir.path().join("a-b-c-d-e/1.0/tiny-app")).unwrap();
+
+    let registry = AppRegistry::new_from_dir(&registry_dir.path().to_string_lossy()).unwrap();
+    assert_eq!(
+        versions(&registry),
+        vec![
+            ("a-b-c-d-e".to_owned(), "1.0".to_owned(), true),
+            ("a-b-c-d-e".to_owned(), "2.0".to_owned(), false),
+        ]
+    );
+    assert_eq!(
+        fs::read_link(registry_dir.path().join("active/a-b-c-d-e")).unwrap(),
+        registry_dir.path().join("a-b-c-d-e/1.0")
+    );
+}
+
+#[test]
+fn rebuild_bad_index() {
+    let registry_dir = TempDir::new().unwrap();
+    {
+        let registry = AppRegistry::new_from_dir(&registry_dir.path().to_string_lossy()).unwrap();
+        register(&registry, "a-b-c-d-e", "1.0");
+    }
+    fs::write(registry_dir.path().join(INDEX_FILE), "not toml [").unwrap();
+
+    let registry = AppRegistry::new_from_dir(&registry_dir.path().to_string_lossy()).unwrap();
+    assert_eq!(
+        versions(&registry),
+        vec![("a-b-c-d-e".to_owned(), "1.0".to_owned(), true)]
+    );
+
+    // The bad index is kept for inspection, and a good one replaces it
+    assert_eq!(
+        fs::read_to_string(registry_dir.path().join(QUARANTINE_DIR).join(INDEX_FILE)).unwrap(),
+        "not toml ["
+    );
+    assert_eq!(
+        load_index(&registry_dir.path().to_string_lossy())
+            .unwrap()
+            .unwrap()
+            .len(),
+        1
+    );
+}
+
+#[test]
+fn quarantine_bad_app_toml() {
+    let registry_dir = TempDir::new().unwrap();
+    let app_dir = registry_dir.path().join("a-b-c-d-e/1.0");
+    fs::create_dir_all(&app_dir).unwrap();
+    fs::write(app_dir.join("app.toml"), "active_version = ").unwrap();
+
+    let registry = AppRegistry::new_from_dir(&registry_dir.path().to_string_lossy()).unwrap();
+    assert!(versions(&registry).is_empty());
+    assert!(!app_dir.exists());
+    assert!(registry_dir
+        .path()
+        .join(QUARANTINE_DIR)
+        .join("a-b-c-d-e/1.0/app.toml")
+        .exists());
+}
+
+#[test]
+fn verify_and_repair() {
+    let registry_dir = TempDir::new().unwrap();
+    let path = registry_dir.path();
+    let registry = AppRegistry::new_from_dir(&path.to_string_lossy()).unwrap();
+    register(&registry, "a-a-a-a-a", "1.0");
+    register(&registry, "a-a-a-a-a", "2.0");
+    register(&registry, "b-b-b-b-b", "1.0");
+    register(&registry, "c-c-c-c-c", "1.0");
+    assert!(registry.verify(false).unwrap().is_empty());
+
+    // The active version has been modified
+    fs::write(path.join("a-a-a-a-a/2.0/tiny-app"), "junk").unwrap();
+    // The only version is missing its binary
+    fs::remove_file(path.join("b-b-b-b-b/1.0/tiny-app")).unwrap();
+    // A version which isn't in the index, and a directory which isn't an app at all
+    let mut entry = registry
+        .entries
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|entry| entry.app.uuid == "c-c-c-c-c")
+        .cloned()
+        .unwrap();
+    entry.app.metadata.version = "2.0".to_owned();
+    entry.app.path = path
+        .join("c-c-c-c-c/2.0/tiny-app")
+        .to_string_lossy()
+        .into_owned();
+    fs::create_dir(path.join("c-c-c-c-c/2.0")).unwrap();
+    fs::copy(path.join("c-c-c-c-c/1.0/tiny-app"), &entry.app.path).unwrap();
+    entry.save().unwrap();
+    fs::create_dir(path.join("c-c-c-c-c/junk")).unwrap();
+    // An app which was never registered
+    ::std::os::unix::fs::symlink(path.join("e-e-e-e-e/1.0"), path.join("active/e-e-e-e-e"))
+        .unwrap();
+
+    let issues = registry.verify(false).unwrap();
+    let expected = [
+        "a-a-a-a-a version 2.0: Checksum mismatch: tiny-app has checksum",
+        "b-b-b-b-b version 1.0: ",
+        "c-c-c-c-c version 2.0 is installed but not registered",
+        "c-c-c-c-c/junk is not a valid app",
+        "e-e-e-e-e has an active symlink but isn't registered",
+    ];
+    assert_eq!(issues.len(), expected.len(), "{:?}", issues);
+    for want in expected.iter() {
+        assert!(
+            issues.iter().any(|issue| issue.contains(want)),
+            "{:?} not in {:?}",
+            want,
+            issues
+        );
+    }
+
+    // Nothing is changed unless asked to repair
+    assert_eq!(versions(&registry).len(), 4);
+    assert!(path.join("c-c-c-c-c/junk").exists());
+    assert!(path.join("active/e-e-e-e-e").symlink_metadata().is_ok());
+
+    let issues = registry.verify(true).unwrap();
+    assert!(issues.contains(&"a-a-a-a-a has no active version".to_owned()));
+    let expected = vec![
+        ("a-a-a-a-a".to_owned(), "1.0".to_owned(), true),
+        ("c-c-c-c-c".to_owned(), "1.0".to_owned(), true),
+        ("c-c-c-c-c".to_owned(), "2.0".to_owned(), false),
+    ];
+    assert_eq!(versions(&registry), expected);
+
+    // Bad files are quarantined rather than deleted
+    for moved in &["a-a-a-a-a/2.0", "b-b-b-b-b/1.0", "c-c-c-c-c/junk"] {
+        assert!(!path.join(moved).exists());
+        assert!(path.join(QUARANTINE_DIR).join(moved).exists());
+    }
+    assert_eq!(
+        fs::read_link(path.join("active/a-a-a-a-a")).unwrap(),
+        path.join("a-a-a-a-a/1.0")
+    );
+    assert!(path.join("active/b-b-b-b-b").symlink_metadata().is_err());
+    assert!(path.join("active/e-e-e-e-e").symlink_metadata().is_err());
+
+    assert_eq!(registry.verify(false).unwrap(), Vec::<String>::new());
+    let registry = AppRegistry::new_from_dir(&path.to_string_lossy()).unwrap();
+    assert_eq!(versions(&registry), expected);
+}
+
+#[test]
+fn verify_registry_mutation() {
+    let registry_dir = TempDir::new().unwrap();
+    {
+        let registry = AppRegistry::new_from_dir(&registry_dir.path().to_string_lossy()).unwrap();
+        register(&registry, "a-b-c-d-e", "1.0");
+    }
+    fs::remove_file(registry_dir.path().join("a-b-c-d-e/1.0/tiny-app")).unwrap();
+    let service = mock_service!(registry_dir);
+
+    let request = r#"mutation { verifyRegistry(repair: true) { success errors issues } }"#;
+    let res: serde_json::Value =
+        serde_json::from_str(&service.process(request.to_owned())).unwrap();
+    assert_eq!(
+        res["data"]["verifyRegistry"],
+        json!({
+            "success": true,
+            "errors": "",
+            "issues": [
+                format!(
+                    "a-b-c-d-e version 1.0: {}/a-b-c-d-e/1.0/tiny-app is missing",
+                    registry_dir.path().display()
+                ),
+                "a-b-c-d-e has an active symlink but isn't registered",
+            ],
+        })
+    );
+
+    let res: serde_json::Value =
+        serde_json::from_str(&service.process(request.to_owned())).unwrap();
+    assert_eq!(res["data"]["verifyRegistry"]["issues"], json!([]));
+}
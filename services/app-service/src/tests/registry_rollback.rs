@@ -0,0 +1,290 @@
+/*
+ * Copyright (C) 2018 Kubos Corporation
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use app_entry::AppRegistryEntry;
+use kubos_app::RunLevel;
+use kubos_service::{Config, Service};
+use serde_json;
+use std::fs;
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tempfile::TempDir;
+
+use super::*;
+
+use registry::*;
+use schema;
+use supervisor::*;
+
+fn entry(registry: &AppRegistry, version: &str) -> AppRegistryEntry {
+    registry
+        .entries
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|entry| entry.app.metadata.version == version)
+        .unwrap()
+        .clone()
+}
+
+fn active_version(registry: &AppRegistry) -> String {
+    registry
+        .entries
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|entry| entry.active_version)
+        .map(|entry| entry.app.metadata.version.clone())
+        .unwrap()
+}
+
+// Wait for the latest instance of the given app version to finish
+fn wait_for_exit(registry: &AppRegistry, version: &str) -> AppInstance {
+    let start = Instant::now();
+    loop {
+        if let Some(instance) = registry
+            .supervisor
+            .instances("a-b-c-d-e")
+            .into_iter()
+            .filter(|instance| instance.app.metadata.version == version)
+            .last()
+        {
+            if !instance.is_active() {
+                return instance;
+            }
+        }
+        assert!(
+            start.elapsed() < Duration::from_secs(5),
+            "Version {} never exited",
+            version
+        );
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[test]
+fn rollback_mutation() {
+    let registry_dir = TempDir::new().unwrap();
+    {
+        let registry = AppRegistry::new_from_dir(&registry_dir.path().to_string_lossy()).unwrap();
+        register_app(&registry, "a-b-c-d-e", "1.0", "exit 0", "");
+        register_app(&registry, "a-b-c-d-e", "2.0", "exit 0", "");
+        assert_eq!(
+            entry(&registry, "2.0").previous_version,
+            Some("1.0".to_owned())
+        );
+    }
+    let service = mock_service!(registry_dir);
+
+    let res: serde_json::Value = serde_json::from_str(&service.process(
+        r#"mutation { rollback(uuid: "a-b-c-d-e", reason: "Bad telemetry") { success errors } }"#
+            .to_owned(),
+    )).unwrap();
+    assert_eq!(
+        res["data"]["rollback"],
+        json!({ "success": true, "errors": "" })
+    );
+
+    let res: serde_json::Value = serde_json::from_str(&service.process(
+        r#"{ apps { active previousVersion rollbackReason app { version } } }"#.to_owned(),
+    ))
+    .unwrap();
+    let mut apps = res["data"]["apps"].as_array().unwrap().clone();
+    apps.sort_by_key(|app| app["app"]["version"].to_string());
+    assert_eq!(
+        json!(apps),
+        json!([
+            { "active": true, "previousVersion": null, "rollbackReason": null, "app": { "version": "1.0" } },
+            { "active": false, "previousVersion": "1.0", "rollbackReason": "Bad telemetry", "app": { "version": "2.0" } },
+        ])
+    );
+
+    // The reason is recorded on disk
+    let saved = AppRegistryEntry::from_dir(&registry_dir.path().join("a-b-c-d-e/2.0")).unwrap();
+    assert_eq!(saved.rollback_reason, Some("Bad telemetry".to_owned()));
+    assert_eq!(
+        fs::read_link(registry_dir.path().join("active/a-b-c-d-e")).unwrap(),
+        PathBuf::from(format!("{}/a-b-c-d-e/1.0", registry_dir.path().display()))
+    );
+
+    // 1.0 was the first version registered
+    let res: serde_json::Value = serde_json::from_str(
+        &service
+            .process(r#"mutation { rollback(uuid: "a-b-c-d-e") { success errors } }"#.to_owned()),
+    )
+    .unwrap();
+    assert_eq!(
+        res["data"]["rollback"],
+        json!({ "success": false, "errors": "Failed to change app version: No previous version of a-b-c-d-e to roll back to" })
+    );
+}
+
+#[test]
+fn set_version_mutation() {
+    let registry_dir = TempDir::new().unwrap();
+    {
+        let registry = AppRegistry::new_from_dir(&registry_dir.path().to_string_lossy()).unwrap();
+        register_app(&registry, "a-b-c-d-e", "1.0", "exit 0", "");
+        register_app(&registry, "a-b-c-d-e", "2.0", "exit 0", "");
+        registry.rollback("a-b-c-d-e", "Bad telemetry").unwrap();
+    }
+    let service = mock_service!(registry_dir);
+
+    let res: serde_json::Value = serde_json::from_str(
+        &service.process(
+            r#"mutation { setVersion(uuid: "a-b-c-d-e", version: "2.0") { success errors } }"#
+                .to_owned(),
+        ),
+    )
+    .unwrap();
+    assert_eq!(
+        res["data"]["setVersion"],
+        json!({ "success": true, "errors": "" })
+    );
+
+    let res: serde_json::Value = serde_json::from_str(
+        &service.process(r#"{ apps(active: true) { rollbackReason app { version } } }"#.to_owned()),
+    )
+    .unwrap();
+    assert_eq!(
+        res["data"]["apps"],
+        json!([{ "rollbackReason": null, "app": { "version": "2.0" } }])
+    );
+
+    let res: serde_json::Value = serde_json::from_str(
+        &service.process(
+            r#"mutation { setVersion(uuid: "a-b-c-d-e", version: "3.0") { success errors } }"#
+                .to_owned(),
+        ),
+    )
+    .unwrap();
+    assert_eq!(
+        res["data"]["setVersion"],
+        json!({ "success": false, "errors": "Failed to change app version: a-b-c-d-e version 3.0 not found in registry" })
+    );
+}
+
+#[test]
+fn auto_rollback_after_restarts() {
+    let registry_dir = TempDir::new().unwrap();
+    let registry = AppRegistry::new_from_dir(&registry_dir.path().to_string_lossy()).unwrap();
+    register_app(&registry, "a-b-c-d-e", "1.0", "exit 0", "");
+    register_app(
+        &registry,
+        "a-b-c-d-e",
+        "2.0",
+        "exit 1",
+        r#"
+            [restart]
+            policy = "on-failure"
+            max_retries = 5
+            backoff = 0
+            "#,
+    );
+
+    registry
+        .start_app("a-b-c-d-e", RunLevel::OnBoot, None)
+        .unwrap();
+
+    // The previous version is started in its place
+    let started = wait_for_exit(&registry, "1.0");
+    assert_eq!(started.state, AppState::Exited);
+    assert_eq!(started.run_level, RunLevel::OnBoot);
+
+    // And the failing version stops being restarted once it's been rolled back
+    thread::sleep(Duration::from_millis(100));
+    assert!(registry
+        .supervisor
+        .instances("a-b-c-d-e")
+        .iter()
+        .all(|instance| !instance.is_active()));
+
+    assert_eq!(active_version(&registry), "1.0");
+    let saved = AppRegistryEntry::from_dir(&registry_dir.path().join("a-b-c-d-e/2.0")).unwrap();
+    assert_eq!(saved.boot_failures, 3);
+    assert_eq!(
+        saved.rollback_reason,
+        Some(
+            "Failed to start with the OnBoot run level 3 times in a row. Last failure: Exited with code 1"
+                .to_owned()
+        )
+    );
+}
+
+#[test]
+fn auto_rollback_across_boots() {
+    let registry_dir = TempDir::new().unwrap();
+    {
+        let registry = AppRegistry::new_from_dir(&registry_dir.path().to_string_lossy()).unwrap();
+        register_app(&registry, "a-b-c-d-e", "1.0", "exit 0", "");
+        register_app(&registry, "a-b-c-d-e", "2.0", "exit 1", "");
+    }
+
+    for boot in 1..3 {
+        let registry = AppRegistry::new_from_dir(&registry_dir.path().to_string_lossy()).unwrap();
+        registry
+            .start_app("a-b-c-d-e", RunLevel::OnBoot, None)
+            .unwrap();
+        wait_for_exit(&registry, "2.0");
+        assert_eq!(entry(&registry, "2.0").boot_failures, boot);
+        assert_eq!(active_version(&registry), "2.0");
+
+        // Failures when started on command don't count
+        registry
+            .start_app("a-b-c-d-e", RunLevel::OnCommand, None)
+            .unwrap();
+        wait_for_exit(&registry, "2.0");
+        assert_eq!(entry(&registry, "2.0").boot_failures, boot);
+    }
+
+    let registry = AppRegistry::new_from_dir(&registry_dir.path().to_string_lossy()).unwrap();
+    registry
+        .start_app("a-b-c-d-e", RunLevel::OnBoot, None)
+        .unwrap();
+    wait_for_exit(&registry, "1.0");
+    assert_eq!(active_version(&registry), "1.0");
+    assert_eq!(entry(&registry, "2.0").boot_failures, 3);
+}
+
+#[test]
+fn boot_success_resets_failures() {
+    let registry_dir = TempDir::new().unwrap();
+    let registry = AppRegistry::new_from_dir(&registry_dir.path().to_string_lossy()).unwrap();
+    register_app(&registry, "a-b-c-d-e", "1.0", "exit 0", "");
+    // Fails the first time it's run at boot only. Registering it runs it with OnUpgrade.
+    let marker = registry_dir.path().join("ran");
+    register_app(
+        &registry,
+        "a-b-c-d-e",
+        "2.0",
+        &format!(
+            "[ \"$2\" != OnBoot ] && exit 0; [ -e {0} ] && exit 0; touch {0}; exit 1",
+            marker.display()
+        ),
+        "",
+    );
+
+    for expected in &[1, 0] {
+        registry
+            .start_app("a-b-c-d-e", RunLevel::OnBoot, None)
+            .unwrap();
+        wait_for_exit(&registry, "2.0");
+        assert_eq!(entry(&registry, "2.0").boot_failures, *expected);
+    }
+    assert_eq!(active_version(&registry), "2.0");
+}
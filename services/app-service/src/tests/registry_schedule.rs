@@ -0,0 +1,193 @@
+/*
+ * Copyright (C) 2018 Kubos Corporation
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use kubos_service::{Config, Service};
+use serde_json;
+
+use tempfile::TempDir;
+
+use super::*;
+
+use logs::read_logs;
+use registry::*;
+use schema;
+
+#[test]
+fn schedule_mutations() {
+    let registry_dir = TempDir::new().unwrap();
+    create_app(&registry_dir, "exit 0", "");
+    let service = mock_service!(registry_dir);
+
+    let res: serde_json::Value = serde_json::from_str(
+        &service.process(
+            r#"mutation {
+            scheduleApp(uuid: "a-b-c-d-e", every: 5400, args: ["--mode", "science"]) {
+                success errors schedule { id uuid every args lastRun }
+            }
+        }"#
+            .to_owned(),
+        ),
+    )
+    .unwrap();
+    assert_eq!(
+        res["data"]["scheduleApp"],
+        json!({
+            "success": true,
+            "errors": "",
+            "schedule": {
+                "id": 1,
+                "uuid": "a-b-c-d-e",
+                "every": 5400,
+                "args": ["--mode", "science"],
+                "lastRun": null,
+            }
+        })
+    );
+
+    // Schedules are kept across restarts of the service
+    let service = mock_service!(registry_dir);
+    let res: serde_json::Value = serde_json::from_str(
+        &service.process(r#"{ schedules(uuid: "a-b-c-d-e") { id every } }"#.to_owned()),
+    )
+    .unwrap();
+    assert_eq!(
+        res["data"]["schedules"],
+        json!([{ "id": 1, "every": 5400 }])
+    );
+
+    let cancel = r#"mutation { cancelSchedule(id: 1) { success errors } }"#;
+    let res: serde_json::Value = serde_json::from_str(&service.process(cancel.to_owned())).unwrap();
+    assert_eq!(
+        res["data"]["cancelSchedule"],
+        json!({ "success": true, "errors": "" })
+    );
+
+    let res: serde_json::Value = serde_json::from_str(&service.process(cancel.to_owned())).unwrap();
+    assert_eq!(
+        res["data"]["cancelSchedule"],
+        json!({ "success": false, "errors": "Failed to schedule app: Schedule 1 not found" })
+    );
+
+    let service = mock_service!(registry_dir);
+    let res: serde_json::Value =
+        serde_json::from_str(&service.process(r#"{ schedules { id } }"#.to_owned())).unwrap();
+    assert_eq!(res["data"]["schedules"], json!([]));
+
+    // Ids of cancelled schedules aren't reused
+    let res: serde_json::Value = serde_json::from_str(&service.process(
+        r#"mutation { scheduleApp(uuid: "a-b-c-d-e", every: 60) { schedule { id } } }"#.to_owned(),
+    ))
+    .unwrap();
+    assert_eq!(res["data"]["scheduleApp"]["schedule"], json!({ "id": 2 }));
+}
+
+#[test]
+fn schedule_invalid() {
+    let registry_dir = TempDir::new().unwrap();
+    create_app(&registry_dir, "exit 0", "");
+    let service = mock_service!(registry_dir);
+
+    let cases = vec![
+        (
+            r#"scheduleApp(uuid: "f-g-h-i-j", every: 60)"#,
+            "No active version found for UUID f-g-h-i-j",
+        ),
+        (
+            r#"scheduleApp(uuid: "a-b-c-d-e")"#,
+            "Failed to schedule app: Either a start time or a period is required",
+        ),
+        (
+            r#"scheduleApp(uuid: "a-b-c-d-e", at: 1000.0)"#,
+            "Failed to schedule app: Start time 1000 is in the past",
+        ),
+        (
+            r#"scheduleApp(uuid: "a-b-c-d-e", every: 0)"#,
+            "Failed to schedule app: Period must be at least one second",
+        ),
+    ];
+
+    for (mutation, error) in cases {
+        let res: serde_json::Value = serde_json::from_str(&service.process(format!(
+            "mutation {{ {} {{ success errors schedule {{ id }} }} }}",
+            mutation
+        )))
+        .unwrap();
+        assert_eq!(
+            res["data"]["scheduleApp"],
+            json!({ "success": false, "errors": error, "schedule": null })
+        );
+    }
+}
+
+#[test]
+fn run_due_schedules() {
+    let registry_dir = TempDir::new().unwrap();
+    create_app(&registry_dir, "exit 0", "");
+    let registry = AppRegistry::new_from_dir(&registry_dir.path().to_string_lossy()).unwrap();
+
+    let once = registry
+        .scheduler
+        .add(
+            "a-b-c-d-e",
+            Some(100.0),
+            None,
+            vec!["once".to_owned()],
+            50.0,
+        )
+        .unwrap();
+    let periodic = registry
+        .scheduler
+        .add("a-b-c-d-e", None, Some(10), vec![], 50.0)
+        .unwrap();
+    let missing = registry
+        .scheduler
+        .add("f-g-h-i-j", None, Some(10), vec![], 50.0)
+        .unwrap();
+    assert_eq!(periodic.next_run, 60.0);
+
+    // Nothing is due yet
+    registry.run_schedules(55.0);
+    assert!(registry.supervisor.instances("a-b-c-d-e").is_empty());
+
+    registry.run_schedules(100.0);
+
+    let schedules = registry.scheduler.list();
+    assert_eq!(schedules.len(), 2);
+    assert!(schedules.iter().all(|schedule| schedule.id != once.id));
+
+    // Missed periods are only made up once
+    let periodic = schedules.iter().find(|s| s.id == periodic.id).unwrap();
+    assert_eq!(periodic.next_run, 110.0);
+    assert_eq!(periodic.last_run, Some(100.0));
+    assert_eq!(periodic.last_error, None);
+
+    let missing = schedules.iter().find(|s| s.id == missing.id).unwrap();
+    assert_eq!(
+        missing.last_error,
+        Some("Failed to start app: No active version found for UUID f-g-h-i-j".to_owned())
+    );
+
+    // Both the one-off and the periodic run are started on command
+    let logs = read_logs(&registry_dir.path().join("a-b-c-d-e/1.0/logs"), 100).unwrap();
+    let started: Vec<&String> = logs
+        .iter()
+        .filter(|line| line.contains("[service] Started"))
+        .collect();
+    assert_eq!(started.len(), 2);
+    assert!(started
+        .iter()
+        .all(|line| line.ends_with("with run level OnCommand")));
+}
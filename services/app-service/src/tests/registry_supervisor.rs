@@ -0,0 +1,242 @@
+/*
+ * Copyright (C) 2018 Kubos Corporation
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use kubos_app::RunLevel;
+use kubos_service::{Config, Service};
+use nix::sys::signal::Signal;
+use serde_json;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tempfile::TempDir;
+
+use super::*;
+
+use registry::*;
+use schema;
+use supervisor::*;
+
+#[test]
+fn supervise_exit() {
+    let registry_dir = TempDir::new().unwrap();
+    create_app(&registry_dir, "exit 0", "");
+
+    let registry = AppRegistry::new_from_dir(&registry_dir.path().to_string_lossy()).unwrap();
+    let pid = registry
+        .start_app("a-b-c-d-e", RunLevel::OnCommand, None)
+        .unwrap();
+
+    let instance = wait_for(&registry, AppState::Exited);
+    assert_eq!(instance.pid, pid);
+    assert_eq!(instance.exit_code, Some(0));
+    assert_eq!(instance.signal, None);
+    assert!(instance.end_time.is_some());
+    assert_eq!(instance.restarts, 0);
+}
+
+#[test]
+fn supervise_failure_no_restart() {
+    let registry_dir = TempDir::new().unwrap();
+    create_app(&registry_dir, "exit 3", "");
+
+    let registry = AppRegistry::new_from_dir(&registry_dir.path().to_string_lossy()).unwrap();
+    registry
+        .start_app("a-b-c-d-e", RunLevel::OnCommand, None)
+        .unwrap();
+
+    let instance = wait_for(&registry, AppState::Failed);
+    assert_eq!(instance.exit_code, Some(3));
+    assert_eq!(instance.restarts, 0);
+}
+
+#[test]
+fn supervise_restart_on_failure() {
+    let registry_dir = TempDir::new().unwrap();
+    create_app(
+        &registry_dir,
+        "exit 1",
+        r#"
+            [app.metadata.restart]
+            policy = "on-failure"
+            max_retries = 2
+            backoff = 0
+            "#,
+    );
+
+    let registry = AppRegistry::new_from_dir(&registry_dir.path().to_string_lossy()).unwrap();
+    registry
+        .start_app("a-b-c-d-e", RunLevel::OnBoot, None)
+        .unwrap();
+
+    let instance = wait_for(&registry, AppState::Failed);
+    assert_eq!(instance.restarts, 2);
+    assert_eq!(instance.exit_code, Some(1));
+    assert_eq!(instance.run_level, RunLevel::OnBoot);
+    assert_eq!(registry.supervisor.instances("a-b-c-d-e").len(), 1);
+}
+
+#[test]
+fn supervise_kill_no_restart() {
+    let registry_dir = TempDir::new().unwrap();
+    create_app(
+        &registry_dir,
+        "exec sleep 30",
+        r#"
+            [app.metadata.restart]
+            policy = "on-failure"
+            backoff = 0
+            "#,
+    );
+
+    let registry = AppRegistry::new_from_dir(&registry_dir.path().to_string_lossy()).unwrap();
+    let pid = registry
+        .start_app("a-b-c-d-e", RunLevel::OnCommand, None)
+        .unwrap();
+
+    assert_eq!(
+        registry
+            .supervisor
+            .signal("a-b-c-d-e", None, Signal::SIGKILL)
+            .unwrap(),
+        vec![pid]
+    );
+
+    let instance = wait_for(&registry, AppState::Stopped);
+    assert_eq!(instance.signal, Some(9));
+    assert_eq!(instance.restarts, 0);
+
+    // Nothing left to kill
+    assert!(
+        registry
+            .supervisor
+            .signal("a-b-c-d-e", None, Signal::SIGKILL)
+            .is_err()
+    );
+}
+
+#[test]
+fn supervise_signal_restart() {
+    let registry_dir = TempDir::new().unwrap();
+    create_app(
+        &registry_dir,
+        "exec sleep 30",
+        r#"
+            [app.metadata.restart]
+            policy = "on-failure"
+            backoff = 0
+            "#,
+    );
+
+    let registry = AppRegistry::new_from_dir(&registry_dir.path().to_string_lossy()).unwrap();
+    let pid = registry
+        .start_app("a-b-c-d-e", RunLevel::OnCommand, None)
+        .unwrap();
+
+    // Signals other than SIGTERM and SIGKILL aren't a request to stop the app
+    registry
+        .supervisor
+        .signal("a-b-c-d-e", None, Signal::SIGUSR1)
+        .unwrap();
+
+    let start = Instant::now();
+    loop {
+        let instance = registry.supervisor.instances("a-b-c-d-e").pop().unwrap();
+        if instance.restarts == 1 && instance.state == AppState::Running {
+            assert_ne!(instance.pid, pid);
+            break;
+        }
+        assert!(
+            start.elapsed() < Duration::from_secs(5),
+            "App was not restarted: {:?}",
+            instance
+        );
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    registry.supervisor.stop("a-b-c-d-e", None, None).unwrap();
+    let instance = wait_for(&registry, AppState::Stopped);
+    assert_eq!(instance.restarts, 1);
+}
+
+#[test]
+fn stop_app_mutation() {
+    let registry_dir = TempDir::new().unwrap();
+    create_app(&registry_dir, "exec sleep 30", "");
+
+    let service = mock_service!(registry_dir);
+
+    let res: serde_json::Value = serde_json::from_str(&service.process(
+        r#"mutation { startApp(uuid: "a-b-c-d-e", runLevel: "OnCommand") { success pid } }"#
+            .to_owned(),
+    )).unwrap();
+    assert_eq!(res["data"]["startApp"]["success"], true);
+    let pid = res["data"]["startApp"]["pid"].clone();
+
+    let query = r#"{ apps { app { pid } processes { pid state signal restarts } } }"#;
+    let res: serde_json::Value = serde_json::from_str(&service.process(query.to_owned())).unwrap();
+    assert_eq!(res["data"]["apps"][0]["app"]["pid"], pid);
+    assert_eq!(res["data"]["apps"][0]["processes"][0]["state"], "RUNNING");
+
+    let res: serde_json::Value = serde_json::from_str(&service.process(
+        r#"mutation { stopApp(uuid: "a-b-c-d-e", timeout: 1) { success errors } }"#.to_owned(),
+    )).unwrap();
+    assert_eq!(
+        res["data"]["stopApp"],
+        json!({ "success": true, "errors": "" })
+    );
+
+    let start = Instant::now();
+    loop {
+        let res: serde_json::Value =
+            serde_json::from_str(&service.process(query.to_owned())).unwrap();
+        if res["data"]["apps"][0]["processes"][0]["state"] == "STOPPED" {
+            assert_eq!(
+                res["data"]["apps"][0],
+                json!({
+                    "app": { "pid": 0 },
+                    "processes": [{ "pid": pid, "state": "STOPPED", "signal": 15, "restarts": 0 }]
+                })
+            );
+            break;
+        }
+        assert!(start.elapsed() < Duration::from_secs(5), "App not stopped: {}", res);
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[test]
+fn kill_app_mutation_bad_signal() {
+    let registry_dir = TempDir::new().unwrap();
+    create_app(&registry_dir, "exec sleep 30", "");
+
+    let service = mock_service!(registry_dir);
+
+    let res: serde_json::Value = serde_json::from_str(&service.process(
+        r#"mutation { killApp(uuid: "a-b-c-d-e", signal: 1000) { success errors } }"#.to_owned(),
+    )).unwrap();
+    assert_eq!(
+        res["data"]["killApp"],
+        json!({ "success": false, "errors": "Invalid signal: 1000" })
+    );
+
+    let res: serde_json::Value = serde_json::from_str(&service.process(
+        r#"mutation { killApp(uuid: "a-b-c-d-e") { success errors } }"#.to_owned(),
+    )).unwrap();
+    assert_eq!(
+        res["data"]["killApp"],
+        json!({ "success": false, "errors": "Failed to kill app: No running instance of a-b-c-d-e found" })
+    );
+}
@@ -17,6 +17,7 @@
 extern crate kubos_app;
 extern crate toml;
 
+use std::collections::BTreeMap;
 use tempfile::TempDir;
 
 use app_entry::*;
@@ -49,7 +50,7 @@ fn empty_apps_dir_empty_reg() {
     let registry_dir = TempDir::new().unwrap();
 
     let registry = AppRegistry::new_from_dir(&registry_dir.path().to_string_lossy()).unwrap();
-    assert_eq!(registry.entries.borrow().len(), 0);
+    assert_eq!(registry.entries.lock().unwrap().len(), 0);
 }
 
 #[test]
@@ -61,21 +62,52 @@ fn serialize_entry() {
                 name: String::from("dummy"),
                 version: String::from("0.0.1"),
                 author: String::from("noone"),
+                requires: vec![String::from("telemetry-service")],
+                requires_timeout: None,
+                working_dir: Some(String::from("data")),
+                env: vec![(String::from("MODE"), String::from("safe"))]
+                    .into_iter()
+                    .collect(),
+                limits: ResourceLimits {
+                    cpu_time: Some(60),
+                    memory: None,
+                },
+                tick: Some(30),
+                shutdown_timeout: None,
+                config: Some(toml::from_str("threshold = 5\n[payload]\nport = 9000").unwrap()),
+                restart: RestartPolicy::default(),
+                checksums: BTreeMap::new(),
             },
             pid: 101,
             path: String::from("/fake/path"),
         },
         active_version: true,
+        boot_failures: 2,
+        previous_version: None,
+        rollback_reason: Some(String::from("Crashed")),
     };
 
     let str = toml::to_string(&dummy).unwrap();
     let parsed: AppRegistryEntry = toml::from_str(&str).unwrap();
 
     assert_eq!(parsed.active_version, dummy.active_version);
+    assert_eq!(parsed.boot_failures, dummy.boot_failures);
+    assert_eq!(parsed.previous_version, dummy.previous_version);
+    assert_eq!(parsed.rollback_reason, dummy.rollback_reason);
     assert_eq!(parsed.app.uuid, dummy.app.uuid);
     assert_eq!(parsed.app.pid, dummy.app.pid);
     assert_eq!(parsed.app.path, dummy.app.path);
     assert_eq!(parsed.app.metadata.name, dummy.app.metadata.name);
     assert_eq!(parsed.app.metadata.version, dummy.app.metadata.version);
     assert_eq!(parsed.app.metadata.author, dummy.app.metadata.author);
+    assert_eq!(parsed.app.metadata.requires, dummy.app.metadata.requires);
+    assert_eq!(parsed.app.metadata.working_dir, dummy.app.metadata.working_dir);
+    assert_eq!(parsed.app.metadata.env, dummy.app.metadata.env);
+    assert_eq!(parsed.app.metadata.limits, dummy.app.metadata.limits);
+    assert_eq!(parsed.app.metadata.tick, dummy.app.metadata.tick);
+    assert_eq!(
+        parsed.app.metadata.shutdown_timeout,
+        dummy.app.metadata.shutdown_timeout
+    );
+    assert_eq!(parsed.app.metadata.config, dummy.app.metadata.config);
 }
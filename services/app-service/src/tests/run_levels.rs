@@ -0,0 +1,128 @@
+/*
+ * Copyright (C) 2018 Kubos Corporation
+ *
+ * Licensed under the Apache License, Version 2.0 (the "License");
+ * you may not use this file except in compliance with the License.
+ * You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use kubos_app::RunLevel;
+use kubos_service::{Config, Service};
+use serde_json;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tempfile::TempDir;
+
+use super::*;
+
+use registry::*;
+use schema;
+
+fn started_with(lines: &[String], run_level: &str) -> usize {
+    let want = format!("with run level {}", run_level);
+    lines.iter().filter(|line| line.ends_with(&want)).count()
+}
+
+#[test]
+fn upgrade_runs_new_version() {
+    let registry_dir = TempDir::new().unwrap();
+    let registry = AppRegistry::new_from_dir(&registry_dir.path().to_string_lossy()).unwrap();
+
+    register_app(&registry, "a-b-c-d-e", "1.0", "echo \"$@\"", "");
+    register_app(&registry, "a-b-c-d-e", "2.0", "echo \"$@\"", "");
+
+    wait_for_log(&registry_dir, "2.0", "[stdout] -r OnUpgrade");
+    // Neither the first version nor the one being replaced is run
+    assert_eq!(
+        started_with(&version_logs(&registry_dir, "1.0"), "OnUpgrade"),
+        0
+    );
+
+    // Re-registering the same version isn't an upgrade
+    register_app(&registry, "a-b-c-d-e", "2.0", "echo \"$@\"", "");
+    thread::sleep(Duration::from_millis(200));
+    assert_eq!(
+        started_with(&version_logs(&registry_dir, "2.0"), "OnUpgrade"),
+        1
+    );
+}
+
+#[test]
+fn run_ticks() {
+    let registry_dir = TempDir::new().unwrap();
+    let registry = AppRegistry::new_from_dir(&registry_dir.path().to_string_lossy()).unwrap();
+    register_app(&registry, "a-b-c-d-e", "1.0", "sleep 1", "tick = 10");
+
+    registry.run_ticks(100.0);
+    registry.run_ticks(109.0);
+    assert!(registry.supervisor.instances("a-b-c-d-e").is_empty());
+
+    registry.run_ticks(110.0);
+    wait_for_log(&registry_dir, "1.0", "with run level OnTick");
+
+    // Skipped while the previous tick is still running
+    registry.run_ticks(120.0);
+    assert_eq!(registry.supervisor.instances("a-b-c-d-e").len(), 1);
+    assert_eq!(
+        registry.supervisor.instances("a-b-c-d-e")[0].run_level,
+        RunLevel::OnTick
+    );
+}
+
+#[test]
+fn stop_with_shutdown_timeout() {
+    let registry_dir = TempDir::new().unwrap();
+    let registry = AppRegistry::new_from_dir(&registry_dir.path().to_string_lossy()).unwrap();
+    // Ignores SIGTERM, so it has to be killed once its shutdown timeout passes
+    register_app(
+        &registry,
+        "a-b-c-d-e",
+        "1.0",
+        "trap 'echo \"term $KUBOS_APP_SHUTDOWN_TIMEOUT\"' TERM\nwhile true; do sleep 0.1; done",
+        "shutdown_timeout = 1",
+    );
+
+    registry
+        .start_app("a-b-c-d-e", RunLevel::OnCommand, None)
+        .unwrap();
+    thread::sleep(Duration::from_millis(200));
+
+    let start = Instant::now();
+    registry.supervisor.stop("a-b-c-d-e", None, None).unwrap();
+    let lines = wait_for_log(&registry_dir, "1.0", "killed by signal 9");
+    assert!(start.elapsed() >= Duration::from_secs(1));
+    assert!(lines.iter().any(|line| line.ends_with("[stdout] term 1")));
+}
+
+#[test]
+fn start_unknown_run_level() {
+    let registry_dir = TempDir::new().unwrap();
+    {
+        let registry = AppRegistry::new_from_dir(&registry_dir.path().to_string_lossy()).unwrap();
+        register_app(&registry, "a-b-c-d-e", "1.0", "exit 0", "");
+    }
+    let service = mock_service!(registry_dir);
+
+    let res: serde_json::Value = serde_json::from_str(&service.process(
+        r#"mutation { startApp(uuid: "a-b-c-d-e", runLevel: "OnLaunch") { success errors pid } }"#
+            .to_owned(),
+    ))
+    .unwrap();
+    assert_eq!(
+        res["data"]["startApp"],
+        json!({
+            "success": false,
+            "errors": "Unknown run level was requested - OnLaunch. Available run levels: OnBoot, OnCommand, OnUpgrade, OnTick",
+            "pid": null,
+        })
+    );
+}
@@ -64,7 +64,6 @@ fn upgrade_new() {
     );
 
     let expected = json!({
-            "errors": "",
             "data": {
                "register": {
                    "entry": {
@@ -122,7 +121,6 @@ fn upgrade_good() {
     );
 
     let expected = json!({
-            "errors": "",
             "data": {
                "register": {
                    "entry": {
@@ -151,7 +149,6 @@ fn upgrade_good() {
     fs::write(app_bin.join("manifest.toml"), manifest).unwrap();
 
     let expected = json!({
-            "errors": "",
             "data": {
                "register": {
                    "entry": {
@@ -176,7 +173,6 @@ fn upgrade_good() {
     "#;
 
     let expected = json!({
-            "errors": "",
             "data": {
                "apps": [
                  {
@@ -243,7 +239,6 @@ fn upgrade_new_name() {
     );
 
     let expected = json!({
-            "errors": "",
             "data": {
                "register": {
                    "entry": {
@@ -278,7 +273,6 @@ fn upgrade_new_name() {
     fs::write(app_bin.join("manifest.toml"), manifest).unwrap();
 
     let expected = json!({
-            "errors": "",
             "data": {
                "register": {
                    "entry": {
@@ -303,7 +297,6 @@ fn upgrade_new_name() {
     "#;
 
     let expected = json!({
-            "errors": "",
             "data": {
                "apps": [
                  {
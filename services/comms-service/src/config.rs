@@ -16,13 +16,12 @@
 // Contributed by: William Greer (wgreer184@gmail.com) and Sam Justice (sam.justice1@gmail.com)
 //
 
-//! TOML parser for the `comms-service`. This module parses a `toml` file and returns a
-//! struct containing configuration information for a `comms-service`.
+//! Configuration for the `comms-service`. This module loads a service's section of the
+//! KubOS config file and returns a struct containing its configuration information.
 
 use failure::Error;
-use std::fs::File;
-use std::io::Read;
-use toml::*;
+use kubos_system::{Config, ServiceSettings};
+use std::net::Ipv4Addr;
 
 // Default values for control block configurations.
 const DEFAULT_HANDLER_START: u16 = 13100;
@@ -31,6 +30,51 @@ const DEFAULT_TIMEOUT: u64 = 1500;
 static DEFAULT_GROUND_IP: &str = "192.168.8.1";
 static DEFAULT_SATELLITE_IP: &str = "192.168.8.2";
 
+/// Settings read from a communication service's section of the config file
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct CommsServiceSettings {
+    /// First port of the range used by message handlers
+    pub handler_port_min: Option<u16>,
+    /// Last port of the range used by message handlers
+    pub handler_port_max: Option<u16>,
+    /// Ports used by downlink endpoints
+    pub downlink_ports: Option<Vec<u16>>,
+    /// Timeout, in milliseconds, for GraphQL operations within message handlers
+    pub timeout: Option<u64>,
+    /// IP address of the ground gateway
+    pub ground_ip: Option<String>,
+    /// Port the ground gateway is bound to
+    pub ground_port: Option<u16>,
+    /// Satellite's IP address
+    pub satellite_ip: Option<String>,
+}
+
+impl ServiceSettings for CommsServiceSettings {
+    fn validate(&self) -> Result<(), Error> {
+        if let (Some(min), Some(max)) = (self.handler_port_min, self.handler_port_max) {
+            if min > max {
+                bail!(
+                    "handler-port-min ({}) must not be greater than handler-port-max ({})",
+                    min,
+                    max
+                );
+            }
+        }
+        check_ipv4("ground-ip", &self.ground_ip)?;
+        check_ipv4("satellite-ip", &self.satellite_ip)?;
+        Ok(())
+    }
+}
+
+fn check_ipv4(key: &str, value: &Option<String>) -> Result<(), Error> {
+    if let Some(ref ip) = *value {
+        ip.parse::<Ipv4Addr>()
+            .map_err(|_| format_err!("{} must be an IPv4 address, found \"{}\"", key, ip))?;
+    }
+    Ok(())
+}
+
 /// A struct that holds useful configuration options to use in a `comms-service` implementation.
 /// Created by parsing a configuration file in the `toml` file format.
 #[derive(Debug)]
@@ -71,93 +115,59 @@ impl Default for CommsConfig {
 
 impl CommsConfig {
     /// Builds a new configuration for a specific `comms-service`.
-    pub fn new(name: &str, path: String) -> Self {
-        Self::parse_file(name, path).unwrap_or(CommsConfig::default())
-    }
-
-    // Function used to parse configuration files.
-    fn parse_file(name: &str, path: String) -> ConfigResult<CommsConfig> {
-        // Read file to string.
-        let mut contents = String::new();
-        let mut file = File::open(path)?;
-        file.read_to_string(&mut contents)?;
-
-        // Read contents and place into TOML table.
-        let table: Value = toml::from_str(&contents)?;
-        let mut config = CommsConfig::default();
-
-        // Go through TOML table and replace default if provided.
-        if let Some(service) = table.get(name) {
-            // Get the integer values from TOML file.
-            if let Ok(num) = get_number_u16("handler-port-min", &service) {
-                config.handler_port_min = num;
-            }
-            if let Ok(num) = get_number_u16("handler-port-max", &service) {
-                config.handler_port_max = num;
-            }
-            if let Ok(num) = get_number_u64("timeout", &service) {
-                config.timeout = num;
-            }
-            if let Some(num) = get_number_u16("ground-port", &service).ok() {
-                config.ground_port = Some(num);
-            }
+    ///
+    /// Any values not provided in the config file are given their default values.
+    /// Returns an error if the file cannot be read or parsed, or if it contains
+    /// unknown keys or invalid values.
+    pub fn new(name: &str, path: String) -> ConfigResult<Self> {
+        let settings = Config::load_from_path(name, path)?.settings::<CommsServiceSettings>()?;
+        let defaults = CommsConfig::default();
 
-            // Get the `downlink_ports` TOML array.
-            if let Some(array) = service.get("downlink-ports") {
-                if let Some(vector) = array.as_array() {
-                    let mut ports = vec![];
-                    for item in vector {
-                        if let Some(val) = item.as_integer() {
-                            ports.push(val as u16);
-                        }
-                    }
-                    config.downlink_ports = Some(ports);
-                }
-            }
-
-            // Pull strings from the TOML file.
-            if let Some(ground_ip) = service.get("ground-ip") {
-                if let Some(val) = ground_ip.as_str() {
-                    config.ground_ip = val.to_string();
-                }
-            }
-            if let Some(satellite_ip) = service.get("satellite-ip") {
-                if let Some(val) = satellite_ip.as_str() {
-                    config.satellite_ip = val.to_string()
-                }
-            }
-        }
-        Ok(config)
+        Ok(CommsConfig {
+            handler_port_min: settings
+                .handler_port_min
+                .unwrap_or(defaults.handler_port_min),
+            handler_port_max: settings
+                .handler_port_max
+                .unwrap_or(defaults.handler_port_max),
+            downlink_ports: settings.downlink_ports,
+            timeout: settings.timeout.unwrap_or(defaults.timeout),
+            ground_ip: settings.ground_ip.unwrap_or(defaults.ground_ip),
+            ground_port: settings.ground_port,
+            satellite_ip: settings.satellite_ip.unwrap_or(defaults.satellite_ip),
+        })
     }
 }
 
-// Helper function to get a `u16` from a TOML table.
-fn get_number_u16(key: &str, table: &Value) -> ConfigResult<u16> {
-    if let Some(val) = table.get(key) {
-        if let Some(num) = val.as_integer() {
-            return Ok(num as u16);
-        }
-    }
-    Err(ConfigError::IntegerParsing.into())
-}
+/// Result returned when creating a `CommsConfig` struct.
+pub type ConfigResult<T> = Result<T, Error>;
 
-// Helper function to get a `u64` from a TOML table.
-fn get_number_u64(key: &str, table: &Value) -> ConfigResult<u64> {
-    if let Some(val) = table.get(key) {
-        if let Some(num) = val.as_integer() {
-            return Ok(num as u64);
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_handler_ports() {
+        let config = Config::new_from_str(
+            "ethernet-service",
+            "[ethernet-service]\nhandler-port-min = 13010\nhandler-port-max = 13002\n",
+        ).unwrap();
+
+        assert!(config.settings::<CommsServiceSettings>().is_err());
     }
-    Err(ConfigError::IntegerParsing.into())
-}
 
-/// Result returned when creating a `CommsConfig` struct.
-pub type ConfigResult<T> = Result<T, Error>;
+    #[test]
+    fn settings_ground_ip() {
+        let config = Config::new_from_str(
+            "ethernet-service",
+            "[ethernet-service]\nground-ip = \"192.168.8\"\n",
+        ).unwrap();
+        let err = config.settings::<CommsServiceSettings>().unwrap_err();
 
-/// This enum defines all errors that can occur during configuration parsing.
-#[derive(Fail, Debug, PartialEq)]
-pub enum ConfigError {
-    /// Failure occured during the parsing of integers from the TOML table.
-    #[fail(display = "Unable to parse integers from the configuration file")]
-    IntegerParsing,
+        assert_eq!(
+            err.to_string(),
+            "Invalid configuration for ethernet-service: \
+             ground-ip must be an IPv4 address, found \"192.168.8\""
+        );
+    }
 }
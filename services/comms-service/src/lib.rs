@@ -65,9 +65,9 @@
 //!
 //! ```toml
 //! [service-name]
-//! handler_port_min = 13002
-//! handler_port_max = 13010
-//! downlink_ports = [13011]
+//! handler-port-min = 13002
+//! handler-port-max = 13010
+//! downlink-ports = [13011]
 //! ground-port = 9001
 //! timeout = 1500
 //! ground-ip = "192.168.8.1"
@@ -79,10 +79,13 @@ extern crate byteorder;
 extern crate failure;
 #[macro_use]
 extern crate juniper;
+extern crate kubos_system;
 #[macro_use]
 extern crate log;
 extern crate pnet;
-extern crate toml;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
 
 mod config;
 mod errors;
@@ -31,7 +31,7 @@ pub fn read(socket: Arc<UdpSocket>) -> CommsResult<Vec<u8>> {
 
 // Function to allow writing over a UDP socket.
 pub fn write(socket: Arc<UdpSocket>, data: &[u8]) -> CommsResult<()> {
-    let config = CommsConfig::new("ethernet-service", CONFIG_PATH.to_string());
+    let config = CommsConfig::new("ethernet-service", CONFIG_PATH.to_string())?;
     socket.send_to(
         data,
         (&*config.ground_ip, config.ground_port.unwrap_or_default()),
@@ -28,20 +28,23 @@
 
 extern crate comms_service;
 extern crate failure;
+extern crate kubos_system;
 extern crate log;
 extern crate syslog;
 
 use comms::*;
 use comms_service::*;
 use failure::Error;
+use kubos_system::{check_config_status, config_path_arg};
 use std::net::{Ipv4Addr, UdpSocket};
+use std::process;
 use std::str::FromStr;
 use std::sync::{Arc, Mutex};
 use syslog::Facility;
 
 mod comms;
 
-// Path to configuration file.
+// Path to configuration file, unless another is passed with '-c'.
 const CONFIG_PATH: &'static str = "comms.toml";
 // Read port for the socket used in the 'read' function.
 const READ_PORT: u16 = 13000;
@@ -61,7 +64,12 @@ fn main() -> EthernetServiceResult<()> {
     .unwrap();
 
     // Read configuration from config file.
-    let config = CommsConfig::new("ethernet-service", CONFIG_PATH.to_string());
+    let path = config_path_arg().unwrap_or_else(|| CONFIG_PATH.to_string());
+    let config = CommsConfig::new("ethernet-service", path);
+    if let Some(code) = check_config_status(&config) {
+        process::exit(code);
+    }
+    let config = config?;
 
     // Create socket to mock reading from a radio.
     let read_conn = Arc::new(UdpSocket::bind((config.satellite_ip.as_str(), READ_PORT))?);
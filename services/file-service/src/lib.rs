@@ -20,17 +20,50 @@ extern crate kubos_system;
 #[macro_use]
 extern crate log;
 extern crate failure;
+extern crate serde;
 extern crate serde_cbor;
+#[macro_use]
+extern crate serde_derive;
 extern crate syslog;
 
 use file_protocol::{FileProtocol, FileProtocolConfig, ProtocolError, State};
-use kubos_system::Config as ServiceConfig;
+use failure::Error;
+use kubos_system::{check_range, Config as ServiceConfig, ConfigWatcher, ServiceSettings};
 use std::collections::HashMap;
 use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::Duration;
 
+/// Settings for the file transfer service
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct FileServiceSettings {
+    /// Directory used for temporary storage of file chunks
+    pub storage_dir: Option<String>,
+    /// Seconds to wait for a new message once a transaction has started
+    pub timeout: Option<u64>,
+    /// Size, in bytes, of each transferred file chunk
+    pub chunk_size: Option<u32>,
+    /// Number of timeouts before a transaction is ended
+    pub hold_count: Option<u16>,
+}
+
+impl ServiceSettings for FileServiceSettings {
+    fn validate(&self) -> Result<(), Error> {
+        if let Some(timeout) = self.timeout {
+            check_range("timeout", timeout, 1, 86400)?;
+        }
+        if let Some(chunk_size) = self.chunk_size {
+            check_range("chunk_size", chunk_size, 1, 65000)?;
+        }
+        if let Some(hold_count) = self.hold_count {
+            check_range("hold_count", hold_count, 1, u16::max_value())?;
+        }
+        Ok(())
+    }
+}
+
 // We need this in this lib.rs file so we can build integration tests
 pub fn recv_loop(config: ServiceConfig) -> Result<(), failure::Error> {
     // Get and bind our UDP listening socket
@@ -40,35 +73,18 @@ pub fn recv_loop(config: ServiceConfig) -> Result<(), failure::Error> {
     let mut host_parts = host.split(':').map(|val| val.to_owned());
     let host_ip = host_parts.next().unwrap();
 
-    // Get the storage directory prefix that we'll be using for our
-    // temporary/intermediate storage location
-    let prefix = match config.get("storage_dir") {
-        Some(val) => val.as_str().and_then(|str| Some(str.to_owned())),
-        None => None,
-    };
-
-    // Get the chunk size to be used for transfers
-    let chunk_size = match config.get("chunk_size") {
-        Some(val) => val.as_integer().unwrap_or(4096),
-        None => 4096,
-    } as usize;
-
-    let hold_count = match config.get("hold_count") {
-        Some(val) => val.as_integer().unwrap_or(5),
-        None => 5,
-    } as u16;
-
-    let f_config = FileProtocolConfig::new(prefix, chunk_size, hold_count);
+    // The chunk size also determines the size of our receive buffer, so it can only
+    // be changed by restarting the service
+    let chunk_size = get_chunk_size(&config);
 
     let c_protocol = cbor_protocol::Protocol::new(host.clone(), chunk_size);
 
-    let timeout = config
-        .get("timeout")
-        .and_then(|val| {
-            val.as_integer()
-                .and_then(|num| Some(Duration::from_secs(num as u64)))
-        })
-        .unwrap_or(Duration::from_secs(2));
+    // Re-read the remaining settings whenever the config file is reloaded (via SIGHUP),
+    // so they can be tuned without restarting the service
+    let watcher = ConfigWatcher::new(config);
+    if let Err(err) = watcher.watch() {
+        warn!("Configuration reloading is unavailable: {}", err);
+    }
 
     // Setup map of channel IDs to thread channels
     let raw_threads: HashMap<u32, Sender<serde_cbor::Value>> = HashMap::new();
@@ -85,9 +101,7 @@ pub fn recv_loop(config: ServiceConfig) -> Result<(), failure::Error> {
             }
         };
 
-        let config_ref = f_config.clone();
         let host_ref = host_ip.clone();
-        let timeout_ref = timeout.clone();
 
         let channel_id = match file_protocol::parse_channel_id(&first_message) {
             Ok(channel_id) => channel_id,
@@ -104,6 +118,7 @@ pub fn recv_loop(config: ServiceConfig) -> Result<(), failure::Error> {
             // Break the processing work off into its own thread so we can
             // listen for requests from other clients
             let shared_threads = threads.clone();
+            let (config_ref, timeout_ref) = transfer_settings(&watcher.config(), chunk_size);
             thread::spawn(move || {
                 let state = State::Holding {
                     count: 0,
@@ -148,3 +163,39 @@ pub fn recv_loop(config: ServiceConfig) -> Result<(), failure::Error> {
         }
     }
 }
+
+fn get_chunk_size(config: &ServiceConfig) -> usize {
+    let chunk_size = match config.get("chunk_size") {
+        Some(val) => val.as_integer().unwrap_or(4096),
+        None => 4096,
+    };
+    chunk_size as usize
+}
+
+// Get the settings to be used for a new transfer from the current configuration
+fn transfer_settings(config: &ServiceConfig, chunk_size: usize) -> (FileProtocolConfig, Duration) {
+    // Get the storage directory prefix that we'll be using for our
+    // temporary/intermediate storage location
+    let prefix = match config.get("storage_dir") {
+        Some(val) => val.as_str().and_then(|str| Some(str.to_owned())),
+        None => None,
+    };
+
+    let hold_count = match config.get("hold_count") {
+        Some(val) => val.as_integer().unwrap_or(5),
+        None => 5,
+    } as u16;
+
+    let timeout = config
+        .get("timeout")
+        .and_then(|val| {
+            val.as_integer()
+                .and_then(|num| Some(Duration::from_secs(num as u64)))
+        })
+        .unwrap_or(Duration::from_secs(2));
+
+    (
+        FileProtocolConfig::new(prefix, chunk_size, hold_count),
+        timeout,
+    )
+}
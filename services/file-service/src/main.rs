@@ -25,7 +25,8 @@ extern crate log;
 extern crate syslog;
 
 use file_service::*;
-use kubos_system::Config as ServiceConfig;
+use kubos_system::{check_config_status, Config as ServiceConfig};
+use std::process;
 use syslog::Facility;
 
 fn main() {
@@ -35,7 +36,17 @@ fn main() {
         Some("kubos-file-service"),
     ).unwrap();
 
-    let config = ServiceConfig::new("file-transfer-service");
+    let loaded = ServiceConfig::load_settings::<FileServiceSettings>("file-transfer-service");
+    if let Some(code) = check_config_status(&loaded) {
+        process::exit(code);
+    }
+    let (config, _) = match loaded {
+        Ok(loaded) => loaded,
+        Err(err) => {
+            error!("Failed to load configuration: {}", err);
+            process::exit(1);
+        }
+    };
 
     info!("Starting file transfer service at {}", config.hosturl());
 
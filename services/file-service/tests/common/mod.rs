@@ -46,7 +46,7 @@ macro_rules! service_new {
                 "#,
                     $chunk_size, $port
                 ),
-            ))
+            ).unwrap())
             .unwrap();
         });
 
@@ -0,0 +1,65 @@
+//
+// Copyright (C) 2018 Kubos Corporation
+//
+// Licensed under the Apache License, Version 2.0 (the "License")
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+extern crate file_service;
+extern crate kubos_system;
+
+use file_service::FileServiceSettings;
+use kubos_system::Config as ServiceConfig;
+
+fn settings(section: &str) -> Result<FileServiceSettings, String> {
+    ServiceConfig::new_from_str("file-transfer-service", section)
+        .unwrap()
+        .settings::<FileServiceSettings>()
+        .map_err(|err| err.to_string())
+}
+
+#[test]
+fn settings_good() {
+    let result = settings(
+        r#"
+        [file-transfer-service]
+        storage_dir = "/home/system/file-storage"
+        timeout = 5
+        chunk_size = 4096
+        hold_count = 6
+        "#,
+    );
+
+    assert_eq!(
+        result,
+        Ok(FileServiceSettings {
+            storage_dir: Some("/home/system/file-storage".to_owned()),
+            timeout: Some(5),
+            chunk_size: Some(4096),
+            hold_count: Some(6),
+        })
+    );
+}
+
+#[test]
+fn settings_out_of_range() {
+    let result = settings("[file-transfer-service]\nchunk_size = 0\n");
+
+    assert_eq!(
+        result,
+        Err(
+            "Invalid configuration for file-transfer-service: \
+             chunk_size must be between 1 and 65000, found 0"
+                .to_owned()
+        )
+    );
+}
@@ -93,6 +93,8 @@
 #[macro_use]
 extern crate juniper;
 extern crate kubos_service;
+extern crate kubos_system;
+#[macro_use]
 extern crate log;
 extern crate syslog;
 
@@ -100,8 +102,10 @@ mod model;
 mod schema;
 
 use kubos_service::{Config, Service};
+use kubos_system::{check_config_status, NoSettings};
 use model::Supervisor;
 use schema::{MutationRoot, QueryRoot};
+use std::process;
 use syslog::Facility;
 
 fn main() {
@@ -110,9 +114,21 @@ fn main() {
         log::LevelFilter::Debug,
         Some("iobc-supervisor-service"),
     ).unwrap();
-    
+
+    let loaded = Config::load_settings::<NoSettings>("iobc-supervisor-service");
+    if let Some(code) = check_config_status(&loaded) {
+        process::exit(code);
+    }
+    let (config, _) = match loaded {
+        Ok(loaded) => loaded,
+        Err(err) => {
+            error!("Failed to load configuration: {}", err);
+            process::exit(1);
+        }
+    };
+
     Service::new(
-        Config::new("iobc-supervisor-service"),
+        config,
         Supervisor::new(),
         QueryRoot,
         MutationRoot,
@@ -91,6 +91,22 @@
 //! }
 //! ```
 //!
+//! ## Error Details
+//!
+//! Get the same errors as `errors`, along with each error's code, such as `BAD_ARGUMENT` or `HARDWARE_ERROR`,
+//! its subsystem and its causes. Querying either field clears the errors.
+//!
+//! ```json
+//! {
+//!     errorDetails: [{
+//!         code: String,
+//!         subsystem: String,
+//!         message: String,
+//!         causes: [String]
+//!     }]
+//! }
+//! ```
+//!
 //! ## Power Status
 //!
 //! Get the current power state and uptime of the system
@@ -224,6 +240,21 @@
 //! }
 //! ```
 //!
+//! ## Error Details
+//!
+//! Get the same errors as `errors`, along with each error's code, subsystem and causes
+//!
+//! ```json
+//! mutation {
+//!     errorDetails: [{
+//!         code: String,
+//!         subsystem: String,
+//!         message: String,
+//!         causes: [String]
+//!     }]
+//! }
+//! ```
+//!
 //! ## No-Op
 //!
 //! Execute a trivial command against the system
@@ -351,24 +382,32 @@
 #[cfg(test)]
 #[macro_use]
 extern crate double;
+#[macro_use]
 extern crate failure;
 extern crate isis_ants_api;
 #[macro_use]
 extern crate juniper;
 #[macro_use]
 extern crate kubos_service;
+extern crate kubos_system;
 #[macro_use]
 extern crate log;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
 #[cfg(test)]
 #[macro_use]
 extern crate serde_json;
 extern crate syslog;
 
+use failure::Error;
 use isis_ants_api::AntSResult;
 use kubos_service::{Config, Service};
+use kubos_system::{check_config_status, check_range, ServiceSettings};
 use model::Subsystem;
 pub use objects::*;
 use schema::{MutationRoot, QueryRoot};
+use std::process;
 use syslog::Facility;
 
 mod model;
@@ -377,51 +416,77 @@ mod schema;
 #[cfg(test)]
 mod tests;
 
+fn parse_hex_u8(key: &str, value: &str) -> Result<u8, Error> {
+    let digits = value.trim_start_matches("0x");
+    u8::from_str_radix(digits, 16)
+        .map_err(|_| format_err!("{} must be a one-byte hex value, found \"{}\"", key, value))
+}
+
+/// Settings for the ISIS antenna systems service
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct IsisAntsServiceSettings {
+    /// I2C bus the antenna system is connected to
+    pub bus: String,
+    /// I2C address of the primary microcontroller, in hex
+    pub primary: String,
+    /// I2C address of the secondary microcontroller, in hex
+    pub secondary: String,
+    /// Number of antennas present in the system
+    pub antennas: u8,
+    /// Watchdog timeout, in seconds
+    pub wd_timeout: u32,
+}
+
+impl IsisAntsServiceSettings {
+    /// Returns the I2C address of the primary microcontroller
+    pub fn primary_addr(&self) -> u8 {
+        parse_hex_u8("primary", &self.primary).unwrap_or_default()
+    }
+
+    /// Returns the I2C address of the secondary microcontroller
+    pub fn secondary_addr(&self) -> u8 {
+        parse_hex_u8("secondary", &self.secondary).unwrap_or_default()
+    }
+}
+
+impl ServiceSettings for IsisAntsServiceSettings {
+    fn validate(&self) -> Result<(), Error> {
+        parse_hex_u8("primary", &self.primary)?;
+        parse_hex_u8("secondary", &self.secondary)?;
+        check_range("antennas", self.antennas, 1, 4)?;
+        Ok(())
+    }
+}
+
 fn main() -> AntSResult<()> {
     syslog::init(
         Facility::LOG_DAEMON,
         log::LevelFilter::Debug,
         Some("isis-ants-service"),
     ).unwrap();
-    
-    let config = Config::new("isis-ants-service");
 
-    let bus = config
-        .get("bus")
-        .expect("No 'bus' value found in 'isis-ants-service' section of config");
-    let bus = bus.as_str().unwrap();
-
-    let primary = config
-        .get("primary")
-        .expect("No 'primary' value found in 'isis-ants-service' section of config");
-    let primary = primary.as_str().unwrap();
-    let primary: u8 = match primary.starts_with("0x") {
-        true => u8::from_str_radix(&primary[2..], 16).unwrap(),
-        false => u8::from_str_radix(primary, 16).unwrap(),
+    let loaded = Config::load_settings::<IsisAntsServiceSettings>("isis-ants-service");
+    if let Some(code) = check_config_status(&loaded) {
+        process::exit(code);
+    }
+    let (config, settings) = match loaded {
+        Ok(loaded) => loaded,
+        Err(err) => {
+            error!("Failed to load configuration: {}", err);
+            process::exit(1);
+        }
     };
 
-    let secondary = config
-        .get("secondary")
-        .expect("No 'secondary' value found in 'isis-ants-service' section of config");
-    let secondary = secondary.as_str().unwrap();
-    let secondary: u8 = match secondary.starts_with("0x") {
-        true => u8::from_str_radix(&secondary[2..], 16).unwrap(),
-        false => u8::from_str_radix(secondary, 16).unwrap(),
-    };
-
-    let antennas = config
-        .get("antennas")
-        .expect("No 'antennas' value found in 'isis-ants-service' section of config");
-    let antennas = antennas.as_integer().unwrap() as u8;
-
-    let wd_timeout = config
-        .get("wd_timeout")
-        .expect("No 'wd_timeout' value found in 'isis-ants-service' section of config");
-    let wd_timeout = wd_timeout.as_integer().unwrap() as u32;
-
     Service::new(
         config,
-        Subsystem::new(bus, primary, secondary, antennas, wd_timeout)?,
+        Subsystem::new(
+            &settings.bus,
+            settings.primary_addr(),
+            settings.secondary_addr(),
+            settings.antennas,
+            settings.wd_timeout,
+        )?,
         QueryRoot,
         MutationRoot,
     ).start();
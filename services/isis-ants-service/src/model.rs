@@ -16,16 +16,28 @@
 
 use failure::Error;
 use isis_ants_api::*;
+use kubos_service::{ErrorCode, ServiceError};
 use std::cell::{Cell, RefCell};
 use std::str;
 
 use objects::*;
 
+// Name recorded as the subsystem of each error generated by this service
+const SUBSYSTEM: &str = "isis-ants";
+
+// The C API only distinguishes out-of-bounds parameters from every other failure
+fn error_code(err: &Error) -> ErrorCode {
+    match err.downcast_ref::<AntsError>() {
+        Some(AntsError::ConfigError) => ErrorCode::BadArgument,
+        _ => ErrorCode::Hardware,
+    }
+}
+
 pub struct Subsystem {
     pub ants: Box<IAntS>,
     pub count: u8,
     pub controller: Cell<ConfigureController>,
-    pub errors: RefCell<Vec<String>>,
+    pub errors: RefCell<Vec<ServiceError>>,
     pub last_cmd: Cell<AckCommand>,
 }
 
@@ -50,6 +62,37 @@ impl Subsystem {
         })
     }
 
+    // Removes and returns all errors encountered since the last call
+    pub fn take_errors(&self) -> Vec<ServiceError> {
+        match self.errors.try_borrow_mut() {
+            Ok(mut master_vec) => {
+                let current = master_vec.clone();
+                master_vec.clear();
+                master_vec.shrink_to_fit();
+                current
+            }
+            _ => vec![
+                ServiceError::new(
+                    ErrorCode::Internal,
+                    "Error: Failed to borrow master errors vector",
+                ).subsystem(SUBSYSTEM),
+            ],
+        }
+    }
+
+    // Returns all errors encountered while processing the current request
+    pub fn request_errors(&self) -> Vec<ServiceError> {
+        match self.errors.try_borrow() {
+            Ok(master_vec) => master_vec.clone(),
+            _ => vec![
+                ServiceError::new(
+                    ErrorCode::Internal,
+                    "Error: Failed to borrow master errors vector",
+                ).subsystem(SUBSYSTEM),
+            ],
+        }
+    }
+
     // Queries
 
     pub fn get_config(&self) -> AntSResult<ConfigureController> {
@@ -57,7 +100,7 @@ impl Subsystem {
     }
 
     pub fn get_arm_status(&self) -> AntSResult<ArmStatus> {
-        let result = run!(self.ants.get_deploy(), self.errors);
+        let result = run!(self.ants.get_deploy(), self.errors, SUBSYSTEM, error_code);
         let armed = result.unwrap_or_default().sys_armed;
 
         Ok(match armed {
@@ -67,7 +110,7 @@ impl Subsystem {
     }
 
     pub fn get_deploy_status(&self) -> AntSResult<GetDeployResponse> {
-        let result = run!(self.ants.get_deploy(), self.errors);
+        let result = run!(self.ants.get_deploy(), self.errors, SUBSYSTEM, error_code);
 
         let mut status = DeploymentStatus::Error;
 
@@ -131,7 +174,7 @@ impl Subsystem {
     }
 
     pub fn get_power(&self) -> AntSResult<GetPowerResponse> {
-        let result = run!(self.ants.get_uptime(), self.errors);
+        let result = run!(self.ants.get_uptime(), self.errors, SUBSYSTEM, error_code);
         let uptime = result.unwrap_or_default();
 
         let state = match uptime {
@@ -146,32 +189,69 @@ impl Subsystem {
     }
 
     pub fn get_telemetry(&self) -> AntSResult<Telemetry> {
-        let nominal = run!(self.ants.get_system_telemetry(), self.errors).unwrap_or_default();
+        let nominal = run!(
+            self.ants.get_system_telemetry(),
+            self.errors,
+            SUBSYSTEM,
+            error_code
+        ).unwrap_or_default();
 
         let debug = TelemetryDebug {
             ant1: AntennaStats {
-                act_count: run!(self.ants.get_activation_count(KANTSAnt::Ant1), self.errors)
-                    .unwrap_or_default(),
-                act_time: run!(self.ants.get_activation_time(KANTSAnt::Ant1), self.errors)
-                    .unwrap_or_default(),
+                act_count: run!(
+                    self.ants.get_activation_count(KANTSAnt::Ant1),
+                    self.errors,
+                    SUBSYSTEM,
+                    error_code
+                ).unwrap_or_default(),
+                act_time: run!(
+                    self.ants.get_activation_time(KANTSAnt::Ant1),
+                    self.errors,
+                    SUBSYSTEM,
+                    error_code
+                ).unwrap_or_default(),
             },
             ant2: AntennaStats {
-                act_count: run!(self.ants.get_activation_count(KANTSAnt::Ant2), self.errors)
-                    .unwrap_or_default(),
-                act_time: run!(self.ants.get_activation_time(KANTSAnt::Ant2), self.errors)
-                    .unwrap_or_default(),
+                act_count: run!(
+                    self.ants.get_activation_count(KANTSAnt::Ant2),
+                    self.errors,
+                    SUBSYSTEM,
+                    error_code
+                ).unwrap_or_default(),
+                act_time: run!(
+                    self.ants.get_activation_time(KANTSAnt::Ant2),
+                    self.errors,
+                    SUBSYSTEM,
+                    error_code
+                ).unwrap_or_default(),
             },
             ant3: AntennaStats {
-                act_count: run!(self.ants.get_activation_count(KANTSAnt::Ant3), self.errors)
-                    .unwrap_or_default(),
-                act_time: run!(self.ants.get_activation_time(KANTSAnt::Ant3), self.errors)
-                    .unwrap_or_default(),
+                act_count: run!(
+                    self.ants.get_activation_count(KANTSAnt::Ant3),
+                    self.errors,
+                    SUBSYSTEM,
+                    error_code
+                ).unwrap_or_default(),
+                act_time: run!(
+                    self.ants.get_activation_time(KANTSAnt::Ant3),
+                    self.errors,
+                    SUBSYSTEM,
+                    error_code
+                ).unwrap_or_default(),
             },
             ant4: AntennaStats {
-                act_count: run!(self.ants.get_activation_count(KANTSAnt::Ant4), self.errors)
-                    .unwrap_or_default(),
-                act_time: run!(self.ants.get_activation_time(KANTSAnt::Ant4), self.errors)
-                    .unwrap_or_default(),
+                act_count: run!(
+                    self.ants.get_activation_count(KANTSAnt::Ant4),
+                    self.errors,
+                    SUBSYSTEM,
+                    error_code
+                ).unwrap_or_default(),
+                act_time: run!(
+                    self.ants.get_activation_time(KANTSAnt::Ant4),
+                    self.errors,
+                    SUBSYSTEM,
+                    error_code
+                ).unwrap_or_default(),
             },
         };
 
@@ -189,8 +269,8 @@ impl Subsystem {
 
     pub fn arm(&self, state: ArmState) -> AntSResult<ArmResponse> {
         let result = match state {
-            ArmState::Arm => run!(self.ants.arm(), self.errors),
-            ArmState::Disarm => run!(self.ants.disarm(), self.errors),
+            ArmState::Arm => run!(self.ants.arm(), self.errors, SUBSYSTEM, error_code),
+            ArmState::Disarm => run!(self.ants.disarm(), self.errors, SUBSYSTEM, error_code),
         };
 
         Ok(ArmResponse {
@@ -211,7 +291,12 @@ impl Subsystem {
             ConfigureController::Secondary => KANTSController::Secondary,
         };
 
-        let result = run!(self.ants.configure(conv), self.errors);
+        let result = run!(
+            self.ants.configure(conv),
+            self.errors,
+            SUBSYSTEM,
+            error_code
+        );
 
         if result.is_ok() {
             self.controller.set(controller);
@@ -230,7 +315,7 @@ impl Subsystem {
     pub fn control_power(&self, state: PowerState) -> AntSResult<ControlPowerResponse> {
         match state {
             PowerState::Reset => {
-                let result = run!(self.ants.reset(), self.errors);
+                let result = run!(self.ants.reset(), self.errors, SUBSYSTEM, error_code);
 
                 Ok(ControlPowerResponse {
                     power: state,
@@ -242,7 +327,12 @@ impl Subsystem {
                 })
             }
             _ => {
-                push_err!(self.errors, "controlPower: Invalid power state".to_owned());
+                push_err!(
+                    self.errors,
+                    ErrorCode::BadArgument,
+                    SUBSYSTEM,
+                    "controlPower: Invalid power state"
+                );
 
                 Ok(ControlPowerResponse {
                     power: state,
@@ -261,18 +351,43 @@ impl Subsystem {
         }
 
         let result = match ant {
-            DeployType::All => run!(self.ants.auto_deploy(conv), self.errors),
+            DeployType::All => run!(
+                self.ants.auto_deploy(conv),
+                self.errors,
+                SUBSYSTEM,
+                error_code
+            ),
             DeployType::Antenna1 => {
-                run!(self.ants.deploy(KANTSAnt::Ant1, force, conv), self.errors)
+                run!(
+                    self.ants.deploy(KANTSAnt::Ant1, force, conv),
+                    self.errors,
+                    SUBSYSTEM,
+                    error_code
+                )
             }
             DeployType::Antenna2 => {
-                run!(self.ants.deploy(KANTSAnt::Ant2, force, conv), self.errors)
+                run!(
+                    self.ants.deploy(KANTSAnt::Ant2, force, conv),
+                    self.errors,
+                    SUBSYSTEM,
+                    error_code
+                )
             }
             DeployType::Antenna3 => {
-                run!(self.ants.deploy(KANTSAnt::Ant3, force, conv), self.errors)
+                run!(
+                    self.ants.deploy(KANTSAnt::Ant3, force, conv),
+                    self.errors,
+                    SUBSYSTEM,
+                    error_code
+                )
             }
             DeployType::Antenna4 => {
-                run!(self.ants.deploy(KANTSAnt::Ant4, force, conv), self.errors)
+                run!(
+                    self.ants.deploy(KANTSAnt::Ant4, force, conv),
+                    self.errors,
+                    SUBSYSTEM,
+                    error_code
+                )
             }
         };
 
@@ -286,7 +401,12 @@ impl Subsystem {
     }
 
     pub fn integration_test(&self) -> AntSResult<IntegrationTestResults> {
-        let nom_result = run!(self.ants.get_system_telemetry(), self.errors);
+        let nom_result = run!(
+            self.ants.get_system_telemetry(),
+            self.errors,
+            SUBSYSTEM,
+            error_code
+        );
 
         let debug_errors: RefCell<Vec<String>> = RefCell::new(vec![]);
 
@@ -336,7 +456,12 @@ impl Subsystem {
             }
             let concat = debug_errors.join(", ");
             errors.push_str(&format!("Debug: {}", concat));
-            push_err!(self.errors, format!("get_test_results(debug): {}", concat));
+            push_err!(
+                self.errors,
+                ErrorCode::Hardware,
+                SUBSYSTEM,
+                format!("get_test_results(debug): {}", concat)
+            );
         }
 
         Ok(IntegrationTestResults {
@@ -348,7 +473,12 @@ impl Subsystem {
     }
 
     pub fn noop(&self) -> AntSResult<NoopResponse> {
-        let result = run!(self.ants.watchdog_kick(), self.errors);
+        let result = run!(
+            self.ants.watchdog_kick(),
+            self.errors,
+            SUBSYSTEM,
+            error_code
+        );
 
         Ok(NoopResponse {
             success: result.is_ok(),
@@ -101,8 +101,7 @@ impl IAntS for MockAntS {
 macro_rules! wrap {
     ($result:ident) => {{
         json!({
-                        "data": $result,
-                        "errors": ""
+                        "data": $result
                 }).to_string()
     }};
 }
@@ -110,7 +109,7 @@ macro_rules! wrap {
 macro_rules! service_new {
     ($mock:ident) => {{
         Service::new(
-            Config::new("isis-ants-service"),
+            Config::new_from_str("isis-ants-service", "[isis-ants-service]").unwrap(),
             Subsystem {
                 ants: Box::new($mock),
                 count: 4,
@@ -126,6 +125,7 @@ macro_rules! service_new {
 
 mod mutations;
 mod queries;
+mod settings;
 
 #[test]
 fn ping() {
@@ -155,7 +155,7 @@ fn deploy_status_deployed_2_antennas() {
     mock.get_deploy.return_value(Ok(deploy_status));
 
     let service = Service::new(
-        Config::new("isis-ants-service"),
+        Config::new_from_str("isis-ants-service", "[isis-ants-service]").unwrap(),
         Subsystem {
             ants: Box::new(mock),
             errors: RefCell::new(vec![]),
@@ -228,7 +228,7 @@ fn deploy_status_stowed_2_antennas() {
     mock.get_deploy.return_value(Ok(deploy_status));
 
     let service = Service::new(
-        Config::new("isis-ants-service"),
+        Config::new_from_str("isis-ants-service", "[isis-ants-service]").unwrap(),
         Subsystem {
             ants: Box::new(mock),
             errors: RefCell::new(vec![]),
@@ -52,7 +52,7 @@ fn query_errors_single() {
         }"#;
 
     let expected = json!({
-            "errors": ["watchdog_kick (services/isis-ants-service/src/model.rs:351): Configuration error"]
+            "errors": ["watchdog_kick (services/isis-ants-service/src/model.rs:475): Configuration error"]
     });
 
     assert_eq!(service.process(query.to_owned()), wrap!(expected));
@@ -78,7 +78,7 @@ fn query_errors_multiple() {
         }"#;
 
     let expected = json!({
-            "errors": ["watchdog_kick (services/isis-ants-service/src/model.rs:351): Configuration error", "watchdog_kick (services/isis-ants-service/src/model.rs:351): Configuration error"]
+            "errors": ["watchdog_kick (services/isis-ants-service/src/model.rs:475): Configuration error", "watchdog_kick (services/isis-ants-service/src/model.rs:475): Configuration error"]
     });
 
     assert_eq!(service.process(query.to_owned()), wrap!(expected));
@@ -110,3 +110,36 @@ fn query_errors_clear_after_query() {
 
     assert_eq!(service.process(query.to_owned()), wrap!(expected));
 }
+
+#[test]
+fn query_error_details() {
+    let mock = mock_new!();
+
+    let service = service_new!(mock);
+
+    let noop = r#"mutation {
+            noop {
+                success
+            }
+        }"#;
+
+    service.process(noop.to_owned());
+
+    let query = r#"{
+            errorDetails {
+                code,
+                subsystem,
+                message
+            }
+        }"#;
+
+    let expected = json!({
+            "errorDetails": [{
+                "code": "BAD_ARGUMENT",
+                "subsystem": "isis-ants",
+                "message": "watchdog_kick (services/isis-ants-service/src/model.rs:475): Configuration error"
+            }]
+    });
+
+    assert_eq!(service.process(query.to_owned()), wrap!(expected));
+}
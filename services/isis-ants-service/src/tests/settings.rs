@@ -0,0 +1,56 @@
+//
+// Copyright (C) 2018 Kubos Corporation
+//
+// Licensed under the Apache License, Version 2.0 (the "License")
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use failure::Error;
+use kubos_system::Config;
+use IsisAntsServiceSettings;
+
+fn settings(primary: &str) -> Result<IsisAntsServiceSettings, Error> {
+    Config::new_from_str(
+        "isis-ants-service",
+        &format!(
+            r#"
+            [isis-ants-service]
+            bus = "KI2C1"
+            primary = "{}"
+            secondary = "0x32"
+            antennas = 4
+            wd_timeout = 10
+            "#,
+            primary
+        ),
+    ).unwrap()
+    .settings::<IsisAntsServiceSettings>()
+}
+
+#[test]
+fn settings_addresses() {
+    let settings = settings("0x31").unwrap();
+
+    assert_eq!(settings.primary_addr(), 0x31);
+    assert_eq!(settings.secondary_addr(), 0x32);
+}
+
+#[test]
+fn settings_bad_address() {
+    let err = settings("0x310").unwrap_err();
+
+    assert_eq!(
+        err.to_string(),
+        "Invalid configuration for isis-ants-service: \
+         primary must be a one-byte hex value, found \"0x310\""
+    );
+}
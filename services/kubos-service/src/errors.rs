@@ -0,0 +1,317 @@
+//
+// Copyright (C) 2018 Kubos Corporation
+//
+// Licensed under the Apache License, Version 2.0 (the "License")
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Structured errors returned in the `errors` array of a service response
+
+use failure::Error;
+use juniper::{ExecutionError, FieldError, GraphQLError, Value};
+use serde_json;
+
+/// Machine-readable classification of a service error.
+///
+/// The code is reported in the `extensions.code` field of each error in a response,
+/// allowing clients to distinguish, for example, hardware timeouts from bad arguments
+/// without having to parse the error message.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ErrorCode {
+    /// The request contained an invalid argument value
+    BadArgument,
+    /// The underlying hardware did not respond in time
+    Timeout,
+    /// The underlying hardware reported an error or could not be communicated with
+    Hardware,
+    /// The requested item does not exist
+    NotFound,
+    /// An unexpected error occurred within the service
+    Internal,
+    /// The request could not be parsed as a GraphQL document
+    ParseFailed,
+    /// The request did not pass validation against the service's schema
+    ValidationFailed,
+}
+
+impl ErrorCode {
+    /// Returns the string form of the code, as reported in `extensions.code`
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            ErrorCode::BadArgument => "BAD_ARGUMENT",
+            ErrorCode::Timeout => "TIMEOUT",
+            ErrorCode::Hardware => "HARDWARE_ERROR",
+            ErrorCode::NotFound => "NOT_FOUND",
+            ErrorCode::Internal => "INTERNAL_ERROR",
+            ErrorCode::ParseFailed => "GRAPHQL_PARSE_FAILED",
+            ErrorCode::ValidationFailed => "GRAPHQL_VALIDATION_FAILED",
+        }
+    }
+}
+
+/// An error which can be returned from a GraphQL field and which will be reported
+/// with its code, originating subsystem and cause chain under `extensions`
+///
+/// # Examples
+///
+/// ```
+/// # extern crate juniper;
+/// # extern crate kubos_service;
+/// use juniper::FieldResult;
+/// use kubos_service::{ErrorCode, ServiceError};
+///
+/// fn deploy(armed: bool) -> FieldResult<bool> {
+///     if !armed {
+///         Err(ServiceError::new(ErrorCode::BadArgument, "Antenna system not armed")
+///             .subsystem("antenna"))?;
+///     }
+///     Ok(true)
+/// }
+/// # fn main() {
+/// #     assert!(deploy(false).is_err());
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct ServiceError {
+    code: ErrorCode,
+    subsystem: Option<String>,
+    message: String,
+    causes: Vec<String>,
+}
+
+impl ServiceError {
+    /// Creates a new error with the given code and message
+    pub fn new(code: ErrorCode, message: &str) -> Self {
+        ServiceError {
+            code,
+            subsystem: None,
+            message: message.to_owned(),
+            causes: vec![],
+        }
+    }
+
+    /// Creates a new error from a `failure` error chain.
+    ///
+    /// The top-level error becomes the message and every underlying cause
+    /// is recorded, in order, in the error's cause chain.
+    pub fn from_fail<E: Into<Error>>(code: ErrorCode, err: E) -> Self {
+        let err: Error = err.into();
+        let mut chain = err.iter_chain().map(|cause| format!("{}", cause));
+
+        ServiceError {
+            code,
+            subsystem: None,
+            message: chain.next().unwrap_or_default(),
+            causes: chain.collect(),
+        }
+    }
+
+    /// Creates a new error from a `failure` error chain, using `classify` to pick the code.
+    ///
+    /// This lets a service map, for example, a UART timeout to [`ErrorCode::Timeout`]
+    /// and every other driver error to [`ErrorCode::Hardware`].
+    ///
+    /// [`ErrorCode::Timeout`]: enum.ErrorCode.html#variant.Timeout
+    /// [`ErrorCode::Hardware`]: enum.ErrorCode.html#variant.Hardware
+    pub fn from_fail_with<E, F>(err: E, classify: F) -> Self
+    where
+        E: Into<Error>,
+        F: FnOnce(&Error) -> ErrorCode,
+    {
+        let err: Error = err.into();
+        let code = classify(&err);
+        ServiceError::from_fail(code, err)
+    }
+
+    /// Records the subsystem which generated this error
+    pub fn subsystem(mut self, subsystem: &str) -> Self {
+        self.subsystem = Some(subsystem.to_owned());
+        self
+    }
+
+    /// Prefixes the error's message, for example with the name of the failing call
+    pub fn prefix(mut self, prefix: &str) -> Self {
+        self.message = format!("{}: {}", prefix, self.message);
+        self
+    }
+
+    /// Returns the error's code
+    pub fn code(&self) -> ErrorCode {
+        self.code
+    }
+
+    /// Returns the subsystem which generated this error, if one was recorded
+    pub fn get_subsystem(&self) -> Option<&str> {
+        self.subsystem.as_ref().map(|subsystem| subsystem.as_str())
+    }
+
+    /// Returns the error's top-level message
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Returns the chain of underlying causes for this error
+    pub fn causes(&self) -> &[String] {
+        &self.causes
+    }
+
+    /// Returns the message followed by its causes, separated by commas,
+    /// matching the strings built by [`process_errors!`](macro.process_errors.html)
+    pub fn description(&self) -> String {
+        let mut description = self.message.clone();
+        for cause in &self.causes {
+            description.push_str(&format!(", {}", cause));
+        }
+        description
+    }
+}
+
+graphql_object!(ServiceError: () as "ServiceError" |&self| {
+    description: "An error encountered by a service, with its code and cause chain"
+
+    field code() -> &str as "Machine-readable error code, such as TIMEOUT" {
+        self.code.as_str()
+    }
+
+    field subsystem() -> Option<&str> as "Subsystem which generated the error" {
+        self.get_subsystem()
+    }
+
+    field message() -> &str as "Top-level error message" {
+        &self.message
+    }
+
+    field causes() -> &Vec<String> as "Underlying causes of the error, in order" {
+        &self.causes
+    }
+});
+
+impl From<ServiceError> for FieldError {
+    fn from(err: ServiceError) -> FieldError {
+        let mut extensions = vec![("code", Value::string(err.code.as_str()))];
+        if let Some(subsystem) = err.subsystem {
+            extensions.push(("subsystem", Value::string(subsystem)));
+        }
+        if !err.causes.is_empty() {
+            extensions.push((
+                "causes",
+                Value::list(err.causes.into_iter().map(Value::string).collect()),
+            ));
+        }
+
+        FieldError::new(err.message, Value::object(extensions.into_iter().collect()))
+    }
+}
+
+/// Converts the errors generated while executing a query into spec-compliant
+/// GraphQL error objects.
+///
+/// Any object data attached to a field error is reported as that error's `extensions`.
+/// Errors which don't specify a code are given the `INTERNAL_ERROR` code.
+pub fn execution_errors(errs: &[ExecutionError]) -> Vec<serde_json::Value> {
+    errs.iter()
+        .map(|err| {
+            let mut extensions = match serde_json::to_value(err.error().data()) {
+                Ok(data @ serde_json::Value::Object(_)) => data,
+                _ => json!({}),
+            };
+            if extensions.get("code").is_none() {
+                extensions["code"] = json!(ErrorCode::Internal.as_str());
+            }
+
+            json!({
+                "message": err.error().message(),
+                "locations": [err.location()],
+                "path": err.path(),
+                "extensions": extensions,
+            })
+        })
+        .collect()
+}
+
+/// Converts an error which prevented a query from being executed into
+/// spec-compliant GraphQL error objects
+pub fn request_errors(err: &GraphQLError) -> Vec<serde_json::Value> {
+    let code = match *err {
+        GraphQLError::ParseError(_) => ErrorCode::ParseFailed,
+        _ => ErrorCode::ValidationFailed,
+    };
+
+    let errors = match serde_json::to_value(err) {
+        Ok(serde_json::Value::Array(errors)) => errors,
+        Ok(serde_json::Value::String(message)) => vec![json!({ "message": message })],
+        _ => vec![json!({ "message": "Failed to process request" })],
+    };
+
+    errors
+        .into_iter()
+        .map(|mut error| {
+            error["extensions"] = json!({ "code": code.as_str() });
+            error
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Fail)]
+    #[fail(display = "UART timed out")]
+    struct RootError;
+
+    #[derive(Debug, Fail)]
+    #[fail(display = "Failed to read telemetry")]
+    struct TopError {
+        #[fail(cause)]
+        cause: RootError,
+    }
+
+    #[test]
+    fn from_fail_chain() {
+        let err = ServiceError::from_fail(ErrorCode::Timeout, TopError { cause: RootError });
+
+        assert_eq!(err.message(), "Failed to read telemetry");
+        assert_eq!(err.causes(), &["UART timed out".to_owned()]);
+        assert_eq!(err.code(), ErrorCode::Timeout);
+    }
+
+    #[test]
+    fn from_fail_with_classify() {
+        let err = ServiceError::from_fail_with(TopError { cause: RootError }, |err| {
+            match err.iter_chain().any(|cause| cause.downcast_ref::<RootError>().is_some()) {
+                true => ErrorCode::Timeout,
+                false => ErrorCode::Hardware,
+            }
+        });
+
+        assert_eq!(err.code(), ErrorCode::Timeout);
+        assert_eq!(err.description(), "Failed to read telemetry, UART timed out");
+    }
+
+    #[test]
+    fn field_error_extensions() {
+        let err: FieldError = ServiceError::from_fail(ErrorCode::Timeout, TopError { cause: RootError })
+            .subsystem("gps")
+            .into();
+
+        assert_eq!(err.message(), "Failed to read telemetry");
+        assert_eq!(
+            serde_json::to_value(err.data()).unwrap(),
+            json!({
+                "code": "TIMEOUT",
+                "subsystem": "gps",
+                "causes": ["UART timed out"]
+            })
+        );
+    }
+}
@@ -0,0 +1,271 @@
+//
+// Copyright (C) 2018 Kubos Corporation
+//
+// Licensed under the Apache License, Version 2.0 (the "License")
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Common `serviceInfo` query which is automatically added to the query root of every service
+
+use juniper::meta::MetaType;
+use juniper::{Arguments, ExecutionResult, Executor, GraphQLType, Registry};
+use kubos_system;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use service::Context;
+
+/// Name of the query field injected into every service's query root
+pub const SERVICE_INFO_FIELD: &str = "serviceInfo";
+/// Number of request latencies retained for the `serviceInfo` query
+pub const LATENCY_HISTORY: usize = 10;
+
+/// Runtime statistics about a service instance
+#[derive(Clone, Debug)]
+pub struct ServiceStats {
+    name: String,
+    addr: String,
+    start: Instant,
+    requests: i32,
+    errors: i32,
+    latencies: VecDeque<Duration>,
+}
+
+impl ServiceStats {
+    pub fn new(name: &str, addr: &str) -> Self {
+        ServiceStats {
+            name: name.to_owned(),
+            addr: addr.to_owned(),
+            start: Instant::now(),
+            requests: 0,
+            errors: 0,
+            latencies: VecDeque::with_capacity(LATENCY_HISTORY),
+        }
+    }
+
+    /// Update the address the service is listening on
+    pub fn set_addr(&mut self, addr: &str) {
+        self.addr = addr.to_owned();
+    }
+
+    /// Record the outcome of a single processed request
+    pub fn record(&mut self, elapsed: Duration, failed: bool) {
+        self.requests = self.requests.saturating_add(1);
+        if failed {
+            self.errors = self.errors.saturating_add(1);
+        }
+
+        if self.latencies.len() == LATENCY_HISTORY {
+            self.latencies.pop_front();
+        }
+        self.latencies.push_back(elapsed);
+    }
+
+    fn snapshot(&self) -> ServiceInfo {
+        ServiceInfo {
+            name: self.name.clone(),
+            version: kubos_system::kubos_versions()
+                .curr
+                .unwrap_or_else(|| "unknown".to_owned()),
+            uptime: as_secs(self.start.elapsed()),
+            requests: self.requests,
+            errors: self.errors,
+            latencies: self
+                .latencies
+                .iter()
+                .map(|latency| as_secs(*latency) * 1000.0)
+                .collect(),
+            addr: self.addr.clone(),
+        }
+    }
+}
+
+fn as_secs(duration: Duration) -> f64 {
+    duration.as_secs() as f64 + f64::from(duration.subsec_nanos()) / 1_000_000_000.0
+}
+
+/// Snapshot of a service's statistics, as returned by the `serviceInfo` query
+pub struct ServiceInfo {
+    name: String,
+    version: String,
+    uptime: f64,
+    requests: i32,
+    errors: i32,
+    latencies: Vec<f64>,
+    addr: String,
+}
+
+graphql_object!(ServiceInfo: () as "ServiceInfo" |&self| {
+    description: "Common health and usage information reported by every service"
+
+    field name() -> &str as "Name of the service" {
+        &self.name
+    }
+
+    field version() -> &str as "Version of KubOS the service is running under" {
+        &self.version
+    }
+
+    field uptime() -> f64 as "Seconds since the service was started" {
+        self.uptime
+    }
+
+    field requests() -> i32 as "Number of requests processed since startup" {
+        self.requests
+    }
+
+    field errors() -> i32 as "Number of requests which generated errors since startup" {
+        self.errors
+    }
+
+    field latencies() -> &Vec<f64> as "Processing time, in milliseconds, of the most recent requests" {
+        &self.latencies
+    }
+
+    field addr() -> &str as "Address the service is listening on" {
+        &self.addr
+    }
+});
+
+/// Query root wrapper which adds the `serviceInfo` field to a service's own query root
+pub struct ServiceQuery<Query>(pub Query);
+
+impl<Query, S> GraphQLType for ServiceQuery<Query>
+where
+    Query: GraphQLType<Context = Context<S>, TypeInfo = ()>,
+{
+    type Context = Context<S>;
+    type TypeInfo = ();
+
+    fn name(info: &()) -> Option<&str> {
+        Query::name(info)
+    }
+
+    fn meta<'r>(info: &(), registry: &mut Registry<'r>) -> MetaType<'r> {
+        match Query::meta(info, registry) {
+            MetaType::Object(mut meta) => {
+                if !meta.fields.iter().any(|f| f.name == SERVICE_INFO_FIELD) {
+                    let field = registry
+                        .field::<ServiceInfo>(SERVICE_INFO_FIELD, &())
+                        .description("Common health and usage information about this service");
+                    meta.fields.push(field);
+                }
+                MetaType::Object(meta)
+            }
+            other => other,
+        }
+    }
+
+    fn resolve_field(
+        &self,
+        info: &(),
+        field_name: &str,
+        arguments: &Arguments,
+        executor: &Executor<Context<S>>,
+    ) -> ExecutionResult {
+        if field_name == SERVICE_INFO_FIELD {
+            let info = executor.context().stats().borrow().snapshot();
+            executor.resolve_with_ctx(&(), &info)
+        } else {
+            self.0.resolve_field(info, field_name, arguments, executor)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use juniper::FieldResult;
+    use kubos_system::Config;
+    use service::Service;
+    use serde_json::{self, Value};
+
+    struct QueryRoot;
+
+    graphql_object!(QueryRoot: Context<()> as "Query" |&self| {
+        field ping() -> FieldResult<String> {
+            Ok(String::from("pong"))
+        }
+    });
+
+    struct MutationRoot;
+
+    graphql_object!(MutationRoot: Context<()> as "Mutation" |&self| {});
+
+    fn service<'a>() -> Service<'a, QueryRoot, MutationRoot, ()> {
+        let config = Config::new_from_str(
+            "test-service",
+            r#"
+            [test-service.addr]
+            ip = "127.0.0.1"
+            port = 9999
+            "#,
+        ).unwrap();
+        Service::new(config, (), QueryRoot, MutationRoot)
+    }
+
+    #[test]
+    fn service_info_injected() {
+        let service = service();
+
+        let res: Value =
+            serde_json::from_str(&service.process("{ serviceInfo { name addr } }".to_owned()))
+                .unwrap();
+
+        assert_eq!(res["data"]["serviceInfo"]["name"], "test-service");
+        assert_eq!(res["data"]["serviceInfo"]["addr"], "127.0.0.1:9999");
+    }
+
+    #[test]
+    fn service_info_original_fields() {
+        let service = service();
+
+        let res: Value =
+            serde_json::from_str(&service.process("{ ping }".to_owned())).unwrap();
+
+        assert_eq!(res["data"]["ping"], "pong");
+    }
+
+    #[test]
+    fn service_info_counts() {
+        let service = service();
+
+        service.process("{ ping }".to_owned());
+        service.process("{ notAField }".to_owned());
+
+        let res: Value = serde_json::from_str(&service.process(
+            "{ serviceInfo { requests errors latencies } }".to_owned(),
+        )).unwrap();
+
+        assert_eq!(res["data"]["serviceInfo"]["requests"], 2);
+        assert_eq!(res["data"]["serviceInfo"]["errors"], 1);
+        assert_eq!(
+            res["data"]["serviceInfo"]["latencies"]
+                .as_array()
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+
+    #[test]
+    fn service_stats_latency_history() {
+        let mut stats = ServiceStats::new("test", "0.0.0.0:0");
+
+        for _ in 0..(LATENCY_HISTORY + 5) {
+            stats.record(Duration::from_millis(1), false);
+        }
+
+        assert_eq!(stats.latencies.len(), LATENCY_HISTORY);
+        assert_eq!(stats.requests, (LATENCY_HISTORY + 5) as i32);
+    }
+}
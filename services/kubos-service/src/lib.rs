@@ -69,7 +69,7 @@
 //! use schema::{MutationRoot, QueryRoot};
 //!
 //! Service::new(
-//!     Config::new("service-name"),
+//!     Config::load("service-name")?,
 //!     Subsystem::new(),
 //!     QueryRoot,
 //!     MutationRoot,
@@ -83,7 +83,7 @@
 //! use model::Subsystem;
 //! use schema::{MutationRoot, QueryRoot};
 //!
-//! let config = Config::new("example-service");
+//! let config = Config::load("example-service")?;
 //! let subsystem = Subsystem { bus = config["bus"] ) };
 //! Service::new(
 //!     config,
@@ -104,10 +104,47 @@
 //! ```bash
 //! $ ./example-service -c config.toml
 //! ```
+//!
+//! ## Reloading Configuration
+//!
+//! After editing the configuration file, send the service a `SIGHUP` signal or issue the
+//! `reloadConfig` mutation, which every service provides. An invalid file is rejected and
+//! the current configuration is kept. Resolvers read the current configuration with
+//! `Context::config`, and `Service::config_watcher` notifies subscribers of changes.
+//! Changes to the `addr` section only take effect after a restart.
+//!
+//! ## Errors
+//!
+//! Responses follow the GraphQL specification. The `extensions` of each error contain a
+//! machine-readable `code` (see [`ErrorCode`]) and, for a [`ServiceError`], the
+//! originating `subsystem` and the chain of underlying `causes`.
+//!
+//! [`ErrorCode`]: enum.ErrorCode.html
+//! [`ServiceError`]: struct.ServiceError.html
+//!
+//! ## Service Information
+//!
+//! Every service answers a `serviceInfo` query with its `name`, `version`, `uptime`,
+//! `requests` and `errors` counts, recent request `latencies` and listening `addr`.
+//!
+//! ## Service Discovery
+//!
+//! Services announce themselves with `kubos_system::Discovery` when started and every
+//! `kubos_system::HEARTBEAT_INTERVAL` afterwards, so clients can find them with
+//! `Config::discover("service-name")`.
+//!
+//! ## Storage
+//!
+//! Resolvers can keep simple state with `Context::get` and `Context::set`. If the
+//! service's config contains a `[service-name.storage]` section with a `dir` (and an
+//! optional `max_size`), values are saved to `<dir>/<service-name>.json` and restored
+//! when the service restarts. `Service::try_new`, `Context::try_set` and
+//! `Context::try_clear` return the storage errors which `Service::new`, `Context::set`
+//! and `Context::clear` only log.
 
-#[cfg(test)]
 #[macro_use]
 extern crate failure;
+#[macro_use]
 extern crate juniper;
 #[macro_use]
 extern crate log;
@@ -116,9 +153,16 @@ extern crate serde;
 extern crate serde_json;
 
 extern crate kubos_system;
+#[cfg(test)]
+extern crate tempfile;
 
+mod errors;
+mod info;
 mod macros;
+mod reload;
 mod service;
+mod storage;
 
-pub use kubos_system::Config;
+pub use errors::{ErrorCode, ServiceError};
+pub use kubos_system::{Config, ConfigWatcher};
 pub use service::{Context, Service};
@@ -18,6 +18,12 @@
 /// Iterate through a failure::Error and concatenate the error
 /// and all its causes into a single string
 ///
+/// This and the other macros in this module build the flattened strings stored in a service's
+/// own `errors` list. Errors returned from a GraphQL field should instead be converted with
+/// [`ServiceError::from_fail`], which keeps the cause chain and reports it with an error code.
+///
+/// [`ServiceError::from_fail`]: struct.ServiceError.html#method.from_fail
+///
 /// # Examples
 ///
 /// ```
@@ -86,12 +92,18 @@ macro_rules! process_errors {
     }};
 }
 
-/// Convenience macro to push an error string onto the master errors vector
+/// Convenience macro to push an error onto the master errors vector
+///
+/// Given an error code and subsystem name, the message is stored as a [`ServiceError`],
+/// so that the code can later be reported to clients.
+///
+/// [`ServiceError`]: struct.ServiceError.html
 ///
 /// # Examples
 ///
 /// ```
 /// # #[macro_use] extern crate kubos_service;
+/// use kubos_service::ErrorCode;
 /// use std::cell::RefCell;
 /// # fn main() {
 /// let master_err = RefCell::new(vec![]);
@@ -103,6 +115,12 @@ macro_rules! process_errors {
 ///     vec!["Message1".to_owned(), "Message2".to_owned()],
 ///     master_err.borrow().clone()
 /// );
+///
+/// let master_err = RefCell::new(vec![]);
+///
+/// push_err!(master_err, ErrorCode::Timeout, "gps", "No response");
+///
+/// assert_eq!(master_err.borrow()[0].code(), ErrorCode::Timeout);
 /// # }
 /// ```
 #[macro_export]
@@ -112,12 +130,23 @@ macro_rules! push_err {
             master_vec.push($err);
         }
     }};
+    ($master:expr, $code:expr, $subsystem:expr, $msg:expr) => {{
+        push_err!(
+            $master,
+            $crate::ServiceError::new($code, &$msg).subsystem($subsystem)
+        )
+    }};
 }
 
 /// Execute a function and return `Result<func_data, String>`
 /// Optionally:
 ///   Add the error string to the master error string for later consumption,
 ///   prefixed with the name of the function being called
+///   Or, given a subsystem name and a function which picks an [`ErrorCode`] for the error,
+///   add the error to the master vector as a [`ServiceError`]
+///
+/// [`ErrorCode`]: enum.ErrorCode.html
+/// [`ServiceError`]: struct.ServiceError.html
 ///
 /// # Examples
 ///
@@ -205,12 +234,28 @@ macro_rules! run {
             result
         }
     }};
+    ($func:expr, $master:expr, $subsystem:expr, $classify:expr) => {{
+        $func.map_err(|err| {
+            let error = $crate::ServiceError::from_fail_with(err, $classify).subsystem($subsystem);
+            let message = error.description();
+
+            let mut name = stringify!($func).split('(').next().unwrap();
+            name = name.split(&[':', '.'][..]).last().unwrap();
+            push_err!(
+                $master,
+                error.prefix(&format!("{} ({}:{})", name, file!(), line!()))
+            );
+
+            message
+        })
+    }};
 }
 
 #[cfg(test)]
 mod tests {
     use failure::Error;
     use std::cell::RefCell;
+    use {ErrorCode, ServiceError};
 
     #[derive(Debug, Fail)]
     pub enum RootError {
@@ -292,6 +337,18 @@ mod tests {
         );
     }
 
+    #[test]
+    fn push_err_code() {
+        let master_err = RefCell::new(vec![]);
+
+        push_err!(master_err, ErrorCode::Hardware, "imu", "Message");
+
+        assert_eq!(
+            vec![ServiceError::new(ErrorCode::Hardware, "Message").subsystem("imu")],
+            master_err.borrow().clone()
+        );
+    }
+
     #[test]
     fn run_default() {
         let result = run!(test_func(true, "test".to_owned()));
@@ -306,7 +363,7 @@ mod tests {
 
         assert_eq!(result, Err("TopError: top, RootError: root".to_owned()));
         assert_eq!(
-            vec!["test_func (services/kubos-service/src/macros.rs:305): TopError: top, RootError: root".to_owned()],
+            vec!["test_func (services/kubos-service/src/macros.rs:362): TopError: top, RootError: root".to_owned()],
             master_err.borrow().clone()
         );
     }
@@ -321,4 +378,27 @@ mod tests {
         assert_eq!(test_vec, master_err.borrow().clone());
     }
 
+    #[test]
+    fn run_push_code() {
+        let master_err = RefCell::new(vec![]);
+        let result = run!(
+            test_func(true, "test".to_owned()),
+            master_err,
+            "imu",
+            |_: &Error| ErrorCode::Timeout
+        );
+
+        assert_eq!(result, Err("TopError: top, RootError: root".to_owned()));
+
+        let errors = master_err.borrow();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].code(), ErrorCode::Timeout);
+        assert_eq!(errors[0].get_subsystem(), Some("imu"));
+        assert_eq!(
+            errors[0].message(),
+            "test_func (services/kubos-service/src/macros.rs:384): TopError: top"
+        );
+        assert_eq!(errors[0].causes(), &["RootError: root".to_owned()]);
+    }
+
 }
@@ -0,0 +1,162 @@
+//
+// Copyright (C) 2018 Kubos Corporation
+//
+// Licensed under the Apache License, Version 2.0 (the "License")
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Common `reloadConfig` mutation which is automatically added to the mutation root of every service
+
+use juniper::meta::MetaType;
+use juniper::{Arguments, ExecutionResult, Executor, GraphQLType, Registry};
+
+use service::Context;
+
+/// Name of the mutation field injected into every service's mutation root
+pub const RELOAD_CONFIG_FIELD: &str = "reloadConfig";
+
+/// Response fields for the `reloadConfig` mutation
+#[derive(GraphQLObject)]
+pub struct ReloadConfigResponse {
+    /// Any errors encountered while reloading the configuration
+    pub errors: String,
+    /// Request completion success or failure
+    pub success: bool,
+}
+
+/// Mutation root wrapper which adds the `reloadConfig` field to a service's own mutation root
+pub struct ServiceMutation<Mutation>(pub Mutation);
+
+impl<Mutation, S> GraphQLType for ServiceMutation<Mutation>
+where
+    Mutation: GraphQLType<Context = Context<S>, TypeInfo = ()>,
+{
+    type Context = Context<S>;
+    type TypeInfo = ();
+
+    fn name(info: &()) -> Option<&str> {
+        Mutation::name(info)
+    }
+
+    fn meta<'r>(info: &(), registry: &mut Registry<'r>) -> MetaType<'r> {
+        match Mutation::meta(info, registry) {
+            MetaType::Object(mut meta) => {
+                if !meta.fields.iter().any(|f| f.name == RELOAD_CONFIG_FIELD) {
+                    let field = registry
+                        .field::<ReloadConfigResponse>(RELOAD_CONFIG_FIELD, &())
+                        .description("Re-read this service's configuration file");
+                    meta.fields.push(field);
+                }
+                MetaType::Object(meta)
+            }
+            other => other,
+        }
+    }
+
+    fn resolve_field(
+        &self,
+        info: &(),
+        field_name: &str,
+        arguments: &Arguments,
+        executor: &Executor<Context<S>>,
+    ) -> ExecutionResult {
+        if field_name == RELOAD_CONFIG_FIELD {
+            let response = match executor.context().config_watcher().reload() {
+                Ok(_) => ReloadConfigResponse {
+                    errors: "".to_owned(),
+                    success: true,
+                },
+                Err(err) => ReloadConfigResponse {
+                    errors: err.to_string(),
+                    success: false,
+                },
+            };
+            executor.resolve_with_ctx(&(), &response)
+        } else {
+            self.0.resolve_field(info, field_name, arguments, executor)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use juniper::FieldResult;
+    use kubos_system::Config;
+    use serde_json::{self, Value};
+    use service::{Context, Service};
+    use std::fs;
+    use std::sync::{Arc, Mutex};
+    use tempfile::TempDir;
+
+    struct QueryRoot;
+
+    graphql_object!(QueryRoot: Context<()> as "Query" |&self| {
+        field timeout(&executor) -> FieldResult<i32> {
+            let timeout = executor.context().config().get("timeout")
+                .and_then(|val| val.as_integer())
+                .unwrap_or(0);
+            Ok(timeout as i32)
+        }
+    });
+
+    struct MutationRoot;
+
+    graphql_object!(MutationRoot: Context<()> as "Mutation" |&self| {});
+
+    fn write_config(path: &str, timeout: &str) {
+        fs::write(path, format!("[test-service]\ntimeout = {}\n", timeout)).unwrap();
+    }
+
+    #[test]
+    fn reload_config() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("config.toml").to_string_lossy().to_string();
+        write_config(&path, "1");
+
+        let service = Service::new(
+            Config::new_from_path("test-service", path.clone()).unwrap(),
+            (),
+            QueryRoot,
+            MutationRoot,
+        );
+
+        let notified = Arc::new(Mutex::new(false));
+        let notified_ref = notified.clone();
+        service
+            .config_watcher()
+            .subscribe(move |_| *notified_ref.lock().unwrap() = true);
+
+        write_config(&path, "7");
+
+        let res: Value = serde_json::from_str(&service.process(
+            "mutation { reloadConfig { success errors } }".to_owned(),
+        )).unwrap();
+        assert_eq!(res["data"]["reloadConfig"]["success"], true);
+        assert_eq!(*notified.lock().unwrap(), true);
+
+        let res: Value =
+            serde_json::from_str(&service.process("{ timeout }".to_owned())).unwrap();
+        assert_eq!(res["data"]["timeout"], 7);
+
+        // A bad file must be rejected and the previous config kept
+        write_config(&path, "\"seven");
+
+        let res: Value = serde_json::from_str(&service.process(
+            "mutation { reloadConfig { success errors } }".to_owned(),
+        )).unwrap();
+        assert_eq!(res["data"]["reloadConfig"]["success"], false);
+
+        let res: Value =
+            serde_json::from_str(&service.process("{ timeout }".to_owned())).unwrap();
+        assert_eq!(res["data"]["timeout"], 7);
+    }
+}
@@ -14,18 +14,48 @@
 // limitations under the License.
 //
 
+use errors::{execution_errors, request_errors};
+use info::{ServiceQuery, ServiceStats};
 use juniper::{execute, Context as JuniperContext, GraphQLType, RootNode, Variables};
-use kubos_system::Config;
-use serde_json;
+use kubos_system::{self, Config, ConfigWatcher, Discovery, ServiceRecord, HEARTBEAT_INTERVAL};
+use failure::Error;
+use reload::ServiceMutation;
+use serde_json::{self, Value};
 use std::cell::RefCell;
-use std::collections::HashMap;
 use std::net::{SocketAddr, UdpSocket};
+use std::str::FromStr;
+use std::thread;
+use std::time::Instant;
+use storage::Storage;
+
+// Largest possible UDP payload, so that requests such as bulk inserts aren't truncated
+const MAX_REQUEST_SIZE: usize = 65_507;
+
+// Describes every type, field and argument of a schema, used to hash it
+const SCHEMA_QUERY: &str = "{ __schema { queryType { name } mutationType { name } types { \
+    kind name \
+    fields { name args { name type { ...TypeRef } } type { ...TypeRef } } \
+    inputFields { name type { ...TypeRef } } \
+    enumValues { name } } } } \
+    fragment TypeRef on __Type { kind name ofType { kind name ofType { kind name ofType { kind name } } } }";
 
 /// Context struct used by a service to provide Juniper context,
 /// subsystem access and persistent storage.
+///
+/// By default, stored values only last until the service is restarted. If the service's
+/// config section contains a `storage` section, values are instead saved to
+/// `<dir>/<service-name>.json` and restored when the service starts:
+///
+/// ```toml
+/// [my-service.storage]
+/// dir = "/home/system/kubos/storage"
+/// max_size = 65536  # Optional. Total size of all keys and values, in bytes
+/// ```
 pub struct Context<T> {
     subsystem: T,
-    storage: RefCell<HashMap<String, String>>,
+    storage: RefCell<Storage>,
+    stats: RefCell<ServiceStats>,
+    config: ConfigWatcher,
 }
 
 impl<T> JuniperContext for Context<T> {}
@@ -36,43 +66,123 @@ impl<T> Context<T> {
         &self.subsystem
     }
 
+    /// Returns the service's current configuration, including any changes
+    /// applied since the service was started
+    pub fn config(&self) -> Config {
+        self.config.config()
+    }
+
+    pub(crate) fn config_watcher(&self) -> &ConfigWatcher {
+        &self.config
+    }
+
+    pub(crate) fn stats(&self) -> &RefCell<ServiceStats> {
+        &self.stats
+    }
+
     /// Attempts to get a value from the context's storage
     ///
+    /// Returns an empty string if the key is not present.
+    ///
     /// # Arguments
     ///
     /// `name` - Key to search for in storage
     pub fn get(&self, name: &str) -> String {
-        let stor = self.storage.borrow();
-        match stor.get(&name.to_string()) {
-            Some(s) => s.clone(),
-            None => "".to_string(),
-        }
+        self.storage
+            .borrow()
+            .get(name)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Attempts to get a value from the context's storage and parse it as the requested type
+    ///
+    /// Returns `None` if the key is not present or its value can't be parsed.
+    ///
+    /// # Arguments
+    ///
+    /// `name` - Key to search for in storage
+    pub fn get_as<V: FromStr>(&self, name: &str) -> Option<V> {
+        self.storage
+            .borrow()
+            .get(name)
+            .and_then(|value| value.parse().ok())
+    }
+
+    /// Attempts to get an integer value from the context's storage
+    pub fn get_int(&self, name: &str) -> Option<i64> {
+        self.get_as(name)
+    }
+
+    /// Attempts to get a floating point value from the context's storage
+    pub fn get_float(&self, name: &str) -> Option<f64> {
+        self.get_as(name)
+    }
+
+    /// Attempts to get a boolean value from the context's storage
+    pub fn get_bool(&self, name: &str) -> Option<bool> {
+        self.get_as(name)
     }
 
     /// Sets a value in the context's storage
     ///
+    /// If the value would exceed the storage's size limit, or could not be persisted,
+    /// the error is logged and the previous value is kept. Use `try_set` to handle it.
+    ///
     /// # Arguments
     ///
     /// `key` - Key to store value under
     /// `value` - Value to store
     pub fn set(&self, key: &str, value: &str) {
-        let mut stor = self.storage.borrow_mut();
-        stor.insert(key.to_string(), value.to_string());
+        if let Err(err) = self.try_set(key, value) {
+            warn!("Failed to store {}: {}", key, err);
+        }
+    }
+
+    /// Sets a value in the context's storage
+    ///
+    /// Returns an error if the value would exceed the storage's size limit,
+    /// or if it could not be persisted. In either case, the previous value is kept.
+    ///
+    /// # Arguments
+    ///
+    /// `key` - Key to store value under
+    /// `value` - Value to store
+    pub fn try_set(&self, key: &str, value: &str) -> Result<(), Error> {
+        self.storage.borrow_mut().set(key, value)
     }
 
     /// Clears a single key/value from storage
     ///
+    /// If the change could not be persisted, the error is logged. Use `try_clear` to
+    /// handle it.
+    ///
     /// # Arguments
     ///
     /// `key` - Key to clear (along with corresponding value)
     pub fn clear(&self, name: &String) {
-        let mut storage = self.storage.borrow_mut();
-        storage.remove(name);
+        if let Err(err) = self.try_clear(name) {
+            warn!("Failed to clear {}: {}", name, err);
+        }
+    }
+
+    /// Clears a single key/value from storage, returning an error if the change
+    /// could not be persisted
+    ///
+    /// # Arguments
+    ///
+    /// `key` - Key to clear (along with corresponding value)
+    pub fn try_clear(&self, name: &str) -> Result<(), Error> {
+        self.storage.borrow_mut().remove(name)
     }
 
     /// Clears all key/value pairs from storage
+    ///
+    /// If the change could not be persisted, the error is logged.
     pub fn clear_all(&self) {
-        self.storage.borrow_mut().clear();
+        if let Err(err) = self.storage.borrow_mut().clear() {
+            warn!("Failed to clear storage: {}", err);
+        }
     }
 }
 
@@ -83,6 +193,15 @@ impl<T> Context<T> {
 /// of exposing a subsystem to GraphQL queries and means
 /// for persistence throughout GraphQL queries.
 ///
+/// Every service automatically exposes a `serviceInfo` query alongside the
+/// fields of its own query root, reporting the service's name, version, uptime,
+/// request and error counts, recent request latencies and bound address.
+///
+/// Once started, a service announces its name, address, version and schema hash in the
+/// service discovery directory (see `kubos_system::Discovery`) and refreshes the
+/// announcement every `kubos_system::HEARTBEAT_INTERVAL`, so that clients can find it
+/// by name and tell whether it is still running.
+///
 /// ### Examples
 ///
 /// # Creating and starting a service.
@@ -99,12 +218,13 @@ impl<T> Context<T> {
 /// ```
 pub struct Service<'a, Query, Mutation, S>
 where
-    Query: GraphQLType<Context = Context<S>> + Send + Sync + 'static,
-    Mutation: GraphQLType<Context = Context<S>> + Send + Sync + 'static,
+    Query: GraphQLType<Context = Context<S>, TypeInfo = ()> + Send + Sync + 'static,
+    Mutation: GraphQLType<Context = Context<S>, TypeInfo = ()> + Send + Sync + 'static,
 {
     config: Config,
-    root_node: RootNode<'a, Query, Mutation>,
+    root_node: RootNode<'a, ServiceQuery<Query>, ServiceMutation<Mutation>>,
     context: Context<S>,
+    discovery: Discovery,
 }
 
 impl<'a, Query, Mutation, S> Service<'a, Query, Mutation, S>
@@ -120,17 +240,110 @@ where
     /// `subsystem` - An instance of the subsystem struct. This one instance will be used by all queries.
     /// `query` - The root query struct holding all other GraphQL queries.
     /// `mutation` - The root mutation struct holding all other GraphQL mutations.
+    ///
+    /// If the service's persistent storage is configured but can't be opened, the error is
+    /// logged and stored values only last until the service is restarted. Use `try_new` to
+    /// handle the error instead.
     pub fn new(config: Config, subsystem: S, query: Query, mutation: Mutation) -> Self {
+        let storage = Storage::from_config(&config).unwrap_or_else(|err| {
+            error!(
+                "Failed to open persistent storage, values won't be kept across restarts: {}",
+                err
+            );
+            Storage::in_memory()
+        });
+        Self::with_storage(config, storage, subsystem, query, mutation)
+    }
+
+    /// Creates a new service instance, returning an error if the service's persistent
+    /// storage is configured but can't be opened
+    ///
+    /// # Arguments
+    ///
+    /// See `new`
+    pub fn try_new(
+        config: Config,
+        subsystem: S,
+        query: Query,
+        mutation: Mutation,
+    ) -> Result<Self, Error> {
+        let storage = Storage::from_config(&config)?;
+        Ok(Self::with_storage(config, storage, subsystem, query, mutation))
+    }
+
+    fn with_storage(
+        config: Config,
+        storage: Storage,
+        subsystem: S,
+        query: Query,
+        mutation: Mutation,
+    ) -> Self {
+        let stats = ServiceStats::new(config.name(), &config.hosturl());
         Service {
-            config: config,
-            root_node: RootNode::new(query, mutation),
+            config: config.clone(),
+            root_node: RootNode::new(ServiceQuery(query), ServiceMutation(mutation)),
             context: Context {
                 subsystem: subsystem,
-                storage: RefCell::new(HashMap::new()),
+                storage: RefCell::new(storage),
+                stats: RefCell::new(stats),
+                config: ConfigWatcher::new(config),
             },
+            discovery: Discovery::new(),
         }
     }
 
+    /// Announces the service in the given discovery directory, rather than the default one
+    pub fn discovery(mut self, discovery: Discovery) -> Self {
+        self.discovery = discovery;
+        self
+    }
+
+    /// Returns a hash of the service's GraphQL schema.
+    ///
+    /// The hash only changes when the service's types, fields or arguments do, so clients
+    /// can use it to detect that a service has been updated with an incompatible schema.
+    pub fn schema_hash(&self) -> String {
+        let schema = match execute(
+            SCHEMA_QUERY,
+            None,
+            &self.root_node,
+            &Variables::new(),
+            &self.context,
+        ) {
+            Ok((val, _)) => serde_json::to_value(&val).unwrap_or(Value::Null),
+            Err(_) => Value::Null,
+        };
+
+        // Type order isn't guaranteed, so sort the types before hashing them
+        let mut types = schema["__schema"]["types"]
+            .as_array()
+            .cloned()
+            .unwrap_or_default();
+        types.sort_by(|a, b| a["name"].as_str().cmp(&b["name"].as_str()));
+        let canonical = json!({
+            "query": schema["__schema"]["queryType"],
+            "mutation": schema["__schema"]["mutationType"],
+            "types": types,
+        });
+
+        // 64-bit FNV-1a
+        let hash = canonical
+            .to_string()
+            .bytes()
+            .fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+                (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+            });
+        format!("{:016x}", hash)
+    }
+
+    /// Returns the watcher for this service's configuration file.
+    ///
+    /// Subscribe to it in order to be notified when the configuration is reloaded,
+    /// either by the `reloadConfig` mutation or by a `SIGHUP` signal.
+    pub fn config_watcher(&self) -> &ConfigWatcher {
+        &self.context.config
+    }
+
     /// Starts the service's GraphQL/UDP server. This function runs
     /// without return.
     ///
@@ -143,9 +356,20 @@ where
         let addr = self.config.hosturl().parse::<SocketAddr>().unwrap();
 
         let socket = UdpSocket::bind(&addr).unwrap();
-        info!("Listening on: {}", socket.local_addr().unwrap());
+        let local_addr = socket.local_addr().unwrap();
+        info!("Listening on: {}", local_addr);
+        self.context
+            .stats
+            .borrow_mut()
+            .set_addr(&local_addr.to_string());
+
+        if let Err(err) = self.context.config.watch() {
+            warn!("Configuration reloading on SIGHUP is unavailable: {}", err);
+        }
+
+        self.announce(&local_addr);
 
-        let mut buf = [0; 4096];
+        let mut buf = vec![0; MAX_REQUEST_SIZE];
         loop {
             // Wait for an incoming message
             let (size, peer) = socket
@@ -169,9 +393,36 @@ where
         }
     }
 
+    // Publishes the service's discovery record and keeps it refreshed from a background thread
+    fn announce(&self, addr: &SocketAddr) {
+        let version = kubos_system::kubos_versions()
+            .curr
+            .unwrap_or_else(|| "unknown".to_owned());
+        let mut record =
+            ServiceRecord::new(self.config.name(), addr, &version, &self.schema_hash());
+
+        if let Err(err) = self.discovery.announce(&record) {
+            warn!("Service discovery is unavailable: {}", err);
+            return;
+        }
+
+        let discovery = self.discovery.clone();
+        thread::spawn(move || loop {
+            thread::sleep(HEARTBEAT_INTERVAL);
+            record.refresh();
+            if let Err(err) = discovery.announce(&record) {
+                warn!("Failed to refresh service discovery record: {}", err);
+            }
+        });
+    }
+
     /// Processes a GraphQL query
+    ///
+    /// Returns the JSON-encoded response. Any errors encountered are reported as an array of
+    /// GraphQL error objects under the `errors` key, which is omitted if no errors occurred.
     pub fn process(&self, query: String) -> String {
-        match execute(
+        let start = Instant::now();
+        let (response, failed) = match execute(
             &query,
             None,
             &self.root_node,
@@ -179,20 +430,129 @@ where
             &self.context,
         ) {
             Ok((val, errs)) => {
-                let errs_msg: String = errs.into_iter()
-                    .map(|x| serde_json::to_string(&x).unwrap())
-                    .collect();
-
-                json!({
-                    "data": val,
-                    "errors": errs_msg})
-                    .to_string()
+                if errs.is_empty() {
+                    (json!({ "data": val }), false)
+                } else {
+                    (
+                        json!({
+                            "data": val,
+                            "errors": execution_errors(&errs)
+                        }),
+                        true,
+                    )
+                }
             }
-            Err(e) => {
+            Err(e) => (
                 json!({
-                    "errors": e
-                }).to_string()
+                    "errors": request_errors(&e)
+                }),
+                true,
+            ),
+        };
+
+        self.context
+            .stats
+            .borrow_mut()
+            .record(start.elapsed(), failed);
+
+        response.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use juniper::FieldResult;
+    use std::fs;
+    use std::time::Duration;
+    use tempfile::TempDir;
+
+    struct QueryRoot;
+
+    graphql_object!(QueryRoot: Context<()> as "Query" |&self| {
+        field ping() -> FieldResult<String> {
+            Ok(String::from("pong"))
+        }
+    });
+
+    struct OtherQueryRoot;
+
+    graphql_object!(OtherQueryRoot: Context<()> as "Query" |&self| {
+        field ping(count: i32) -> FieldResult<String> {
+            Ok(String::from("pong"))
+        }
+    });
+
+    struct MutationRoot;
+
+    graphql_object!(MutationRoot: Context<()> as "Mutation" |&self| {});
+
+    fn config(port: u16) -> Config {
+        Config::new_from_str(
+            "test-service",
+            &format!("[test-service.addr]\nip = \"127.0.0.1\"\nport = {}\n", port),
+        ).unwrap()
+    }
+
+    #[test]
+    fn schema_hash_stable() {
+        let first = Service::new(config(9999), (), QueryRoot, MutationRoot);
+        let second = Service::new(config(9998), (), QueryRoot, MutationRoot);
+        let other = Service::new(config(9999), (), OtherQueryRoot, MutationRoot);
+
+        assert_eq!(first.schema_hash().len(), 16);
+        assert_eq!(first.schema_hash(), second.schema_hash());
+        assert_ne!(first.schema_hash(), other.schema_hash());
+    }
+
+    #[test]
+    fn unopenable_storage_in_memory() {
+        let dir = TempDir::new().unwrap();
+        // A file where the storage directory should be
+        let blocked = dir.path().join("storage");
+        fs::write(&blocked, "").unwrap();
+        let config = Config::new_from_str(
+            "test-service",
+            &format!("[test-service.storage]\ndir = \"{}\"\n", blocked.display()),
+        ).unwrap();
+
+        assert!(Service::try_new(config.clone(), (), QueryRoot, MutationRoot).is_err());
+
+        let service = Service::new(config, (), QueryRoot, MutationRoot);
+        service.context.set("mode", "safe");
+        assert_eq!(service.context.get("mode"), "safe");
+        assert!(service.context.try_clear("mode").is_ok());
+        assert_eq!(service.context.get("mode"), "");
+    }
+
+    #[test]
+    fn start_announces() {
+        let dir = TempDir::new().unwrap();
+        let discovery = Discovery::at(dir.path());
+
+        let service_discovery = discovery.clone();
+        thread::spawn(move || {
+            Service::new(config(0), (), QueryRoot, MutationRoot)
+                .discovery(service_discovery)
+                .start();
+        });
+
+        let mut record = None;
+        for _ in 0..50 {
+            if let Ok(found) = discovery.lookup("test-service") {
+                record = Some(found);
+                break;
             }
+            thread::sleep(Duration::from_millis(100));
         }
+
+        let record = record.expect("Service was not announced");
+        assert_eq!(record.ip, "127.0.0.1");
+        assert_ne!(record.port, 0);
+        assert_eq!(
+            record.schema_hash,
+            Service::new(config(0), (), QueryRoot, MutationRoot).schema_hash()
+        );
+        assert!(record.is_alive());
     }
 }
@@ -0,0 +1,234 @@
+//
+// Copyright (C) 2018 Kubos Corporation
+//
+// Licensed under the Apache License, Version 2.0 (the "License")
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Key/value storage backing `Context::get` and `Context::set`, optionally persisted to disk
+
+use failure::Error;
+use kubos_system::{atomic_write, Config, StorageSettings, DEFAULT_STORAGE_SIZE};
+use serde_json;
+use std::collections::HashMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+/// A service's key/value store.
+///
+/// When persistent, every change is written to a temporary file which is synced and then
+/// renamed over the storage file, so the file on disk always holds either the old or the
+/// new set of values, even if the service crashes or loses power mid-write.
+#[derive(Debug)]
+pub struct Storage {
+    values: HashMap<String, String>,
+    path: Option<PathBuf>,
+    max_size: usize,
+}
+
+impl Storage {
+    /// Creates storage which only lives as long as the service
+    pub fn in_memory() -> Self {
+        Storage {
+            values: HashMap::new(),
+            path: None,
+            max_size: DEFAULT_STORAGE_SIZE,
+        }
+    }
+
+    /// Opens the persistent storage for the service named `name`, loading any
+    /// previously stored values
+    pub fn open(name: &str, settings: &StorageSettings) -> Result<Self, Error> {
+        let dir = Path::new(&settings.dir);
+        fs::create_dir_all(dir)
+            .map_err(|err| format_err!("Failed to create {}: {}", settings.dir, err))?;
+
+        let path = dir.join(format!("{}.json", name));
+        let values = match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents)
+                .map_err(|err| format_err!("Failed to parse {}: {}", path.display(), err))?,
+            Err(ref err) if err.kind() == ErrorKind::NotFound => HashMap::new(),
+            Err(err) => bail!("Failed to read {}: {}", path.display(), err),
+        };
+
+        Ok(Storage {
+            values,
+            path: Some(path),
+            max_size: settings.max_size(),
+        })
+    }
+
+    /// Creates the storage described by a service's configuration.
+    ///
+    /// Returns an error if the configured storage can't be opened, rather than silently
+    /// discarding the values the service stores.
+    pub fn from_config(config: &Config) -> Result<Self, Error> {
+        match config.storage()? {
+            Some(settings) => Storage::open(config.name(), &settings),
+            None => Ok(Storage::in_memory()),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&String> {
+        self.values.get(key)
+    }
+
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), Error> {
+        let current = self
+            .values
+            .get(key)
+            .map(|old| key.len() + old.len())
+            .unwrap_or(0);
+        let size = self.size() - current + key.len() + value.len();
+        if size > self.max_size {
+            bail!(
+                "Storing {} would exceed the storage limit of {} bytes",
+                key,
+                self.max_size
+            );
+        }
+
+        let previous = self.values.insert(key.to_owned(), value.to_owned());
+        self.save().map_err(|err| {
+            // Keep memory consistent with what's on disk
+            match previous {
+                Some(previous) => self.values.insert(key.to_owned(), previous),
+                None => self.values.remove(key),
+            };
+            err
+        })
+    }
+
+    pub fn remove(&mut self, key: &str) -> Result<(), Error> {
+        if self.values.remove(key).is_some() {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    pub fn clear(&mut self) -> Result<(), Error> {
+        self.values.clear();
+        self.save()
+    }
+
+    /// Total size, in bytes, of all stored keys and values
+    pub fn size(&self) -> usize {
+        self.values.iter().map(|(k, v)| k.len() + v.len()).sum()
+    }
+
+    fn save(&self) -> Result<(), Error> {
+        let path = match self.path {
+            Some(ref path) => path,
+            None => return Ok(()),
+        };
+
+        let contents = serde_json::to_string(&self.values)?;
+        atomic_write(path, contents.as_bytes())
+            .map_err(|err| format_err!("Failed to write {}: {}", path.display(), err))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn settings(dir: &TempDir, max_size: Option<usize>) -> StorageSettings {
+        StorageSettings {
+            dir: dir.path().to_string_lossy().to_string(),
+            max_size,
+        }
+    }
+
+    #[test]
+    fn persists_across_open() {
+        let dir = TempDir::new().unwrap();
+
+        let mut storage = Storage::open("test-service", &settings(&dir, None)).unwrap();
+        storage.set("mode", "safe").unwrap();
+        storage.set("deploy_count", "3").unwrap();
+        storage.remove("mode").unwrap();
+
+        let storage = Storage::open("test-service", &settings(&dir, None)).unwrap();
+        assert_eq!(storage.get("mode"), None);
+        assert_eq!(storage.get("deploy_count"), Some(&"3".to_owned()));
+        assert!(dir.path().join("test-service.json").exists());
+        assert!(!dir.path().join("test-service.json.tmp").exists());
+    }
+
+    #[test]
+    fn keyed_per_service() {
+        let dir = TempDir::new().unwrap();
+
+        let mut first = Storage::open("first-service", &settings(&dir, None)).unwrap();
+        first.set("key", "first").unwrap();
+
+        let second = Storage::open("second-service", &settings(&dir, None)).unwrap();
+        assert_eq!(second.get("key"), None);
+    }
+
+    #[test]
+    fn size_limit() {
+        let dir = TempDir::new().unwrap();
+
+        let mut storage = Storage::open("test-service", &settings(&dir, Some(10))).unwrap();
+        storage.set("key", "value").unwrap();
+        assert!(storage.set("other", "value").is_err());
+        // Replacing a value only counts the difference
+        storage.set("key", "1234567").unwrap();
+        assert!(storage.set("key", "12345678").is_err());
+
+        let storage = Storage::open("test-service", &settings(&dir, Some(10))).unwrap();
+        assert_eq!(storage.get("key"), Some(&"1234567".to_owned()));
+        assert_eq!(storage.get("other"), None);
+    }
+
+    #[test]
+    fn corrupt_file() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join("test-service.json"), "{\"key\": ").unwrap();
+
+        assert!(Storage::open("test-service", &settings(&dir, None)).is_err());
+    }
+
+    #[test]
+    fn unreadable_file() {
+        let dir = TempDir::new().unwrap();
+        // A directory in place of the storage file can't be read, but isn't missing either
+        fs::create_dir(dir.path().join("test-service.json")).unwrap();
+
+        assert!(Storage::open("test-service", &settings(&dir, None)).is_err());
+    }
+
+    #[test]
+    fn from_config_reports_errors() {
+        let dir = TempDir::new().unwrap();
+        let file = dir.path().join("file");
+        fs::write(&file, "").unwrap();
+
+        // The storage directory can't be created under a regular file
+        let config = Config::new_from_str(
+            "test-service",
+            &format!(
+                "[test-service.storage]\ndir = \"{}\"\n",
+                file.join("storage").display()
+            ),
+        ).unwrap();
+        assert!(Storage::from_config(&config).is_err());
+
+        let config = Config::new_from_str("test-service", "[test-service]").unwrap();
+        assert!(Storage::from_config(&config).is_ok());
+    }
+}
@@ -74,6 +74,22 @@
 //! }
 //! ```
 //!
+//! ## Error Details
+//!
+//! Get the same errors as `errors`, along with each error's code, such as `TIMEOUT` or
+//! `HARDWARE_ERROR`, its subsystem and its causes. Querying either field clears the errors.
+//!
+//! ```json
+//! {
+//!     errorDetails: [{
+//!         code: String,
+//!         subsystem: String,
+//!         message: String,
+//!         causes: [String]
+//!     }]
+//! }
+//! ```
+//!
 //! ## Power Status
 //!
 //! Get the current power state and uptime of the system
@@ -281,6 +297,21 @@
 //! }
 //! ```
 //!
+//! ## Error Details
+//!
+//! Get the same errors as `errors`, along with each error's code, subsystem and causes
+//!
+//! ```json
+//! mutation {
+//!     errorDetails: [{
+//!         code: String,
+//!         subsystem: String,
+//!         message: String,
+//!         causes: [String]
+//!     }]
+//! }
+//! ```
+//!
 //! ## No-Op
 //!
 //! Execute a trivial command against the system
@@ -412,6 +443,7 @@ extern crate failure;
 extern crate juniper;
 #[macro_use]
 extern crate kubos_service;
+extern crate kubos_system;
 #[macro_use]
 extern crate log;
 extern crate mai400_api;
@@ -427,10 +459,12 @@ mod schema;
 mod tests;
 
 use kubos_service::{Config, Service};
+use kubos_system::{check_config_status, NoSettings};
 use mai400_api::MAIResult;
 use model::{ReadData, Subsystem};
 pub use objects::*;
 use schema::{MutationRoot, QueryRoot};
+use std::process;
 use std::sync::Arc;
 use syslog::Facility;
 
@@ -440,9 +474,21 @@ fn main() -> MAIResult<()> {
         log::LevelFilter::Debug,
         Some("mai400-service"),
     ).unwrap();
-    
+
+    let loaded = Config::load_settings::<NoSettings>("mai400-service");
+    if let Some(code) = check_config_status(&loaded) {
+        process::exit(code);
+    }
+    let (config, _) = match loaded {
+        Ok(loaded) => loaded,
+        Err(err) => {
+            error!("Failed to load configuration: {}", err);
+            process::exit(1);
+        }
+    };
+
     Service::new(
-        Config::new("mai400-service"),
+        config,
         Subsystem::new("/dev/ttyS5", Arc::new(ReadData::new()))?,
         QueryRoot,
         MutationRoot,
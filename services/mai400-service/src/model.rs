@@ -15,8 +15,10 @@
 //
 
 use failure::Error;
+use kubos_service::{ErrorCode, ServiceError};
 use mai400_api::*;
 use std::cell::{Cell, RefCell};
+use std::io;
 use std::sync::mpsc::channel;
 use std::sync::mpsc::{Receiver, Sender, TryRecvError};
 use std::sync::{Arc, Mutex};
@@ -25,6 +27,28 @@ use std::time::Duration;
 
 use objects::*;
 
+// Name recorded as the subsystem of each error generated by this service
+const SUBSYSTEM: &str = "mai400";
+
+// UART timeouts are reported with their own code, so that clients can tell an
+// unresponsive device apart from one which is returning errors
+fn uart_error_code(err: &UartError) -> ErrorCode {
+    match err {
+        UartError::IoError {
+            cause: io::ErrorKind::TimedOut,
+            ..
+        } => ErrorCode::Timeout,
+        _ => ErrorCode::Hardware,
+    }
+}
+
+fn error_code(err: &Error) -> ErrorCode {
+    match err.downcast_ref::<MAIError>() {
+        Some(MAIError::UartError { cause }) => uart_error_code(cause),
+        _ => ErrorCode::Hardware,
+    }
+}
+
 pub struct ReadData {
     pub std_telem: Mutex<StandardTelemetry>,
     pub irehs_telem: Mutex<IREHSTelemetry>,
@@ -65,7 +89,7 @@ impl ReadData {
 
 // The MAI-400 sends a set of telemtery messages every 250ms
 // This function continuously reads them and updates the persistent data structs
-pub fn read_thread(mai: MAI400, data: Arc<ReadData>, sender: Sender<String>) {
+pub fn read_thread(mai: MAI400, data: Arc<ReadData>, sender: Sender<ServiceError>) {
     let mut err_count = 0;
     loop {
         let (std, imu, irehs) = match mai.get_message() {
@@ -78,10 +102,15 @@ pub fn read_thread(mai: MAI400, data: Arc<ReadData>, sender: Sender<String>) {
                         // go ahead and retry a few times to see if it clears up
                         if err_count > 10 {
                             sender
-                                .send(format!(
-                                    "UartError: {:?}. Read thread bailing. Service restart required.",
-                                    cause
-                                ))
+                                .send(
+                                    ServiceError::new(
+                                        uart_error_code(&cause),
+                                        &format!(
+                                            "UartError: {:?}. Read thread bailing. Service restart required.",
+                                            cause
+                                        ),
+                                    ).subsystem(SUBSYSTEM),
+                                )
                                 .unwrap();
                             break;
                         }
@@ -91,10 +120,15 @@ pub fn read_thread(mai: MAI400, data: Arc<ReadData>, sender: Sender<String>) {
                     }
                     _ => {
                         sender
-                            .send(format!(
-                                "Unexpected read errors encountered: {}. Service restart required.",
-                                process_errors!(err)
-                            ))
+                            .send(
+                                ServiceError::new(
+                                    ErrorCode::Hardware,
+                                    &format!(
+                                        "Unexpected read errors encountered: {}. Service restart required.",
+                                        process_errors!(err)
+                                    ),
+                                ).subsystem(SUBSYSTEM),
+                            )
                             .unwrap();
                         break;
                     }
@@ -119,9 +153,9 @@ pub fn read_thread(mai: MAI400, data: Arc<ReadData>, sender: Sender<String>) {
 pub struct Subsystem {
     pub mai: MAI400,
     pub last_cmd: Cell<AckCommand>,
-    pub errors: RefCell<Vec<String>>,
+    pub errors: RefCell<Vec<ServiceError>>,
     pub persistent: Arc<ReadData>,
-    pub receiver: Receiver<String>,
+    pub receiver: Receiver<ServiceError>,
 }
 
 impl Subsystem {
@@ -162,13 +196,48 @@ impl Subsystem {
                 TryRecvError::Disconnected => {
                     push_err!(
                         self.errors,
-                        "Read thread panicked. Service restart required.".to_owned()
+                        ErrorCode::Internal,
+                        SUBSYSTEM,
+                        "Read thread panicked. Service restart required."
                     );
                 }
             },
         }
     }
 
+    // Removes and returns all errors encountered since the last call
+    pub fn take_errors(&self) -> Vec<ServiceError> {
+        self.get_read_health();
+
+        match self.errors.try_borrow_mut() {
+            Ok(mut master_vec) => {
+                let current = master_vec.clone();
+                master_vec.clear();
+                master_vec.shrink_to_fit();
+                current
+            }
+            _ => vec![
+                ServiceError::new(
+                    ErrorCode::Internal,
+                    "Error: Failed to borrow master errors vector",
+                ).subsystem(SUBSYSTEM),
+            ],
+        }
+    }
+
+    // Returns all errors encountered while processing the current request
+    pub fn request_errors(&self) -> Vec<ServiceError> {
+        match self.errors.try_borrow() {
+            Ok(master_vec) => master_vec.clone(),
+            _ => vec![
+                ServiceError::new(
+                    ErrorCode::Internal,
+                    "Error: Failed to borrow master errors vector",
+                ).subsystem(SUBSYSTEM),
+            ],
+        }
+    }
+
     // Queries
 
     pub fn get_power(&self) -> Result<GetPowerResponse, Error> {
@@ -247,7 +316,9 @@ impl Subsystem {
             false => {
                 push_err!(
                     self.errors,
-                    "Noop: Unable to communicate with MAI400".to_owned()
+                    ErrorCode::Timeout,
+                    SUBSYSTEM,
+                    "Noop: Unable to communicate with MAI400"
                 );
                 (false, "Unable to communicate with MAI400".to_owned())
             }
@@ -259,7 +330,7 @@ impl Subsystem {
     pub fn control_power(&self, state: PowerState) -> Result<ControlPowerResponse, Error> {
         match state {
             PowerState::Reset => {
-                let result = run!(self.mai.reset(), self.errors);
+                let result = run!(self.mai.reset(), self.errors, SUBSYSTEM, error_code);
 
                 Ok(ControlPowerResponse {
                     power: state,
@@ -271,7 +342,12 @@ impl Subsystem {
                 })
             }
             _ => {
-                push_err!(self.errors, "controlPower: Invalid power state".to_owned());
+                push_err!(
+                    self.errors,
+                    ErrorCode::BadArgument,
+                    SUBSYSTEM,
+                    "controlPower: Invalid power state"
+                );
 
                 Ok(ControlPowerResponse {
                     power: state,
@@ -292,7 +368,7 @@ impl Subsystem {
             .map(|chunk| u8::from_str_radix(::std::str::from_utf8(chunk).unwrap(), 16).unwrap())
             .collect();
 
-        let result = run!(self.mai.passthrough(tx.as_slice()), self.errors);
+        let result = run!(self.mai.passthrough(tx.as_slice()), self.errors, SUBSYSTEM, error_code);
 
         Ok(GenericResponse {
             success: result.is_ok(),
@@ -318,7 +394,9 @@ impl Subsystem {
                     qbi_cmd[3] as i16,
                 ],
             ),
-            self.errors
+            self.errors,
+            SUBSYSTEM,
+            error_code
         );
 
         Ok(GenericResponse {
@@ -338,7 +416,9 @@ impl Subsystem {
     ) -> Result<GenericResponse, Error> {
         let result = run!(
             self.mai.set_mode_sun(mode, sun_angle_enable, sun_rot_angle),
-            self.errors
+            self.errors,
+            SUBSYSTEM,
+            error_code
         );
 
         Ok(GenericResponse {
@@ -359,7 +439,7 @@ impl Subsystem {
         let mut errors = "".to_owned();
 
         if let Some(time) = gps_time {
-            let result = run!(self.mai.set_gps_time(time as u32), self.errors);
+            let result = run!(self.mai.set_gps_time(time as u32), self.errors, SUBSYSTEM, error_code);
             success &= result.is_ok();
             if let Err(err) = result {
                 errors.push_str(&format!("update(gpsTime): {}", err));
@@ -389,7 +469,9 @@ impl Subsystem {
                     ],
                     params.time_epoch as u32,
                 ),
-                self.errors
+                self.errors,
+                SUBSYSTEM,
+                error_code
             );
             success &= result.is_ok();
             if let Err(err) = result {
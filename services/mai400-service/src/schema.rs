@@ -15,7 +15,7 @@
 //
 
 use juniper::FieldResult;
-use kubos_service;
+use kubos_service::{self, ServiceError};
 use model::*;
 use objects::*;
 
@@ -56,17 +56,24 @@ graphql_object!(QueryRoot: Context as "Query" |&self| {
     // }
     field errors(&executor) -> FieldResult<Vec<String>>
     {
-        executor.context().subsystem().get_read_health();
+        Ok(executor
+            .context()
+            .subsystem()
+            .take_errors()
+            .iter()
+            .map(|err| err.description())
+            .collect())
+    }
 
-        match executor.context().subsystem().errors.try_borrow_mut() {
-            Ok(mut master_vec) => {
-                let current = master_vec.clone();
-                master_vec.clear();
-                master_vec.shrink_to_fit();
-                Ok(current)
-            },
-            _ => Ok(vec!["Error: Failed to borrow master errors vector".to_owned()])
-        }
+    // Get all errors encountered since the last time this field or `errors` was queried,
+    // along with each error's code, subsystem and causes
+    //
+    // {
+    //     errorDetails: [ServiceError]
+    // }
+    field error_details(&executor) -> FieldResult<Vec<ServiceError>>
+    {
+        Ok(executor.context().subsystem().take_errors())
     }
 
     // Get the current power state and uptime of the system
@@ -273,10 +280,24 @@ graphql_object!(MutationRoot: Context as "Mutation" |&self| {
     // }
     field errors(&executor) -> FieldResult<Vec<String>>
     {
-        match executor.context().subsystem().errors.try_borrow() {
-            Ok(master_vec) => Ok(master_vec.clone()),
-            _ => Ok(vec!["Error: Failed to borrow master errors vector".to_owned()])
-        }
+        Ok(executor
+            .context()
+            .subsystem()
+            .request_errors()
+            .iter()
+            .map(|err| err.description())
+            .collect())
+    }
+
+    // Get all errors encountered while processing this GraphQL request,
+    // along with each error's code, subsystem and causes
+    //
+    // mutation {
+    //     errorDetails: [ServiceError]
+    // }
+    field error_details(&executor) -> FieldResult<Vec<ServiceError>>
+    {
+        Ok(executor.context().subsystem().request_errors())
     }
 
     // Execute a trivial command against the system
@@ -53,7 +53,7 @@ macro_rules! service_new {
         });
 
         Service::new(
-            Config::new("mai400-service"),
+            Config::new_from_str("mai400-service", "[mai400-service]").unwrap(),
             Subsystem {
                 mai,
                 last_cmd: Cell::new(AckCommand::None),
@@ -90,7 +90,7 @@ macro_rules! service_new_with_read {
         thread::spawn(move || read_thread(mai_ref, data_ref, sender));
 
         Service::new(
-            Config::new("mai400-service"),
+            Config::new_from_str("mai400-service", "[mai400-service]").unwrap(),
             Subsystem {
                 mai,
                 last_cmd: Cell::new(AckCommand::None),
@@ -67,8 +67,7 @@ fn read_panic() {
     });
 
     let expected = json!({
-                "data": expected,
-                "errors": ""
+                "data": expected
         }).to_string();
 
     assert_eq!(service.process(query.to_owned()), expected);
@@ -27,8 +27,7 @@ use tests::test_data::*;
 macro_rules! wrap {
     ($result:ident) => {{
         json!({
-                                    "data": $result,
-                                    "errors": ""
+                                    "data": $result
                             }).to_string()
     }};
 }
@@ -1305,6 +1304,112 @@ fn mutation_errors_multiple() {
     assert_eq!(service.process(query.to_owned()), wrap!(expected));
 }
 
+#[test]
+fn query_error_details() {
+    let mock = MockStream::default();
+
+    let service = service_new!(mock);
+
+    let noop = r#"mutation {
+            noop {
+                success
+            }
+        }"#;
+
+    service.process(noop.to_owned());
+
+    let query = r#"{
+            errorDetails {
+                code,
+                subsystem,
+                message
+            }
+        }"#;
+
+    let expected = json!({
+            "errorDetails": [{
+                "code": "TIMEOUT",
+                "subsystem": "mai400",
+                "message": "Noop: Unable to communicate with MAI400"
+            }]
+    });
+
+    assert_eq!(service.process(query.to_owned()), wrap!(expected));
+
+    let query = r#"{
+            errors
+        }"#;
+
+    let expected = json!({
+            "errors": []
+    });
+
+    assert_eq!(service.process(query.to_owned()), wrap!(expected));
+}
+
+#[test]
+fn mutation_error_details_uart_timeout() {
+    let mut mock = MockStream::default();
+
+    mock.write.set_result(Err(UartError::IoError {
+        cause: ::std::io::ErrorKind::TimedOut,
+        description: "Operation timed out".to_owned(),
+    }));
+
+    let service = service_new!(mock);
+
+    let query = r#"mutation {
+            controlPower(state: RESET) {
+                success
+            },
+            errorDetails {
+                code,
+                subsystem,
+                causes
+            }
+        }"#;
+
+    let expected = json!({
+            "controlPower": {
+                "success": false
+            },
+            "errorDetails": [{
+                "code": "TIMEOUT",
+                "subsystem": "mai400",
+                "causes": ["IO Error: Operation timed out"]
+            }]
+    });
+
+    assert_eq!(service.process(query.to_owned()), wrap!(expected));
+}
+
+#[test]
+fn mutation_error_details_uart_error() {
+    let mock = MockStream::default();
+
+    let service = service_new!(mock);
+
+    let query = r#"mutation {
+            controlPower(state: RESET) {
+                success
+            },
+            errorDetails {
+                code
+            }
+        }"#;
+
+    let expected = json!({
+            "controlPower": {
+                "success": false
+            },
+            "errorDetails": [{
+                "code": "HARDWARE_ERROR"
+            }]
+    });
+
+    assert_eq!(service.process(query.to_owned()), wrap!(expected));
+}
+
 #[test]
 fn noop_good() {
     let mock = MockStream::default();
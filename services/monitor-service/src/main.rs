@@ -56,6 +56,8 @@ extern crate failure;
 #[macro_use]
 extern crate juniper;
 extern crate kubos_service;
+extern crate kubos_system;
+#[macro_use]
 extern crate log;
 extern crate regex;
 extern crate syslog;
@@ -65,6 +67,7 @@ extern crate syslog;
 extern crate lazy_static;
 
 use kubos_service::{Config, Service};
+use kubos_system::{check_config_status, NoSettings};
 use schema::{MutationRoot, QueryRoot};
 use syslog::Facility;
 
@@ -83,7 +86,17 @@ fn main() {
         Some("kubos-monitor-service"),
     ).unwrap();
     
-    let config = Config::new("monitor-service");
+    let loaded = Config::load_settings::<NoSettings>("monitor-service");
+    if let Some(code) = check_config_status(&loaded) {
+        ::std::process::exit(code);
+    }
+    let (config, _) = match loaded {
+        Ok(loaded) => loaded,
+        Err(err) => {
+            error!("Failed to load configuration: {}", err);
+            ::std::process::exit(1);
+        }
+    };
 
     Service::new(
         config,
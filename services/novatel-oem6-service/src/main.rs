@@ -81,6 +81,22 @@
 //! }
 //! ```
 //!
+//! ## Error Details
+//!
+//! Get the same errors as `errors`, along with each error's code, such as `TIMEOUT` or `HARDWARE_ERROR`,
+//! its subsystem and its causes. Querying either field clears the errors.
+//!
+//! ```json
+//! {
+//!     errorDetails: [{
+//!         code: String,
+//!         subsystem: String,
+//!         message: String,
+//!         causes: [String]
+//!     }]
+//! }
+//! ```
+//!
 //! ## Power Status
 //!
 //! Get the current power state of the system
@@ -218,6 +234,21 @@
 //! }
 //! ```
 //!
+//! ## Error Details
+//!
+//! Get the same errors as `errors`, along with each error's code, subsystem and causes
+//!
+//! ```json
+//! mutation {
+//!     errorDetails: [{
+//!         code: String,
+//!         subsystem: String,
+//!         message: String,
+//!         causes: [String]
+//!     }]
+//! }
+//! ```
+//!
 //! ## No-Op
 //!
 //! Execute a trivial command against the system
@@ -317,9 +348,13 @@ extern crate failure;
 extern crate juniper;
 #[macro_use]
 extern crate kubos_service;
+extern crate kubos_system;
 #[macro_use]
 extern crate log;
 extern crate novatel_oem6_api;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
 #[cfg(test)]
 #[macro_use]
 extern crate serde_json;
@@ -332,27 +367,45 @@ mod schema;
 mod tests;
 
 use kubos_service::{Config, Service};
+use kubos_system::{check_config_status, ServiceSettings};
 use model::{LockData, Subsystem};
 use novatel_oem6_api::OEMResult;
 pub use objects::*;
 use schema::{MutationRoot, QueryRoot};
+use std::process;
 use std::sync::Arc;
 use syslog::Facility;
 
+/// Settings for the NovAtel OEM6 GPS service
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct NovatelOem6ServiceSettings {
+    /// UART device the GPS is connected to
+    pub bus: String,
+}
+
+impl ServiceSettings for NovatelOem6ServiceSettings {}
+
 fn main() -> OEMResult<()> {
     syslog::init(
         Facility::LOG_DAEMON,
         log::LevelFilter::Debug,
         Some("novatel-oem6-service"),
     ).unwrap();
-    
-    let config = Config::new("novatel-oem6-service");
-    let bus = config
-        .get("bus")
-        .expect("No 'bus' value found in 'novatel-oem6-service' section of config");
-    let bus = bus.as_str().unwrap();
 
-    let subsystem = Subsystem::new(bus, Arc::new(LockData::new()))?;
+    let loaded = Config::load_settings::<NovatelOem6ServiceSettings>("novatel-oem6-service");
+    if let Some(code) = check_config_status(&loaded) {
+        process::exit(code);
+    }
+    let (config, settings) = match loaded {
+        Ok(loaded) => loaded,
+        Err(err) => {
+            error!("Failed to load configuration: {}", err);
+            process::exit(1);
+        }
+    };
+
+    let subsystem = Subsystem::new(&settings.bus, Arc::new(LockData::new()))?;
 
     Service::new(config, subsystem, QueryRoot, MutationRoot).start();
 
@@ -15,10 +15,14 @@
 //
 
 use failure::Error;
+use kubos_service::{ErrorCode, ServiceError};
 use novatel_oem6_api::Log::*;
 use novatel_oem6_api::*;
 use std::cell::{Cell, RefCell};
-use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TryRecvError, TrySendError};
+use std::io;
+use std::sync::mpsc::{
+    sync_channel, Receiver, RecvTimeoutError, SyncSender, TryRecvError, TrySendError,
+};
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::Duration;
@@ -27,6 +31,25 @@ use objects::*;
 
 pub const RECV_TIMEOUT: Duration = Duration::from_millis(350);
 
+// Name recorded as the subsystem of each error generated by this service
+const SUBSYSTEM: &str = "novatel-oem6";
+
+// Missing responses are reported with their own code, so that clients can tell an
+// unresponsive device apart from one which is rejecting commands
+fn error_code(err: &Error) -> ErrorCode {
+    match err.downcast_ref::<OEMError>() {
+        Some(OEMError::NoResponse) => ErrorCode::Timeout,
+        Some(OEMError::UartError {
+            cause:
+                UartError::IoError {
+                    cause: io::ErrorKind::TimedOut,
+                    ..
+                },
+        }) => ErrorCode::Timeout,
+        _ => ErrorCode::Hardware,
+    }
+}
+
 pub struct LockData {
     pub status: Mutex<LockStatus>,
     pub info: Mutex<LockInfo>,
@@ -121,7 +144,7 @@ pub fn log_thread(
 pub struct Subsystem {
     pub oem: OEM6,
     pub last_cmd: Cell<AckCommand>,
-    pub errors: RefCell<Vec<String>>,
+    pub errors: RefCell<Vec<ServiceError>>,
     pub lock_data: Arc<LockData>,
     pub error_recv: Receiver<RxStatusEventLog>,
     pub version_recv: Receiver<VersionLog>,
@@ -156,13 +179,57 @@ impl Subsystem {
         })
     }
 
-    fn get_version_log(&self) -> Result<VersionLog, String> {
+    fn get_version_log(&self) -> Result<VersionLog, ServiceError> {
         match self.oem.request_version() {
             Ok(_) => match self.version_recv.recv_timeout(RECV_TIMEOUT) {
                 Ok(log) => Ok(log),
-                Err(err) => Err(format!("Failed to receive version info - {}", err).to_owned()),
+                Err(err) => {
+                    let code = match err {
+                        RecvTimeoutError::Timeout => ErrorCode::Timeout,
+                        RecvTimeoutError::Disconnected => ErrorCode::Internal,
+                    };
+                    Err(ServiceError::new(
+                        code,
+                        &format!("Failed to receive version info - {}", err),
+                    ).subsystem(SUBSYSTEM))
+                }
             },
-            Err(err) => Err(process_errors!(err)),
+            Err(err) => Err(ServiceError::from_fail_with(err, error_code).subsystem(SUBSYSTEM)),
+        }
+    }
+
+    // Removes and returns all errors encountered since the last call
+    pub fn take_errors(&self) -> Vec<ServiceError> {
+        self.get_errors();
+
+        match self.errors.try_borrow_mut() {
+            Ok(mut master_vec) => {
+                let current = master_vec.clone();
+                master_vec.clear();
+                master_vec.shrink_to_fit();
+                current
+            }
+            _ => vec![
+                ServiceError::new(
+                    ErrorCode::Internal,
+                    "Error: Failed to borrow master errors vector",
+                ).subsystem(SUBSYSTEM),
+            ],
+        }
+    }
+
+    // Returns all errors encountered while processing the current request
+    pub fn request_errors(&self) -> Vec<ServiceError> {
+        self.get_errors();
+
+        match self.errors.try_borrow() {
+            Ok(master_vec) => master_vec.clone(),
+            _ => vec![
+                ServiceError::new(
+                    ErrorCode::Internal,
+                    "Error: Failed to borrow master errors vector",
+                ).subsystem(SUBSYSTEM),
+            ],
         }
     }
 
@@ -173,6 +240,8 @@ impl Subsystem {
             Ok(msg) => {
                 push_err!(
                     self.errors,
+                    ErrorCode::Hardware,
+                    SUBSYSTEM,
                     format!(
                         "RxStatusEvent({}, {}, {}): {}",
                         msg.word, msg.bit, msg.event, msg.description
@@ -182,6 +251,8 @@ impl Subsystem {
                 while let Ok(err) = self.error_recv.try_recv() {
                     push_err!(
                         self.errors,
+                        ErrorCode::Hardware,
+                        SUBSYSTEM,
                         format!(
                             "RxStatusEvent({}, {}, {}): {}",
                             err.word, err.bit, err.event, err.description
@@ -194,7 +265,12 @@ impl Subsystem {
                 TryRecvError::Empty => {}
                 // We've lost connection with the errors channel
                 TryRecvError::Disconnected => {
-                    push_err!(self.errors, "Errors sender disconnected".to_owned());
+                    push_err!(
+                        self.errors,
+                        ErrorCode::Internal,
+                        SUBSYSTEM,
+                        "Errors sender disconnected"
+                    );
                 }
             },
         }
@@ -210,17 +286,15 @@ impl Subsystem {
     }
 
     pub fn get_system_status(&self) -> Result<SystemStatus, Error> {
-        self.get_errors();
-
-        let mut errors = match self.errors.try_borrow() {
-            Ok(master_vec) => master_vec.clone(),
-            _ => vec!["Error: Failed to borrow master errors vector".to_owned()],
-        };
+        let mut errors: Vec<String> = self.request_errors()
+            .iter()
+            .map(|err| err.description())
+            .collect();
 
         let status = match self.get_version_log() {
             Ok(log) => log.recv_status,
             Err(err) => {
-                errors.push(format!("System Status: {}", err).to_owned());
+                errors.push(format!("System Status: {}", err.description()));
                 /* My first thought was to use ReceiverStatusFlags::empty(),
                  * but I'm worried that that is easily mistaken for this function
                  * actually having succeeded
@@ -255,12 +329,10 @@ impl Subsystem {
     }
 
     pub fn get_telemetry(&self) -> Result<Telemetry, Error> {
-        self.get_errors();
-
-        let mut errors = match self.errors.try_borrow() {
-            Ok(master_vec) => master_vec.clone(),
-            _ => vec!["Error: Failed to borrow master errors vector".to_owned()],
-        };
+        let mut errors: Vec<String> = self.request_errors()
+            .iter()
+            .map(|err| err.description())
+            .collect();
 
         let (status, version_info) = match self.get_version_log() {
             Ok(log) => (
@@ -274,9 +346,9 @@ impl Subsystem {
                 }),
             ),
             Err(err) => {
-                let temp = format!("Get Telemetry: {}", err);
-                errors.push(temp.clone());
-                push_err!(self.errors, temp);
+                let err = err.prefix("Get Telemetry");
+                errors.push(err.description());
+                push_err!(self.errors, err);
                 (ReceiverStatusFlags::all(), None)
             }
         };
@@ -307,9 +379,9 @@ impl Subsystem {
         let errors = match result {
             Ok(_) => "".to_owned(),
             Err(err) => {
-                let temp = format!("Noop: {}", err);
-                push_err!(self.errors, temp);
-                err
+                let errors = err.description();
+                push_err!(self.errors, err.prefix("Noop"));
+                errors
             }
         };
 
@@ -338,7 +410,9 @@ impl Subsystem {
                     }
                     ConfigOption::UnlogPositionData => self.oem.request_unlog(MessageID::BestXYZ),
                 },
-                self.errors
+                self.errors,
+                SUBSYSTEM,
+                error_code
             );
 
             success &= result.is_ok();
@@ -375,7 +449,12 @@ impl Subsystem {
             .map(|chunk| u8::from_str_radix(::std::str::from_utf8(chunk).unwrap(), 16).unwrap())
             .collect();
 
-        let result = run!(self.oem.passthrough(tx.as_slice()), self.errors);
+        let result = run!(
+            self.oem.passthrough(tx.as_slice()),
+            self.errors,
+            SUBSYSTEM,
+            error_code
+        );
 
         Ok(GenericResponse {
             success: result.is_ok(),
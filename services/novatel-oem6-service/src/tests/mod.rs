@@ -62,7 +62,7 @@ macro_rules! service_new {
         thread::sleep(Duration::from_millis(500));
 
         Service::new(
-            Config::new("novatel-oem6-service"),
+            Config::new_from_str("novatel-oem6-service", "[novatel-oem6-service]").unwrap(),
             Subsystem {
                 oem,
                 last_cmd: Cell::new(AckCommand::None),
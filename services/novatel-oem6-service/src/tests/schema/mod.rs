@@ -25,8 +25,7 @@ use std::sync::mpsc::sync_channel;
 macro_rules! wrap {
     ($result:ident) => {{
         json!({
-                                    "data": $result,
-                                    "errors": ""
+                                    "data": $result
                             }).to_string()
     }};
 }
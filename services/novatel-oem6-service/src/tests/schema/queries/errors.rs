@@ -165,6 +165,52 @@ fn query_errors_mixed() {
     assert_eq!(service.process(query.to_owned()), wrap!(expected));
 }
 
+#[test]
+fn query_error_details_mixed() {
+    let mut mock = MockStream::default();
+
+    mock.write.set_input(LOG_VERSION_COMMAND.to_vec());
+
+    let mut output = LOG_RESPONSE_GOOD.to_vec();
+    output.extend_from_slice(&ERROR_LOG);
+    mock.read.set_output(output);
+
+    let service = service_new!(mock);
+
+    let noop = r#"mutation {
+            noop {
+                success
+            }
+        }"#;
+
+    service.process(noop.to_owned());
+
+    let query = r#"{
+            errorDetails {
+                code,
+                subsystem,
+                message
+            }
+        }"#;
+
+    let expected = json!({
+            "errorDetails": [
+                {
+                    "code": "TIMEOUT",
+                    "subsystem": "novatel-oem6",
+                    "message": "Noop: Failed to receive version info - timed out waiting on channel"
+                },
+                {
+                    "code": "HARDWARE_ERROR",
+                    "subsystem": "novatel-oem6",
+                    "message": "RxStatusEvent(1, 19, 1): No Valid Position Calculated"
+                }
+            ]
+    });
+
+    assert_eq!(service.process(query.to_owned()), wrap!(expected));
+}
+
 #[test]
 fn query_errors_clear_after_query() {
     let mut mock = MockStream::default();
@@ -21,12 +21,15 @@ extern crate failure;
 extern crate kubos_system;
 #[macro_use]
 extern crate log;
+extern crate serde;
 extern crate serde_cbor;
+#[macro_use]
+extern crate serde_derive;
 extern crate shell_protocol;
 extern crate syslog;
 
 use channel_protocol::{ChannelMessage, ChannelProtocol};
-use kubos_system::Config as ServiceConfig;
+use kubos_system::{Config as ServiceConfig, ServiceSettings};
 use shell_protocol::{ProcessHandler, ProtocolError, ShellMessage, ShellProtocol};
 use std::collections::HashMap;
 use std::net::SocketAddr;
@@ -35,6 +38,16 @@ use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::Duration;
 
+/// Settings for the shell service
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct ShellServiceSettings {
+    /// Seconds to wait for a new message from a shell client
+    pub timeout: Option<u64>,
+}
+
+impl ServiceSettings for ShellServiceSettings {}
+
 #[derive(Debug)]
 struct ThreadProcess {
     pub sender: Sender<(ChannelMessage, SocketAddr)>,
@@ -24,8 +24,9 @@ extern crate shell_protocol;
 extern crate shell_service;
 extern crate syslog;
 
-use kubos_system::Config as ServiceConfig;
+use kubos_system::{check_config_status, Config as ServiceConfig};
 use shell_service::*;
+use std::process;
 use syslog::Facility;
 
 fn main() {
@@ -35,7 +36,17 @@ fn main() {
         Some("kubos-shell-service"),
     ).unwrap();
 
-    let config = ServiceConfig::new("shell-service");
+    let loaded = ServiceConfig::load_settings::<ShellServiceSettings>("shell-service");
+    if let Some(code) = check_config_status(&loaded) {
+        process::exit(code);
+    }
+    let (config, _) = match loaded {
+        Ok(loaded) => loaded,
+        Err(err) => {
+            error!("Failed to load configuration: {}", err);
+            process::exit(1);
+        }
+    };
 
     info!("Starting shell service at {}", config.hosturl());
 
@@ -0,0 +1,233 @@
+//
+// Copyright (C) 2018 Kubos Corporation
+//
+// Licensed under the Apache License, Version 2.0 (the "License")
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Direct telemetry insertions encoded as CBOR.
+//!
+//! Each message is a CBOR array, framed as in `cbor_protocol`, of the message type, an
+//! acknowledgment ID (or null), and the message's arguments:
+//!
+//! - `["define", ack, id, subsystem, parameter, ...]` adds parameter IDs to the sender's
+//!   dictionary, for use in later samples
+//! - `["samples", ack, sample, ...]` inserts samples in a single transaction. Each sample is
+//!   either `[timestamp, id, value]` or `[timestamp, subsystem, parameter, value]`, where a null
+//!   timestamp is replaced by the current time.
+//!
+//! If an acknowledgment ID is given, the service replies with
+//! `["ack", ack, entries inserted, error or null]`.
+
+use cbor_protocol::{Protocol, ProtocolError};
+use ingest::{IngestProtocol, IngestStats};
+use kubos_telemetry_db::{self, DatabasePool, TypedEntry, TypedValue};
+use limits::Limits;
+use senders::SenderMap;
+use serde_cbor::{ser, Value};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use udp::MAX_MESSAGE_SIZE;
+
+// Maximum number of parameter IDs in a single sender's dictionary
+const MAX_DICTIONARY_SIZE: usize = 65_536;
+
+// Parameter IDs defined by a single sender
+type Dictionary = HashMap<u64, (String, String)>;
+
+pub struct CborUdp {
+    db: Arc<DatabasePool>,
+    limits: Arc<Limits>,
+    stats: Arc<IngestStats>,
+    dictionaries: SenderMap<SocketAddr, Dictionary>,
+}
+
+impl CborUdp {
+    pub fn new(db: Arc<DatabasePool>, limits: Arc<Limits>, stats: Arc<IngestStats>) -> Self {
+        CborUdp {
+            db,
+            limits,
+            stats,
+            dictionaries: SenderMap::new(),
+        }
+    }
+
+    pub fn start(mut self, url: String) {
+        info!("CBOR UDP listening on: {}", url);
+        let protocol = Protocol::new(url, MAX_MESSAGE_SIZE);
+
+        loop {
+            // The sender is kept even if the message fails to parse, so that it can still be
+            // counted against them
+            let (peer, message) = match protocol.recv_message_from() {
+                Ok(received) => received,
+                Err(err) => {
+                    warn!("Failed to receive a CBOR message: {}", err);
+                    continue;
+                }
+            };
+
+            let message = match message {
+                Ok(Value::Array(message)) => message,
+                Ok(_) => vec![],
+                // Pause and resume frames aren't used
+                Err(ProtocolError::NoDataReceived) => continue,
+                Err(err) => {
+                    let result = Err(format!("Failed to parse message: {}", err));
+                    warn!("Failed to process CBOR message from {}: {}", peer, err);
+                    self.stats.record(peer, IngestProtocol::Cbor, &result);
+                    continue;
+                }
+            };
+
+            let result = self.process(peer, &message);
+            if let Err(ref err) = result {
+                warn!("Failed to process CBOR message from {}: {}", peer, err);
+            }
+            self.stats.record(peer, IngestProtocol::Cbor, &result);
+
+            if let Some(&Value::U64(ack)) = message.get(1) {
+                let (entries, error) = match result {
+                    Ok(entries) => (entries, None),
+                    Err(err) => (0, Some(err)),
+                };
+                match ser::to_vec_packed(&("ack", ack, entries, error)) {
+                    Ok(reply) => {
+                        if let Err(err) = protocol.send_message(&reply, peer) {
+                            warn!("Failed to acknowledge CBOR message: {}", err);
+                        }
+                    }
+                    Err(err) => warn!("Failed to create acknowledgment: {}", err),
+                }
+            }
+        }
+    }
+
+    // Returns the number of entries inserted
+    fn process(&mut self, peer: SocketAddr, message: &[Value]) -> Result<usize, String> {
+        if message.len() < 2 {
+            return Err("Message must start with a type and an acknowledgment ID".to_owned());
+        }
+        match message[1] {
+            Value::U64(_) | Value::Null => {}
+            ref ack => return Err(format!("Invalid acknowledgment ID: {:?}", ack)),
+        }
+
+        match message[0] {
+            Value::String(ref kind) if kind == "define" => {
+                self.define(peer, &message[2..]).map(|_| 0)
+            }
+            Value::String(ref kind) if kind == "samples" => self.samples(peer, &message[2..]),
+            ref kind => Err(format!("Unknown message type: {:?}", kind)),
+        }
+    }
+
+    fn define(&mut self, peer: SocketAddr, args: &[Value]) -> Result<(), String> {
+        if args.len() % 3 != 0 {
+            return Err("Definitions must each have an ID, subsystem and parameter".to_owned());
+        }
+
+        // Every definition is checked before any are added
+        let definitions = args
+            .chunks(3)
+            .map(|definition| match *definition {
+                [Value::U64(id), Value::String(ref subsystem), Value::String(ref parameter)] => {
+                    Ok((id, (subsystem.clone(), parameter.clone())))
+                }
+                _ => Err(format!("Invalid definition: {:?}", definition)),
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        let dictionary = self.dictionaries.get_or_insert_with(peer, HashMap::new);
+        for (id, names) in definitions {
+            if dictionary.len() >= MAX_DICTIONARY_SIZE && !dictionary.contains_key(&id) {
+                return Err(format!(
+                    "Dictionaries can't have more than {} parameters",
+                    MAX_DICTIONARY_SIZE
+                ));
+            }
+            dictionary.insert(id, names);
+        }
+        Ok(())
+    }
+
+    fn samples(&mut self, peer: SocketAddr, args: &[Value]) -> Result<usize, String> {
+        let now = kubos_telemetry_db::systime();
+        let dictionary = self.dictionaries.get_or_insert_with(peer, HashMap::new);
+
+        let entries = args
+            .iter()
+            .map(|sample| {
+                let sample = match *sample {
+                    Value::Array(ref sample) => sample,
+                    _ => return Err(format!("Invalid sample: {:?}", sample)),
+                };
+                let (timestamp, subsystem, parameter, value) = match sample[..] {
+                    [ref timestamp, Value::U64(id), ref value] => match dictionary.get(&id) {
+                        Some((subsystem, parameter)) => (timestamp, subsystem, parameter, value),
+                        None => return Err(format!("Unknown parameter ID: {}", id)),
+                    },
+                    [ref timestamp, Value::String(ref subsystem), Value::String(ref parameter), ref value] => {
+                        (timestamp, subsystem, parameter, value)
+                    }
+                    _ => return Err(format!("Invalid sample: {:?}", sample)),
+                };
+
+                Ok(TypedEntry {
+                    timestamp: parse_timestamp(timestamp)?.unwrap_or(now),
+                    subsystem,
+                    parameter,
+                    value: parse_value(value)?,
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        let db = self.db.writer().map_err(|err| format!("{}", err))?;
+        db.insert_typed_bulk(&entries)
+            .map_err(|err| format!("{}", err))?;
+
+        for entry in &entries {
+            self.limits.check(
+                &db,
+                entry.timestamp,
+                entry.subsystem,
+                entry.parameter,
+                &entry.value,
+            );
+        }
+
+        Ok(entries.len())
+    }
+}
+
+fn parse_timestamp(timestamp: &Value) -> Result<Option<f64>, String> {
+    match *timestamp {
+        Value::U64(timestamp) => Ok(Some(timestamp as f64)),
+        Value::I64(timestamp) => Ok(Some(timestamp as f64)),
+        Value::F64(timestamp) if timestamp.is_finite() => Ok(Some(timestamp)),
+        Value::Null => Ok(None),
+        ref timestamp => Err(format!("Invalid timestamp: {:?}", timestamp)),
+    }
+}
+
+fn parse_value(value: &Value) -> Result<TypedValue, String> {
+    match *value {
+        Value::U64(int) if int <= i64::max_value() as u64 => Ok(TypedValue::Integer(int as i64)),
+        Value::I64(int) => Ok(TypedValue::Integer(int)),
+        Value::F64(float) if float.is_finite() => Ok(TypedValue::Float(float)),
+        Value::Bool(boolean) => Ok(TypedValue::Boolean(boolean)),
+        Value::String(ref string) => Ok(TypedValue::String(string.clone())),
+        Value::Bytes(ref blob) => Ok(TypedValue::Blob(blob.clone())),
+        ref value => Err(format!("Invalid value: {:?}", value)),
+    }
+}
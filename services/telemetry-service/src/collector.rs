@@ -0,0 +1,179 @@
+//
+// Copyright (C) 2018 Kubos Corporation
+//
+// Licensed under the Apache License, Version 2.0 (the "License")
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use kubos_app::{self, QueryError, ServiceConfig};
+use kubos_system::Discovery;
+use kubos_telemetry_db::{self, DatabasePool, TypedEntry, TypedValue};
+use limits::Limits;
+use serde_json::Value;
+use settings::CollectorSettings;
+use std::sync::Arc;
+use std::thread::{sleep, spawn};
+use std::time::Duration;
+
+/// A collector, along with the configuration of the service it queries
+pub struct Collector {
+    pub settings: CollectorSettings,
+    pub service: ServiceConfig,
+}
+
+/// Looks up each collector's service in the config file, returning an error if any of them
+/// aren't configured
+///
+/// # Arguments
+/// `collectors` - Queries to make
+/// `config_path` - Path of the config file to look up the queried services in
+pub fn resolve(
+    collectors: Vec<CollectorSettings>,
+    config_path: Option<&str>,
+) -> Result<Vec<Collector>, String> {
+    collectors
+        .into_iter()
+        .enumerate()
+        .map(|(index, settings)| {
+            let service = match config_path {
+                Some(path) => ServiceConfig::load_from_path(&settings.service, path.to_owned()),
+                None => ServiceConfig::load(&settings.service),
+            };
+            let service = service.map_err(|err| {
+                format!(
+                    "collectors[{}]: service {} is not configured: {}",
+                    index, settings.service, err
+                )
+            })?;
+            Ok(Collector { settings, service })
+        })
+        .collect()
+}
+
+/// Starts a thread for each collector, which queries its service immediately and then once
+/// every collection interval. Each query is sent to the address the service currently
+/// announces, if it's running, or else to its configured address.
+///
+/// # Arguments
+/// `database` - Database to store collected values in
+/// `limits` - Limits to check collected values against
+/// `collectors` - Queries to make
+pub fn start(database: Arc<DatabasePool>, limits: Arc<Limits>, collectors: Vec<Collector>) {
+    for Collector { settings, service } in collectors {
+        let database = database.clone();
+        let limits = limits.clone();
+        let interval = Duration::from_secs(settings.interval);
+
+        spawn(move || {
+            let discovery = Discovery::new();
+            loop {
+                let service = service.clone().discovered(&discovery);
+                if let Err(err) = collect(&database, &limits, &service, &settings) {
+                    warn!(
+                        "Failed to collect {} telemetry from {}: {}",
+                        settings.subsystem, settings.service, err
+                    );
+                }
+                sleep(interval);
+            }
+        });
+    }
+}
+
+/// Queries a collector's service and stores the values selected from its response, all with
+/// the time the query was sent. Returns the number of entries stored.
+///
+/// If the service reports errors along with partial data, the values found in the partial
+/// data are still stored. Values which are missing, null, or not a number, boolean or string
+/// are skipped.
+pub fn collect(
+    database: &Arc<DatabasePool>,
+    limits: &Limits,
+    service: &ServiceConfig,
+    collector: &CollectorSettings,
+) -> Result<usize, String> {
+    let timestamp = kubos_telemetry_db::systime();
+    let timeout = Duration::from_secs(collector.timeout());
+
+    let data = match kubos_app::query(service.clone(), &collector.query, Some(timeout)) {
+        Ok(data) => data,
+        Err(err) => match err.downcast_ref::<QueryError>().and_then(|err| err.data()) {
+            Some(data) => {
+                warn!(
+                    "{} reported errors to the {} collector: {}",
+                    collector.service, collector.subsystem, err
+                );
+                data.clone()
+            }
+            None => return Err(err.to_string()),
+        },
+    };
+
+    let mut entries = vec![];
+    for (parameter, path) in &collector.parameters {
+        match lookup(&data, path).and_then(typed_value) {
+            Some(value) => entries.push(TypedEntry {
+                timestamp,
+                subsystem: &collector.subsystem,
+                parameter,
+                value,
+            }),
+            None => debug!(
+                "No value found at {} for {} {}",
+                path, collector.subsystem, parameter
+            ),
+        }
+    }
+
+    if entries.is_empty() {
+        return Ok(0);
+    }
+
+    let db = database.writer().map_err(|err| err.to_string())?;
+    db.insert_typed_bulk(&entries)
+        .map_err(|err| err.to_string())?;
+
+    for entry in &entries {
+        limits.check(
+            &db,
+            entry.timestamp,
+            entry.subsystem,
+            entry.parameter,
+            &entry.value,
+        );
+    }
+
+    Ok(entries.len())
+}
+
+// Follows a dot-separated path of field names and array indices
+fn lookup<'a>(data: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(data, |value, part| match *value {
+        Value::Array(ref items) => part
+            .parse::<usize>()
+            .ok()
+            .and_then(|index| items.get(index)),
+        _ => value.get(part),
+    })
+}
+
+fn typed_value(value: &Value) -> Option<TypedValue> {
+    match *value {
+        Value::Bool(boolean) => Some(TypedValue::Boolean(boolean)),
+        Value::Number(ref number) => number
+            .as_i64()
+            .map(TypedValue::Integer)
+            .or_else(|| number.as_f64().map(TypedValue::Float)),
+        Value::String(ref string) => Some(TypedValue::String(string.clone())),
+        _ => None,
+    }
+}
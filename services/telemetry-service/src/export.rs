@@ -0,0 +1,406 @@
+//
+// Copyright (C) 2018 Kubos Corporation
+//
+// Licensed under the Apache License, Version 2.0 (the "License")
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use diesel::prelude::*;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use juniper::{FieldError, FieldResult, Value};
+use kubos_telemetry_db::{self, DatabasePool};
+use schema::{self, Filter};
+use serde_cbor;
+use serde_json;
+use std::cmp;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::mem;
+use std::path::Path;
+use std::sync::Arc;
+use tar;
+
+// Number of entries read from the database at a time
+const PAGE_SIZE: i64 = 1000;
+
+/// Format of routed telemetry files
+#[derive(Clone, Copy, Debug, GraphQLEnum, PartialEq)]
+pub enum ExportFormat {
+    /// A JSON array of entries
+    Json,
+    /// Comma-separated values, with a header line
+    Csv,
+    /// A CBOR array of entries
+    Cbor,
+}
+
+impl ExportFormat {
+    // Written at the start of each file
+    fn header(self) -> &'static [u8] {
+        match self {
+            ExportFormat::Json => b"[",
+            ExportFormat::Csv => b"timestamp,subsystem,parameter,value\n",
+            // Start of an indefinite-length array
+            ExportFormat::Cbor => &[0x9f],
+        }
+    }
+
+    // Written between consecutive entries
+    fn separator(self) -> &'static [u8] {
+        match self {
+            ExportFormat::Json => b",",
+            _ => b"",
+        }
+    }
+
+    // Written at the end of each file
+    fn footer(self) -> &'static [u8] {
+        match self {
+            ExportFormat::Json => b"]",
+            ExportFormat::Csv => b"",
+            // End of the indefinite-length array
+            ExportFormat::Cbor => &[0xff],
+        }
+    }
+
+    fn encode(self, entry: &kubos_telemetry_db::Entry) -> FieldResult<Vec<u8>> {
+        Ok(match self {
+            ExportFormat::Json => serde_json::to_vec(entry)?,
+            ExportFormat::Csv => format!(
+                "{},{},{},{}\n",
+                entry.timestamp,
+                csv_field(&entry.subsystem),
+                csv_field(&entry.parameter),
+                csv_field(&entry.value)
+            )
+            .into_bytes(),
+            ExportFormat::Cbor => serde_cbor::to_vec(entry)?,
+        })
+    }
+}
+
+// Quotes a CSV field if it contains any characters with special meaning
+fn csv_field(field: &str) -> String {
+    if field.contains(|c| c == ',' || c == '"' || c == '\n' || c == '\r') {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+/// Files written by an export
+pub struct Exported {
+    /// Paths of the files written
+    pub files: Vec<String>,
+    /// Sequence number of the last entry exported, if any were
+    pub last: Option<i64>,
+}
+
+/// Writes the selected entries to one or more files, newest first.
+///
+/// Entries are read from the database a page at a time, so the whole selection never needs to
+/// fit in memory.
+///
+/// If a cursor is given, only entries inserted after those it has acknowledged are exported,
+/// in the order they were inserted (so that a limit selects the earliest of them), and the last
+/// entry exported is recorded so that it can be acknowledged later. Entries are followed in
+/// insertion order rather than by timestamp, so that entries which arrive late are still
+/// exported.
+///
+/// # Arguments
+/// `database` - Database to read entries from
+/// `filter` - Entries to export
+/// `cursor` - Name of an export cursor
+/// `output` - Path of the output file
+/// `format` - Format of the output file
+/// `compress` - Whether to wrap each output file in a tar.gz archive
+/// `max_file_size` - Maximum (uncompressed) size of each output file. If given, entries are
+/// split across files named `<output>.1`, `<output>.2`, and so on. A single entry larger than
+/// the maximum is written to a file of its own.
+pub fn export(
+    database: &Arc<DatabasePool>,
+    filter: Filter,
+    cursor: Option<&str>,
+    output: &str,
+    format: ExportFormat,
+    compress: bool,
+    max_file_size: Option<i32>,
+) -> FieldResult<Exported> {
+    let max_file_size = match max_file_size {
+        Some(size) if size < 1 => {
+            return Err(FieldError::new(
+                format!("Invalid maximum file size: {}", size),
+                Value::null(),
+            ))
+        }
+        Some(size) => Some(size as usize),
+        None => None,
+    };
+
+    let after = match cursor {
+        Some(name) if name.is_empty() => {
+            return Err(FieldError::new(
+                "Cursor name must not be empty",
+                Value::null(),
+            ))
+        }
+        Some(name) => database
+            .reader()?
+            .cursor(name)?
+            .and_then(|cursor| cursor.acked),
+        None => None,
+    };
+
+    let output_path = Path::new(output);
+    if output_path.file_name().is_none() {
+        return Err(FieldError::new(
+            "Unable to parse output file name",
+            Value::null(),
+        ));
+    }
+    if let Some(parent) = output_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut writer = PartWriter::new(output, format, compress, max_file_size)?;
+    let mut last: Option<i64> = None;
+    for_each_entry(
+        database,
+        filter,
+        after,
+        cursor.is_some(),
+        |entry, sequence| {
+            last = cmp::max(last, Some(sequence));
+            writer.write(&format.encode(entry)?)
+        },
+    )?;
+    let files = writer.finish()?;
+
+    if let (Some(name), Some(last)) = (cursor, last) {
+        database.writer()?.set_cursor_exported(name, last)?;
+    }
+
+    Ok(Exported { files, last })
+}
+
+// Calls `f` with each selected entry and its sequence number (rowid), newest first. If
+// `in_sequence`, entries are instead given in the order they were inserted, starting after the
+// sequence number `after`.
+fn for_each_entry<F>(
+    database: &Arc<DatabasePool>,
+    filter: Filter,
+    after: Option<i64>,
+    in_sequence: bool,
+    mut f: F,
+) -> FieldResult<()>
+where
+    F: FnMut(&kubos_telemetry_db::Entry, i64) -> FieldResult<()>,
+{
+    use diesel::dsl::sql;
+    use diesel::sql_types::BigInt;
+    use kubos_telemetry_db::telemetry::{self, dsl};
+
+    let mut remaining = filter.limit.map(i64::from);
+    let mut last_sequence = after;
+    let mut last_key: Option<(f64, String, String)> = None;
+
+    loop {
+        let page_size = remaining.map_or(PAGE_SIZE, |remaining| remaining.min(PAGE_SIZE));
+        if page_size <= 0 {
+            return Ok(());
+        }
+
+        // Tables created by older versions have no `sequence` column, but it's always an
+        // alias of the rowid
+        let mut query =
+            schema::select(&filter).select((telemetry::all_columns, sql::<BigInt>("rowid")));
+
+        // Continue after the last entry of the previous page
+        if in_sequence {
+            if let Some(sequence) = last_sequence {
+                query = query.filter(sql::<BigInt>("rowid").gt(sequence));
+            }
+            query = query.order(sql::<BigInt>("rowid"));
+        } else {
+            // Ordering by the whole primary key keeps the position unambiguous, and lets
+            // SQLite walk the primary key index
+            if let Some((timestamp, subsystem, parameter)) = last_key.take() {
+                query = query.filter(
+                    dsl::timestamp
+                        .lt(timestamp)
+                        .or(dsl::timestamp.eq(timestamp).and(
+                            dsl::subsystem.lt(subsystem.clone()).or(dsl::subsystem
+                                .eq(subsystem)
+                                .and(dsl::parameter.lt(parameter))),
+                        )),
+                );
+            }
+            query = query.order((
+                dsl::timestamp.desc(),
+                dsl::subsystem.desc(),
+                dsl::parameter.desc(),
+            ));
+        }
+
+        let entries = query
+            .limit(page_size)
+            .load::<(kubos_telemetry_db::Entry, i64)>(&database.reader()?.connection)?;
+
+        for &(ref entry, sequence) in &entries {
+            f(entry, sequence)?;
+        }
+
+        match entries.last() {
+            Some(&(ref entry, sequence)) if entries.len() as i64 == page_size => {
+                last_sequence = Some(sequence);
+                last_key = Some((
+                    entry.timestamp,
+                    entry.subsystem.clone(),
+                    entry.parameter.clone(),
+                ));
+            }
+            _ => return Ok(()),
+        }
+        remaining = remaining.map(|remaining| remaining - page_size);
+    }
+}
+
+// Writes encoded entries to a sequence of output files
+struct PartWriter<'a> {
+    output: &'a str,
+    format: ExportFormat,
+    compress: bool,
+    max_size: Option<usize>,
+    file: BufWriter<File>,
+    path: String,
+    size: usize,
+    entries: usize,
+    files: Vec<String>,
+}
+
+impl<'a> PartWriter<'a> {
+    fn new(
+        output: &'a str,
+        format: ExportFormat,
+        compress: bool,
+        max_size: Option<usize>,
+    ) -> io::Result<Self> {
+        let path = match max_size {
+            Some(_) => format!("{}.1", output),
+            None => output.to_owned(),
+        };
+        let mut file = BufWriter::new(File::create(&path)?);
+        file.write_all(format.header())?;
+
+        Ok(PartWriter {
+            output,
+            format,
+            compress,
+            max_size,
+            file,
+            path,
+            size: format.header().len(),
+            entries: 0,
+            files: vec![],
+        })
+    }
+
+    fn write(&mut self, record: &[u8]) -> FieldResult<()> {
+        let separator = if self.entries > 0 {
+            self.format.separator()
+        } else {
+            b""
+        };
+
+        if let Some(max_size) = self.max_size {
+            let size = self.size + separator.len() + record.len() + self.format.footer().len();
+            if self.entries > 0 && size > max_size {
+                self.next_part()?;
+                return self.write(record);
+            }
+        }
+
+        self.file.write_all(separator)?;
+        self.file.write_all(record)?;
+        self.size += separator.len() + record.len();
+        self.entries += 1;
+        Ok(())
+    }
+
+    // Finishes the current file and starts the next one
+    fn next_part(&mut self) -> io::Result<()> {
+        let path = format!("{}.{}", self.output, self.files.len() + 2);
+        let mut file = BufWriter::new(File::create(&path)?);
+        file.write_all(self.format.header())?;
+
+        let file = mem::replace(&mut self.file, file);
+        let path = mem::replace(&mut self.path, path);
+        self.files
+            .push(finish_part(file, path, self.format, self.compress)?);
+
+        self.size = self.format.header().len();
+        self.entries = 0;
+        Ok(())
+    }
+
+    fn finish(self) -> io::Result<Vec<String>> {
+        let PartWriter {
+            format,
+            compress,
+            file,
+            path,
+            mut files,
+            ..
+        } = self;
+        files.push(finish_part(file, path, format, compress)?);
+        Ok(files)
+    }
+}
+
+// Writes the footer of a file and compresses it, if requested, returning its final path
+fn finish_part(
+    mut file: BufWriter<File>,
+    path: String,
+    format: ExportFormat,
+    compress: bool,
+) -> io::Result<String> {
+    file.write_all(format.footer())?;
+    file.flush()?;
+    drop(file);
+
+    if !compress {
+        return Ok(path);
+    }
+
+    let file_name = Path::new(&path)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Unable to parse output file name",
+            )
+        })?
+        .to_owned();
+
+    let tar_path = format!("{}.tar.gz", path);
+    let tar_file = File::create(&tar_path)?;
+    let encoder = GzEncoder::new(tar_file, Compression::default());
+    let mut tar = tar::Builder::new(encoder);
+    tar.append_file(file_name, &mut File::open(&path)?)?;
+    tar.into_inner()?.finish()?;
+
+    fs::remove_file(&path)?;
+
+    Ok(tar_path)
+}
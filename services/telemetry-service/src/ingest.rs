@@ -0,0 +1,130 @@
+//
+// Copyright (C) 2018 Kubos Corporation
+//
+// Licensed under the Apache License, Version 2.0 (the "License")
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use juniper::FieldResult;
+use kubos_telemetry_db;
+use senders::SenderMap;
+use std::net::SocketAddr;
+use std::sync::Mutex;
+
+/// Encoding of direct UDP insertions
+#[derive(Clone, Copy, Debug, Eq, GraphQLEnum, Hash, Ord, PartialEq, PartialOrd)]
+pub enum IngestProtocol {
+    /// JSON messages, received on the `direct_port`
+    Json,
+    /// CBOR messages, received on the `cbor_port`
+    Cbor,
+}
+
+/// Counts of the messages received from a single sender
+#[derive(Clone)]
+pub struct SenderStats {
+    sender: SocketAddr,
+    protocol: IngestProtocol,
+    messages: u64,
+    entries: u64,
+    errors: u64,
+    last_error: Option<String>,
+    last_message: f64,
+}
+
+graphql_object!(SenderStats: () |&self| {
+    description: "Direct UDP insertions received from a single sender"
+
+    field sender() -> String as "Address and port the messages were sent from" {
+        self.sender.to_string()
+    }
+
+    field protocol() -> IngestProtocol as "Encoding of the messages" {
+        self.protocol
+    }
+
+    field messages() -> i32 as "Number of messages received" {
+        self.messages as i32
+    }
+
+    field entries() -> i32 as "Number of entries inserted" {
+        self.entries as i32
+    }
+
+    field errors() -> i32 as "Number of messages which couldn't be processed" {
+        self.errors as i32
+    }
+
+    field last_error() -> &Option<String> as "Description of the most recent error" {
+        &self.last_error
+    }
+
+    field last_message() -> f64 as "Time the most recent message was received" {
+        self.last_message
+    }
+});
+
+/// Ingest counters of every sender of direct UDP insertions
+pub struct IngestStats {
+    senders: Mutex<SenderMap<(SocketAddr, IngestProtocol), SenderStats>>,
+}
+
+impl IngestStats {
+    pub fn new() -> Self {
+        IngestStats {
+            senders: Mutex::new(SenderMap::new()),
+        }
+    }
+
+    /// Counts a message from a sender, with either the number of entries it inserted or the
+    /// reason it couldn't be processed
+    pub fn record(
+        &self,
+        sender: SocketAddr,
+        protocol: IngestProtocol,
+        result: &Result<usize, String>,
+    ) {
+        let mut senders = match self.senders.lock() {
+            Ok(senders) => senders,
+            Err(err) => {
+                error!("Failed to lock ingest counters: {}", err);
+                return;
+            }
+        };
+
+        let stats = senders.get_or_insert_with((sender, protocol), || SenderStats {
+            sender,
+            protocol,
+            messages: 0,
+            entries: 0,
+            errors: 0,
+            last_error: None,
+            last_message: 0.0,
+        });
+        stats.messages += 1;
+        stats.last_message = kubos_telemetry_db::systime();
+        match *result {
+            Ok(entries) => stats.entries += entries as u64,
+            Err(ref err) => {
+                stats.errors += 1;
+                stats.last_error = Some(err.clone());
+            }
+        }
+    }
+
+    /// Returns the counters of each sender, sorted by address and then protocol
+    pub fn senders(&self) -> FieldResult<Vec<SenderStats>> {
+        let mut senders: Vec<SenderStats> = self.senders.lock()?.values().cloned().collect();
+        senders.sort_by_key(|stats| (stats.sender, stats.protocol));
+        Ok(senders)
+    }
+}
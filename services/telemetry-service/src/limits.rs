@@ -0,0 +1,320 @@
+//
+// Copyright (C) 2018 Kubos Corporation
+//
+// Licensed under the Apache License, Version 2.0 (the "License")
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use diesel::prelude::*;
+use juniper::FieldResult;
+use kubos_telemetry_db::{self, Database, DatabasePool, NewEvent, TypedValue};
+use settings::LimitSettings;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+/// Alarm state of a telemetry parameter
+#[derive(Clone, Copy, Debug, GraphQLEnum, PartialEq)]
+pub enum AlarmState {
+    Nominal,
+    YellowLow,
+    YellowHigh,
+    RedLow,
+    RedHigh,
+}
+
+impl AlarmState {
+    /// Returns the name the state is stored in the database as
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AlarmState::Nominal => "nominal",
+            AlarmState::YellowLow => "yellow_low",
+            AlarmState::YellowHigh => "yellow_high",
+            AlarmState::RedLow => "red_low",
+            AlarmState::RedHigh => "red_high",
+        }
+    }
+
+    fn parse(state: &str) -> Option<Self> {
+        match state {
+            "nominal" => Some(AlarmState::Nominal),
+            "yellow_low" => Some(AlarmState::YellowLow),
+            "yellow_high" => Some(AlarmState::YellowHigh),
+            "red_low" => Some(AlarmState::RedLow),
+            "red_high" => Some(AlarmState::RedHigh),
+            _ => None,
+        }
+    }
+
+    // Values exactly on a limit are within it
+    fn evaluate(limits: &LimitSettings, value: f64) -> Self {
+        let below = |limit: Option<f64>| limit.map_or(false, |limit| value < limit);
+        let above = |limit: Option<f64>| limit.map_or(false, |limit| value > limit);
+
+        if below(limits.red_low) {
+            AlarmState::RedLow
+        } else if above(limits.red_high) {
+            AlarmState::RedHigh
+        } else if below(limits.yellow_low) {
+            AlarmState::YellowLow
+        } else if above(limits.yellow_high) {
+            AlarmState::YellowHigh
+        } else {
+            AlarmState::Nominal
+        }
+    }
+}
+
+// Most recently evaluated entry of a parameter
+#[derive(Clone)]
+struct Status {
+    state: AlarmState,
+    timestamp: f64,
+    value: f64,
+}
+
+/// A parameter which is currently outside of its limits
+pub struct Alarm {
+    subsystem: String,
+    parameter: String,
+    status: Status,
+}
+
+graphql_object!(Alarm: () |&self| {
+    description: "A telemetry parameter which is currently outside of its limits"
+
+    field subsystem() -> &String as "Subsystem name" {
+        &self.subsystem
+    }
+
+    field parameter() -> &String as "Telemetry parameter" {
+        &self.parameter
+    }
+
+    field state() -> AlarmState as "Alarm state" {
+        self.status.state
+    }
+
+    field timestamp() -> f64 as "Timestamp of the most recently checked entry" {
+        self.status.timestamp
+    }
+
+    field value() -> f64 as "Numeric value of the most recently checked entry" {
+        self.status.value
+    }
+});
+
+/// A change in a parameter's alarm state
+pub struct Event(kubos_telemetry_db::Event);
+
+graphql_object!(Event: () |&self| {
+    description: "A change in a telemetry parameter's alarm state"
+
+    field timestamp() -> f64 as "Timestamp of the entry which caused the change" {
+        self.0.timestamp
+    }
+
+    field subsystem() -> &String as "Subsystem name" {
+        &self.0.subsystem
+    }
+
+    field parameter() -> &String as "Telemetry parameter" {
+        &self.0.parameter
+    }
+
+    field value() -> f64 as "Numeric value of the entry which caused the change" {
+        self.0.value
+    }
+
+    field state() -> Option<AlarmState> as "The new alarm state" {
+        AlarmState::parse(&self.0.state)
+    }
+
+    field previous() -> Option<AlarmState> as "The alarm state before the change" {
+        AlarmState::parse(&self.0.previous)
+    }
+});
+
+/// Checks inserted entries against their parameters' limits, recording each change in a
+/// parameter's alarm state as an event
+pub struct Limits {
+    limits: BTreeMap<String, BTreeMap<String, LimitSettings>>,
+    statuses: Mutex<HashMap<(String, String), Status>>,
+}
+
+impl Limits {
+    /// Creates a checker for the given limits, picking up each parameter's alarm state
+    /// from its most recent event. Events of parameters which no longer have limits are
+    /// ignored.
+    pub fn new(limits: BTreeMap<String, BTreeMap<String, LimitSettings>>, db: &Database) -> Self {
+        let mut statuses = HashMap::new();
+        if !limits.is_empty() {
+            match db.latest_events() {
+                Ok(events) => {
+                    for event in events {
+                        let limited = limits
+                            .get(&event.subsystem)
+                            .map_or(false, |params| params.contains_key(&event.parameter));
+                        if !limited {
+                            continue;
+                        }
+                        if let Some(state) = AlarmState::parse(&event.state) {
+                            statuses.insert(
+                                (event.subsystem, event.parameter),
+                                Status {
+                                    state,
+                                    timestamp: event.timestamp,
+                                    value: event.value,
+                                },
+                            );
+                        }
+                    }
+                }
+                Err(err) => warn!("Failed to read previous alarm states: {}", err),
+            }
+        }
+
+        Limits {
+            limits,
+            statuses: Mutex::new(statuses),
+        }
+    }
+
+    /// Checks an inserted entry against its parameter's limits, if it has any.
+    ///
+    /// Only numeric values are checked, and entries older than the last entry checked for the
+    /// same parameter are ignored.
+    pub fn check(
+        &self,
+        db: &Database,
+        timestamp: f64,
+        subsystem: &str,
+        parameter: &str,
+        value: &TypedValue,
+    ) {
+        let limits = match self
+            .limits
+            .get(subsystem)
+            .and_then(|params| params.get(parameter))
+        {
+            Some(limits) => limits,
+            None => return,
+        };
+        let value = match value.as_f64() {
+            Some(value) => value,
+            None => return,
+        };
+
+        let mut statuses = match self.statuses.lock() {
+            Ok(statuses) => statuses,
+            Err(err) => {
+                error!("Failed to lock alarm states: {}", err);
+                return;
+            }
+        };
+
+        let key = (subsystem.to_owned(), parameter.to_owned());
+        let previous = match statuses.get(&key) {
+            Some(status) if timestamp < status.timestamp => return,
+            Some(status) => status.state,
+            None => AlarmState::Nominal,
+        };
+
+        let state = AlarmState::evaluate(limits, value);
+        if state != previous {
+            info!(
+                "{} {} changed from {} to {} with value {}",
+                subsystem,
+                parameter,
+                previous.as_str(),
+                state.as_str(),
+                value
+            );
+            if let Err(err) = db.insert_event(&NewEvent {
+                timestamp,
+                subsystem,
+                parameter,
+                value,
+                state: state.as_str(),
+                previous: previous.as_str(),
+            }) {
+                warn!("Failed to record alarm event: {}", err);
+            }
+        }
+
+        statuses.insert(
+            key,
+            Status {
+                state,
+                timestamp,
+                value,
+            },
+        );
+    }
+
+    /// Returns the parameters which are currently outside of their limits, sorted by name
+    pub fn alarms(&self) -> FieldResult<Vec<Alarm>> {
+        let mut alarms: Vec<Alarm> = self
+            .statuses
+            .lock()?
+            .iter()
+            .filter(|&(_, status)| status.state != AlarmState::Nominal)
+            .map(|(&(ref subsystem, ref parameter), status)| Alarm {
+                subsystem: subsystem.clone(),
+                parameter: parameter.clone(),
+                status: status.clone(),
+            })
+            .collect();
+        alarms.sort_by(|a, b| (&a.subsystem, &a.parameter).cmp(&(&b.subsystem, &b.parameter)));
+        Ok(alarms)
+    }
+}
+
+/// Returns the selected alarm events, newest first
+pub fn events(
+    database: &Arc<DatabasePool>,
+    timestamp_ge: Option<f64>,
+    timestamp_le: Option<f64>,
+    subsystem: Option<String>,
+    parameter: Option<String>,
+    limit: Option<i32>,
+) -> FieldResult<Vec<Event>> {
+    use diesel::sqlite::Sqlite;
+    use kubos_telemetry_db::events::{self, dsl};
+
+    let mut query = events::table.into_boxed::<Sqlite>();
+
+    if let Some(sub) = subsystem {
+        query = query.filter(dsl::subsystem.eq(sub));
+    }
+
+    if let Some(param) = parameter {
+        query = query.filter(dsl::parameter.eq(param));
+    }
+
+    if let Some(time_ge) = timestamp_ge {
+        query = query.filter(dsl::timestamp.ge(time_ge));
+    }
+
+    if let Some(time_le) = timestamp_le {
+        query = query.filter(dsl::timestamp.le(time_le));
+    }
+
+    if let Some(l) = limit {
+        query = query.limit(l.into());
+    }
+
+    let events = query
+        .order((dsl::timestamp.desc(), dsl::id.desc()))
+        .load::<kubos_telemetry_db::Event>(&database.reader()?.connection)?;
+
+    Ok(events.into_iter().map(Event).collect())
+}
@@ -36,6 +36,92 @@
 //! service's IP address, and `port` specifies the port on which the service will be
 //! listening for UDP packets.
 //!
+//! Old entries can be deleted automatically by adding a retention policy:
+//!
+//! ```
+//! [telemetry-service.retention]
+//! max_age = 604800
+//! max_size = 104857600
+//! interval = 60
+//!
+//! [telemetry-service.retention.subsystems.eps]
+//! max_age = 86400
+//! ```
+//!
+//! Where `max_age` is the number of seconds after which entries are deleted, `max_size` is
+//! the size in bytes above which the oldest entries are deleted, `interval` is the number of
+//! seconds between pruning passes (60 by default), and each entry of `subsystems` overrides
+//! `max_age` for a single subsystem.
+//!
+//! Inserted entries can be checked against alarm limits, given per parameter:
+//!
+//! ```
+//! [telemetry-service.limits.eps.voltage]
+//! red_low = 5.5
+//! yellow_low = 6.5
+//! yellow_high = 8.2
+//! red_high = 8.4
+//! ```
+//!
+//! Any of the four limits may be left out. Numeric values beyond a red limit put the parameter
+//! in a red alarm state, and otherwise values beyond a yellow limit put it in a yellow alarm
+//! state. Each change in a parameter's alarm state is recorded as an event.
+//!
+//! The service can also collect telemetry from other services itself, by periodically sending
+//! them a query and storing values selected from the response:
+//!
+//! ```
+//! [[telemetry-service.collectors]]
+//! service = "eps-service"
+//! subsystem = "eps"
+//! query = "{ telemetry { voltage, current } }"
+//! interval = 60
+//! timeout = 1
+//!
+//! [telemetry-service.collectors.parameters]
+//! voltage = "telemetry.voltage"
+//! current = "telemetry.current"
+//! ```
+//!
+//! Where `service` is the name of the service's section in the config file, `interval` is the
+//! number of seconds between queries, `timeout` is the number of seconds to wait for a response
+//! (1 by default) and each entry of `parameters` gives the path of a value within the response's
+//! `data`, as field names and array indices separated by dots. All of a collector's values are
+//! stored under `subsystem` with the time the query was sent. The service exits at startup if a
+//! collector's `service` has no section in the config file.
+//!
+//! Entries can also be inserted directly, without a GraphQL request, by sending UDP messages to
+//! the `direct_port` as JSON or to the `cbor_port` as CBOR:
+//!
+//! ```
+//! [telemetry-service]
+//! direct_port = 8104
+//! cbor_port = 8105
+//! ```
+//!
+//! CBOR messages are framed as for other KubOS UDP protocols, and are arrays of a message type,
+//! an acknowledgment ID (or null) and the message's arguments. A `define` message assigns
+//! numeric IDs to parameters, which the sender may then use in place of the subsystem and
+//! parameter names:
+//!
+//! ```
+//! ["define", 1, 1, "eps", "voltage", 2, "eps", "current"]
+//! ["samples", 2, [1000, 1, 7.2], [1000, 2, 0.5], [null, "gps", "lock", true]]
+//! ```
+//!
+//! All of a `samples` message's entries are inserted in a single transaction, and a null
+//! timestamp is replaced by the current time. When an acknowledgment ID is given, the service
+//! replies with `["ack", id, entries inserted, error or null]`. Parameter IDs are kept
+//! separately for each sender. Messages received from each sender on either port are counted,
+//! and can be queried with `ingestStats`.
+//!
+//! The database is switched to write-ahead logging when the service starts. Changes are made
+//! through a single connection, and queries through a pool of read-only connections, so that
+//! long queries and exports don't hold up inserts.
+//!
+//! The service will exit with an error if the config file is missing or invalid,
+//! or if `database` is not provided.
+//!
 //! # Starting the Service
 //!
 //! The service should be started automatically by its init script, but may also be started manually:
@@ -47,24 +133,145 @@
 //!
 //! # Panics
 //!
-//! Attempts to grab database path from Configuration and will `panic!` if not found.
 //! Attempts to connect to database at provided path and will `panic!` if connection fails.
 //! Attempts to create telemetry table and will `panic!` if table creation fails.
 //!
 //! # GraphQL Schema
 //!
 //! ```graphql
+//! enum ValueType {
+//!   INTEGER
+//!   FLOAT
+//!   BOOLEAN
+//!   STRING
+//!   BLOB
+//! }
+//!
+//! enum ExportFormat {
+//!   JSON
+//!   CSV
+//!   CBOR
+//! }
+//!
+//! enum Aggregate {
+//!   MIN
+//!   MAX
+//!   MEAN
+//!   COUNT
+//!   FIRST
+//!   LAST
+//! }
+//!
+//! type Stats {
+//!   subsystem: String!
+//!   parameter: String!
+//!   start: Float!
+//!   end: Float!
+//!   count: Int
+//!   min: Float
+//!   max: Float
+//!   mean: Float
+//!   first: Float
+//!   last: Float
+//! }
+//!
+//! type Bucket {
+//!   subsystem: String!
+//!   parameter: String!
+//!   start: Float!
+//!   end: Float!
+//!   count: Int
+//!   min: Float
+//!   max: Float
+//!   mean: Float
+//!   first: Float
+//!   last: Float
+//! }
+//!
+//! type SubsystemStats {
+//!   subsystem: String!
+//!   entries: Int!
+//!   oldest: Float!
+//!   newest: Float!
+//! }
+//!
+//! type DbStats {
+//!   size: Float!
+//!   freeSize: Float!
+//!   entries: Int!
+//!   subsystems: [SubsystemStats!]!
+//! }
+//!
+//! enum AlarmState {
+//!   NOMINAL
+//!   YELLOW_LOW
+//!   YELLOW_HIGH
+//!   RED_LOW
+//!   RED_HIGH
+//! }
+//!
+//! type Alarm {
+//!   subsystem: String!
+//!   parameter: String!
+//!   state: AlarmState!
+//!   timestamp: Float!
+//!   value: Float!
+//! }
+//!
+//! type Event {
+//!   timestamp: Float!
+//!   subsystem: String!
+//!   parameter: String!
+//!   value: Float!
+//!   state: AlarmState
+//!   previous: AlarmState
+//! }
+//!
+//! enum IngestProtocol {
+//!   JSON
+//!   CBOR
+//! }
+//!
+//! type SenderStats {
+//!   sender: String!
+//!   protocol: IngestProtocol!
+//!   messages: Int!
+//!   entries: Int!
+//!   errors: Int!
+//!   lastError: String
+//!   lastMessage: Float!
+//! }
+//!
+//! type Cursor {
+//!   name: String!
+//!   acked: Float
+//!   exported: Float
+//! }
+//!
 //! type Entry {
 //!   timestamp: Integer!
 //!   subsystem: String!
 //!   parameter: String!
-//!   value: Float!
+//!   value: String!
+//!   valueType: ValueType!
+//!   numericValue: Float
+//!   booleanValue: Boolean
+//!   blobValue: String
 //! }
 //!
-//! query telemetry(timestampGe: Integer, timestampLe: Integer, subsystem: String, parameter: String): Entry
-//! query routedTelemetry(timestampGe: Integer, timestampLe: Integer, subsystem: String, parameter: String, output: String!, compress: Boolean = true): String!
+//! query telemetry(timestampGe: Integer, timestampLe: Integer, subsystem: String, parameter: String, valueGe: Float, valueLe: Float): Entry
+//! query telemetryStats(timestampGe: Float, timestampLe: Float, subsystem: String, parameter: String, valueGe: Float, valueLe: Float, aggregates: [Aggregate]): [Stats]
+//! query telemetryBuckets(width: Float!, timestampGe: Float, timestampLe: Float, subsystem: String, parameter: String, valueGe: Float, valueLe: Float, aggregates: [Aggregate]): [Bucket]
+//! query alarms: [Alarm!]!
+//! query events(timestampGe: Float, timestampLe: Float, subsystem: String, parameter: String, limit: Int): [Event!]!
+//! query dbStats: DbStats!
+//! query routedTelemetry(timestampGe: Integer, timestampLe: Integer, subsystem: String, parameter: String, valueGe: Float, valueLe: Float, output: String!, compress: Boolean = true, format: ExportFormat = JSON, maxFileSize: Int, cursor: String): String!
+//! query cursors: [Cursor!]!
+//! query ingestStats: [SenderStats!]!
 //!
-//! mutation insert(timestamp: Integer, subsystem: String!, parameter: String!, value: String!):{ success: Boolean!, errors: String! }
+//! mutation insert(timestamp: Integer, subsystem: String!, parameter: String!, value: String!, valueType: ValueType):{ success: Boolean!, errors: String! }
+//! mutation insertBulk(timestamp: Float, entries: [{ timestamp: Float, subsystem: String!, parameter: String!, value: String!, valueType: ValueType }]!):{ success: Boolean!, errors: String! }
+//! mutation ackCursor(cursor: String!, sequence: Float):{ success: Boolean!, errors: String!, acked: Float }
 //! ```
 //!
 //! # Example Queries
@@ -129,6 +336,78 @@
 //! }
 //! ```
 //!
+//! ## Select all voltage entries of the eps subsystem with values below 7.2
+//! ```graphql
+//! {
+//!   telemetry(subsystem: "eps", parameter: "voltage", valueLe: 7.2) {
+//!     timestamp,
+//!     numericValue
+//!   }
+//! }
+//! ```
+//!
+//! ## Select the hourly minimum, maximum and mean eps voltage over a day
+//! ```graphql
+//! {
+//!   telemetryBuckets(width: 3600, timestampGe: 1530000000, timestampLe: 1530086400, subsystem: "eps", parameter: "voltage", aggregates: [MIN, MAX, MEAN]) {
+//!     start,
+//!     min,
+//!     max,
+//!     mean
+//!   }
+//! }
+//! ```
+//!
+//! ## Select the parameters which are currently outside of their limits
+//! ```graphql
+//! {
+//!   alarms {
+//!     subsystem,
+//!     parameter,
+//!     state,
+//!     value
+//!   }
+//! }
+//! ```
+//!
+//! ## Select the ten most recent alarm state changes of the eps subsystem
+//! ```graphql
+//! {
+//!   events(subsystem: "eps", limit: 10) {
+//!     timestamp,
+//!     parameter,
+//!     previous,
+//!     state
+//!   }
+//! }
+//! ```
+//!
+//! ## Check how much space each subsystem's telemetry is using
+//! ```graphql
+//! {
+//!   dbStats {
+//!     size,
+//!     subsystems {
+//!       subsystem,
+//!       entries,
+//!       oldest
+//!     }
+//!   }
+//! }
+//! ```
+//!
+//! ## Check which senders' direct insertions are failing
+//! ```graphql
+//! {
+//!   ingestStats {
+//!     sender,
+//!     protocol,
+//!     errors,
+//!     lastError
+//!   }
+//! }
+//! ```
+//!
 //! ## Select ten entries occurring on or after the timestamp 1008
 //! ```graphql
 //! {
@@ -155,6 +434,20 @@
 //! }
 //! ```
 //!
+//! ## Route all eps entries to CSV files of at most 64KiB each, `/home/system/eps.csv.1.tar.gz`, `/home/system/eps.csv.2.tar.gz`, ...
+//! ```graphql
+//! {
+//!   routedTelemetry(subsystem: "eps", output: "/home/system/eps.csv", format: CSV, maxFileSize: 65536)
+//! }
+//! ```
+//!
+//! ## Route all entries which the ground station "ground-a" hasn't acknowledged receiving to `/home/system/ground-a.tar.gz`
+//! ```graphql
+//! {
+//!   routedTelemetry(cursor: "ground-a", output: "/home/system/ground-a")
+//! }
+//! ```
+//!
 //! # Example Mutations
 //!
 //! ## Insert a new entry, allowing the service to generate the timestamp
@@ -178,6 +471,30 @@
 //!
 //! ```
 //!
+//! ## Insert multiple entries in a single transaction, sharing a timestamp
+//! ```graphql
+//! mutation {
+//! 	insertBulk(timestamp: 533, entries: [
+//! 		{ subsystem: "eps", parameter: "voltage", value: "5.1" },
+//! 		{ subsystem: "eps", parameter: "current", value: "0.2" }
+//! 	]) {
+//! 		success,
+//! 		errors
+//! 	}
+//! }
+//! ```
+//!
+//! ## Acknowledge that the most recent export for "ground-a" has been received
+//! ```graphql
+//! mutation {
+//!     ackCursor(cursor: "ground-a") {
+//!         success,
+//!         errors,
+//!         acked
+//!     }
+//! }
+//! ```
+//!
 //! ## Delete all entries from the EPS subsystem occuring before timestamp 1003
 //! ```graphql
 //! mutation {
@@ -188,26 +505,45 @@
 //!     }
 //! }
 //! ```
+extern crate cbor_protocol;
+#[macro_use]
 extern crate diesel;
+#[macro_use]
+extern crate failure;
 extern crate flate2;
 #[macro_use]
 extern crate juniper;
+extern crate kubos_app;
 extern crate kubos_service;
+extern crate kubos_system;
 extern crate kubos_telemetry_db;
 #[macro_use]
 extern crate log;
 #[macro_use]
 extern crate serde_derive;
+extern crate serde_cbor;
 extern crate serde_json;
 extern crate syslog;
 extern crate tar;
 
+mod cbor;
+mod collector;
+mod export;
+mod ingest;
+mod limits;
+mod retention;
 mod schema;
+mod senders;
+mod settings;
+mod stats;
 mod udp;
 
 use kubos_service::{Config, Service};
+use kubos_system::check_config_status;
 use kubos_telemetry_db::Database;
 use schema::{MutationRoot, QueryRoot, Subsystem};
+use settings::TelemetryServiceSettings;
+use std::process;
 use syslog::Facility;
 
 fn main() {
@@ -216,28 +552,50 @@ fn main() {
         log::LevelFilter::Debug,
         Some("kubos-telemetry-service"),
     ).unwrap();
-    
-    let config = Config::new("telemetry-service");
 
-    let db_path = config
-        .get("database")
-        .expect("No database path found in config file");
-    let db_path = db_path.as_str().unwrap_or("");
+    let loaded = Config::load_settings::<TelemetryServiceSettings>("telemetry-service");
+    if let Some(code) = check_config_status(&loaded) {
+        process::exit(code);
+    }
+    let (config, settings) = match loaded {
+        Ok(loaded) => loaded,
+        Err(err) => {
+            error!("Failed to load configuration: {}", err);
+            process::exit(1);
+        }
+    };
 
-    let db = Database::new(&db_path);
+    let db = Database::new(&settings.database);
     db.setup();
 
-    let direct_udp = config.get("direct_port").map(|port| {
-        let host = config.hosturl();
-        let mut host_parts = host.split(':').map(|val| val.to_owned());
-        let host_ip = host_parts.next().unwrap();
+    let host = config.hosturl();
+    let host_ip = host.split(':').next().unwrap().to_owned();
+
+    let direct_udp = settings
+        .direct_port
+        .map(|port| format!("{}:{}", host_ip, port));
+    let cbor_udp = settings
+        .cbor_port
+        .map(|port| format!("{}:{}", host_ip, port));
 
-        format!("{}:{}", host_ip, port)
-    });
+    let collectors = match collector::resolve(settings.collectors, config.path()) {
+        Ok(collectors) => collectors,
+        Err(err) => {
+            error!("Failed to load configuration: {}", err);
+            process::exit(1);
+        }
+    };
 
     Service::new(
         config,
-        Subsystem::new(db, direct_udp),
+        Subsystem::new(
+            db,
+            direct_udp,
+            cbor_udp,
+            settings.retention,
+            settings.limits,
+            collectors,
+        ),
         QueryRoot,
         MutationRoot,
     ).start();
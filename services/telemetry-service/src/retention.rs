@@ -0,0 +1,245 @@
+//
+// Copyright (C) 2018 Kubos Corporation
+//
+// Licensed under the Apache License, Version 2.0 (the "License")
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use diesel;
+use diesel::dsl::sql;
+use diesel::prelude::*;
+use diesel::sql_query;
+use diesel::sql_types::{BigInt, Bool, Double, Text};
+use juniper::FieldResult;
+use kubos_telemetry_db::{self, Database, DatabasePool};
+use settings::RetentionSettings;
+use std::sync::Arc;
+use std::thread::{sleep, spawn};
+use std::time::Duration;
+
+// Maximum number of delete-and-vacuum passes made while shrinking the database to its
+// maximum size, so that a limit smaller than an empty database can't stall pruning
+const MAX_SIZE_PASSES: usize = 10;
+
+// Alarm events other than the latest of each parameter, which holds its current alarm state
+// and so is never pruned
+const OLD_EVENTS: &str = "id NOT IN (SELECT MAX(id) FROM events GROUP BY subsystem, parameter)";
+
+/// Starts a thread which prunes the database immediately, and then once every
+/// retention interval
+///
+/// A database created by an older version is converted to incremental vacuuming by the first
+/// pass with enough free disk space. Until then, entries are still deleted, but the file
+/// doesn't shrink.
+pub fn start(database: Arc<DatabasePool>, settings: RetentionSettings) {
+    let interval = Duration::from_secs(settings.interval());
+    let mut incremental = false;
+    let mut warned = false;
+    spawn(move || loop {
+        match database.writer() {
+            Ok(db) => {
+                if !incremental {
+                    match db.enable_incremental_vacuum() {
+                        Ok(true) => incremental = true,
+                        Ok(false) if !warned => {
+                            warn!(
+                                "Not enough disk space to enable incremental vacuum. \
+                                 Pruning entries without shrinking the database file"
+                            );
+                            warned = true;
+                        }
+                        Ok(false) => {}
+                        Err(err) => warn!("Failed to enable incremental vacuum: {}", err),
+                    }
+                }
+
+                match prune(&db, &settings, kubos_telemetry_db::systime()) {
+                    Ok(0) => {}
+                    Ok(deleted) => info!("Pruned {} telemetry entries", deleted),
+                    Err(err) => warn!("Failed to prune telemetry database: {}", err),
+                }
+            }
+            Err(err) => {
+                error!("Failed to lock telemetry database: {}", err);
+                return;
+            }
+        }
+        sleep(interval);
+    });
+}
+
+/// Deletes entries older than their subsystem's maximum age and then, if the database's
+/// entries still take up more than its maximum size, the oldest remaining entries.
+/// Alarm events are deleted along with the entries of the same age.
+/// Returns the number of entries deleted.
+///
+/// # Arguments
+/// `db` - Database to prune
+/// `settings` - Retention policy
+/// `now` - Current time, which entry ages are relative to
+pub fn prune(db: &Database, settings: &RetentionSettings, now: f64) -> QueryResult<usize> {
+    use kubos_telemetry_db::telemetry::dsl;
+    use kubos_telemetry_db::{events, telemetry};
+
+    let mut deleted = 0;
+    let mut events_deleted = 0;
+
+    for (subsystem, retention) in &settings.subsystems {
+        let cutoff = now - retention.max_age as f64;
+        deleted += diesel::delete(
+            telemetry::table
+                .filter(dsl::subsystem.eq(subsystem))
+                .filter(dsl::timestamp.lt(cutoff)),
+        )
+        .execute(&db.connection)?;
+        events_deleted += diesel::delete(
+            events::table
+                .filter(events::subsystem.eq(subsystem))
+                .filter(events::timestamp.lt(cutoff))
+                .filter(sql::<Bool>(OLD_EVENTS)),
+        )
+        .execute(&db.connection)?;
+    }
+
+    if let Some(max_age) = settings.max_age {
+        let cutoff = now - max_age as f64;
+        let overridden: Vec<&String> = settings.subsystems.keys().collect();
+        deleted += diesel::delete(
+            telemetry::table
+                .filter(dsl::subsystem.ne_all(&overridden))
+                .filter(dsl::timestamp.lt(cutoff)),
+        )
+        .execute(&db.connection)?;
+        events_deleted += diesel::delete(
+            events::table
+                .filter(events::subsystem.ne_all(&overridden))
+                .filter(events::timestamp.lt(cutoff))
+                .filter(sql::<Bool>(OLD_EVENTS)),
+        )
+        .execute(&db.connection)?;
+    }
+
+    if deleted > 0 || events_deleted > 0 {
+        db.vacuum()?;
+    }
+
+    if let Some(max_size) = settings.max_size {
+        for _ in 0..MAX_SIZE_PASSES {
+            // Free pages aren't counted, since they're reused even if they can't be released
+            let size = db.used_size()?;
+            if size <= max_size {
+                break;
+            }
+
+            let entries: i64 = telemetry::table.count().get_result(&db.connection)?;
+            if entries == 0 {
+                break;
+            }
+
+            // Assume entries take up the file evenly, and delete the fraction over the limit
+            let excess = (size - max_size) as f64 / size as f64;
+            let count = ((entries as f64 * excess).ceil() as i64).max(1);
+            deleted += db.delete_oldest(count)?;
+
+            // Events are kept for as long as the oldest remaining entry
+            sql_query(format!(
+                "DELETE FROM events WHERE timestamp < (SELECT MIN(timestamp) FROM telemetry) \
+                 AND {}",
+                OLD_EVENTS
+            ))
+            .execute(&db.connection)?;
+            db.vacuum()?;
+        }
+    }
+
+    Ok(deleted)
+}
+
+/// Number of entries of a subsystem, and the range of their timestamps
+#[derive(QueryableByName)]
+pub struct SubsystemStats {
+    #[sql_type = "Text"]
+    subsystem: String,
+    #[sql_type = "BigInt"]
+    entries: i64,
+    #[sql_type = "Double"]
+    oldest: f64,
+    #[sql_type = "Double"]
+    newest: f64,
+}
+
+graphql_object!(SubsystemStats: () |&self| {
+    description: "Entries stored for a subsystem"
+
+    field subsystem() -> &String as "Subsystem name" {
+        &self.subsystem
+    }
+
+    field entries() -> i32 as "Number of entries" {
+        self.entries as i32
+    }
+
+    field oldest() -> f64 as "Timestamp of the oldest entry" {
+        self.oldest
+    }
+
+    field newest() -> f64 as "Timestamp of the newest entry" {
+        self.newest
+    }
+});
+
+/// Storage used by the telemetry database
+pub struct DbStats {
+    size: u64,
+    free_size: u64,
+    subsystems: Vec<SubsystemStats>,
+}
+
+graphql_object!(DbStats: () |&self| {
+    description: "Storage used by the telemetry database"
+
+    field size() -> f64 as "Size of the database file, in bytes" {
+        self.size as f64
+    }
+
+    field free_size() -> f64
+        as "Bytes within the database file which are unused, and can be released by vacuuming"
+    {
+        self.free_size as f64
+    }
+
+    field entries() -> i32 as "Total number of entries" {
+        self.subsystems.iter().map(|stats| stats.entries as i32).sum()
+    }
+
+    field subsystems() -> &Vec<SubsystemStats> as "Entries stored for each subsystem" {
+        &self.subsystems
+    }
+});
+
+/// Reports the size of the database and the entries stored for each subsystem
+pub fn db_stats(database: &Arc<DatabasePool>) -> FieldResult<DbStats> {
+    let db = database.reader()?;
+
+    let subsystems = sql_query(
+        "SELECT subsystem, COUNT(*) AS entries, \
+         MIN(timestamp) AS oldest, MAX(timestamp) AS newest \
+         FROM telemetry GROUP BY subsystem ORDER BY subsystem",
+    )
+    .load::<SubsystemStats>(&db.connection)?;
+
+    Ok(DbStats {
+        size: db.size()?,
+        free_size: db.free_size()?,
+        subsystems,
+    })
+}
@@ -14,39 +14,71 @@
 // limitations under the License.
 //
 
+use cbor::CborUdp;
+use collector::{self, Collector};
 use diesel;
 use diesel::prelude::*;
-use flate2::write::GzEncoder;
-use flate2::Compression;
-use juniper::{FieldError, FieldResult, Value};
+use diesel::sqlite::Sqlite;
+use export::{self, ExportFormat};
+use ingest::{IngestStats, SenderStats};
+use juniper::FieldResult;
 use kubos_service;
-use kubos_telemetry_db;
-use serde_json;
-use std::fs;
-use std::fs::File;
-use std::io::prelude::*;
-use std::path::Path;
-use std::sync::{Arc, Mutex};
+use kubos_telemetry_db::{self, telemetry, TypedValue};
+use limits::{self, Alarm, Event, Limits};
+use retention::{self, DbStats};
+use settings::{LimitSettings, RetentionSettings};
+use stats::{self, Aggregate, Bucket, Stats};
+use std::collections::BTreeMap;
+use std::sync::Arc;
 use std::thread::spawn;
-use tar;
 use udp::*;
 
 type Context = kubos_service::Context<Subsystem>;
 
 pub struct Subsystem {
-    pub database: Arc<Mutex<kubos_telemetry_db::Database>>,
+    pub database: Arc<kubos_telemetry_db::DatabasePool>,
+    pub limits: Arc<Limits>,
+    pub ingest: Arc<IngestStats>,
 }
 
 impl Subsystem {
-    pub fn new(database: kubos_telemetry_db::Database, direct_udp: Option<String>) -> Self {
-        let db = Arc::new(Mutex::new(database));
+    pub fn new(
+        database: kubos_telemetry_db::Database,
+        direct_udp: Option<String>,
+        cbor_udp: Option<String>,
+        retention: Option<RetentionSettings>,
+        limits: BTreeMap<String, BTreeMap<String, LimitSettings>>,
+        collectors: Vec<Collector>,
+    ) -> Self {
+        let limits = Arc::new(Limits::new(limits, &database));
+        let db = Arc::new(kubos_telemetry_db::DatabasePool::new(
+            database,
+            kubos_telemetry_db::DEFAULT_READERS,
+        ));
+
+        let ingest = Arc::new(IngestStats::new());
 
         if let Some(udp_url) = direct_udp {
-            let udp = DirectUdp::new(db.clone());
+            let udp = DirectUdp::new(db.clone(), limits.clone(), ingest.clone());
             spawn(move || udp.start(udp_url.to_owned()));
         }
 
-        Subsystem { database: db }
+        if let Some(cbor_url) = cbor_udp {
+            let cbor = CborUdp::new(db.clone(), limits.clone(), ingest.clone());
+            spawn(move || cbor.start(cbor_url));
+        }
+
+        if let Some(retention) = retention {
+            retention::start(db.clone(), retention);
+        }
+
+        collector::start(db.clone(), limits.clone(), collectors);
+
+        Subsystem {
+            database: db,
+            limits,
+            ingest,
+        }
     }
 }
 
@@ -68,48 +100,130 @@ graphql_object!(Entry: () |&self| {
         &self.0.parameter
     }
 
-    field value() -> &String as "Telemetry value" {
+    field value() -> &String as "Telemetry value, as a string" {
         &self.0.value
     }
+
+    field value_type() -> ValueType as "Type of the telemetry value" {
+        self.0.typed_value.value_type().into()
+    }
+
+    field numeric_value() -> Option<f64>
+        as "Telemetry value as a number, for integer, float and boolean (0 or 1) values"
+    {
+        self.0.typed_value.as_f64()
+    }
+
+    field boolean_value() -> Option<bool> as "Telemetry value, for boolean values" {
+        match self.0.typed_value {
+            TypedValue::Boolean(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    field blob_value() -> Option<String> as "Telemetry value as a hex string, for blob values" {
+        match self.0.typed_value {
+            TypedValue::Blob(_) => Some(self.0.typed_value.to_string()),
+            _ => None,
+        }
+    }
 });
 
-fn query_db(
-    database: &Arc<Mutex<kubos_telemetry_db::Database>>,
-    timestamp_ge: Option<f64>,
-    timestamp_le: Option<f64>,
-    subsystem: Option<String>,
-    parameter: Option<String>,
-    limit: Option<i32>,
-) -> FieldResult<Vec<Entry>> {
-    use diesel::sqlite::SqliteConnection;
-    use kubos_telemetry_db::telemetry;
+/// Type of a telemetry value
+#[derive(Clone, Copy, GraphQLEnum)]
+pub enum ValueType {
+    Integer,
+    Float,
+    Boolean,
+    String,
+    Blob,
+}
+
+impl From<kubos_telemetry_db::ValueType> for ValueType {
+    fn from(value_type: kubos_telemetry_db::ValueType) -> Self {
+        match value_type {
+            kubos_telemetry_db::ValueType::Integer => ValueType::Integer,
+            kubos_telemetry_db::ValueType::Float => ValueType::Float,
+            kubos_telemetry_db::ValueType::Boolean => ValueType::Boolean,
+            kubos_telemetry_db::ValueType::String => ValueType::String,
+            kubos_telemetry_db::ValueType::Blob => ValueType::Blob,
+        }
+    }
+}
+
+impl From<ValueType> for kubos_telemetry_db::ValueType {
+    fn from(value_type: ValueType) -> Self {
+        match value_type {
+            ValueType::Integer => kubos_telemetry_db::ValueType::Integer,
+            ValueType::Float => kubos_telemetry_db::ValueType::Float,
+            ValueType::Boolean => kubos_telemetry_db::ValueType::Boolean,
+            ValueType::String => kubos_telemetry_db::ValueType::String,
+            ValueType::Blob => kubos_telemetry_db::ValueType::Blob,
+        }
+    }
+}
+
+/// Selection of telemetry entries
+#[derive(Default)]
+pub struct Filter {
+    pub timestamp_ge: Option<f64>,
+    pub timestamp_le: Option<f64>,
+    pub subsystem: Option<String>,
+    pub parameter: Option<String>,
+    pub value_ge: Option<f64>,
+    pub value_le: Option<f64>,
+    pub limit: Option<i32>,
+}
+
+/// Builds a query selecting the filtered entries, without any ordering or limit
+pub fn select(filter: &Filter) -> telemetry::BoxedQuery<'static, Sqlite> {
     use kubos_telemetry_db::telemetry::dsl;
 
-    let mut query = telemetry::table.into_boxed::<<SqliteConnection as Connection>::Backend>();
+    let mut query = telemetry::table.into_boxed::<Sqlite>();
 
-    if let Some(sub) = subsystem {
-        query = query.filter(dsl::subsystem.eq(sub));
+    if let Some(ref sub) = filter.subsystem {
+        query = query.filter(dsl::subsystem.eq(sub.clone()));
     }
 
-    if let Some(param) = parameter {
-        query = query.filter(dsl::parameter.eq(param));
+    if let Some(ref param) = filter.parameter {
+        query = query.filter(dsl::parameter.eq(param.clone()));
     }
 
-    if let Some(time_ge) = timestamp_ge {
+    if let Some(time_ge) = filter.timestamp_ge {
         query = query.filter(dsl::timestamp.ge(time_ge));
     }
 
-    if let Some(time_le) = timestamp_le {
+    if let Some(time_le) = filter.timestamp_le {
         query = query.filter(dsl::timestamp.le(time_le));
     }
 
-    if let Some(l) = limit {
+    // Values without a numeric representation never match a value range
+    if let Some(value_ge) = filter.value_ge {
+        query = query.filter(dsl::float_value.ge(value_ge));
+    }
+
+    if let Some(value_le) = filter.value_le {
+        query = query.filter(dsl::float_value.le(value_le));
+    }
+
+    query
+}
+
+fn query_db(
+    database: &Arc<kubos_telemetry_db::DatabasePool>,
+    filter: Filter,
+) -> FieldResult<Vec<Entry>> {
+    use kubos_telemetry_db::telemetry::dsl;
+
+    let mut query = select(&filter);
+
+    if let Some(l) = filter.limit {
         query = query.limit(l.into());
     }
 
     query = query.order(dsl::timestamp.desc());
 
-    let entries = query.load::<kubos_telemetry_db::Entry>(&database.lock()?.connection)?;
+    let entries = query.load::<kubos_telemetry_db::Entry>(&database.reader()?.connection)?;
 
     let mut g_entries: Vec<Entry> = Vec::new();
     for entry in entries {
@@ -128,57 +242,134 @@ graphql_object!(QueryRoot: Context |&self| {
         timestamp_le: Option<f64>,
         subsystem: Option<String>,
         parameter: Option<String>,
+        value_ge: Option<f64>,
+        value_le: Option<f64>,
         limit: Option<i32>,
     ) -> FieldResult<Vec<Entry>>
         as "Telemetry entries in database"
     {
-        query_db(&executor.context().subsystem().database, timestamp_ge, timestamp_le, subsystem, parameter, limit)
+        query_db(&executor.context().subsystem().database, Filter {
+            timestamp_ge, timestamp_le, subsystem, parameter, value_ge, value_le, limit,
+        })
+    }
+    field telemetry_stats(
+        &executor,
+        timestamp_ge: Option<f64>,
+        timestamp_le: Option<f64>,
+        subsystem: Option<String>,
+        parameter: Option<String>,
+        value_ge: Option<f64>,
+        value_le: Option<f64>,
+        aggregates: Option<Vec<Aggregate>>,
+    ) -> FieldResult<Vec<Stats>>
+        as "Aggregates (by default, all of them) of the selected entries of each telemetry parameter"
+    {
+        stats::stats(&executor.context().subsystem().database, Filter {
+            timestamp_ge, timestamp_le, subsystem, parameter, value_ge, value_le, limit: None,
+        }, aggregates)
+    }
+
+    field telemetry_buckets(
+        &executor,
+        width: f64,
+        timestamp_ge: Option<f64>,
+        timestamp_le: Option<f64>,
+        subsystem: Option<String>,
+        parameter: Option<String>,
+        value_ge: Option<f64>,
+        value_le: Option<f64>,
+        aggregates: Option<Vec<Aggregate>>,
+    ) -> FieldResult<Vec<Bucket>>
+        as "Aggregates (by default, all of them) of the selected entries of each telemetry parameter within consecutive time buckets of the given width (in seconds), starting at timestampGe (or 0)"
+    {
+        stats::buckets(&executor.context().subsystem().database, Filter {
+            timestamp_ge, timestamp_le, subsystem, parameter, value_ge, value_le, limit: None,
+        }, width, aggregates)
+    }
+
+    field db_stats(&executor) -> FieldResult<DbStats>
+        as "Size of the database and the entries stored for each subsystem"
+    {
+        retention::db_stats(&executor.context().subsystem().database)
     }
+
     field routed_telemetry(
         &executor,
         timestamp_ge: Option<f64>,
         timestamp_le: Option<f64>,
         subsystem: Option<String>,
         parameter: Option<String>,
+        value_ge: Option<f64>,
+        value_le: Option<f64>,
         limit: Option<i32>,
         output: String,
         compress = true: bool,
+        format = (ExportFormat::Json): ExportFormat,
+        max_file_size: Option<i32>,
+        cursor: Option<String>,
     ) -> FieldResult<String>
-        as "Telemetry entries in database"
+        as "Write telemetry entries to a file, returning its path. If a maximum file size (in bytes) is given, entries are split across files named <output>.1, <output>.2, and so on, and the paths of all of the files are returned, one per line. If a cursor is given, only entries inserted after those it has acknowledged are written, in the order they were inserted"
     {
-        let entries = query_db(&executor.context().subsystem().database, timestamp_ge, timestamp_le, subsystem, parameter, limit)?;
-        let entries = serde_json::to_vec(&entries)?;
+        let exported = export::export(&executor.context().subsystem().database, Filter {
+            timestamp_ge, timestamp_le, subsystem, parameter, value_ge, value_le, limit,
+        }, cursor.as_ref().map(|cursor| cursor.as_str()), &output, format, compress, max_file_size)?;
 
-        let output_str = output.clone();
-        let output_path = Path::new(&output_str);
+        Ok(exported.files.join("\n"))
+    }
 
-        let file_name_raw = output_path.file_name()
-            .ok_or_else(|| return FieldError::new("Unable to parse output file name", Value::null()))?;
-        let file_name = file_name_raw.to_str().ok_or_else(|| return FieldError::new("Unable to parse output file name to string", Value::null()))?;
+    field alarms(&executor) -> FieldResult<Vec<Alarm>>
+        as "Telemetry parameters which are currently outside of their limits"
+    {
+        executor.context().subsystem().limits.alarms()
+    }
 
-        if let Some(parent) = output_path.parent() {
-            fs::create_dir_all(parent)?;
-        }
+    field events(
+        &executor,
+        timestamp_ge: Option<f64>,
+        timestamp_le: Option<f64>,
+        subsystem: Option<String>,
+        parameter: Option<String>,
+        limit: Option<i32>,
+    ) -> FieldResult<Vec<Event>>
+        as "Changes in the alarm states of telemetry parameters, newest first"
+    {
+        limits::events(&executor.context().subsystem().database,
+            timestamp_ge, timestamp_le, subsystem, parameter, limit)
+    }
 
-        {
-            let mut output_file = File::create(output_path)?;
-            output_file.write_all(&entries)?;
-        }
+    field cursors(&executor) -> FieldResult<Vec<Cursor>>
+        as "Export cursors"
+    {
+        Ok(executor.context().subsystem().database.reader()?.cursors()?
+            .into_iter()
+            .map(Cursor)
+            .collect())
+    }
+
+    field ingest_stats(&executor) -> FieldResult<Vec<SenderStats>>
+        as "Counts of the direct UDP insertions received from each sender"
+    {
+        executor.context().subsystem().ingest.senders()
+    }
+});
 
-        if compress {
-            let tar_path = format!("{}.tar.gz", output_str);
-            let tar_file = File::create(&tar_path)?;
-            let encoder = GzEncoder::new(tar_file, Compression::default());
-            let mut tar = tar::Builder::new(encoder);
-            tar.append_file(file_name, &mut File::open(output_path)?)?;
-            tar.finish()?;
+/// A named position in the telemetry, used to export only entries which haven't already
+/// been received
+pub struct Cursor(kubos_telemetry_db::Cursor);
 
-            fs::remove_file(output_path)?;
+graphql_object!(Cursor: () |&self| {
+    description: "A named position in the telemetry, used to export only entries which haven't already been received"
 
-            Ok(tar_path)
-        } else {
-            Ok(output)
-        }
+    field name() -> &String as "Cursor name" {
+        &self.0.name
+    }
+
+    field acked() -> Option<f64> as "Sequence number of the last entry acknowledged as received" {
+        self.0.acked.map(|sequence| sequence as f64)
+    }
+
+    field exported() -> Option<f64> as "Sequence number of the last entry in the most recent export" {
+        self.0.exported.map(|sequence| sequence as f64)
     }
 });
 
@@ -190,6 +381,23 @@ struct InsertResponse {
     errors: String,
 }
 
+/// A telemetry entry to insert as part of a bulk insert
+#[derive(GraphQLInputObject)]
+struct InsertEntry {
+    timestamp: Option<f64>,
+    subsystem: String,
+    parameter: String,
+    value: String,
+    value_type: Option<ValueType>,
+}
+
+#[derive(GraphQLObject)]
+struct AckCursorResponse {
+    success: bool,
+    errors: String,
+    acked: Option<f64>,
+}
+
 #[derive(GraphQLObject)]
 struct DeleteResponse {
     success: bool,
@@ -198,12 +406,94 @@ struct DeleteResponse {
 }
 
 graphql_object!(MutationRoot: Context | &self | {
-    field insert(&executor, timestamp: Option<f64>, subsystem: String, parameter: String, value: String) -> FieldResult<InsertResponse> {
-        let result = match timestamp {
-            Some(time) => executor.context().subsystem().database.lock()?.insert(time, &subsystem, &parameter, &value),
-            None => executor.context().subsystem().database.lock()?.insert_systime(&subsystem, &parameter, &value),
+    field insert(&executor, timestamp: Option<f64>, subsystem: String, parameter: String, value: String, value_type: Option<ValueType>)
+        -> FieldResult<InsertResponse>
+        as "Insert an entry. If no value type is given, it's inferred from the value"
+    {
+        let timestamp = timestamp.unwrap_or_else(kubos_telemetry_db::systime);
+        let database = executor.context().subsystem().database.writer()?;
+
+        let result = match value_type {
+            Some(value_type) => TypedValue::parse(value_type.into(), &value).and_then(|typed_value| {
+                database.insert_typed(timestamp, &subsystem, &parameter, typed_value.clone())
+                    .map(|_| typed_value)
+                    .map_err(|err| err.to_string())
+            }),
+            None => database.insert(timestamp, &subsystem, &parameter, &value)
+                .map(|_| TypedValue::infer(&value))
+                .map_err(|err| err.to_string()),
+        };
+
+        if let Ok(ref typed_value) = result {
+            executor.context().subsystem().limits.check(&database, timestamp, &subsystem, &parameter, typed_value);
+        }
+
+        Ok(InsertResponse {
+            success: result.is_ok(),
+            errors: match result {
+                Ok(_) => "".to_owned(),
+                Err(err) => format!("{}", err),
+            },
+        })
+    }
+
+    field insert_bulk(&executor, timestamp: Option<f64>, entries: Vec<InsertEntry>) -> FieldResult<InsertResponse>
+        as "Insert multiple entries in a single transaction. Entries without a timestamp use the given timestamp, or the current time"
+    {
+        let timestamp = timestamp.unwrap_or_else(kubos_telemetry_db::systime);
+        let database = executor.context().subsystem().database.writer()?;
+
+        let result = if entries.iter().any(|entry| entry.value_type.is_some()) {
+            entries
+                .iter()
+                .map(|entry| {
+                    let value = match entry.value_type {
+                        Some(value_type) => TypedValue::parse(value_type.into(), &entry.value)?,
+                        None => TypedValue::infer(&entry.value),
+                    };
+                    Ok(kubos_telemetry_db::TypedEntry {
+                        timestamp: entry.timestamp.unwrap_or(timestamp),
+                        subsystem: &entry.subsystem,
+                        parameter: &entry.parameter,
+                        value,
+                    })
+                })
+                .collect::<Result<Vec<_>, String>>()
+                .and_then(|typed_entries| {
+                    database.insert_typed_bulk(&typed_entries).map_err(|err| err.to_string())
+                })
+        } else {
+            let new_entries: Vec<kubos_telemetry_db::NewEntry> = entries
+                .iter()
+                .map(|entry| kubos_telemetry_db::NewEntry {
+                    timestamp: entry.timestamp.unwrap_or(timestamp),
+                    subsystem: &entry.subsystem,
+                    parameter: &entry.parameter,
+                    value: &entry.value,
+                })
+                .collect();
+
+            database.insert_bulk(&new_entries).map_err(|err| err.to_string())
         };
 
+        if result.is_ok() {
+            for entry in &entries {
+                let value = match entry.value_type {
+                    Some(value_type) => TypedValue::parse(value_type.into(), &entry.value),
+                    None => Ok(TypedValue::infer(&entry.value)),
+                };
+                if let Ok(value) = value {
+                    executor.context().subsystem().limits.check(
+                        &database,
+                        entry.timestamp.unwrap_or(timestamp),
+                        &entry.subsystem,
+                        &entry.parameter,
+                        &value,
+                    );
+                }
+            }
+        }
+
         Ok(InsertResponse {
             success: result.is_ok(),
             errors: match result {
@@ -213,6 +503,45 @@ graphql_object!(MutationRoot: Context | &self | {
         })
     }
 
+    field ack_cursor(&executor, cursor: String, sequence: Option<f64>) -> FieldResult<AckCursorResponse>
+        as "Acknowledge that a cursor's entries up to and including the given sequence number (by default, the last entry of its most recent export) have been received"
+    {
+        let database = executor.context().subsystem().database.writer()?;
+
+        let result = if cursor.is_empty() {
+            Err("Cursor name must not be empty".to_owned())
+        } else {
+            match sequence {
+                Some(sequence) if sequence < 0.0 || sequence.fract() != 0.0 => {
+                    Err(format!("Invalid sequence number: {}", sequence))
+                }
+                Some(sequence) => Ok(sequence as i64),
+                None => match database.cursor(&cursor) {
+                    Ok(Some(kubos_telemetry_db::Cursor { exported: Some(exported), .. })) => Ok(exported),
+                    Ok(_) => Err(format!("Nothing has been exported for cursor {}", cursor)),
+                    Err(err) => Err(err.to_string()),
+                },
+            }
+        }.and_then(|sequence| {
+            database.ack_cursor(&cursor, sequence)
+                .map(|_| sequence)
+                .map_err(|err| err.to_string())
+        });
+
+        Ok(match result {
+            Ok(sequence) => AckCursorResponse {
+                success: true,
+                errors: "".to_owned(),
+                acked: Some(sequence as f64),
+            },
+            Err(err) => AckCursorResponse {
+                success: false,
+                errors: err,
+                acked: None,
+            },
+        })
+    }
+
     field delete(
         &executor,
         timestamp_ge: Option<f64>,
@@ -243,7 +572,7 @@ graphql_object!(MutationRoot: Context | &self | {
             selection = selection.filter(dsl::timestamp.le(time_le));
         }
 
-        let result = selection.execute(&executor.context().subsystem().database.lock()?.connection);
+        let result = selection.execute(&executor.context().subsystem().database.writer()?.connection);
 
         match result {
             Ok(num) => Ok(DeleteResponse {
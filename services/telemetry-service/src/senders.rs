@@ -0,0 +1,66 @@
+//
+// Copyright (C) 2018 Kubos Corporation
+//
+// Licensed under the Apache License, Version 2.0 (the "License")
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use std::collections::hash_map::{HashMap, Values};
+use std::hash::Hash;
+
+// Maximum number of senders tracked. Once reached, the sender heard from least recently is
+// forgotten to make room for a new one.
+const MAX_SENDERS: usize = 256;
+
+/// State kept for each sender of direct UDP insertions, up to `MAX_SENDERS` of them
+pub struct SenderMap<K, V> {
+    senders: HashMap<K, V>,
+    // When each sender was last heard from, as a count of the lookups made
+    last_heard: HashMap<K, u64>,
+    lookups: u64,
+}
+
+impl<K: Copy + Eq + Hash, V> SenderMap<K, V> {
+    pub fn new() -> Self {
+        SenderMap {
+            senders: HashMap::new(),
+            last_heard: HashMap::new(),
+            lookups: 0,
+        }
+    }
+
+    /// Returns a sender's state, creating it with `default` if needed, and marks the sender as
+    /// the one heard from most recently
+    pub fn get_or_insert_with<F: FnOnce() -> V>(&mut self, sender: K, default: F) -> &mut V {
+        self.lookups += 1;
+
+        if !self.senders.contains_key(&sender) && self.senders.len() >= MAX_SENDERS {
+            let oldest = self
+                .last_heard
+                .iter()
+                .min_by_key(|&(_, last_heard)| *last_heard)
+                .map(|(sender, _)| *sender);
+            if let Some(oldest) = oldest {
+                self.senders.remove(&oldest);
+                self.last_heard.remove(&oldest);
+            }
+        }
+
+        self.last_heard.insert(sender, self.lookups);
+        self.senders.entry(sender).or_insert_with(default)
+    }
+
+    /// Returns the state of each sender, in no particular order
+    pub fn values(&self) -> Values<K, V> {
+        self.senders.values()
+    }
+}
@@ -0,0 +1,267 @@
+//
+// Copyright (C) 2018 Kubos Corporation
+//
+// Licensed under the Apache License, Version 2.0 (the "License")
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+//! Typed settings for the telemetry service's section of the config file
+
+use failure::Error;
+use kubos_system::{check_range, ServiceSettings};
+use std::collections::BTreeMap;
+
+/// Default number of seconds between telemetry database pruning passes
+pub const DEFAULT_RETENTION_INTERVAL: u64 = 60;
+
+/// Retention policy of the telemetry database, read from the optional
+/// `[telemetry-service.retention]` section of the config file
+///
+/// ```toml
+/// [telemetry-service.retention]
+/// max_age = 604800
+/// max_size = 104857600
+/// interval = 60
+///
+/// [telemetry-service.retention.subsystems.eps]
+/// max_age = 86400
+/// ```
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct RetentionSettings {
+    /// Age, in seconds, after which entries are deleted
+    pub max_age: Option<u64>,
+    /// Size, in bytes, above which the oldest entries are deleted
+    pub max_size: Option<u64>,
+    /// Seconds between pruning passes
+    pub interval: Option<u64>,
+    /// Subsystems whose entries are kept for a different length of time than `max_age`
+    #[serde(default)]
+    pub subsystems: BTreeMap<String, SubsystemRetention>,
+}
+
+impl RetentionSettings {
+    /// Returns the pruning interval, falling back to `DEFAULT_RETENTION_INTERVAL`
+    pub fn interval(&self) -> u64 {
+        self.interval.unwrap_or(DEFAULT_RETENTION_INTERVAL)
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        if let Some(max_age) = self.max_age {
+            check_range("retention.max_age", max_age, 1, u64::from(u32::max_value()))?;
+        }
+        if let Some(max_size) = self.max_size {
+            check_range("retention.max_size", max_size, 65536, u64::max_value())?;
+        }
+        if let Some(interval) = self.interval {
+            check_range("retention.interval", interval, 1, 86400)?;
+        }
+        for (name, subsystem) in &self.subsystems {
+            if name.is_empty() {
+                bail!("retention subsystem names must not be empty");
+            }
+            check_range(
+                &format!("retention.subsystems.{}.max_age", name),
+                subsystem.max_age,
+                1,
+                u64::from(u32::max_value()),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Retention policy of a single subsystem's telemetry, overriding the database-wide `max_age`
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct SubsystemRetention {
+    /// Age, in seconds, after which the subsystem's entries are deleted
+    pub max_age: u64,
+}
+
+/// Alarm limits of a telemetry parameter's numeric value, read from the optional
+/// `[telemetry-service.limits.<subsystem>.<parameter>]` sections of the config file.
+/// Values beyond a red limit are in a red alarm state, and otherwise values beyond a yellow
+/// limit are in a yellow alarm state.
+///
+/// ```toml
+/// [telemetry-service.limits.eps.voltage]
+/// red_low = 6.0
+/// yellow_low = 6.5
+/// yellow_high = 8.2
+/// red_high = 8.4
+/// ```
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct LimitSettings {
+    /// Values below this are in a red alarm state
+    pub red_low: Option<f64>,
+    /// Values below this are in a yellow alarm state
+    pub yellow_low: Option<f64>,
+    /// Values above this are in a yellow alarm state
+    pub yellow_high: Option<f64>,
+    /// Values above this are in a red alarm state
+    pub red_high: Option<f64>,
+}
+
+impl LimitSettings {
+    fn validate(&self) -> Result<(), Error> {
+        let limits: Vec<(&str, f64)> = [
+            ("red_low", self.red_low),
+            ("yellow_low", self.yellow_low),
+            ("yellow_high", self.yellow_high),
+            ("red_high", self.red_high),
+        ].iter()
+            .filter_map(|&(name, limit)| limit.map(|limit| (name, limit)))
+            .collect();
+
+        if limits.is_empty() {
+            bail!("at least one limit must be given");
+        }
+        for &(name, limit) in &limits {
+            if !limit.is_finite() {
+                bail!("{} must be a finite number, found {}", name, limit);
+            }
+        }
+        for pair in limits.windows(2) {
+            if pair[0].1 > pair[1].1 {
+                bail!(
+                    "{} must not be greater than {}, found {} and {}",
+                    pair[0].0,
+                    pair[1].0,
+                    pair[0].1,
+                    pair[1].1
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Default number of seconds a telemetry collector waits for a response to its query
+pub const DEFAULT_COLLECTOR_TIMEOUT: u64 = 1;
+
+/// A query which the telemetry service periodically sends to another service, read from the
+/// optional `[[telemetry-service.collectors]]` sections of the config file. Each value selected
+/// from the response by `parameters` is stored as an entry of `subsystem`.
+///
+/// ```toml
+/// [[telemetry-service.collectors]]
+/// service = "eps-service"
+/// subsystem = "eps"
+/// query = "{ telemetry { voltage, current } }"
+/// interval = 60
+///
+/// [telemetry-service.collectors.parameters]
+/// voltage = "telemetry.voltage"
+/// current = "telemetry.current"
+/// ```
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct CollectorSettings {
+    /// Name of the service to query, as given in the config file
+    pub service: String,
+    /// Subsystem name under which the collected values are stored
+    pub subsystem: String,
+    /// GraphQL query sent to the service
+    pub query: String,
+    /// Seconds between queries
+    pub interval: u64,
+    /// Seconds to wait for a response
+    pub timeout: Option<u64>,
+    /// Paths of the values to store within the response's `data`, by parameter name.
+    /// A path is a list of field names and array indices separated by dots,
+    /// like `telemetry.cells.0.voltage`.
+    pub parameters: BTreeMap<String, String>,
+}
+
+impl CollectorSettings {
+    /// Returns the query timeout, falling back to `DEFAULT_COLLECTOR_TIMEOUT`
+    pub fn timeout(&self) -> u64 {
+        self.timeout.unwrap_or(DEFAULT_COLLECTOR_TIMEOUT)
+    }
+
+    fn validate(&self) -> Result<(), Error> {
+        if self.service.is_empty() {
+            bail!("service must not be empty");
+        }
+        if self.subsystem.is_empty() {
+            bail!("subsystem must not be empty");
+        }
+        if self.query.trim().is_empty() {
+            bail!("query must not be empty");
+        }
+        check_range("interval", self.interval, 1, 86400)?;
+        if let Some(timeout) = self.timeout {
+            check_range("timeout", timeout, 1, 86400)?;
+        }
+        if self.parameters.is_empty() {
+            bail!("at least one parameter must be given");
+        }
+        for (parameter, path) in &self.parameters {
+            if parameter.is_empty() {
+                bail!("parameter names must not be empty");
+            }
+            if path.split('.').any(|part| part.is_empty()) {
+                bail!("parameters.{} must be a dot-separated path, found \"{}\"", parameter, path);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Settings for the telemetry database service
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct TelemetryServiceSettings {
+    /// Path to the telemetry database file
+    pub database: String,
+    /// Port on which to accept direct UDP telemetry insertions
+    pub direct_port: Option<u16>,
+    /// Port on which to accept direct UDP telemetry insertions encoded as CBOR
+    pub cbor_port: Option<u16>,
+    /// Policy for automatically deleting old entries. Entries are kept forever if not given.
+    pub retention: Option<RetentionSettings>,
+    /// Alarm limits of telemetry parameters, by subsystem and then parameter name
+    #[serde(default)]
+    pub limits: BTreeMap<String, BTreeMap<String, LimitSettings>>,
+    /// Queries of other services whose results are periodically stored
+    #[serde(default)]
+    pub collectors: Vec<CollectorSettings>,
+}
+
+impl ServiceSettings for TelemetryServiceSettings {
+    fn validate(&self) -> Result<(), Error> {
+        if self.database.is_empty() {
+            bail!("database must not be empty");
+        }
+        if self.cbor_port.is_some() && self.cbor_port == self.direct_port {
+            bail!("cbor_port must not be the same as direct_port");
+        }
+        if let Some(ref retention) = self.retention {
+            retention.validate()?;
+        }
+        for (subsystem, parameters) in &self.limits {
+            for (parameter, limits) in parameters {
+                limits
+                    .validate()
+                    .map_err(|err| format_err!("limits.{}.{}: {}", subsystem, parameter, err))?;
+            }
+        }
+        for (index, collector) in self.collectors.iter().enumerate() {
+            collector
+                .validate()
+                .map_err(|err| format_err!("collectors[{}]: {}", index, err))?;
+        }
+        Ok(())
+    }
+}
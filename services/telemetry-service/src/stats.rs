@@ -0,0 +1,276 @@
+//
+// Copyright (C) 2018 Kubos Corporation
+//
+// Licensed under the Apache License, Version 2.0 (the "License")
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+use diesel::prelude::*;
+use diesel::sql_query;
+use diesel::sql_types::{BigInt, Double, Nullable, Text};
+use juniper::{FieldError, FieldResult, Value};
+use kubos_telemetry_db::DatabasePool;
+use schema::Filter;
+use std::sync::Arc;
+
+/// Aggregate function computed over the numeric values of a group of entries
+#[derive(Clone, Copy, Debug, GraphQLEnum, PartialEq)]
+pub enum Aggregate {
+    Min,
+    Max,
+    Mean,
+    Count,
+    First,
+    Last,
+}
+
+const ALL_AGGREGATES: [Aggregate; 6] = [
+    Aggregate::Min,
+    Aggregate::Max,
+    Aggregate::Mean,
+    Aggregate::Count,
+    Aggregate::First,
+    Aggregate::Last,
+];
+
+/// Aggregates of a group of entries of a single parameter.
+/// Aggregates which weren't requested are `None`.
+#[derive(QueryableByName)]
+pub struct AggregateRow {
+    #[sql_type = "Text"]
+    pub subsystem: String,
+    #[sql_type = "Text"]
+    pub parameter: String,
+    #[sql_type = "Nullable<BigInt>"]
+    pub bucket: Option<i64>,
+    #[sql_type = "Double"]
+    pub first_timestamp: f64,
+    #[sql_type = "Double"]
+    pub last_timestamp: f64,
+    #[sql_type = "Nullable<BigInt>"]
+    pub count: Option<i64>,
+    #[sql_type = "Nullable<Double>"]
+    pub min: Option<f64>,
+    #[sql_type = "Nullable<Double>"]
+    pub max: Option<f64>,
+    #[sql_type = "Nullable<Double>"]
+    pub mean: Option<f64>,
+    #[sql_type = "Nullable<Double>"]
+    pub first: Option<f64>,
+    #[sql_type = "Nullable<Double>"]
+    pub last: Option<f64>,
+}
+
+/// Aggregates of all selected entries of a single parameter
+pub struct Stats(AggregateRow);
+
+graphql_object!(Stats: () |&self| {
+    description: "Aggregates of the selected entries of a telemetry parameter"
+
+    field subsystem() -> &String as "Subsystem name" {
+        &self.0.subsystem
+    }
+
+    field parameter() -> &String as "Telemetry parameter" {
+        &self.0.parameter
+    }
+
+    field start() -> f64 as "Timestamp of the first entry" {
+        self.0.first_timestamp
+    }
+
+    field end() -> f64 as "Timestamp of the last entry" {
+        self.0.last_timestamp
+    }
+
+    field count() -> Option<i32> as "Number of entries" {
+        self.0.count.map(|count| count as i32)
+    }
+
+    field min() -> Option<f64> as "Smallest numeric value" {
+        self.0.min
+    }
+
+    field max() -> Option<f64> as "Largest numeric value" {
+        self.0.max
+    }
+
+    field mean() -> Option<f64> as "Mean of the numeric values" {
+        self.0.mean
+    }
+
+    field first() -> Option<f64> as "Numeric value of the first entry" {
+        self.0.first
+    }
+
+    field last() -> Option<f64> as "Numeric value of the last entry" {
+        self.0.last
+    }
+});
+
+/// Aggregates of the selected entries of a single parameter within a time bucket
+pub struct Bucket {
+    row: AggregateRow,
+    start: f64,
+    width: f64,
+}
+
+graphql_object!(Bucket: () |&self| {
+    description: "Aggregates of the entries of a telemetry parameter within a time bucket"
+
+    field subsystem() -> &String as "Subsystem name" {
+        &self.row.subsystem
+    }
+
+    field parameter() -> &String as "Telemetry parameter" {
+        &self.row.parameter
+    }
+
+    field start() -> f64 as "Start of the bucket (inclusive)" {
+        self.start
+    }
+
+    field end() -> f64 as "End of the bucket (exclusive)" {
+        self.start + self.width
+    }
+
+    field count() -> Option<i32> as "Number of entries" {
+        self.row.count.map(|count| count as i32)
+    }
+
+    field min() -> Option<f64> as "Smallest numeric value" {
+        self.row.min
+    }
+
+    field max() -> Option<f64> as "Largest numeric value" {
+        self.row.max
+    }
+
+    field mean() -> Option<f64> as "Mean of the numeric values" {
+        self.row.mean
+    }
+
+    field first() -> Option<f64> as "Numeric value of the first entry in the bucket" {
+        self.row.first
+    }
+
+    field last() -> Option<f64> as "Numeric value of the last entry in the bucket" {
+        self.row.last
+    }
+});
+
+/// Computes the requested aggregates (by default, all of them) of each parameter
+pub fn stats(
+    database: &Arc<DatabasePool>,
+    filter: Filter,
+    aggregates: Option<Vec<Aggregate>>,
+) -> FieldResult<Vec<Stats>> {
+    Ok(aggregate(database, filter, None, aggregates)?
+        .into_iter()
+        .map(Stats)
+        .collect())
+}
+
+/// Computes the requested aggregates (by default, all of them) of each parameter within
+/// consecutive buckets of the given width, starting from the filter's `timestamp_ge` (or 0)
+pub fn buckets(
+    database: &Arc<DatabasePool>,
+    filter: Filter,
+    width: f64,
+    aggregates: Option<Vec<Aggregate>>,
+) -> FieldResult<Vec<Bucket>> {
+    if !(width > 0.0) || !width.is_finite() {
+        return Err(FieldError::new(
+            format!("Invalid bucket width: {}", width),
+            Value::null(),
+        ));
+    }
+
+    let origin = filter.timestamp_ge.unwrap_or(0.0);
+    Ok(aggregate(database, filter, Some(width), aggregates)?
+        .into_iter()
+        .map(|row| Bucket {
+            start: origin + row.bucket.unwrap_or(0) as f64 * width,
+            row,
+            width,
+        })
+        .collect())
+}
+
+// Groups the selected entries by parameter and, if a width is given, by bucket
+fn aggregate(
+    database: &Arc<DatabasePool>,
+    filter: Filter,
+    width: Option<f64>,
+    aggregates: Option<Vec<Aggregate>>,
+) -> FieldResult<Vec<AggregateRow>> {
+    let aggregates = aggregates.unwrap_or_else(|| ALL_AGGREGATES.to_vec());
+    let column = |aggregate: Aggregate, expr: &str, name: &str| {
+        if aggregates.contains(&aggregate) {
+            format!("{} AS {}", expr, name)
+        } else {
+            format!("NULL AS {}", name)
+        }
+    };
+
+    // Only join on the first and last entries of each group if they're needed
+    let mut joins = String::new();
+    if aggregates.contains(&Aggregate::First) {
+        joins.push_str(
+            " LEFT JOIN telemetry f ON f.timestamp = g.first_timestamp \
+             AND f.subsystem = g.subsystem AND f.parameter = g.parameter",
+        );
+    }
+    if aggregates.contains(&Aggregate::Last) {
+        joins.push_str(
+            " LEFT JOIN telemetry l ON l.timestamp = g.last_timestamp \
+             AND l.subsystem = g.subsystem AND l.parameter = g.parameter",
+        );
+    }
+
+    // The bucket is NULL (so entries are only grouped by parameter) if no width is given
+    let query = format!(
+        "SELECT g.subsystem, g.parameter, g.bucket, g.first_timestamp, g.last_timestamp, \
+         {}, {}, {}, {}, {}, {} \
+         FROM (SELECT subsystem, parameter, \
+         CAST((timestamp - ?8) / ?7 AS INTEGER) AS bucket, \
+         MIN(timestamp) AS first_timestamp, MAX(timestamp) AS last_timestamp, \
+         COUNT(*) AS count, MIN(float_value) AS min, MAX(float_value) AS max, \
+         AVG(float_value) AS mean \
+         FROM telemetry \
+         WHERE (?1 IS NULL OR timestamp >= ?1) AND (?2 IS NULL OR timestamp <= ?2) \
+         AND (?3 IS NULL OR subsystem = ?3) AND (?4 IS NULL OR parameter = ?4) \
+         AND (?5 IS NULL OR float_value >= ?5) AND (?6 IS NULL OR float_value <= ?6) \
+         GROUP BY subsystem, parameter, bucket) g{} \
+         ORDER BY g.subsystem, g.parameter, g.bucket",
+        column(Aggregate::Count, "g.count", "count"),
+        column(Aggregate::Min, "g.min", "min"),
+        column(Aggregate::Max, "g.max", "max"),
+        column(Aggregate::Mean, "g.mean", "mean"),
+        column(Aggregate::First, "f.float_value", "first"),
+        column(Aggregate::Last, "l.float_value", "last"),
+        joins
+    );
+
+    let rows = sql_query(query)
+        .bind::<Nullable<Double>, _>(filter.timestamp_ge)
+        .bind::<Nullable<Double>, _>(filter.timestamp_le)
+        .bind::<Nullable<Text>, _>(filter.subsystem)
+        .bind::<Nullable<Text>, _>(filter.parameter)
+        .bind::<Nullable<Double>, _>(filter.value_ge)
+        .bind::<Nullable<Double>, _>(filter.value_le)
+        .bind::<Nullable<Double>, _>(width)
+        .bind::<Double, _>(filter.timestamp_ge.unwrap_or(0.0))
+        .load::<AggregateRow>(&database.reader()?.connection)?;
+
+    Ok(rows)
+}
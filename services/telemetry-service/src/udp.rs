@@ -14,65 +14,145 @@
 // limitations under the License.
 //
 
-use kubos_telemetry_db::Database;
+use ingest::{IngestProtocol, IngestStats};
+use kubos_telemetry_db::{self, DatabasePool, NewEntry, TypedValue};
+use limits::Limits;
 use serde_json::{self, Value};
 use std::net::{SocketAddr, UdpSocket};
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
+
+// Largest possible UDP payload
+pub const MAX_MESSAGE_SIZE: usize = 65_507;
+
+struct Message {
+    timestamp: Option<f64>,
+    subsystem: String,
+    parameter: String,
+    value: String,
+}
 
 pub struct DirectUdp {
-    db: Arc<Mutex<Database>>,
+    db: Arc<DatabasePool>,
+    limits: Arc<Limits>,
+    stats: Arc<IngestStats>,
 }
 
 impl DirectUdp {
-    pub fn new(db: Arc<Mutex<Database>>) -> Self {
-        DirectUdp { db }
+    pub fn new(db: Arc<DatabasePool>, limits: Arc<Limits>, stats: Arc<IngestStats>) -> Self {
+        DirectUdp { db, limits, stats }
     }
 
     pub fn start(&self, url: String) {
         let socket = UdpSocket::bind(url.parse::<SocketAddr>().unwrap()).unwrap();
         info!("Direct UDP listening on: {}", socket.local_addr().unwrap());
 
+        let mut buf = vec![0; MAX_MESSAGE_SIZE];
         loop {
             // Wait for an incoming message
-            let mut buf = [0; 4096];
-            let (size, _peer) = socket
+            let (size, peer) = socket
                 .recv_from(&mut buf)
                 .map_err(|err| format!("Failed to receive a message: {}", err))
                 .unwrap();
 
-            if let Ok(msg) = serde_json::from_slice(&buf[0..(size)]) {
-                // Go process the request
-                let _res = self.process(msg);
+            // Go process the request
+            let result = serde_json::from_slice(&buf[0..(size)])
+                .map_err(|err| format!("Failed to parse message: {}", err))
+                .and_then(|msg| self.process(msg));
+            if let Err(ref err) = result {
+                warn!(
+                    "Failed to process direct UDP message from {}: {}",
+                    peer, err
+                );
             }
+            self.stats.record(peer, IngestProtocol::Json, &result);
         }
     }
 
-    fn process(&self, message: Value) -> Result<(), String> {
-        let timestamp = serde_json::from_value::<f64>(message["timestamp"].clone()).ok();
+    // Returns the number of entries inserted
+    fn process(&self, message: Value) -> Result<usize, String> {
+        match message {
+            // Multiple entries are inserted in a single transaction
+            Value::Array(messages) => {
+                let messages = messages
+                    .iter()
+                    .map(parse_message)
+                    .collect::<Result<Vec<Message>, String>>()?;
 
-        let subsystem = serde_json::from_value::<String>(message["subsystem"].clone())
-            .map_err(|err| format!("Failed to parse subsystem parameter: {}", err))?;
+                let now = kubos_telemetry_db::systime();
+                let entries: Vec<NewEntry> = messages
+                    .iter()
+                    .map(|message| NewEntry {
+                        timestamp: message.timestamp.unwrap_or(now),
+                        subsystem: &message.subsystem,
+                        parameter: &message.parameter,
+                        value: &message.value,
+                    })
+                    .collect();
 
-        let param = serde_json::from_value::<String>(message["parameter"].clone())
-            .map_err(|err| format!("Failed to parse parameter parameter: {}", err))?;
+                let db = self.db.writer().map_err(|err| format!("{}", err))?;
+                db.insert_bulk(&entries).map_err(|err| format!("{}", err))?;
 
-        let value = serde_json::from_value::<String>(message["value"].clone())
-            .map_err(|err| format!("Failed to parse value parameter: {}", err))?;
+                for entry in &entries {
+                    self.limits.check(
+                        &db,
+                        entry.timestamp,
+                        entry.subsystem,
+                        entry.parameter,
+                        &TypedValue::infer(entry.value),
+                    );
+                }
 
-        if let Some(time) = timestamp {
-            self.db
-                .lock()
-                .map_err(|err| format!("{}", err))?
-                .insert(time, &subsystem, &param, &value)
-                .map_err(|err| format!("{}", err))?;
-        } else {
-            self.db
-                .lock()
-                .map_err(|err| format!("{}", err))?
-                .insert_systime(&subsystem, &param, &value)
+                Ok(entries.len())
+            }
+            message => {
+                let message = parse_message(&message)?;
+                let timestamp = message
+                    .timestamp
+                    .unwrap_or_else(kubos_telemetry_db::systime);
+                let db = self.db.writer().map_err(|err| format!("{}", err))?;
+                db.insert(
+                    timestamp,
+                    &message.subsystem,
+                    &message.parameter,
+                    &message.value,
+                )
                 .map_err(|err| format!("{}", err))?;
-        }
 
-        Ok(())
+                self.limits.check(
+                    &db,
+                    timestamp,
+                    &message.subsystem,
+                    &message.parameter,
+                    &TypedValue::infer(&message.value),
+                );
+
+                Ok(1)
+            }
+        }
     }
 }
+
+fn parse_message(message: &Value) -> Result<Message, String> {
+    let timestamp = serde_json::from_value::<f64>(message["timestamp"].clone()).ok();
+
+    let subsystem = serde_json::from_value::<String>(message["subsystem"].clone())
+        .map_err(|err| format!("Failed to parse subsystem parameter: {}", err))?;
+
+    let parameter = serde_json::from_value::<String>(message["parameter"].clone())
+        .map_err(|err| format!("Failed to parse parameter parameter: {}", err))?;
+
+    // Numbers and booleans are stored with their types, as are strings which contain them
+    let value = match message["value"] {
+        Value::String(ref value) => value.clone(),
+        Value::Number(ref value) => value.to_string(),
+        Value::Bool(value) => value.to_string(),
+        ref value => return Err(format!("Failed to parse value parameter: {}", value)),
+    };
+
+    Ok(Message {
+        timestamp,
+        subsystem,
+        parameter,
+        value,
+    })
+}
@@ -0,0 +1,280 @@
+//
+// Copyright (C) 2018 Kubos Corporation
+//
+// Licensed under the Apache License, Version 2.0 (the "License")
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+#[macro_use]
+extern crate serde_json;
+extern crate tempfile;
+
+mod utils;
+
+use std::env;
+use std::fs;
+use std::net::UdpSocket;
+use std::process::Command;
+use std::sync::mpsc::{channel, Receiver};
+use std::thread;
+use std::time::Duration;
+use tempfile::TempDir;
+use utils::*;
+
+// Answers every query sent to the given port with `response`, passing the queries on
+fn mock_service(port: u16, response: serde_json::Value) -> Receiver<String> {
+    let socket = UdpSocket::bind(("127.0.0.1", port)).unwrap();
+    let (tx, rx) = channel();
+
+    thread::spawn(move || {
+        let mut buf = [0; 4096];
+        while let Ok((amt, source)) = socket.recv_from(&mut buf) {
+            let _ = tx.send(String::from_utf8_lossy(&buf[0..amt]).into_owned());
+            socket
+                .send_to(response.to_string().as_bytes(), &source)
+                .unwrap();
+        }
+    });
+
+    rx
+}
+
+fn collector_config(mock_port: u16) -> String {
+    format!(
+        r#"
+        [[telemetry-service.collectors]]
+        service = "mock-service"
+        subsystem = "eps"
+        query = "{{ telemetry {{ voltage, current, mode, cells {{ ok }} }} }}"
+        interval = 60
+
+        [telemetry-service.collectors.parameters]
+        voltage = "telemetry.voltage"
+        current = "telemetry.current"
+        mode = "telemetry.mode"
+        cell = "telemetry.cells.1.ok"
+        missing = "telemetry.missing"
+
+        [telemetry-service.limits.eps.voltage]
+        red_high = 8.4
+
+        [mock-service.addr]
+        ip = "127.0.0.1"
+        port = {}
+        "#,
+        mock_port
+    )
+}
+
+// Returns the stored eps entries, sorted by parameter
+fn collected(port: u16) -> Vec<serde_json::Value> {
+    let res = do_query(
+        Some(port),
+        r#"{telemetry(subsystem: "eps"){parameter,value,valueType}}"#,
+    );
+    let mut entries = res["data"]["telemetry"].as_array().unwrap().clone();
+    entries.sort_by_key(|entry| entry["parameter"].as_str().unwrap().to_owned());
+    entries
+}
+
+#[test]
+fn test_collector() {
+    let db_dir = TempDir::new().unwrap();
+    let db_path = db_dir.path().join("test.db");
+
+    let db = db_path.to_str().unwrap();
+    let port = 8111;
+    let udp = 8121;
+    let mock = 8131;
+
+    let queries = mock_service(
+        mock,
+        json!({
+            "data": {
+                "telemetry": {
+                    "voltage": 8.5,
+                    "current": 120,
+                    "mode": "NORMAL",
+                    "cells": [{"ok": true}, {"ok": false}]
+                }
+            },
+            "errors": []
+        }),
+    );
+
+    let (handle, sender) = setup_with_config(
+        Some(db),
+        Some(port),
+        Some(udp),
+        None,
+        &collector_config(mock),
+    );
+
+    let query = queries.recv_timeout(Duration::from_secs(5));
+    thread::sleep(Duration::from_millis(200));
+    let entries = collected(port);
+    let alarms = do_query(Some(port), "{alarms{subsystem,parameter,state,value}}");
+
+    teardown(handle, sender);
+
+    assert_eq!(
+        query.unwrap(),
+        "{ telemetry { voltage, current, mode, cells { ok } } }"
+    );
+    assert_eq!(
+        entries,
+        vec![
+            json!({"parameter": "cell", "value": "false", "valueType": "BOOLEAN"}),
+            json!({"parameter": "current", "value": "120", "valueType": "INTEGER"}),
+            json!({"parameter": "mode", "value": "NORMAL", "valueType": "STRING"}),
+            json!({"parameter": "voltage", "value": "8.5", "valueType": "FLOAT"}),
+        ]
+    );
+    assert_eq!(
+        alarms,
+        json!({
+            "data": {
+                "alarms": [{
+                    "subsystem": "eps",
+                    "parameter": "voltage",
+                    "state": "RED_HIGH",
+                    "value": 8.5
+                }]
+            }
+        })
+    );
+}
+
+#[test]
+fn test_collector_partial_data() {
+    let db_dir = TempDir::new().unwrap();
+    let db_path = db_dir.path().join("test.db");
+
+    let db = db_path.to_str().unwrap();
+    let port = 8112;
+    let udp = 8122;
+    let mock = 8132;
+
+    let queries = mock_service(
+        mock,
+        json!({
+            "data": {
+                "telemetry": {
+                    "voltage": 7.5,
+                    "current": null,
+                    "mode": "NORMAL",
+                    "cells": null
+                }
+            },
+            "errors": [{"message": "Failed to read current", "path": ["telemetry", "current"]}]
+        }),
+    );
+
+    let (handle, sender) = setup_with_config(
+        Some(db),
+        Some(port),
+        Some(udp),
+        None,
+        &collector_config(mock),
+    );
+
+    queries.recv_timeout(Duration::from_secs(5)).unwrap();
+    thread::sleep(Duration::from_millis(200));
+    let entries = collected(port);
+
+    teardown(handle, sender);
+
+    assert_eq!(
+        entries,
+        vec![
+            json!({"parameter": "mode", "value": "NORMAL", "valueType": "STRING"}),
+            json!({"parameter": "voltage", "value": "7.5", "valueType": "FLOAT"}),
+        ]
+    );
+}
+
+#[test]
+fn test_collector_service_down() {
+    let db_dir = TempDir::new().unwrap();
+    let db_path = db_dir.path().join("test.db");
+
+    let db = db_path.to_str().unwrap();
+    let port = 8113;
+    let udp = 8123;
+
+    // Nothing is listening on the collector's port, which mustn't stop the service
+    // from answering queries
+    let (handle, sender) = setup_with_config(
+        Some(db),
+        Some(port),
+        Some(udp),
+        None,
+        &collector_config(8133),
+    );
+
+    thread::sleep(Duration::from_millis(200));
+    let entries = collected(port);
+
+    teardown(handle, sender);
+
+    assert!(entries.is_empty());
+}
+
+#[test]
+fn test_collector_unknown_service() {
+    let dir = TempDir::new().unwrap();
+    let config_path = dir.path().join("config.toml");
+    fs::write(
+        &config_path,
+        format!(
+            r#"
+            [telemetry-service]
+            database = "{}"
+            addr = {{ ip = "127.0.0.1", port = 8114 }}
+
+            [[telemetry-service.collectors]]
+            service = "missing-service"
+            subsystem = "eps"
+            query = "{{ telemetry {{ voltage }} }}"
+            interval = 60
+
+            [telemetry-service.collectors.parameters]
+            voltage = "telemetry.voltage"
+            "#,
+            dir.path().join("test.db").display()
+        ),
+    ).unwrap();
+
+    let mut telem_path = env::current_exe().unwrap();
+    telem_path.pop();
+    telem_path.set_file_name("telemetry-service");
+    let mut telem = Command::new(telem_path)
+        .arg("-c")
+        .arg(&config_path)
+        .spawn()
+        .unwrap();
+
+    // The service refuses to start, rather than querying a made-up address
+    let mut status = None;
+    for _ in 0..50 {
+        status = telem.try_wait().unwrap();
+        if status.is_some() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+    if status.is_none() {
+        telem.kill().unwrap();
+    }
+
+    assert_eq!(status.map(|status| status.success()), Some(false));
+}
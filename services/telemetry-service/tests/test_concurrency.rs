@@ -0,0 +1,124 @@
+//
+// Copyright (C) 2018 Kubos Corporation
+//
+// Licensed under the Apache License, Version 2.0 (the "License")
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+#[macro_use]
+extern crate serde_json;
+extern crate tempfile;
+
+mod utils;
+
+use serde_json::ser;
+use std::fs;
+use std::net::UdpSocket;
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
+use tempfile::TempDir;
+use utils::*;
+
+#[test]
+fn test_wal_mode() {
+    let db_dir = TempDir::new().unwrap();
+    let db_path = db_dir.path().join("test.db");
+
+    let db = db_path.to_str().unwrap();
+    let port = 8111;
+    let udp = 8121;
+
+    let (handle, sender) = setup(Some(db), Some(port), Some(udp), None);
+
+    // The service only answers once it has set up the database
+    do_query(Some(port), "{dbStats{entries}}");
+
+    // The journal mode is stored in the database file, so is visible to other processes
+    let mode = Command::new("sqlite3")
+        .arg(db)
+        .arg("PRAGMA journal_mode;")
+        .output()
+        .expect("SQL cmd failed");
+
+    teardown(handle, sender);
+
+    assert_eq!(String::from_utf8_lossy(&mode.stdout).trim(), "wal");
+}
+
+#[test]
+fn test_udp_insert_during_export() {
+    let db_dir = TempDir::new().unwrap();
+    let db_path = db_dir.path().join("test.db");
+
+    let db = db_path.to_str().unwrap();
+    let port = 8112;
+    let udp = 8122;
+
+    // The table is created with typed value columns, so that the service doesn't spend long
+    // migrating it on startup
+    let sql = r"
+        DROP TABLE telemetry;
+        CREATE TABLE telemetry (
+            timestamp INTEGER NOT NULL,
+            subsystem VARCHAR(255) NOT NULL,
+            parameter VARCHAR(255) NOT NULL,
+            value VARCHAR(255) NOT NULL,
+            value_type VARCHAR(16) NOT NULL DEFAULT 'string',
+            int_value BIGINT,
+            float_value DOUBLE,
+            blob_value BLOB,
+            PRIMARY KEY (timestamp, subsystem, parameter));
+        with recursive n(i) as (select 0 union all select i + 1 from n where i < 19999)
+        insert into telemetry (timestamp, subsystem, parameter, value)
+        select i, 'eps', 'voltage', 'value' || i from n;
+    ";
+    let (handle, sender) = setup(Some(db), Some(port), Some(udp), Some(sql));
+
+    let output_dir = TempDir::new().unwrap();
+    let output_path = output_dir.path().join("output.csv");
+
+    let query = format!(
+        r#"{{
+        routedTelemetry(output: "{}", compress: false, format: CSV, timestampLe: 19999)
+    }}"#,
+        output_path.to_str().unwrap()
+    );
+    let export = thread::spawn(move || do_query(Some(port), &query));
+
+    // Insert through the direct UDP port while the export is running
+    thread::sleep(Duration::from_millis(5));
+    let socket = UdpSocket::bind("0.0.0.0:0").unwrap();
+    let service = format!("0.0.0.0:{}", udp);
+    for i in 0..10 {
+        let message =
+            json!({"timestamp": 20000 + i, "subsystem": "gps", "parameter": "lock", "value": i});
+        socket
+            .send_to(&ser::to_vec(&message).unwrap(), &service)
+            .unwrap();
+    }
+
+    let exported = export.join().unwrap();
+    thread::sleep(Duration::from_millis(100));
+    let inserted = do_query(Some(port), r#"{telemetry(subsystem: "gps"){timestamp}}"#);
+
+    teardown(handle, sender);
+
+    assert_eq!(
+        exported,
+        json!({"data": {"routedTelemetry": output_path.to_str().unwrap()}})
+    );
+    let contents = fs::read_to_string(output_path).unwrap();
+    assert_eq!(contents.lines().skip(1).count(), 20000);
+
+    assert_eq!(inserted["data"]["telemetry"].as_array().unwrap().len(), 10);
+}
@@ -0,0 +1,243 @@
+//
+// Copyright (C) 2018 Kubos Corporation
+//
+// Licensed under the Apache License, Version 2.0 (the "License")
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+#[macro_use]
+extern crate serde_json;
+extern crate tempfile;
+
+mod utils;
+
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+use utils::*;
+
+static SQL: &'static str = r"
+insert into telemetry values(1000, 'eps', 'voltage', '3.3');
+insert into telemetry values(1001, 'eps', 'current', '3.4');
+insert into telemetry values(1002, 'eps', 'voltage', '3.2');
+insert into telemetry values(1003, 'eps', 'current', '3.5');
+insert into telemetry values(1004, 'eps', 'voltage', '3.6');
+insert into telemetry values(1000, 'mcu', 'voltage', '4.3');
+insert into telemetry values(1001, 'mcu', 'current', '4.4');
+insert into telemetry values(1002, 'mcu', 'voltage', '4.2');
+insert into telemetry values(1003, 'mcu', 'current', '4.5');
+insert into telemetry values(1004, 'mcu', 'voltage', '4.6');
+";
+
+fn export(port: u16, output: &Path, args: &str) -> serde_json::Value {
+    let query = format!(
+        r#"{{
+        routedTelemetry(output: "{}", compress: false, format: CSV, {})
+    }}"#,
+        output.to_str().unwrap(),
+        args
+    );
+    do_query(Some(port), &query)
+}
+
+fn timestamps(output: &Path) -> Vec<String> {
+    fs::read_to_string(output)
+        .unwrap()
+        .lines()
+        .skip(1)
+        .map(|line| line.split(',').next().unwrap().to_owned())
+        .collect()
+}
+
+#[test]
+fn test_cursor_export_and_ack() {
+    let db_dir = TempDir::new().unwrap();
+    let db_path = db_dir.path().join("test.db");
+
+    let db = db_path.to_str().unwrap();
+    let port = 8111;
+    let udp = 8121;
+
+    let (handle, sender) = setup(Some(db), Some(port), Some(udp), Some(SQL));
+
+    let output_dir = TempDir::new().unwrap();
+    let first = output_dir.path().join("first.csv");
+    let second = output_dir.path().join("second.csv");
+
+    // A new cursor exports the earliest inserted entries first
+    export(port, &first, r#"cursor: "ground-a", limit: 4"#);
+
+    let ack = do_query(
+        Some(port),
+        r#"mutation { ackCursor(cursor: "ground-a") { success, errors, acked } }"#,
+    );
+
+    export(port, &second, r#"cursor: "ground-a""#);
+
+    let cursors = do_query(Some(port), "{cursors{name,acked,exported}}");
+
+    teardown(handle, sender);
+
+    assert_eq!(timestamps(&first), vec!["1000", "1001", "1002", "1003"]);
+    assert_eq!(
+        ack,
+        json!({
+            "data": {
+                "ackCursor": { "success": true, "errors": "", "acked": 4.0 }
+            }
+        })
+    );
+    assert_eq!(
+        timestamps(&second),
+        vec!["1004", "1000", "1001", "1002", "1003", "1004"]
+    );
+    assert_eq!(
+        cursors,
+        json!({
+            "data": {
+                "cursors": [{ "name": "ground-a", "acked": 4.0, "exported": 10.0 }]
+            }
+        })
+    );
+}
+
+#[test]
+fn test_cursor_unacked_export_repeats() {
+    let db_dir = TempDir::new().unwrap();
+    let db_path = db_dir.path().join("test.db");
+
+    let db = db_path.to_str().unwrap();
+    let port = 8112;
+    let udp = 8122;
+
+    let (handle, sender) = setup(Some(db), Some(port), Some(udp), Some(SQL));
+
+    let output_dir = TempDir::new().unwrap();
+    let first = output_dir.path().join("first.csv");
+    let second = output_dir.path().join("second.csv");
+    let other = output_dir.path().join("other.csv");
+
+    export(port, &first, r#"cursor: "ground-a", subsystem: "eps""#);
+    export(port, &second, r#"cursor: "ground-a", subsystem: "eps""#);
+    // Cursors are independent of each other
+    export(port, &other, r#"cursor: "ground-b", subsystem: "eps", limit: 1"#);
+
+    teardown(handle, sender);
+
+    let expected = vec!["1000", "1001", "1002", "1003", "1004"];
+    assert_eq!(timestamps(&first), expected);
+    assert_eq!(timestamps(&second), expected);
+    assert_eq!(timestamps(&other), vec!["1000"]);
+}
+
+#[test]
+fn test_cursor_ack_sequence() {
+    let db_dir = TempDir::new().unwrap();
+    let db_path = db_dir.path().join("test.db");
+
+    let db = db_path.to_str().unwrap();
+    let port = 8113;
+    let udp = 8123;
+
+    let (handle, sender) = setup(Some(db), Some(port), Some(udp), Some(SQL));
+
+    let output_dir = TempDir::new().unwrap();
+    let output = output_dir.path().join("output.csv");
+
+    // Nothing has been exported yet, so there's nothing to acknowledge
+    let unexported = do_query(
+        Some(port),
+        r#"mutation { ackCursor(cursor: "ground-a") { success, errors, acked } }"#,
+    );
+
+    let invalid = do_query(
+        Some(port),
+        r#"mutation { ackCursor(cursor: "ground-a", sequence: 1.5) { success, errors, acked } }"#,
+    );
+
+    // An explicit sequence number creates the cursor
+    let acked = do_query(
+        Some(port),
+        r#"mutation { ackCursor(cursor: "ground-a", sequence: 8) { success, errors, acked } }"#,
+    );
+
+    export(port, &output, r#"cursor: "ground-a""#);
+
+    teardown(handle, sender);
+
+    assert_eq!(
+        unexported,
+        json!({
+            "data": {
+                "ackCursor": {
+                    "success": false,
+                    "errors": "Nothing has been exported for cursor ground-a",
+                    "acked": null
+                }
+            }
+        })
+    );
+    assert_eq!(
+        invalid["data"]["ackCursor"]["errors"],
+        json!("Invalid sequence number: 1.5")
+    );
+    assert_eq!(acked["data"]["ackCursor"]["acked"], json!(8.0));
+    assert_eq!(timestamps(&output), vec!["1003", "1004"]);
+}
+
+#[test]
+fn test_cursor_exports_late_entries() {
+    let db_dir = TempDir::new().unwrap();
+    let db_path = db_dir.path().join("test.db");
+
+    let db = db_path.to_str().unwrap();
+    let port = 8114;
+    let udp = 8124;
+
+    let (handle, sender) = setup(Some(db), Some(port), Some(udp), Some(SQL));
+
+    let output_dir = TempDir::new().unwrap();
+    let first = output_dir.path().join("first.csv");
+    let second = output_dir.path().join("second.csv");
+
+    export(port, &first, r#"cursor: "ground-a""#);
+    do_query(
+        Some(port),
+        r#"mutation { ackCursor(cursor: "ground-a") { success } }"#,
+    );
+
+    // Entries inserted after the acknowledgement are exported, even if they're older
+    do_query(
+        Some(port),
+        r#"mutation { insert(timestamp: 999, subsystem: "eps", parameter: "voltage", value: "3.0") { success } }"#,
+    );
+    do_query(
+        Some(port),
+        r#"mutation { insert(timestamp: 1005, subsystem: "eps", parameter: "voltage", value: "3.1") { success } }"#,
+    );
+    export(port, &second, r#"cursor: "ground-a""#);
+
+    let cursors = do_query(Some(port), "{cursors{name,acked,exported}}");
+
+    teardown(handle, sender);
+
+    assert_eq!(timestamps(&first).len(), 10);
+    assert_eq!(timestamps(&second), vec!["999", "1005"]);
+    assert_eq!(
+        cursors,
+        json!({
+            "data": {
+                "cursors": [{ "name": "ground-a", "acked": 10.0, "exported": 12.0 }]
+            }
+        })
+    );
+}
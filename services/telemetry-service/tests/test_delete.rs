@@ -57,7 +57,6 @@ fn test_delete_ge() {
         }"#;
 
     let mutation_expected = json!({
-            "errors": "",
             "data": {
                 "delete": {
                     "entriesDeleted": 7,
@@ -77,7 +76,6 @@ fn test_delete_ge() {
             }
         }"#;
     let query_expected = json!({
-            "errors": "",
             "data": {
                 "telemetry": [
                     {
@@ -135,7 +133,6 @@ fn test_delete_le() {
         }"#;
 
     let mutation_expected = json!({
-            "errors": "",
             "data": {
                 "delete": {
                     "entriesDeleted": 9,
@@ -155,7 +152,6 @@ fn test_delete_le() {
             }
         }"#;
     let query_expected = json!({
-            "errors": "",
             "data": {
                 "telemetry": [
                     {
@@ -201,7 +197,6 @@ fn test_delete_range() {
         }"#;
 
     let mutation_expected = json!({
-            "errors": "",
             "data": {
                 "delete": {
                     "entriesDeleted": 9,
@@ -221,7 +216,6 @@ fn test_delete_range() {
             }
         }"#;
     let query_expected = json!({
-            "errors": "",
             "data": {
                 "telemetry": [
                     {
@@ -267,7 +261,6 @@ fn test_delete_subsystem() {
         }"#;
 
     let mutation_expected = json!({
-            "errors": "",
             "data": {
                 "delete": {
                     "entriesDeleted": 4,
@@ -285,7 +278,6 @@ fn test_delete_subsystem() {
             }
         }"#;
     let query_expected = json!({
-            "errors": "",
             "data": {
                 "telemetry": [
                     {
@@ -347,7 +339,6 @@ fn test_delete_parameter() {
         }"#;
 
     let mutation_expected = json!({
-            "errors": "",
             "data": {
                 "delete": {
                     "entriesDeleted": 6,
@@ -365,7 +356,6 @@ fn test_delete_parameter() {
             }
         }"#;
     let query_expected = json!({
-            "errors": "",
             "data": {
                 "telemetry": [
                     {
@@ -19,17 +19,21 @@ extern crate serde_json;
 extern crate tempfile;
 
 mod utils;
+use tempfile::TempDir;
 use utils::*;
 
 #[test]
 fn test() {
-    let (handle, sender) = setup(None, None, None, None);
+    let db_dir = TempDir::new().unwrap();
+    let db_path = db_dir.path().join("test.db");
+    let db = db_path.to_str().unwrap();
+
+    let (handle, sender) = setup(Some(db), None, None, None);
     let res = do_query(None, "{telemetry{timestamp,subsystem,parameter,value}}");
     teardown(handle, sender);
     assert_eq!(
         res,
         json!({
-            "errors": "",
             "data": {
                 "telemetry":[]
             }
@@ -19,6 +19,7 @@ extern crate serde_json;
 extern crate tempfile;
 
 mod utils;
+use tempfile::TempDir;
 use utils::*;
 
 static SQL: &'static str = r"
@@ -29,13 +30,16 @@ insert into telemetry values(1001, 'eps', 'voltage', '3.4');
 
 #[test]
 fn test() {
-    let (handle, sender) = setup(None, None, None, Some(SQL));
+    let db_dir = TempDir::new().unwrap();
+    let db_path = db_dir.path().join("test.db");
+    let db = db_path.to_str().unwrap();
+
+    let (handle, sender) = setup(Some(db), None, None, Some(SQL));
     let res = do_query(None, "{telemetry(parameter: \"voltage\"){parameter,value}}");
     teardown(handle, sender);
     assert_eq!(
         res,
         json!({
-            "errors": "",
             "data": {
                 "telemetry": [
                     {"parameter":"voltage","value":"3.4"},
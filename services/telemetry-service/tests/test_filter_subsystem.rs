@@ -19,6 +19,7 @@ extern crate serde_json;
 extern crate tempfile;
 
 mod utils;
+use tempfile::TempDir;
 use utils::*;
 
 static SQL: &'static str = r"
@@ -29,7 +30,11 @@ insert into telemetry values(1011, 'eps', 'voltage', '10');
 
 #[test]
 fn test() {
-    let (handle, sender) = setup(None, None, None, Some(SQL));
+    let db_dir = TempDir::new().unwrap();
+    let db_path = db_dir.path().join("test.db");
+    let db = db_path.to_str().unwrap();
+
+    let (handle, sender) = setup(Some(db), None, None, Some(SQL));
     let res = do_query(
         None,
         "{telemetry(subsystem: \"gps\"){timestamp,subsystem,parameter,value}}",
@@ -38,7 +43,6 @@ fn test() {
     assert_eq!(
         res,
         json!({
-            "errors": "",
             "data": {
                 "telemetry":[
                     {"timestamp":1010.0,"subsystem":"gps","parameter":"x_position","value":"-1.0"}
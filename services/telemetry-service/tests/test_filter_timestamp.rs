@@ -50,7 +50,6 @@ fn test_ge() {
     assert_eq!(
         ge_res,
         json!({
-            "errors": "",
             "data": {
                 "telemetry": [
                     {"value":"3.8"},
@@ -79,7 +78,6 @@ fn test_le() {
     assert_eq!(
         le_res,
         json!({
-            "errors": "",
             "data": {
                 "telemetry": [
                     {"value":"3.5"},
@@ -112,7 +110,6 @@ fn test_range() {
     assert_eq!(
         range_res,
         json!({
-            "errors": "",
             "data": {
                 "telemetry": [
                     {"value":"3.6"},
@@ -145,7 +142,6 @@ fn test_single() {
     assert_eq!(
         single_res,
         json!({
-            "errors": "",
             "data": {
                 "telemetry": [
                     {"value":"3.6"},
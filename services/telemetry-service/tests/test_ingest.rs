@@ -0,0 +1,218 @@
+//
+// Copyright (C) 2018 Kubos Corporation
+//
+// Licensed under the Apache License, Version 2.0 (the "License")
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+extern crate cbor_protocol;
+extern crate serde_cbor;
+#[macro_use]
+extern crate serde_json;
+extern crate tempfile;
+
+mod utils;
+
+use cbor_protocol::Protocol;
+use serde_cbor::Value;
+use std::net::{SocketAddr, UdpSocket};
+use std::thread;
+use std::time::Duration;
+use tempfile::TempDir;
+use utils::*;
+
+// Sends a CBOR message and waits for its acknowledgment
+fn send_cbor(client: &Protocol, cbor_port: u16, message: &[u8]) -> Value {
+    let service: SocketAddr = format!("127.0.0.1:{}", cbor_port).parse().unwrap();
+    client.send_message(message, service).unwrap();
+    let (_, ack) = client
+        .recv_message_peer_timeout(Duration::from_secs(5))
+        .unwrap();
+    ack
+}
+
+fn ack(id: u64, entries: u64, error: Option<&str>) -> Value {
+    Value::Array(vec![
+        Value::String("ack".to_owned()),
+        Value::U64(id),
+        Value::U64(entries),
+        error.map_or(Value::Null, |err| Value::String(err.to_owned())),
+    ])
+}
+
+#[test]
+fn test_cbor_samples() {
+    let db_dir = TempDir::new().unwrap();
+    let db_path = db_dir.path().join("test.db");
+
+    let db = db_path.to_str().unwrap();
+    let port = 8111;
+    let udp = 8121;
+    let cbor = 8131;
+
+    let (handle, sender) = setup_with_config(
+        Some(db),
+        Some(port),
+        Some(udp),
+        None,
+        &format!("cbor_port = {}", cbor),
+    );
+
+    let client = Protocol::new("127.0.0.1:0".to_owned(), 4096);
+
+    let define =
+        serde_cbor::to_vec(&("define", 1, 1, "eps", "voltage", 2, "eps", "enabled")).unwrap();
+    let defined = send_cbor(&client, cbor, &define);
+
+    let samples = serde_cbor::to_vec(&(
+        "samples",
+        2,
+        (1000, 1, 3.3),
+        (1001, 2, true),
+        (1001.5, 1, 3),
+        (1002, "radio", "frame", Value::Bytes(vec![0x0A, 0xFF, 0x10])),
+    ))
+    .unwrap();
+    let inserted = send_cbor(&client, cbor, &samples);
+
+    let res = do_query(
+        Some(port),
+        "{telemetry{timestamp,subsystem,parameter,value,valueType}}",
+    );
+
+    teardown(handle, sender);
+
+    assert_eq!(defined, ack(1, 0, None));
+    assert_eq!(inserted, ack(2, 4, None));
+    assert_eq!(
+        res,
+        json!({
+            "data": {
+                "telemetry": [
+                    {"timestamp": 1002.0, "subsystem": "radio", "parameter": "frame", "value": "0aff10", "valueType": "BLOB"},
+                    {"timestamp": 1001.5, "subsystem": "eps", "parameter": "voltage", "value": "3", "valueType": "INTEGER"},
+                    {"timestamp": 1001.0, "subsystem": "eps", "parameter": "enabled", "value": "true", "valueType": "BOOLEAN"},
+                    {"timestamp": 1000.0, "subsystem": "eps", "parameter": "voltage", "value": "3.3", "valueType": "FLOAT"},
+                ]
+            }
+        })
+    );
+}
+
+#[test]
+fn test_cbor_unknown_id() {
+    let db_dir = TempDir::new().unwrap();
+    let db_path = db_dir.path().join("test.db");
+
+    let db = db_path.to_str().unwrap();
+    let port = 8112;
+    let udp = 8122;
+    let cbor = 8132;
+
+    let (handle, sender) = setup_with_config(
+        Some(db),
+        Some(port),
+        Some(udp),
+        None,
+        &format!("cbor_port = {}", cbor),
+    );
+
+    let client = Protocol::new("127.0.0.1:0".to_owned(), 4096);
+
+    let define = serde_cbor::to_vec(&("define", 1, 1, "eps", "voltage")).unwrap();
+    send_cbor(&client, cbor, &define);
+
+    // None of the samples are inserted if any of them are invalid
+    let samples = serde_cbor::to_vec(&("samples", 2, (1000, 1, 3.3), (1000, 7, 5))).unwrap();
+    let inserted = send_cbor(&client, cbor, &samples);
+
+    let res = do_query(Some(port), "{telemetry{timestamp}}");
+
+    teardown(handle, sender);
+
+    assert_eq!(inserted, ack(2, 0, Some("Unknown parameter ID: 7")));
+    assert_eq!(res, json!({"data": {"telemetry": []}}));
+}
+
+#[test]
+fn test_ingest_stats() {
+    let db_dir = TempDir::new().unwrap();
+    let db_path = db_dir.path().join("test.db");
+
+    let db = db_path.to_str().unwrap();
+    let port = 8113;
+    let udp = 8123;
+    let cbor = 8133;
+
+    let (handle, sender) = setup_with_config(
+        Some(db),
+        Some(port),
+        Some(udp),
+        None,
+        &format!("cbor_port = {}", cbor),
+    );
+
+    // Without an acknowledgment ID, nothing is sent back
+    let client = Protocol::new("127.0.0.1:0".to_owned(), 4096);
+    let cbor_service: SocketAddr = format!("127.0.0.1:{}", cbor).parse().unwrap();
+    let samples = serde_cbor::to_vec(&(
+        "samples",
+        (),
+        ((), "eps", "voltage", 3.3),
+        ((), "eps", "current", 0.5),
+    ))
+    .unwrap();
+    client.send_message(&samples, cbor_service).unwrap();
+
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let json_service = format!("127.0.0.1:{}", udp);
+    let message = json!([
+        {"subsystem": "gps", "parameter": "lock", "value": 1},
+        {"subsystem": "gps", "parameter": "sats", "value": 6},
+        {"subsystem": "gps", "parameter": "fix", "value": 3},
+    ]);
+    socket
+        .send_to(&serde_json::to_vec(&message).unwrap(), &json_service)
+        .unwrap();
+    socket.send_to(b"{not json", &json_service).unwrap();
+
+    thread::sleep(Duration::from_millis(200));
+
+    let res = do_query(
+        Some(port),
+        "{ingestStats{sender,protocol,messages,entries,errors,lastError}}",
+    );
+
+    teardown(handle, sender);
+
+    let mut stats = res["data"]["ingestStats"].as_array().unwrap().clone();
+    assert_eq!(stats.len(), 2);
+    stats.sort_by_key(|stats| stats["protocol"].as_str().unwrap().to_owned());
+
+    assert_eq!(stats[0]["protocol"], "CBOR");
+    assert_eq!(stats[0]["messages"], 1);
+    assert_eq!(stats[0]["entries"], 2);
+    assert_eq!(stats[0]["errors"], 0);
+    assert_eq!(stats[0]["lastError"], json!(null));
+    assert_eq!(
+        stats[1]["sender"],
+        json!(socket.local_addr().unwrap().to_string())
+    );
+    assert_eq!(stats[1]["protocol"], "JSON");
+    assert_eq!(stats[1]["messages"], 2);
+    assert_eq!(stats[1]["entries"], 3);
+    assert_eq!(stats[1]["errors"], 1);
+    assert!(stats[1]["lastError"]
+        .as_str()
+        .unwrap()
+        .starts_with("Failed to parse message"));
+}
@@ -44,7 +44,6 @@ fn test_insert_auto_timestamp() {
             }
         }"#;
     let mutation_expected = json!({
-            "errors": "",
             "data": {
                 "insert": {
                     "errors": "",
@@ -62,7 +61,6 @@ fn test_insert_auto_timestamp() {
             }
         }"#;
     let query_expected = json!({
-            "errors": "",
             "data": {
                 "telemetry": [{
                     "subsystem": "test2",
@@ -97,7 +95,6 @@ fn test_insert_custom_timestamp() {
             }
         }"#;
     let mutation_expected = json!({
-            "errors": "",
             "data": {
                 "insert": {
                     "errors": "",
@@ -116,7 +113,6 @@ fn test_insert_custom_timestamp() {
             }
         }"#;
     let query_expected = json!({
-            "errors": "",
             "data": {
                 "telemetry": [{
                     "timestamp": 5.0,
@@ -146,7 +142,6 @@ fn test_insert_multi_auto() {
     let (handle, sender) = setup(Some(db), Some(port), Some(udp), None);
 
     let mutation_expected = json!({
-            "errors": "",
             "data": {
                 "insert": {
                     "errors": "",
@@ -184,7 +179,6 @@ fn test_insert_multi_auto() {
             }
         }"#;
     let query_expected = json!({
-            "errors": "",
             "data": {
                 "telemetry": [
                 {
@@ -244,7 +238,6 @@ fn test_insert_current_timestamp() {
         }}"#, now);
     
     let mutation_expected = json!({
-            "errors": "",
             "data": {
                 "insert": {
                     "errors": "",
@@ -275,3 +268,89 @@ fn test_insert_current_timestamp() {
     let difference = (now - timestamp).abs();
     assert!(difference < 1.0);
 }
+
+#[test]
+fn test_insert_bulk() {
+    let db_dir = TempDir::new().unwrap();
+    let db_path = db_dir.path().join("test.db");
+
+    let db = db_path.to_str().unwrap();
+    let port = 8115;
+    let udp = 8125;
+
+    let (handle, sender) = setup(Some(db), Some(port), Some(udp), None);
+
+    let mutation = r#"mutation {
+            insertBulk(timestamp: 10, entries: [
+                { subsystem: "eps", parameter: "voltage", value: "4.0" },
+                { subsystem: "eps", parameter: "current", value: "0.5" },
+                { timestamp: 11, subsystem: "eps", parameter: "voltage", value: "4.1" }
+            ]) {
+                success,
+                errors
+            }
+        }"#;
+    let mutation_expected = json!({
+            "data": {
+                "insertBulk": {
+                    "errors": "",
+                    "success": true
+                }
+            }
+        });
+    let mutation_result = do_query(Some(port), mutation);
+
+    let query = r#"{
+            telemetry(subsystem: "eps") {
+                timestamp,
+                parameter,
+                value
+            }
+        }"#;
+    let query_result = do_query(Some(port), query);
+
+    teardown(handle, sender);
+
+    assert_eq!(mutation_result, mutation_expected);
+
+    let mut entries = query_result["data"]["telemetry"].as_array().unwrap().clone();
+    entries.sort_by_key(|entry| entry.to_string());
+    assert_eq!(
+        entries,
+        vec![
+            json!({"timestamp": 10.0, "parameter": "current", "value": "0.5"}),
+            json!({"timestamp": 10.0, "parameter": "voltage", "value": "4.0"}),
+            json!({"timestamp": 11.0, "parameter": "voltage", "value": "4.1"}),
+        ]
+    );
+}
+
+#[test]
+fn test_insert_bulk_rollback() {
+    let db_dir = TempDir::new().unwrap();
+    let db_path = db_dir.path().join("test.db");
+
+    let db = db_path.to_str().unwrap();
+    let port = 8116;
+    let udp = 8126;
+
+    let (handle, sender) = setup(Some(db), Some(port), Some(udp), None);
+
+    // The second entry duplicates the first, so neither should be inserted
+    let mutation = r#"mutation {
+            insertBulk(timestamp: 10, entries: [
+                { subsystem: "eps", parameter: "voltage", value: "4.0" },
+                { subsystem: "eps", parameter: "voltage", value: "4.1" }
+            ]) {
+                success
+            }
+        }"#;
+    let mutation_result = do_query(Some(port), mutation);
+
+    let query_result = do_query(Some(port), "{ telemetry { value } }");
+
+    teardown(handle, sender);
+
+    assert_eq!(mutation_result["data"]["insertBulk"]["success"], false);
+    assert_eq!(query_result, json!({ "data": { "telemetry": [] } }));
+}
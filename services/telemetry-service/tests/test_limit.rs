@@ -19,6 +19,7 @@ extern crate serde_json;
 extern crate tempfile;
 
 mod utils;
+use tempfile::TempDir;
 use utils::*;
 
 static SQL: &'static str = r"
@@ -37,7 +38,11 @@ insert into telemetry values(1010, 'eps', 'voltage', '2.4');
 
 #[test]
 fn test() {
-    let (handle, sender) = setup(None, None, None, Some(SQL));
+    let db_dir = TempDir::new().unwrap();
+    let db_path = db_dir.path().join("test.db");
+    let db = db_path.to_str().unwrap();
+
+    let (handle, sender) = setup(Some(db), None, None, Some(SQL));
     let res = do_query(
         None,
         "{telemetry(limit: 2, timestampGe: 1008){timestamp,subsystem,parameter,value}}",
@@ -46,7 +51,6 @@ fn test() {
     assert_eq!(
         res,
         json!({
-            "errors": "",
             "data": {
                 "telemetry":[
                     {"timestamp":1010.0,"subsystem":"eps","parameter":"voltage","value":"2.4"},
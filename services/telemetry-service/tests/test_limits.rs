@@ -0,0 +1,267 @@
+//
+// Copyright (C) 2018 Kubos Corporation
+//
+// Licensed under the Apache License, Version 2.0 (the "License")
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+#[macro_use]
+extern crate serde_json;
+extern crate tempfile;
+
+mod utils;
+
+use serde_json::ser;
+use std::net::UdpSocket;
+use std::thread;
+use std::time::Duration;
+use tempfile::TempDir;
+use utils::*;
+
+static LIMITS: &'static str = r#"
+[telemetry-service.limits.eps.voltage]
+red_low = 5.5
+yellow_low = 6.5
+yellow_high = 8.2
+red_high = 8.4
+"#;
+
+fn insert(port: u16, timestamp: u32, value: &str) -> serde_json::Value {
+    do_query(
+        Some(port),
+        &format!(
+            r#"mutation {{
+                insert(timestamp: {}, subsystem: "eps", parameter: "voltage", value: "{}") {{
+                    success
+                }}
+            }}"#,
+            timestamp, value
+        ),
+    )
+}
+
+#[test]
+fn test_limits_insert() {
+    let db_dir = TempDir::new().unwrap();
+    let db_path = db_dir.path().join("test.db");
+
+    let db = db_path.to_str().unwrap();
+    let port = 8111;
+    let udp = 8121;
+
+    let (handle, sender) = setup_with_config(Some(db), Some(port), Some(udp), None, LIMITS);
+
+    insert(port, 1000, "7.0");
+    insert(port, 1001, "6.2");
+    insert(port, 1002, "5.0");
+    let red = do_query(
+        Some(port),
+        "{alarms{subsystem,parameter,state,timestamp,value}}",
+    );
+    // Parameters without limits are never in alarm
+    do_query(
+        Some(port),
+        r#"mutation { insert(timestamp: 1003, subsystem: "eps", parameter: "current", value: "100") { success } }"#,
+    );
+    insert(port, 1004, "7.0");
+    let nominal = do_query(Some(port), "{alarms{subsystem,parameter,state}}");
+    let events = do_query(
+        Some(port),
+        "{events{timestamp,subsystem,parameter,value,state,previous}}",
+    );
+
+    teardown(handle, sender);
+
+    assert_eq!(
+        red,
+        json!({
+            "data": {
+                "alarms": [{
+                    "subsystem": "eps",
+                    "parameter": "voltage",
+                    "state": "RED_LOW",
+                    "timestamp": 1002.0,
+                    "value": 5.0
+                }]
+            }
+        })
+    );
+    assert_eq!(nominal, json!({"data": {"alarms": []}}));
+    assert_eq!(
+        events,
+        json!({
+            "data": {
+                "events": [
+                    {
+                        "timestamp": 1004.0, "subsystem": "eps", "parameter": "voltage",
+                        "value": 7.0, "state": "NOMINAL", "previous": "RED_LOW"
+                    },
+                    {
+                        "timestamp": 1002.0, "subsystem": "eps", "parameter": "voltage",
+                        "value": 5.0, "state": "RED_LOW", "previous": "YELLOW_LOW"
+                    },
+                    {
+                        "timestamp": 1001.0, "subsystem": "eps", "parameter": "voltage",
+                        "value": 6.2, "state": "YELLOW_LOW", "previous": "NOMINAL"
+                    },
+                ]
+            }
+        })
+    );
+}
+
+#[test]
+fn test_limits_udp() {
+    let db_dir = TempDir::new().unwrap();
+    let db_path = db_dir.path().join("test.db");
+
+    let db = db_path.to_str().unwrap();
+    let port = 8112;
+    let udp = 8122;
+
+    let (handle, sender) = setup_with_config(Some(db), Some(port), Some(udp), None, LIMITS);
+
+    let socket = UdpSocket::bind("0.0.0.0:0").unwrap();
+    let service = format!("0.0.0.0:{}", udp);
+
+    let single =
+        json!({"timestamp": 1000, "subsystem": "eps", "parameter": "voltage", "value": 8.3});
+    let bulk = json!([
+        {"timestamp": 1001, "subsystem": "eps", "parameter": "current", "value": 1.0},
+        {"timestamp": 1001, "subsystem": "eps", "parameter": "voltage", "value": 9.0},
+    ]);
+    socket
+        .send_to(&ser::to_vec(&single).unwrap(), &service)
+        .unwrap();
+    socket
+        .send_to(&ser::to_vec(&bulk).unwrap(), &service)
+        .unwrap();
+
+    // Give the service time to process the messages, since we're not actually waiting
+    // for a response
+    thread::sleep(Duration::from_secs(1));
+
+    let alarms = do_query(Some(port), "{alarms{parameter,state}}");
+    let events = do_query(Some(port), "{events(limit: 1){state,previous}}");
+    teardown(handle, sender);
+
+    assert_eq!(
+        alarms,
+        json!({"data": {"alarms": [{"parameter": "voltage", "state": "RED_HIGH"}]}})
+    );
+    assert_eq!(
+        events,
+        json!({"data": {"events": [{"state": "RED_HIGH", "previous": "YELLOW_HIGH"}]}})
+    );
+}
+
+#[test]
+fn test_limits_restart() {
+    let db_dir = TempDir::new().unwrap();
+    let db_path = db_dir.path().join("test.db");
+
+    let db = db_path.to_str().unwrap();
+    let port = 8113;
+    let udp = 8123;
+
+    // An alarm raised before the service was (re)started
+    let sql = r"
+        create table events (
+            id integer not null primary key autoincrement,
+            timestamp double not null,
+            subsystem varchar(255) not null,
+            parameter varchar(255) not null,
+            value double not null,
+            state varchar(16) not null,
+            previous varchar(16) not null);
+        insert into events(timestamp, subsystem, parameter, value, state, previous)
+            values(1000, 'eps', 'voltage', 8.3, 'yellow_high', 'nominal');
+        insert into events(timestamp, subsystem, parameter, value, state, previous)
+            values(1000, 'eps', 'current', 9.0, 'red_high', 'nominal');
+    ";
+    let (handle, sender) = setup_with_config(Some(db), Some(port), Some(udp), Some(sql), LIMITS);
+
+    // Parameters whose limits have been removed are no longer in alarm
+    let before = do_query(Some(port), "{alarms{parameter,state}}");
+    // Entries older than the last one checked don't change the alarm state
+    insert(port, 999, "7.0");
+    let after = do_query(Some(port), "{alarms{parameter,state}}");
+    teardown(handle, sender);
+
+    let expected = json!({"data": {"alarms": [{"parameter": "voltage", "state": "YELLOW_HIGH"}]}});
+    assert_eq!(before, expected);
+    assert_eq!(after, expected);
+}
+
+#[test]
+fn test_limits_retention() {
+    let db_dir = TempDir::new().unwrap();
+    let db_path = db_dir.path().join("test.db");
+
+    let db = db_path.to_str().unwrap();
+    let port = 8114;
+    let udp = 8124;
+
+    let sql = r"
+        create table events (
+            id integer not null primary key autoincrement,
+            timestamp double not null,
+            subsystem varchar(255) not null,
+            parameter varchar(255) not null,
+            value double not null,
+            state varchar(16) not null,
+            previous varchar(16) not null);
+        insert into events(timestamp, subsystem, parameter, value, state, previous)
+            values(1000, 'eps', 'voltage', 8.3, 'yellow_high', 'nominal');
+        insert into events(timestamp, subsystem, parameter, value, state, previous)
+            values(1001, 'eps', 'voltage', 8.5, 'red_high', 'yellow_high');
+        insert into events(timestamp, subsystem, parameter, value, state, previous)
+            values(strftime('%s', 'now') - 60, 'eps', 'voltage', 7.0, 'nominal', 'red_high');
+        insert into events(timestamp, subsystem, parameter, value, state, previous)
+            values(1000, 'eps', 'current', 9.0, 'red_high', 'nominal');
+    ";
+    let config = format!(
+        "{}
+        [telemetry-service.retention]
+        max_age = 3600
+        ",
+        LIMITS
+    );
+    let (handle, sender) = setup_with_config(Some(db), Some(port), Some(udp), Some(sql), &config);
+
+    // Pruning runs alongside queries, so wait for the first pass
+    let mut events = serde_json::Value::Null;
+    for _ in 0..50 {
+        events = do_query(Some(port), "{events{parameter,state}}");
+        if events["data"]["events"]
+            .as_array()
+            .map_or(false, |events| events.len() < 4)
+        {
+            break;
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+    teardown(handle, sender);
+
+    // Old events are deleted, except for the latest of each parameter
+    assert_eq!(
+        events,
+        json!({
+            "data": {
+                "events": [
+                    {"parameter": "voltage", "state": "NOMINAL"},
+                    {"parameter": "current", "state": "RED_HIGH"},
+                ]
+            }
+        })
+    );
+}
@@ -0,0 +1,215 @@
+//
+// Copyright (C) 2018 Kubos Corporation
+//
+// Licensed under the Apache License, Version 2.0 (the "License")
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+#[macro_use]
+extern crate serde_json;
+extern crate tempfile;
+
+mod utils;
+
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
+use tempfile::TempDir;
+use utils::*;
+
+// Entries from the distant past and from a minute ago
+static SQL: &'static str = r"
+insert into telemetry values(1000, 'eps', 'voltage', '3.0');
+insert into telemetry values(1001, 'eps', 'voltage', '5.0');
+insert into telemetry values(1000, 'mcu', 'current', '1');
+insert into telemetry values(strftime('%s', 'now') - 60, 'eps', 'voltage', '4.0');
+insert into telemetry values(strftime('%s', 'now') - 60, 'mcu', 'current', '2');
+insert into telemetry values(strftime('%s', 'now') - 60, 'gps', 'lock', 'true');
+";
+
+// Enough entries to grow the database well past 64KiB
+static BIG_SQL: &'static str = r"
+with recursive n(i) as (select 1 union all select i + 1 from n where i < 5000)
+insert into telemetry select i, 'eps', 'status', printf('%.100c', 'x') from n;
+";
+
+// Pruning runs alongside queries, so polls the database's stats until the first pruning pass
+// has had the expected effect, giving up after five seconds
+fn db_stats_once<F>(port: u16, query: &str, pruned: F) -> serde_json::Value
+where
+    F: Fn(&serde_json::Value) -> bool,
+{
+    for _ in 0..50 {
+        let res = do_query(Some(port), query);
+        if pruned(&res["data"]["dbStats"]) {
+            return res;
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+    do_query(Some(port), query)
+}
+
+#[test]
+fn test_db_stats() {
+    let db_dir = TempDir::new().unwrap();
+    let db_path = db_dir.path().join("test.db");
+
+    let db = db_path.to_str().unwrap();
+    let port = 8111;
+    let udp = 8121;
+
+    let (handle, sender) = setup(Some(db), Some(port), Some(udp), Some(SQL));
+    let res = do_query(
+        Some(port),
+        "{dbStats{entries,subsystems{subsystem,entries,oldest,newest}}}",
+    );
+    teardown(handle, sender);
+
+    let stats = &res["data"]["dbStats"];
+    assert_eq!(stats["entries"], json!(6));
+
+    let subsystems = stats["subsystems"].as_array().unwrap();
+    let names: Vec<&str> = subsystems
+        .iter()
+        .map(|stats| stats["subsystem"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, vec!["eps", "gps", "mcu"]);
+    assert_eq!(subsystems[0]["entries"], json!(3));
+    assert_eq!(subsystems[0]["oldest"], json!(1000.0));
+    assert!(subsystems[0]["newest"].as_f64().unwrap() > 1001.0);
+    assert_eq!(subsystems[1]["entries"], json!(1));
+    assert_eq!(subsystems[2]["entries"], json!(2));
+}
+
+#[test]
+fn test_retention_max_age() {
+    let db_dir = TempDir::new().unwrap();
+    let db_path = db_dir.path().join("test.db");
+
+    let db = db_path.to_str().unwrap();
+    let port = 8112;
+    let udp = 8122;
+
+    let (handle, sender) = setup_with_config(
+        Some(db),
+        Some(port),
+        Some(udp),
+        Some(SQL),
+        r#"
+        [telemetry-service.retention]
+        max_age = 3600
+
+        [telemetry-service.retention.subsystems.mcu]
+        max_age = 30
+        "#,
+    );
+    let res = db_stats_once(port, "{dbStats{subsystems{subsystem,entries}}}", |stats| {
+        stats["subsystems"]
+            .as_array()
+            .map_or(false, |subsystems| subsystems.len() == 2)
+    });
+    teardown(handle, sender);
+
+    // The old eps entries are past the database-wide maximum age, and all of the mcu entries
+    // are past the mcu subsystem's maximum age
+    assert_eq!(
+        res,
+        json!({
+            "data": {
+                "dbStats": {
+                    "subsystems": [
+                        {"subsystem": "eps", "entries": 1},
+                        {"subsystem": "gps", "entries": 1},
+                    ]
+                }
+            }
+        })
+    );
+}
+
+#[test]
+fn test_retention_max_size() {
+    let db_dir = TempDir::new().unwrap();
+    let db_path = db_dir.path().join("test.db");
+
+    let db = db_path.to_str().unwrap();
+    let port = 8113;
+    let udp = 8123;
+
+    let (handle, sender) = setup_with_config(
+        Some(db),
+        Some(port),
+        Some(udp),
+        Some(BIG_SQL),
+        r#"
+        [telemetry-service.retention]
+        max_size = 65536
+        "#,
+    );
+    let res = db_stats_once(
+        port,
+        "{dbStats{size,entries,subsystems{oldest,newest}}}",
+        |stats| stats["size"].as_f64().map_or(false, |size| size <= 65536.0),
+    );
+    teardown(handle, sender);
+
+    let stats = &res["data"]["dbStats"];
+    assert!(stats["size"].as_f64().unwrap() <= 65536.0);
+    assert!(stats["entries"].as_i64().unwrap() > 0);
+    assert!(stats["entries"].as_i64().unwrap() < 5000);
+    // Only the oldest entries are deleted
+    assert!(stats["subsystems"][0]["oldest"].as_f64().unwrap() > 1.0);
+    assert_eq!(stats["subsystems"][0]["newest"], json!(5000.0));
+}
+
+#[test]
+fn test_retention_converts_old_db() {
+    let db_dir = TempDir::new().unwrap();
+    let db_path = db_dir.path().join("test.db");
+
+    let db = db_path.to_str().unwrap();
+    let port = 8114;
+    let udp = 8124;
+
+    // Databases created by older versions don't have incremental vacuuming enabled
+    let (handle, sender) = setup_with_config(
+        Some(db),
+        Some(port),
+        Some(udp),
+        Some(SQL),
+        r#"
+        [telemetry-service.retention]
+        max_age = 4000000000
+        "#,
+    );
+
+    // The first pruning pass converts the database alongside queries
+    let mut mode = String::new();
+    for _ in 0..50 {
+        let output = Command::new("sqlite3")
+            .arg(db)
+            .arg("PRAGMA auto_vacuum")
+            .output()
+            .unwrap();
+        mode = String::from_utf8_lossy(&output.stdout).trim().to_owned();
+        if mode == "2" {
+            break;
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+    let res = do_query(Some(port), "{dbStats{entries}}");
+    teardown(handle, sender);
+
+    assert_eq!(res["data"]["dbStats"]["entries"], json!(6));
+    // 2 is INCREMENTAL
+    assert_eq!(mode, "2");
+}
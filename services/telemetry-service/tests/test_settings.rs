@@ -0,0 +1,199 @@
+//
+// Copyright (C) 2018 Kubos Corporation
+//
+// Licensed under the Apache License, Version 2.0 (the "License")
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+extern crate tempfile;
+
+use std::env;
+use std::fs;
+use std::process::Command;
+use tempfile::TempDir;
+
+// Runs the service against the given config file in check mode, returning its stderr
+// if the configuration was rejected
+fn check(config: &str) -> Result<(), String> {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("config.toml");
+    fs::write(&path, config).unwrap();
+
+    let mut telem_path = env::current_exe().unwrap();
+    telem_path.pop();
+    telem_path.set_file_name("telemetry-service");
+
+    let output = Command::new(telem_path)
+        .arg("-c")
+        .arg(&path)
+        .arg("--check-config")
+        .output()
+        .unwrap();
+
+    match output.status.success() {
+        true => Ok(()),
+        false => Err(String::from_utf8_lossy(&output.stderr).trim().to_owned()),
+    }
+}
+
+#[test]
+fn settings_good() {
+    let result = check(
+        r#"
+        [telemetry-service]
+        database = "/var/lib/telemetry.db"
+        direct_port = 8105
+
+        [telemetry-service.addr]
+        ip = "127.0.0.1"
+        port = 8089
+
+        [telemetry-service.retention]
+        max_age = 604800
+        max_size = 1048576
+
+        [telemetry-service.retention.subsystems.eps]
+        max_age = 86400
+
+        [telemetry-service.limits.eps.voltage]
+        red_low = 6.0
+        yellow_low = 6.5
+        red_high = 8.4
+
+        [[telemetry-service.collectors]]
+        service = "eps-service"
+        subsystem = "eps"
+        query = "{ telemetry { voltage } }"
+        interval = 60
+
+        [telemetry-service.collectors.parameters]
+        voltage = "telemetry.voltage"
+        "#,
+    );
+
+    assert_eq!(result, Ok(()));
+}
+
+#[test]
+fn settings_missing_key() {
+    let result = check("[telemetry-service.addr]\nport = 8089\n");
+
+    assert_eq!(
+        result,
+        Err("Invalid configuration for telemetry-service: missing field `database`".to_owned())
+    );
+}
+
+#[test]
+fn settings_unknown_key() {
+    let err =
+        check("[telemetry-service]\ndatabase = \"/tmp/db\"\ndirect-port = 8105\n").unwrap_err();
+
+    assert!(err.contains("unknown field `direct-port`"));
+}
+
+#[test]
+fn settings_wrong_type() {
+    let result = check("[telemetry-service]\ndatabase = \"/tmp/db\"\ndirect_port = \"8105\"\n");
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn settings_cbor_port_conflict() {
+    let result = check(
+        "[telemetry-service]\ndatabase = \"/tmp/db\"\ndirect_port = 8105\ncbor_port = 8105\n",
+    );
+
+    assert_eq!(
+        result,
+        Err(
+            "Invalid configuration for telemetry-service: \
+             cbor_port must not be the same as direct_port"
+                .to_owned()
+        )
+    );
+}
+
+#[test]
+fn settings_retention_invalid() {
+    let result = check(
+        r#"
+        [telemetry-service]
+        database = "/var/lib/telemetry.db"
+
+        [telemetry-service.retention.subsystems.eps]
+        max_age = 0
+        "#,
+    );
+
+    assert_eq!(
+        result,
+        Err(
+            "Invalid configuration for telemetry-service: \
+             retention.subsystems.eps.max_age must be between 1 and 4294967295, found 0"
+                .to_owned()
+        )
+    );
+}
+
+#[test]
+fn settings_limits_out_of_order() {
+    let result = check(
+        r#"
+        [telemetry-service]
+        database = "/var/lib/telemetry.db"
+
+        [telemetry-service.limits.eps.voltage]
+        yellow_low = 6.5
+        red_low = 7.0
+        "#,
+    );
+
+    assert_eq!(
+        result,
+        Err(
+            "Invalid configuration for telemetry-service: \
+             limits.eps.voltage: red_low must not be greater than yellow_low, found 7 and 6.5"
+                .to_owned()
+        )
+    );
+}
+
+#[test]
+fn settings_collectors_bad_path() {
+    let result = check(
+        r#"
+        [telemetry-service]
+        database = "/var/lib/telemetry.db"
+
+        [[telemetry-service.collectors]]
+        service = "eps-service"
+        subsystem = "eps"
+        query = "{ telemetry { voltage } }"
+        interval = 60
+
+        [telemetry-service.collectors.parameters]
+        voltage = "telemetry..voltage"
+        "#,
+    );
+
+    assert_eq!(
+        result,
+        Err(
+            "Invalid configuration for telemetry-service: \
+             collectors[0]: parameters.voltage must be a dot-separated path, \
+             found \"telemetry..voltage\""
+                .to_owned()
+        )
+    );
+}
@@ -0,0 +1,151 @@
+//
+// Copyright (C) 2018 Kubos Corporation
+//
+// Licensed under the Apache License, Version 2.0 (the "License")
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+#[macro_use]
+extern crate serde_json;
+extern crate tempfile;
+
+mod utils;
+
+use tempfile::TempDir;
+use utils::*;
+
+static SQL: &'static str = r"
+insert into telemetry values(1000, 'eps', 'voltage', '3.0');
+insert into telemetry values(1001, 'eps', 'voltage', '5.0');
+insert into telemetry values(1002, 'eps', 'voltage', '4.0');
+insert into telemetry values(1003, 'eps', 'voltage', '2.0');
+insert into telemetry values(1004, 'eps', 'voltage', '6.0');
+insert into telemetry values(1000, 'eps', 'mode', 'nominal');
+insert into telemetry values(1003, 'eps', 'mode', 'safe');
+insert into telemetry values(1001, 'mcu', 'current', '1');
+";
+
+#[test]
+fn test_stats() {
+    let db_dir = TempDir::new().unwrap();
+    let db_path = db_dir.path().join("test.db");
+
+    let db = db_path.to_str().unwrap();
+    let port = 8111;
+    let udp = 8121;
+
+    let (handle, sender) = setup(Some(db), Some(port), Some(udp), Some(SQL));
+
+    let res = do_query(
+        Some(port),
+        "{telemetryStats{subsystem,parameter,start,end,count,min,max,mean,first,last}}",
+    );
+    teardown(handle, sender);
+    assert_eq!(
+        res,
+        json!({
+            "data": {
+                "telemetryStats": [
+                    {
+                        "subsystem": "eps", "parameter": "mode", "start": 1000.0, "end": 1003.0,
+                        "count": 2, "min": null, "max": null, "mean": null, "first": null, "last": null
+                    },
+                    {
+                        "subsystem": "eps", "parameter": "voltage", "start": 1000.0, "end": 1004.0,
+                        "count": 5, "min": 2.0, "max": 6.0, "mean": 4.0, "first": 3.0, "last": 6.0
+                    },
+                    {
+                        "subsystem": "mcu", "parameter": "current", "start": 1001.0, "end": 1001.0,
+                        "count": 1, "min": 1.0, "max": 1.0, "mean": 1.0, "first": 1.0, "last": 1.0
+                    },
+                ]
+            }
+        })
+    );
+}
+
+#[test]
+fn test_stats_filter_aggregates() {
+    let db_dir = TempDir::new().unwrap();
+    let db_path = db_dir.path().join("test.db");
+
+    let db = db_path.to_str().unwrap();
+    let port = 8112;
+    let udp = 8122;
+
+    let (handle, sender) = setup(Some(db), Some(port), Some(udp), Some(SQL));
+
+    let res = do_query(
+        Some(port),
+        r#"{telemetryStats(subsystem: "eps", parameter: "voltage", timestampGe: 1001, aggregates: [MIN, LAST]) {
+            count,min,max,first,last
+        }}"#,
+    );
+    teardown(handle, sender);
+    assert_eq!(
+        res,
+        json!({
+            "data": {
+                "telemetryStats": [
+                    { "count": null, "min": 2.0, "max": null, "first": null, "last": 6.0 },
+                ]
+            }
+        })
+    );
+}
+
+#[test]
+fn test_buckets() {
+    let db_dir = TempDir::new().unwrap();
+    let db_path = db_dir.path().join("test.db");
+
+    let db = db_path.to_str().unwrap();
+    let port = 8113;
+    let udp = 8123;
+
+    let (handle, sender) = setup(Some(db), Some(port), Some(udp), Some(SQL));
+
+    let res = do_query(
+        Some(port),
+        r#"{telemetryBuckets(width: 2, timestampGe: 1000, parameter: "voltage") {
+            start,end,count,min,max,mean,first,last
+        }}"#,
+    );
+    let invalid = do_query(Some(port), "{telemetryBuckets(width: 0) { count }}");
+    teardown(handle, sender);
+
+    assert_eq!(
+        res,
+        json!({
+            "data": {
+                "telemetryBuckets": [
+                    {
+                        "start": 1000.0, "end": 1002.0, "count": 2,
+                        "min": 3.0, "max": 5.0, "mean": 4.0, "first": 3.0, "last": 5.0
+                    },
+                    {
+                        "start": 1002.0, "end": 1004.0, "count": 2,
+                        "min": 2.0, "max": 4.0, "mean": 3.0, "first": 4.0, "last": 2.0
+                    },
+                    {
+                        "start": 1004.0, "end": 1006.0, "count": 1,
+                        "min": 6.0, "max": 6.0, "mean": 6.0, "first": 6.0, "last": 6.0
+                    },
+                ]
+            }
+        })
+    );
+    assert_eq!(
+        invalid["errors"][0]["message"],
+        "Invalid bucket width: 0"
+    );
+}
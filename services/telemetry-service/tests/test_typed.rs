@@ -0,0 +1,224 @@
+//
+// Copyright (C) 2018 Kubos Corporation
+//
+// Licensed under the Apache License, Version 2.0 (the "License")
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+//
+
+#[macro_use]
+extern crate serde_json;
+extern crate tempfile;
+
+mod utils;
+
+use serde_json::ser;
+use std::net::UdpSocket;
+use std::time::Duration;
+use tempfile::TempDir;
+use utils::*;
+
+// Inserted into a table using the original, untyped schema, which the service migrates
+static SQL: &'static str = r"
+insert into telemetry values(1000, 'eps', 'voltage', '3.3');
+insert into telemetry values(1001, 'eps', 'mode', '5');
+insert into telemetry values(1002, 'eps', 'enabled', 'true');
+insert into telemetry values(1003, 'eps', 'state', 'nominal');
+insert into telemetry values(1004, 'eps', 'voltage', '7.5');
+";
+
+#[test]
+fn test_migrated_values() {
+    let db_dir = TempDir::new().unwrap();
+    let db_path = db_dir.path().join("test.db");
+
+    let db = db_path.to_str().unwrap();
+    let port = 8111;
+    let udp = 8121;
+
+    let (handle, sender) = setup(Some(db), Some(port), Some(udp), Some(SQL));
+
+    let res = do_query(
+        Some(port),
+        "{telemetry(timestampLe: 1003){parameter,value,valueType,numericValue,booleanValue}}",
+    );
+    teardown(handle, sender);
+    assert_eq!(
+        res,
+        json!({
+            "data": {
+                "telemetry":[
+                    {"parameter":"state","value":"nominal","valueType":"STRING","numericValue":null,"booleanValue":null},
+                    {"parameter":"enabled","value":"true","valueType":"BOOLEAN","numericValue":1.0,"booleanValue":true},
+                    {"parameter":"mode","value":"5","valueType":"INTEGER","numericValue":5.0,"booleanValue":null},
+                    {"parameter":"voltage","value":"3.3","valueType":"FLOAT","numericValue":3.3,"booleanValue":null},
+                ]
+            }
+        })
+    );
+}
+
+#[test]
+fn test_value_range() {
+    let db_dir = TempDir::new().unwrap();
+    let db_path = db_dir.path().join("test.db");
+
+    let db = db_path.to_str().unwrap();
+    let port = 8112;
+    let udp = 8122;
+
+    let (handle, sender) = setup(Some(db), Some(port), Some(udp), Some(SQL));
+
+    let below = do_query(
+        Some(port),
+        r#"{telemetry(parameter: "voltage", valueLe: 7.2){timestamp,value}}"#,
+    );
+    let between = do_query(Some(port), "{telemetry(valueGe: 1, valueLe: 5){timestamp}}");
+    teardown(handle, sender);
+
+    assert_eq!(
+        below,
+        json!({ "data": { "telemetry": [{ "timestamp": 1000.0, "value": "3.3" }] } })
+    );
+    assert_eq!(
+        between,
+        json!({
+            "data": {
+                "telemetry": [
+                    { "timestamp": 1002.0 },
+                    { "timestamp": 1001.0 },
+                    { "timestamp": 1000.0 },
+                ]
+            }
+        })
+    );
+}
+
+#[test]
+fn test_insert_typed() {
+    let db_dir = TempDir::new().unwrap();
+    let db_path = db_dir.path().join("test.db");
+
+    let db = db_path.to_str().unwrap();
+    let port = 8113;
+    let udp = 8123;
+
+    let (handle, sender) = setup(Some(db), Some(port), Some(udp), None);
+
+    let blob = do_query(
+        Some(port),
+        r#"mutation {
+            insert(timestamp: 1, subsystem: "radio", parameter: "frame", value: "0AFF10", valueType: BLOB) {
+                success,
+                errors
+            }
+        }"#,
+    );
+    let string = do_query(
+        Some(port),
+        r#"mutation {
+            insertBulk(timestamp: 2, entries: [
+                { subsystem: "radio", parameter: "id", value: "12", valueType: STRING },
+                { subsystem: "radio", parameter: "power", value: "1.50" }
+            ]) {
+                success,
+                errors
+            }
+        }"#,
+    );
+    let invalid = do_query(
+        Some(port),
+        r#"mutation {
+            insert(timestamp: 3, subsystem: "radio", parameter: "mode", value: "abc", valueType: INTEGER) {
+                success,
+                errors
+            }
+        }"#,
+    );
+
+    let res = do_query(
+        Some(port),
+        "{telemetry{parameter,value,valueType,numericValue,blobValue}}",
+    );
+    teardown(handle, sender);
+
+    assert_eq!(blob["data"]["insert"]["success"], true);
+    assert_eq!(string["data"]["insertBulk"]["success"], true);
+    assert_eq!(
+        invalid,
+        json!({
+            "data": {
+                "insert": {
+                    "success": false,
+                    "errors": "Invalid integer value: abc"
+                }
+            }
+        })
+    );
+
+    let mut entries = res["data"]["telemetry"].as_array().unwrap().clone();
+    entries.sort_by_key(|entry| entry["parameter"].to_string());
+    assert_eq!(
+        entries,
+        vec![
+            json!({"parameter":"frame","value":"0aff10","valueType":"BLOB","numericValue":null,"blobValue":"0aff10"}),
+            json!({"parameter":"id","value":"12","valueType":"STRING","numericValue":null,"blobValue":null}),
+            json!({"parameter":"power","value":"1.5","valueType":"FLOAT","numericValue":1.5,"blobValue":null}),
+        ]
+    );
+}
+
+#[test]
+fn test_udp_typed() {
+    let db_dir = TempDir::new().unwrap();
+    let db_path = db_dir.path().join("test.db");
+
+    let db = db_path.to_str().unwrap();
+    let port = 8114;
+    let udp = 8124;
+
+    let (handle, sender) = setup(Some(db), Some(port), Some(udp), None);
+
+    let socket = UdpSocket::bind("0.0.0.0:0").unwrap();
+    let service = format!("0.0.0.0:{}", udp);
+
+    let entries = json!([
+        { "timestamp": 1000, "subsystem": "eps", "parameter": "mode", "value": 7 },
+        { "timestamp": 1001, "subsystem": "eps", "parameter": "enabled", "value": false },
+        { "timestamp": 1002, "subsystem": "eps", "parameter": "voltage", "value": "3.25" }
+    ]);
+
+    socket
+        .send_to(&ser::to_vec(&entries).unwrap(), &service)
+        .unwrap();
+
+    // Give the service time to process the message, since we're not actually waiting
+    // for a response
+    ::std::thread::sleep(Duration::from_secs(1));
+
+    let res = do_query(
+        Some(port),
+        "{telemetry{parameter,value,valueType,numericValue}}",
+    );
+    teardown(handle, sender);
+    assert_eq!(
+        res,
+        json!({
+            "data": {
+                "telemetry":[
+                    {"parameter":"voltage","value":"3.25","valueType":"FLOAT","numericValue":3.25},
+                    {"parameter":"enabled","value":"false","valueType":"BOOLEAN","numericValue":0.0},
+                    {"parameter":"mode","value":"7","valueType":"INTEGER","numericValue":7.0},
+                ]
+            }
+        })
+    );
+}
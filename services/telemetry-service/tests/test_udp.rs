@@ -82,7 +82,6 @@ fn test_udp_timestamp() {
     assert_eq!(
         res,
         json!({
-            "errors": "",
             "data": {
                 "telemetry":[
                     {"timestamp":1002.0,"subsystem":"eps","parameter":"voltage","value":"3.2"},
@@ -152,7 +151,6 @@ fn test_udp_no_timestamp() {
     assert_eq!(
         res,
         json!({
-            "errors": "",
             "data": {
                 "telemetry":[
                     {"subsystem":"test4","parameter":"current","value":"2.2"},
@@ -164,3 +162,80 @@ fn test_udp_no_timestamp() {
         })
     );
 }
+
+#[test]
+fn test_udp_array() {
+    let db_dir = TempDir::new().unwrap();
+    let db_path = db_dir.path().join("test.db");
+
+    let db = db_path.to_str().unwrap();
+
+    let port = 8113;
+    let udp = 8123;
+
+    let (handle, sender) = setup(Some(db), Some(port), Some(udp), None);
+
+    let socket = UdpSocket::bind("0.0.0.0:0").unwrap();
+    let service = format!("0.0.0.0:{}", udp);
+
+    let entries = json!([
+        {
+            "timestamp": 1000,
+            "subsystem": "eps",
+            "parameter": "voltage",
+            "value": "3.3"
+        },
+        {
+            "timestamp": 1001,
+            "subsystem": "eps",
+            "parameter": "voltage",
+            "value": "3.4"
+        },
+        {
+            "subsystem": "eps",
+            "parameter": "current",
+            "value": "0.5"
+        }
+    ]);
+
+    // One bad entry causes the whole message to be rejected
+    let bad_entries = json!([
+        {
+            "timestamp": 1002,
+            "subsystem": "eps",
+            "parameter": "voltage",
+            "value": "3.5"
+        },
+        {
+            "timestamp": 1003,
+            "subsystem": "eps",
+            "value": "3.6"
+        }
+    ]);
+
+    socket
+        .send_to(&ser::to_vec(&entries).unwrap(), &service)
+        .unwrap();
+    socket
+        .send_to(&ser::to_vec(&bad_entries).unwrap(), &service)
+        .unwrap();
+
+    // Give the service time to process the messages, since we're not actually waiting
+    // for a response
+    ::std::thread::sleep(Duration::from_secs(1));
+
+    let res = do_query(Some(port), "{telemetry{subsystem,parameter,value}}");
+    teardown(handle, sender);
+    assert_eq!(
+        res,
+        json!({
+            "data": {
+                "telemetry":[
+                    {"subsystem":"eps","parameter":"current","value":"0.5"},
+                    {"subsystem":"eps","parameter":"voltage","value":"3.4"},
+                    {"subsystem":"eps","parameter":"voltage","value":"3.3"},
+                ]
+            }
+        })
+    );
+}
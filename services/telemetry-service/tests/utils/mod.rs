@@ -92,6 +92,18 @@ pub fn setup(
     service_port: Option<u16>,
     udp_port: Option<u16>,
     sql: Option<&str>,
+) -> (JoinHandle<()>, Sender<bool>) {
+    setup_with_config(db, service_port, udp_port, sql, "")
+}
+
+/// Same as `setup`, but appends `extra_config` to the service's `[telemetry-service]` table.
+/// It may set further keys of the table, followed by any of its sub-tables.
+pub fn setup_with_config(
+    db: Option<&str>,
+    service_port: Option<u16>,
+    udp_port: Option<u16>,
+    sql: Option<&str>,
+    extra_config: &str,
 ) -> (JoinHandle<()>, Sender<bool>) {
     let db = db.unwrap_or("test.db");
 
@@ -108,18 +120,41 @@ pub fn setup(
         [telemetry-service]
         database = "{}"
         direct_port = {}
-        
-        [telemetry-service.addr]
-        ip = "127.0.0.1"
-        port = {}
+        addr = {{ ip = "127.0.0.1", port = {} }}
+
+        {}
         "#,
-        db, udp_port, service_port
+        db, udp_port, service_port, extra_config
     );
 
     let mut config_file = File::create(config_path.clone()).unwrap();
     config_file.write_all(&config.as_bytes()).unwrap();
 
-    return start_telemetry(config_path.to_str().unwrap().to_owned());
+    let telemetry = start_telemetry(config_path.to_str().unwrap().to_owned());
+    wait_for_service(service_port);
+    return telemetry;
+}
+
+// Waits up to five seconds for the service to finish setting up its database and start
+// answering queries, so that tests don't send requests before it's listening
+fn wait_for_service(port: u16) {
+    let remote_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), port);
+    let local_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 0);
+
+    let socket = UdpSocket::bind(local_addr).expect("couldn't bind to address");
+    socket
+        .set_read_timeout(Some(Duration::from_millis(100)))
+        .unwrap();
+
+    let mut buf = [0; 1024];
+    for _ in 0..50 {
+        socket
+            .send_to(b"{telemetry(limit: 1){timestamp}}", &remote_addr)
+            .expect("couldn't send message");
+        if socket.recv_from(&mut buf).is_ok() {
+            return;
+        }
+    }
 }
 
 pub fn teardown(handle: JoinHandle<()>, sender: Sender<bool>) {
@@ -14,6 +14,7 @@
 // limitations under the License.
 //
 
+extern crate getopts;
 extern crate kubos_system;
 extern crate kubos_telemetry_db;
 extern crate rand;
@@ -21,10 +22,12 @@ extern crate rand;
 extern crate serde_json;
 extern crate time;
 
-use kubos_system::Config;
-use kubos_telemetry_db::Database;
+use getopts::Options;
+use kubos_system::{Config, DEFAULT_PATH};
+use kubos_telemetry_db::{Database, NewEntry};
 use rand::{thread_rng, Rng};
 use serde_json::ser;
+use std::env;
 use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
 use std::thread;
 use std::time::Duration;
@@ -148,6 +151,147 @@ fn direct_udp_test(config: &Config) {
     );
 }
 
+// Generates `count` entries with random timestamps, returned as (timestamp, parameter) pairs
+fn random_entries(count: usize) -> Vec<(f64, String)> {
+    let mut rng = thread_rng();
+    (0..count)
+        .map(|num| {
+            (
+                rng.gen_range(0, ::std::i32::MAX) as f64,
+                format!("parameter{}", num),
+            )
+        })
+        .collect()
+}
+
+fn print_batch_results(name: &str, batch: usize, times: &[i64]) {
+    let num_batches = times.len() as i64;
+    let sum: i64 = times.iter().sum();
+
+    let average = sum / num_batches.max(1);
+    let rate = (batch as f64) * 1_000_000.0 / (average.max(1) as f64);
+
+    println!(
+        "Average {} time for batches of {} after {} runs: {} us ({:.0} entries per second)",
+        name, batch, num_batches, average, rate
+    );
+}
+
+fn db_bulk_test(config: &Config, batch: usize) {
+    let db_path = config
+        .get("database")
+        .expect("No database path found in config file");
+    let db_path = db_path.as_str().unwrap_or("");
+
+    let db = Database::new(&db_path);
+    db.setup();
+
+    let mut times: Vec<i64> = Vec::new();
+
+    for _ in 0..ITERATIONS {
+        let entries = random_entries(batch);
+        let new_entries: Vec<NewEntry> = entries
+            .iter()
+            .map(|&(timestamp, ref parameter)| NewEntry {
+                timestamp,
+                subsystem: "db-test",
+                parameter,
+                value: "value",
+            })
+            .collect();
+
+        let start = PreciseTime::now();
+        if db.insert_bulk(&new_entries).is_ok() {
+            times.push(start.to(PreciseTime::now()).num_microseconds().unwrap());
+        }
+    }
+
+    print_batch_results("bulk insert", batch, &times);
+}
+
+fn graphql_bulk_test(config: &Config, batch: usize) {
+    let mut times: Vec<i64> = Vec::new();
+
+    for _ in 0..ITERATIONS {
+        let entries: Vec<String> = random_entries(batch)
+            .iter()
+            .map(|&(timestamp, ref parameter)| {
+                format!(
+                    r#"{{ timestamp: {}, subsystem: "db-test", parameter: "{}", value: "4.0" }}"#,
+                    timestamp, parameter
+                )
+            })
+            .collect();
+
+        let mutation = format!(
+            r#"mutation {{
+            insertBulk(entries: [{}]) {{
+                success,
+                errors
+            }}
+        }}"#,
+            entries.join(", ")
+        );
+
+        let remote_addr = config.hosturl();
+        let local_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), 0);
+
+        let socket = UdpSocket::bind(local_addr).expect("Couldn't bind to address");
+
+        let start = PreciseTime::now();
+        socket
+            .send_to(&mutation.as_bytes(), &remote_addr)
+            .expect("Couldn't send message");
+        socket.set_read_timeout(Some(Duration::new(1, 0))).unwrap();
+
+        let mut buf = [0; 1024];
+        match socket.recv_from(&mut buf) {
+            Ok(_) => times.push(start.to(PreciseTime::now()).num_microseconds().unwrap()),
+            Err(e) => panic!("recv function failed: {:?}", e),
+        }
+    }
+
+    print_batch_results("bulk mutation", batch, &times);
+}
+
+fn direct_udp_bulk_test(config: &Config, batch: usize) {
+    let mut times: Vec<i64> = Vec::new();
+
+    let direct_port = config
+        .get("direct_port")
+        .and_then(|port| port.as_integer())
+        .expect("No direct_port found in config file");
+    let remote_addr = format!("{}:{}", config.hosturl().split(':').next().unwrap(), direct_port);
+
+    for _ in 0..ITERATIONS {
+        let local_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), 0);
+
+        let socket = UdpSocket::bind(local_addr).expect("Couldn't bind to address");
+
+        let message: Vec<serde_json::Value> = random_entries(batch)
+            .iter()
+            .map(|&(timestamp, ref parameter)| {
+                json!({
+                    "timestamp": timestamp,
+                    "subsystem": "db-test",
+                    "parameter": parameter,
+                    "value": "3.3"
+                })
+            })
+            .collect();
+
+        let start = PreciseTime::now();
+
+        socket
+            .send_to(&ser::to_vec(&message).unwrap(), &remote_addr)
+            .unwrap();
+
+        times.push(start.to(PreciseTime::now()).num_microseconds().unwrap())
+    }
+
+    print_batch_results("bulk UDP send", batch, &times);
+}
+
 fn test_cleanup(config: &Config) {
     let mutation = r#"mutation {
             delete(subsystem: "db-test") {
@@ -195,7 +339,59 @@ fn test_cleanup(config: &Config) {
 }
 
 fn main() {
-    let config = Config::new("telemetry-service");
+    let args: Vec<String> = env::args().collect();
+
+    let mut opts = Options::new();
+    opts.optopt("c", "config", "Path to config file", "CONFIG");
+    opts.optopt(
+        "b",
+        "batch",
+        "Insert entries in batches of the given size, rather than individually",
+        "SIZE",
+    );
+    let matches = match opts.parse(&args[1..]) {
+        Ok(matches) => matches,
+        Err(err) => {
+            eprintln!("{}", err);
+            eprintln!("{}", opts.usage("Usage: db-test [options]"));
+            ::std::process::exit(1);
+        }
+    };
+
+    let config = Config::new_from_path(
+        "telemetry-service",
+        matches
+            .opt_str("c")
+            .unwrap_or_else(|| DEFAULT_PATH.to_string()),
+    ).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        ::std::process::exit(1);
+    });
+
+    if let Some(batch) = matches.opt_str("b") {
+        let batch = match batch.parse::<usize>() {
+            Ok(batch) if batch > 0 => batch,
+            _ => {
+                eprintln!("Invalid batch size: {}", batch);
+                ::std::process::exit(1);
+            }
+        };
+
+        db_bulk_test(&config, batch);
+
+        thread::sleep(Duration::new(1, 0));
+
+        graphql_bulk_test(&config, batch);
+
+        thread::sleep(Duration::new(1, 0));
+
+        direct_udp_bulk_test(&config, batch);
+
+        thread::sleep(Duration::new(1, 0));
+
+        test_cleanup(&config);
+        return;
+    }
 
     db_test(&config);
 